@@ -0,0 +1,93 @@
+//! Compiles scripts into standalone executables. The chunk is translated
+//! into an object file with cranelift, and the system's C compiler links it
+//! with a small runtime that prints its result.
+
+use std::{path::Path, process::Command};
+
+use cranelift_module::{default_libcall_names, Linkage, Module};
+use cranelift_object::{ObjectBuilder, ObjectModule};
+
+use crate::{
+    chunk::Chunk,
+    codegen::{self, Type},
+    compiler::Compiler,
+    error::BuildError,
+    value::Objects,
+};
+
+const RUNTIME: &str = include_str!("runtime.c");
+
+/// Compiles `source` into an executable at `out` that prints what the
+/// script evaluates to. Uses the compiler in the `CC` environment variable,
+/// or `cc`.
+pub fn build(source: String, out: &Path) -> Result<(), BuildError> {
+    let objects = Objects::new();
+    let chunk = Compiler::compile(source, &objects).map_err(BuildError::Compile)?;
+
+    let isa = codegen::native_isa(true)
+        .ok_or_else(|| BuildError::Codegen("this machine isn't supported".into()))?;
+    let builder = ObjectBuilder::new(isa, "lox", default_libcall_names()).map_err(codegen_error)?;
+    let mut module = ObjectModule::new(builder);
+    let mut context = module.make_context();
+    let (result, _) =
+        codegen::translate(&chunk, &mut context.func).ok_or_else(|| unsupported(&chunk))?;
+    let id = module
+        .declare_function("lox_chunk", Linkage::Export, &context.func.signature)
+        .map_err(codegen_error)?;
+    module
+        .define_function(id, &mut context)
+        .map_err(codegen_error)?;
+    let object = module.finish().emit().map_err(codegen_error)?;
+
+    let dir = std::env::temp_dir().join(format!("lox-build-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let linked = link(&dir, &object, result, out);
+    std::fs::remove_dir_all(&dir)?;
+    linked
+}
+
+fn link(dir: &Path, object: &[u8], result: Type, out: &Path) -> Result<(), BuildError> {
+    let object_path = dir.join("chunk.o");
+    let runtime_path = dir.join("runtime.c");
+    std::fs::write(&object_path, object)?;
+    std::fs::write(&runtime_path, RUNTIME)?;
+
+    // the values of the LOX_* types in the runtime
+    let result = match result {
+        Type::Number => 0,
+        Type::Bool => 1,
+        Type::Nil => 2,
+    };
+    let compiler = std::env::var("CC").unwrap_or_else(|_| "cc".into());
+    let output = Command::new(compiler)
+        .arg("-O2")
+        .arg(format!("-DLOX_RESULT={}", result))
+        .arg(&runtime_path)
+        .arg(&object_path)
+        .arg("-o")
+        .arg(out)
+        .arg("-lm")
+        .output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        let message = String::from_utf8_lossy(&output.stderr);
+        Err(BuildError::Link(message.trim().to_string()))
+    }
+}
+
+// why codegen couldn't translate `chunk`
+fn unsupported(chunk: &Chunk) -> BuildError {
+    let mut offset = 0;
+    while let Some(len) = chunk.instruction_len(offset) {
+        if !chunk.jump_targets(offset).is_empty() {
+            return BuildError::Branching;
+        }
+        offset += len;
+    }
+    BuildError::Unsupported
+}
+
+fn codegen_error(error: impl std::fmt::Display) -> BuildError {
+    BuildError::Codegen(error.to_string())
+}
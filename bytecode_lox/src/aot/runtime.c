@@ -0,0 +1,81 @@
+// The part of a compiled script that isn't generated: it runs the chunk and
+// prints what it returns, like the vm does. LOX_RESULT is the type of that
+// value, defined when this is compiled next to the chunk's object file.
+
+#include <math.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#define LOX_NUMBER 0
+#define LOX_BOOL 1
+#define LOX_NIL 2
+
+double lox_chunk(void);
+
+// prints numbers the way the vm does: with the fewest digits that read back
+// as the same number, and never in exponent notation
+static void print_number(double value) {
+    if (isnan(value)) {
+        puts("NaN");
+        return;
+    }
+    if (isinf(value)) {
+        puts(value < 0 ? "-inf" : "inf");
+        return;
+    }
+
+    char buffer[32];
+    for (int precision = 0; precision < 17; precision++) {
+        snprintf(buffer, sizeof buffer, "%.*e", precision, value);
+        if (strtod(buffer, NULL) == value) {
+            break;
+        }
+    }
+
+    // the buffer holds [-]d[.ddd]e(+|-)xx, the digits go around the point
+    char digits[32];
+    int count = 0;
+    const char *c = buffer;
+    if (*c == '-') {
+        putchar('-');
+        c++;
+    }
+    for (; *c != 'e'; c++) {
+        if (*c != '.') {
+            digits[count++] = *c;
+        }
+    }
+    int point = atoi(c + 1) + 1;
+
+    if (point <= 0) {
+        fputs("0.", stdout);
+        for (int i = point; i < 0; i++) {
+            putchar('0');
+        }
+        fwrite(digits, 1, count, stdout);
+    } else if (point >= count) {
+        fwrite(digits, 1, count, stdout);
+        for (int i = count; i < point; i++) {
+            putchar('0');
+        }
+    } else {
+        fwrite(digits, 1, point, stdout);
+        putchar('.');
+        fwrite(digits + point, 1, count - point, stdout);
+    }
+    putchar('\n');
+}
+
+int main(void) {
+    double result = lox_chunk();
+#if LOX_RESULT == LOX_NUMBER
+    print_number(result);
+#elif LOX_RESULT == LOX_BOOL
+    puts(result != 0 ? "true" : "false");
+#else
+    (void)result;
+    puts("nil");
+#endif
+    return 0;
+}
@@ -0,0 +1,190 @@
+//! Builds chunks from instructions instead of source, for tests and for
+//! tools that generate bytecode. Jumps go to labels, which can be placed
+//! after them, and their distances are filled in by `build`.
+
+use std::{convert::TryFrom, ops::Range};
+
+use crate::{
+    chunk::{Chunk, OpCode},
+    error::BuilderError,
+    instruction::Instruction,
+    value::Value,
+};
+
+/// Where a jump lands, once it's placed with `ChunkBuilder::place`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Label(usize);
+
+// a jump distance waiting for where its label is
+struct Patch {
+    // of the jump, for errors
+    offset: usize,
+    // of the two bytes of the distance
+    operand: usize,
+    // distances count from here
+    end: usize,
+    label: Label,
+}
+
+/// Builds a chunk instruction by instruction. The chunk it builds isn't
+/// checked beyond its jumps and constants, `verifier::verify` checks the
+/// rest.
+pub struct ChunkBuilder {
+    chunk: Chunk,
+    line: usize,
+    columns: Range<u32>,
+    labels: Vec<Option<usize>>,
+    patches: Vec<Patch>,
+    error: Option<BuilderError>,
+}
+
+impl Default for ChunkBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChunkBuilder {
+    pub fn new() -> Self {
+        Self {
+            chunk: Chunk::new(),
+            line: 1,
+            columns: 0..0,
+            labels: vec![],
+            patches: vec![],
+            error: None,
+        }
+    }
+
+    /// Marks the instructions that follow as compiled from `columns` of
+    /// `line`. Until it's called, they're from line 1.
+    pub fn at(&mut self, line: usize, columns: Range<u32>) -> &mut Self {
+        self.line = line;
+        self.columns = columns;
+        self
+    }
+
+    /// Where the next instruction goes.
+    pub fn offset(&self) -> usize {
+        self.chunk.code.len()
+    }
+
+    /// Adds `value` to the constants, and an `OP_CONSTANT` that loads it.
+    pub fn push_constant(&mut self, value: Value) -> &mut Self {
+        let index = self.chunk.add_constant(value);
+        match u8::try_from(index) {
+            Ok(index) => self.instruction(Instruction::Constant(index)),
+            Err(_) => {
+                self.error.get_or_insert(BuilderError::TooManyConstants);
+                self
+            }
+        }
+    }
+
+    /// Adds an instruction without operands.
+    ///
+    /// # Panics
+    ///
+    /// If `opcode` takes operands, which `push_constant`, the jumps and
+    /// `jump_table` add instead.
+    pub fn op(&mut self, opcode: OpCode) -> &mut Self {
+        assert_eq!(
+            opcode.operand_count(),
+            0,
+            "{} takes operands",
+            opcode.mnemonic()
+        );
+        let byte = opcode.as_u8();
+        self.chunk.write(byte, self.line, self.columns.clone());
+        self
+    }
+
+    /// Adds `instruction` as it is, jump distances included.
+    pub fn instruction(&mut self, instruction: Instruction) -> &mut Self {
+        instruction.encode(&mut self.chunk, self.line, self.columns.clone());
+        self
+    }
+
+    /// A label that isn't anywhere yet.
+    pub fn label(&mut self) -> Label {
+        self.labels.push(None);
+        Label(self.labels.len() - 1)
+    }
+
+    /// Puts `label` where the next instruction goes.
+    ///
+    /// # Panics
+    ///
+    /// If the label was already placed.
+    pub fn place(&mut self, label: Label) -> &mut Self {
+        let offset = self.offset();
+        let place = &mut self.labels[label.0];
+        assert!(place.is_none(), "the label was already placed");
+        *place = Some(offset);
+        self
+    }
+
+    /// Adds an `OP_JUMP` to `label`.
+    pub fn jump_to(&mut self, label: Label) -> &mut Self {
+        self.jump(Instruction::Jump(0), label)
+    }
+
+    /// Adds an `OP_JUMPIFFALSE` to `label`.
+    pub fn jump_if_false_to(&mut self, label: Label) -> &mut Self {
+        self.jump(Instruction::JumpIfFalse(0), label)
+    }
+
+    fn jump(&mut self, instruction: Instruction, label: Label) -> &mut Self {
+        let offset = self.offset();
+        self.patches.push(Patch {
+            offset,
+            operand: offset + 1,
+            end: offset + instruction.size(),
+            label,
+        });
+        self.instruction(instruction)
+    }
+
+    /// Adds an `OP_JUMPTABLE` that jumps to the label of its case, or to
+    /// `default`.
+    ///
+    /// # Panics
+    ///
+    /// If there are more than 255 cases.
+    pub fn jump_table(&mut self, cases: &[Label], default: Label) -> &mut Self {
+        let offset = self.offset();
+        let instruction = Instruction::JumpTable {
+            cases: vec![0; cases.len()],
+            default: 0,
+        };
+        let end = offset + instruction.size();
+        for (i, label) in cases.iter().chain(Some(&default)).enumerate() {
+            self.patches.push(Patch {
+                offset,
+                operand: offset + 2 + 2 * i,
+                end,
+                label: *label,
+            });
+        }
+        self.instruction(instruction)
+    }
+
+    /// The chunk, with the distance of every jump to its label filled in.
+    pub fn build(mut self) -> Result<Chunk, BuilderError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        for patch in &self.patches {
+            let target =
+                self.labels[patch.label.0].ok_or(BuilderError::UnplacedLabel(patch.offset))?;
+            if target < patch.end {
+                return Err(BuilderError::BackwardJump(patch.offset, target));
+            }
+            let distance = u16::try_from(target - patch.end)
+                .map_err(|_| BuilderError::JumpTooFar(patch.offset, target))?;
+            self.chunk.code[patch.operand..patch.operand + 2]
+                .copy_from_slice(&distance.to_be_bytes());
+        }
+        Ok(self.chunk)
+    }
+}
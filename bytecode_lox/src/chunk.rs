@@ -1,6 +1,11 @@
-use lox_proc_macros::U8Enum;
+use std::ops::Range;
 
-use crate::value::Value;
+use lox_proc_macros::{OpcodeInfo, U8Enum};
+
+use crate::{
+    instruction::{Instruction, Instructions},
+    value::Value,
+};
 
 // Because OP_CONSTANT uses only a single byte for its operand, a chunk may
 // only contain up to 256 different constants. That’s small enough that people
@@ -29,9 +34,11 @@ use crate::value::Value;
 // Defining two instructions seems to be the best of both worlds. What
 // sacrifices, if any, does it force on us?
 
-#[derive(Copy, Clone, U8Enum)]
+#[derive(Copy, Clone, U8Enum, OpcodeInfo)]
 #[repr(u8)]
 pub enum OpCode {
+    /// followed by the index of the constant
+    #[opcode(operands = 1)]
     Constant,
     Nil,
     True,
@@ -48,6 +55,24 @@ pub enum OpCode {
     Divide,
     Not,
     Negate,
+    /// pushes another copy of the value on top of the stack
+    Dup,
+    /// swaps the two values on top of the stack
+    Swap,
+    Pop,
+    /// followed by how far forward to jump, as two big endian bytes
+    #[opcode(operands = 2)]
+    Jump,
+    /// like `Jump`, when the value on top of the stack is falsey. the value
+    /// stays on the stack
+    #[opcode(operands = 2)]
+    JumpIfFalse,
+    /// followed by a number of cases, then a table with how far forward to
+    /// jump for each case and for none of them, two big endian bytes each.
+    /// pops the case, a whole number counting from 0, so a dense `switch`
+    /// takes a single jump instead of a comparison for each case
+    #[opcode(operands = 1)]
+    JumpTable,
     Return,
 }
 
@@ -56,11 +81,24 @@ struct LineInfo {
     line: u32, // I hope nobody has more than 4.294.967.295 lines in a source file
 }
 
+struct ColumnInfo {
+    count: u32,
+    columns: Range<u32>,
+}
+
 pub struct Chunk {
     pub code: Vec<u8>,
     pub constants: Vec<Value>,
     // line information is stored using run-length encoding
     lines: Vec<LineInfo>,
+    // and so are the columns of the token each byte was compiled from
+    columns: Vec<ColumnInfo>,
+}
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Chunk {
@@ -69,21 +107,25 @@ impl Chunk {
             code: vec![],
             constants: vec![],
             lines: vec![],
+            columns: vec![],
         }
     }
-    pub fn write(&mut self, byte: u8, line: usize) {
+    /// Adds `byte`, compiled from the token on `line` that spans `columns`.
+    pub fn write(&mut self, byte: u8, line: usize, columns: Range<u32>) {
         self.code.push(byte);
 
+        match self.columns.last_mut() {
+            Some(last) if last.columns == columns => last.count += 1,
+            _ => self.columns.push(ColumnInfo { count: 1, columns }),
+        }
+
         let line = line as u32; // TODO! checked
 
         if self.lines.last().map(|it| it.line == line).unwrap_or(false) {
             let len = self.lines.len();
             self.lines[len - 1].count += 1;
         } else {
-            self.lines.push(LineInfo {
-                count: 1,
-                line: line,
-            });
+            self.lines.push(LineInfo { count: 1, line });
         }
     }
     pub fn get_line(&self, offset: usize) -> u32 {
@@ -96,6 +138,74 @@ impl Chunk {
         }
         u32::MAX
     }
+    /// The columns of the token the byte at `offset` was compiled from,
+    /// counted in grapheme clusters from 1, the end excluded.
+    pub fn get_columns(&self, offset: usize) -> Range<u32> {
+        let mut i = 0;
+        for info in &self.columns {
+            i += info.count as usize;
+            if offset < i {
+                return info.columns.clone();
+            }
+        }
+        0..0
+    }
+    /// The offsets of each run of code for a line, in order, with the line.
+    pub fn line_runs(&self) -> Vec<(Range<usize>, u32)> {
+        let mut runs = vec![];
+        let mut offset = 0;
+        for info in &self.lines {
+            let end = offset + info.count as usize;
+            runs.push((offset..end, info.line));
+            offset = end;
+        }
+        runs
+    }
+    /// Offsets of the instructions where a run of code for `line` starts,
+    /// which is where execution enters the line.
+    pub fn offsets_of_line(&self, line: u32) -> Vec<usize> {
+        self.line_runs()
+            .into_iter()
+            .filter(|(_, it)| *it == line)
+            .map(|(offsets, _)| offsets.start)
+            .collect()
+    }
+    /// Whether the instruction at `offset` is the first one of a run of code
+    /// for its line.
+    pub fn starts_line(&self, offset: usize) -> bool {
+        let mut i = 0;
+        for line in &self.lines {
+            if offset == i {
+                return true;
+            }
+            i += line.count as usize;
+            if offset < i {
+                return false;
+            }
+        }
+        false
+    }
+    /// How many bytes the instruction at `offset` takes, with its operands
+    /// and the table of a `JumpTable`, or `None` if they aren't all there.
+    pub fn instruction_len(&self, offset: usize) -> Option<usize> {
+        self.decode(offset).map(|it| it.size())
+    }
+    /// Where the jump at `offset` can land, for a `JumpTable` each case in
+    /// order and then the default. Empty if there's no jump there.
+    pub fn jump_targets(&self, offset: usize) -> Vec<usize> {
+        self.decode(offset)
+            .map_or(vec![], |it| it.jump_targets(offset))
+    }
+    /// The instructions, with their offsets, in order.
+    pub fn instructions(&self) -> Instructions<'_> {
+        Instructions::new(self)
+    }
+    fn decode(&self, offset: usize) -> Option<Instruction> {
+        if offset >= self.code.len() {
+            return None;
+        }
+        Instruction::decode(self, offset).ok()
+    }
     pub fn add_constant(&mut self, value: Value) -> usize {
         self.constants.push(value);
         self.constants.len() - 1
@@ -0,0 +1,192 @@
+//! Translates chunks into cranelift's IR, for the jit and for native
+//! executables. Only chunks where everything is a number, a boolean or nil
+//! are translated.
+//!
+//! The types of the values on the stack are known while translating, since
+//! the constants are, so numbers live in float registers and booleans in
+//! byte ones. A chunk that would make the vm report an error, like one that
+//! negates a boolean, isn't translated, so the vm runs it and reports it.
+
+use cranelift_codegen::{
+    ir::{condcodes::FloatCC, condcodes::IntCC, types, AbiParam, Function, InstBuilder},
+    isa::OwnedTargetIsa,
+    settings::{self, Configurable},
+};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext};
+
+use crate::{chunk::Chunk, instruction::Instruction, value::Value};
+
+/// The type of the value a translated chunk returns. It always returns it
+/// as a float, `true` as 1 and nil as 0.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Type {
+    Number,
+    Bool,
+    Nil,
+}
+
+/// The machine this runs on, optimizing for speed. `pic` is for code that
+/// goes into an object file to be linked.
+pub(crate) fn native_isa(pic: bool) -> Option<OwnedTargetIsa> {
+    let mut flags = settings::builder();
+    flags.set("opt_level", "speed").ok()?;
+    flags
+        .set("is_pic", if pic { "true" } else { "false" })
+        .ok()?;
+    cranelift_native::builder()
+        .ok()?
+        .finish(settings::Flags::new(flags))
+        .ok()
+}
+
+// a value on the stack while compiling. nil has no register.
+#[derive(Clone, Copy)]
+struct Slot {
+    kind: Type,
+    value: Option<cranelift_codegen::ir::Value>,
+}
+
+/// Writes the chunk's code into `function`, which takes no parameters and
+/// returns a float. Returns the type of the value it returns, and how many
+/// instructions the vm would have run for it.
+pub(crate) fn translate(chunk: &Chunk, function: &mut Function) -> Option<(Type, u64)> {
+    function.signature.returns.push(AbiParam::new(types::F64));
+    let mut builder_context = FunctionBuilderContext::new();
+    let mut builder = FunctionBuilder::new(function, &mut builder_context);
+    let block = builder.create_block();
+    builder.switch_to_block(block);
+    builder.seal_block(block);
+
+    let mut stack: Vec<Slot> = vec![];
+    let mut instructions = 0;
+    for instruction in chunk.instructions() {
+        let (_, instruction) = instruction.ok()?;
+        instructions += 1;
+
+        let number = |value| Slot {
+            kind: Type::Number,
+            value: Some(value),
+        };
+        let boolean = |value| Slot {
+            kind: Type::Bool,
+            value: Some(value),
+        };
+        let nil = Slot {
+            kind: Type::Nil,
+            value: None,
+        };
+
+        let slot = match instruction {
+            Instruction::Constant(index) => match *chunk.constants.get(index as usize)? {
+                Value::Number(n) => number(builder.ins().f64const(n)),
+                Value::Bool(b) => boolean(builder.ins().iconst(types::I8, b as i64)),
+                Value::Nil => nil,
+                Value::Obj(_) => return None,
+            },
+            Instruction::Nil => nil,
+            Instruction::True => boolean(builder.ins().iconst(types::I8, 1)),
+            Instruction::False => boolean(builder.ins().iconst(types::I8, 0)),
+            Instruction::Equal | Instruction::NotEqual => {
+                let (b, a) = (stack.pop()?, stack.pop()?);
+                let equal = instruction == Instruction::Equal;
+                let value = match (a.kind, a.value, b.value) {
+                    _ if a.kind != b.kind => builder.ins().iconst(types::I8, !equal as i64),
+                    (Type::Number, Some(a), Some(b)) => {
+                        let cc = if equal {
+                            FloatCC::Equal
+                        } else {
+                            FloatCC::NotEqual
+                        };
+                        builder.ins().fcmp(cc, a, b)
+                    }
+                    (Type::Bool, Some(a), Some(b)) => {
+                        let cc = if equal { IntCC::Equal } else { IntCC::NotEqual };
+                        builder.ins().icmp(cc, a, b)
+                    }
+                    _ => builder.ins().iconst(types::I8, equal as i64),
+                };
+                boolean(value)
+            }
+            Instruction::Greater
+            | Instruction::GreaterEqual
+            | Instruction::Less
+            | Instruction::LessEqual => {
+                let (b, a) = (numeric(stack.pop()?)?, numeric(stack.pop()?)?);
+                let cc = match instruction {
+                    Instruction::Greater => FloatCC::GreaterThan,
+                    Instruction::GreaterEqual => FloatCC::GreaterThanOrEqual,
+                    Instruction::Less => FloatCC::LessThan,
+                    _ => FloatCC::LessThanOrEqual,
+                };
+                boolean(builder.ins().fcmp(cc, a, b))
+            }
+            Instruction::Add
+            | Instruction::Subtract
+            | Instruction::Multiply
+            | Instruction::Divide => {
+                let (b, a) = (numeric(stack.pop()?)?, numeric(stack.pop()?)?);
+                let value = match instruction {
+                    Instruction::Add => builder.ins().fadd(a, b),
+                    Instruction::Subtract => builder.ins().fsub(a, b),
+                    Instruction::Multiply => builder.ins().fmul(a, b),
+                    _ => builder.ins().fdiv(a, b),
+                };
+                number(value)
+            }
+            Instruction::Not => {
+                let a = stack.pop()?;
+                let value = match (a.kind, a.value) {
+                    (Type::Bool, Some(a)) => builder.ins().icmp_imm(IntCC::Equal, a, 0),
+                    (Type::Nil, _) => builder.ins().iconst(types::I8, 1),
+                    _ => builder.ins().iconst(types::I8, 0),
+                };
+                boolean(value)
+            }
+            Instruction::Negate => {
+                let a = numeric(stack.pop()?)?;
+                number(builder.ins().fneg(a))
+            }
+            // values are registers, so copying or moving one emits nothing
+            Instruction::Dup => *stack.last()?,
+            Instruction::Swap => {
+                let (b, a) = (stack.pop()?, stack.pop()?);
+                stack.push(b);
+                a
+            }
+            Instruction::Pop => {
+                stack.pop()?;
+                continue;
+            }
+            // straight-line code only, the vm runs the rest
+            Instruction::Jump(_) | Instruction::JumpIfFalse(_) | Instruction::JumpTable { .. } => {
+                return None
+            }
+            Instruction::Return => {
+                let a = stack.pop()?;
+                // everything comes back as a float, the type says what it was
+                let result = match (a.kind, a.value) {
+                    (Type::Number, Some(a)) => a,
+                    (Type::Bool, Some(a)) => {
+                        let one = builder.ins().f64const(1.0);
+                        let zero = builder.ins().f64const(0.0);
+                        builder.ins().select(a, one, zero)
+                    }
+                    _ => builder.ins().f64const(0.0),
+                };
+                builder.ins().return_(&[result]);
+                builder.finalize();
+                return Some((a.kind, instructions));
+            }
+        };
+        stack.push(slot);
+    }
+    // the chunk ended without returning
+    None
+}
+
+fn numeric(slot: Slot) -> Option<cranelift_codegen::ir::Value> {
+    match slot.kind {
+        Type::Number => slot.value,
+        _ => None,
+    }
+}
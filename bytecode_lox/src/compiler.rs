@@ -1,9 +1,18 @@
+use std::ops::Range;
+
 use lazy_static::lazy_static;
 use lox_proc_macros::U8Enum;
+use unicode_segmentation::UnicodeSegmentation;
 
 #[cfg(feature = "debug_print_code")]
 use crate::debug::disassemble_chunk;
-use crate::{chunk::{Chunk, OpCode}, error::{CompileError, ErrorInfo}, scanner::{Scanner, Token, TokenKind}, value::{Objects, Value}};
+use crate::{
+    chunk::{Chunk, OpCode},
+    error::{CompileError, ErrorInfo},
+    scanner::{Scanner, Token, TokenKind},
+    value::{Objects, Value},
+    verifier::verify,
+};
 
 pub struct Compiler<'source, 'objects> {
     chunk: Chunk,
@@ -12,12 +21,10 @@ pub struct Compiler<'source, 'objects> {
 }
 
 impl<'source, 'objects> Compiler<'source, 'objects> {
-    pub fn compile(source: String, objects: &'objects Objects) -> Result<Chunk, CompileError> {
-        let scanner = Scanner::new(&source);
-
+    pub fn compile(source: String, objects: &'objects Objects) -> Result<Chunk, Vec<CompileError>> {
         let mut compiler = Compiler {
             chunk: Chunk::new(),
-            parser: Parser::new(&scanner),
+            parser: Parser::new(Scanner::new(&source)),
             objects,
         };
 
@@ -27,11 +34,23 @@ impl<'source, 'objects> Compiler<'source, 'objects> {
             .consume(TokenKind::Eof, "Expect end of expression.");
         compiler.end();
 
-        Ok(compiler.chunk)
+        if compiler.parser.errors.is_empty() {
+            debug_assert_eq!(verify(&compiler.chunk), Ok(()));
+            Ok(compiler.chunk)
+        } else {
+            Err(compiler.parser.errors)
+        }
     }
 
     fn emit_byte(&mut self, byte: u8) {
-        self.chunk.write(byte, self.parser.previous.line);
+        let token = &self.parser.previous;
+        self.chunk.write(byte, token.line, columns(token));
+    }
+
+    // for instructions whose token isn't the last one parsed, like an
+    // operator's once its right operand is compiled
+    fn emit_byte_at(&mut self, byte: u8, token: &Token) {
+        self.chunk.write(byte, token.line, columns(token));
     }
 
     fn emit_bytes(&mut self, byte1: u8, byte2: u8) {
@@ -44,11 +63,32 @@ impl<'source, 'objects> Compiler<'source, 'objects> {
         self.emit_bytes(OpCode::Constant.as_u8(), constant);
     }
 
+    // emits `instruction` with a placeholder for how far it jumps, which
+    // `patch_jump` fills in, and returns where the placeholder is
+    fn emit_jump(&mut self, instruction: OpCode) -> usize {
+        self.emit_byte(instruction.as_u8());
+        self.emit_bytes(0xff, 0xff);
+        self.chunk.code.len() - 2
+    }
+
+    // makes the jump with its placeholder at `offset` land after the code
+    // emitted since. too much code to fit in the operand is reported at
+    // `token`, which starts the construct that jumps
+    fn patch_jump(&mut self, offset: usize, token: &Token) {
+        let distance = self.chunk.code.len() - offset - 2;
+        if distance > u16::MAX as usize {
+            let error = ErrorInfo::error(token, "Too much code to jump over.");
+            self.parser.report(CompileError::JumpTooFar(error));
+            return;
+        }
+        self.chunk.code[offset..offset + 2].copy_from_slice(&(distance as u16).to_be_bytes());
+    }
+
     fn make_constant(&mut self, value: Value) -> u8 {
         let constant = self.chunk.add_constant(value);
         if constant > u8::MAX as usize {
-            todo!("Too many constants in one chunk.");
-            // return 0;
+            self.parser.error("Too many constants in one chunk.");
+            return 0;
         }
         constant as u8
     }
@@ -63,8 +103,8 @@ impl<'source, 'objects> Compiler<'source, 'objects> {
         if let Some(prefix_rule) = prefix_rule {
             prefix_rule(self);
         } else {
-            todo!("Expect expression.");
-            // return;
+            self.parser.error("Expect expression.");
+            return;
         }
 
         while precedence.as_u8() <= get_rule(self.parser.current.kind).precedence.as_u8() {
@@ -78,9 +118,7 @@ impl<'source, 'objects> Compiler<'source, 'objects> {
         self.emit_byte(OpCode::Return.as_u8());
         #[cfg(feature = "debug_print_code")]
         {
-            if
-            /* !self.parser.had_error */
-            true {
+            if self.parser.errors.is_empty() {
                 disassemble_chunk(&self.chunk, "code");
             }
         }
@@ -117,56 +155,82 @@ fn number(compiler: &mut Compiler) {
 }
 
 fn string(compiler: &mut Compiler) {
-    let s = compiler.parser.previous.lexeme;
+    let s = &compiler.parser.previous.lexeme;
     let obj = compiler.objects.string(&s[1..s.len() - 1]);
     compiler.emit_constant(Value::Obj(obj));
 }
 
 fn unary(compiler: &mut Compiler) {
-    let operator_kind = compiler.parser.previous.kind;
+    let operator = compiler.parser.previous.clone();
     compiler.parse_precedence(Precedence::Unary);
-    match operator_kind {
-        TokenKind::Minus => compiler.emit_byte(OpCode::Negate.as_u8()),
-        TokenKind::Bang => compiler.emit_byte(OpCode::Not.as_u8()),
+    let opcode = match operator.kind {
+        TokenKind::Minus => OpCode::Negate,
+        TokenKind::Bang => OpCode::Not,
         any => unreachable!("Can't parse operator kind '{:?}' as unary.", any),
-    }
+    };
+    compiler.emit_byte_at(opcode.as_u8(), &operator);
 }
 
 fn binary(compiler: &mut Compiler) {
-    let operator_kind = compiler.parser.previous.kind;
-    let rule = get_rule(operator_kind);
+    let operator = compiler.parser.previous.clone();
+    let rule = get_rule(operator.kind);
     compiler.parse_precedence(Precedence::from_u8(rule.precedence.as_u8() + 1).unwrap());
 
-    match operator_kind {
-        TokenKind::BangEqual => compiler.emit_byte(OpCode::NotEqual.as_u8()),
-        TokenKind::EqualEqual => compiler.emit_byte(OpCode::Equal.as_u8()),
-        TokenKind::Greater => compiler.emit_byte(OpCode::Greater.as_u8()),
-        TokenKind::GreaterEqual => compiler.emit_byte(OpCode::GreaterEqual.as_u8()),
-        TokenKind::Less => compiler.emit_byte(OpCode::Less.as_u8()),
-        TokenKind::LessEqual => compiler.emit_byte(OpCode::LessEqual.as_u8()),
-        TokenKind::Plus => compiler.emit_byte(OpCode::Add.as_u8()),
-        TokenKind::Minus => compiler.emit_byte(OpCode::Subtract.as_u8()),
-        TokenKind::Star => compiler.emit_byte(OpCode::Multiply.as_u8()),
-        TokenKind::Slash => compiler.emit_byte(OpCode::Divide.as_u8()),
+    let opcode = match operator.kind {
+        TokenKind::BangEqual => OpCode::NotEqual,
+        TokenKind::EqualEqual => OpCode::Equal,
+        TokenKind::Greater => OpCode::Greater,
+        TokenKind::GreaterEqual => OpCode::GreaterEqual,
+        TokenKind::Less => OpCode::Less,
+        TokenKind::LessEqual => OpCode::LessEqual,
+        TokenKind::Plus => OpCode::Add,
+        TokenKind::Minus => OpCode::Subtract,
+        TokenKind::Star => OpCode::Multiply,
+        TokenKind::Slash => OpCode::Divide,
         any => unreachable!("Can't parse operator kind '{:?}' as binary.", any),
-    }
+    };
+    compiler.emit_byte_at(opcode.as_u8(), &operator);
+}
+
+// the left operand is the result if it's falsey, otherwise it's popped for
+// the right one
+fn and(compiler: &mut Compiler) {
+    let operator = compiler.parser.previous.clone();
+    let end_jump = compiler.emit_jump(OpCode::JumpIfFalse);
+    compiler.emit_byte(OpCode::Pop.as_u8());
+    compiler.parse_precedence(Precedence::And);
+    compiler.patch_jump(end_jump, &operator);
+}
+
+// the left operand is the result if it's truthy, otherwise it's popped for
+// the right one
+fn or(compiler: &mut Compiler) {
+    let operator = compiler.parser.previous.clone();
+    let else_jump = compiler.emit_jump(OpCode::JumpIfFalse);
+    let end_jump = compiler.emit_jump(OpCode::Jump);
+    compiler.patch_jump(else_jump, &operator);
+    compiler.emit_byte(OpCode::Pop.as_u8());
+    compiler.parse_precedence(Precedence::Or);
+    compiler.patch_jump(end_jump, &operator);
 }
 
 struct Parser<'source> {
-    scanner: &'source Scanner<'source>,
+    scanner: Scanner<'source>,
     current: Token<'source>,
     previous: Token<'source>,
     panic_mode: bool,
+    errors: Vec<CompileError>,
 }
 
 impl<'source> Parser<'source> {
-    pub fn new(scanner: &'source Scanner<'source>) -> Self {
+    pub fn new(mut scanner: Scanner<'source>) -> Self {
         let token = scanner.scan();
         Self {
             scanner,
             current: token.clone(),
             previous: token,
             panic_mode: false,
+            errors: vec![],
         }
     }
     pub fn advance(&mut self) {
@@ -176,11 +240,8 @@ impl<'source> Parser<'source> {
             if self.current.kind != TokenKind::Error {
                 break;
             }
-            self.panic_mode = true;
-            eprintln!(
-                "{}",
-                CompileError::ScanError(ErrorInfo::error(&self.current, ""))
-            )
+            let error = CompileError::ScanError(ErrorInfo::error(&self.current, ""));
+            self.report(error);
         }
     }
     pub fn consume(&mut self, kind: TokenKind, message: &str) {
@@ -189,11 +250,23 @@ impl<'source> Parser<'source> {
             return;
         }
 
-        self.panic_mode = true;
-        eprintln!(
-            "{}",
-            CompileError::ParseError(ErrorInfo::error(&self.current, message))
-        )
+        self.error_at_current(message);
+    }
+    pub fn error(&mut self, message: &str) {
+        let error = CompileError::ParseError(ErrorInfo::error(&self.previous, message));
+        self.report(error);
+    }
+    pub fn error_at_current(&mut self, message: &str) {
+        let error = CompileError::ParseError(ErrorInfo::error(&self.current, message));
+        self.report(error);
+    }
+    // once an error is found the rest are likely to be cascading from it,
+    // so they are not recorded until the parser synchronizes.
+    fn report(&mut self, error: CompileError) {
+        if !self.panic_mode {
+            self.panic_mode = true;
+            self.errors.push(error);
+        }
     }
 }
 
@@ -237,6 +310,7 @@ lazy_static! {
         rule!(RightParen, None, None, None);
         rule!(LeftBrace, None, None, None);
         rule!(RightBrace, None, None, None);
+        rule!(Colon, None, None, None);
         rule!(Comma, None, None, None);
         rule!(Dot, None, None, None);
         rule!(Minus, Some(unary), Some(binary), Term);
@@ -246,8 +320,10 @@ lazy_static! {
         rule!(Star, None, Some(binary), Factor);
         rule!(Bang, Some(unary), None, None);
         rule!(BangEqual, None, Some(binary), Equality);
+        rule!(BangEqualEqual, None, None, None);
         rule!(Equal, None, None, None);
         rule!(EqualEqual, None, Some(binary), Equality);
+        rule!(EqualEqualEqual, None, None, None);
         rule!(Greater, None, Some(binary), Equality);
         rule!(GreaterEqual, None, Some(binary), Equality);
         rule!(Less, None, Some(binary), Equality);
@@ -255,15 +331,18 @@ lazy_static! {
         rule!(Identifier, None, None, None);
         rule!(String, Some(string), None, None);
         rule!(Number, Some(number), None, None);
-        rule!(And, None, None, None);
+        rule!(And, None, Some(and), And);
+        rule!(Break, None, None, None);
+        rule!(Breakpoint, None, None, None);
         rule!(Class, None, None, None);
+        rule!(Continue, None, None, None);
         rule!(Else, None, None, None);
         rule!(False, Some(literal), None, None);
         rule!(For, None, None, None);
         rule!(Fun, None, None, None);
         rule!(If, None, None, None);
         rule!(Nil, Some(literal), None, None);
-        rule!(Or, None, None, None);
+        rule!(Or, None, Some(or), Or);
         rule!(Print, None, None, None);
         rule!(Return, None, None, None);
         rule!(LeftBrace, None, None, None);
@@ -272,6 +351,7 @@ lazy_static! {
         rule!(True, Some(literal), None, None);
         rule!(Var, None, None, None);
         rule!(While, None, None, None);
+        rule!(Comment, None, None, None);
         rule!(Error, None, None, None);
         rule!(Eof, None, None, None);
 
@@ -287,6 +367,11 @@ fn get_rule(kind: TokenKind) -> &'static ParseRule {
     &RULES[kind.as_u8() as usize]
 }
 
+fn columns(token: &Token) -> Range<u32> {
+    let start = token.column as u32;
+    start..start + token.lexeme.graphemes(true).count() as u32
+}
+
 // TODO! To really understand the parser, you need to see how execution threads
 // through the interesting parsing functions—parsePrecedence() and the parser
 // functions stored in the table. Take this (strange) expression:
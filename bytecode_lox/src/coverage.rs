@@ -0,0 +1,63 @@
+//! Line coverage for `lox --coverage`, from the instructions the vm ran.
+
+use std::collections::BTreeMap;
+
+use crate::chunk::Chunk;
+
+/// How often each line with code ran. Fill it with
+/// `VM::interpret_with_coverage`.
+#[derive(Default)]
+pub struct Coverage {
+    lines: BTreeMap<u32, u64>,
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a run of `chunk`, where `hits` counts how often the instruction
+    /// at each offset ran. A line ran as often as its busiest instruction.
+    pub(crate) fn record(&mut self, chunk: &Chunk, hits: &[u64]) {
+        for (offsets, line) in chunk.line_runs() {
+            let most = hits[offsets].iter().copied().max().unwrap_or(0);
+            let entry = self.lines.entry(line).or_insert(0);
+            *entry = (*entry).max(most);
+        }
+    }
+
+    /// Hits by line, for the lines with code.
+    pub fn lines(&self) -> &BTreeMap<u32, u64> {
+        &self.lines
+    }
+
+    /// The counts as an lcov tracefile for the script at `path`.
+    pub fn to_lcov(&self, path: &str) -> String {
+        let mut lcov = format!("TN:\nSF:{}\n", path);
+        for (line, hits) in &self.lines {
+            lcov.push_str(&format!("DA:{},{}\n", line, hits));
+        }
+        let hit = self.lines.values().filter(|hits| **hits > 0).count();
+        lcov.push_str(&format!(
+            "LF:{}\nLH:{}\nend_of_record\n",
+            self.lines.len(),
+            hit
+        ));
+        lcov
+    }
+
+    /// `source` with the hits of each line in front of it, `-` for lines
+    /// without code and `#####` for the ones that never ran.
+    pub fn annotate(&self, source: &str) -> String {
+        let mut annotated = String::new();
+        for (i, text) in source.lines().enumerate() {
+            let count = match self.lines.get(&(i as u32 + 1)) {
+                Some(0) => "#####".to_string(),
+                Some(hits) => hits.to_string(),
+                None => "-".to_string(),
+            };
+            annotated.push_str(&format!("{:>9}:{:>5}: {}\n", count, i + 1, text));
+        }
+        annotated
+    }
+}
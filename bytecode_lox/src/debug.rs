@@ -1,4 +1,4 @@
-use crate::chunk::{Chunk, OpCode};
+use crate::{chunk::Chunk, error::VerifyError, instruction::Instruction};
 
 pub fn disassemble_chunk(chunk: &Chunk, name: &str) {
     println!("== {} ==", name);
@@ -9,50 +9,54 @@ pub fn disassemble_chunk(chunk: &Chunk, name: &str) {
 }
 
 pub fn disassemble_instruction(chunk: &Chunk, offset: usize) -> usize {
-    print!("{:04} ", offset);
+    let (text, next) = format_instruction(chunk, offset);
+    println!("{}", text);
+    next
+}
+
+/// The disassembly of the instruction at `offset`, and the offset of the
+/// next one.
+pub fn format_instruction(chunk: &Chunk, offset: usize) -> (String, usize) {
+    let mut text = format!("{:04} ", offset);
 
     if offset > 0 && chunk.get_line(offset) == chunk.get_line(offset - 1) {
-        print!("   | ");
+        text.push_str("   | ");
     } else {
-        print!("{:4} ", chunk.get_line(offset));
+        text.push_str(&format!("{:4} ", chunk.get_line(offset)));
     }
 
-    let instruction = chunk.code[offset];
-    match OpCode::from_u8(instruction) {
-        Some(OpCode::Return) => simple_instruction("OP_RETURN", offset),
-        Some(OpCode::Negate) => simple_instruction("OP_NEGATE", offset),
-        Some(OpCode::Add) => simple_instruction("OP_ADD", offset),
-        Some(OpCode::Subtract) => simple_instruction("OP_SUBTRACT", offset),
-        Some(OpCode::Multiply) => simple_instruction("OP_MULTIPLY", offset),
-        Some(OpCode::Divide) => simple_instruction("OP_DIVIDE", offset),
-        Some(OpCode::Not) => simple_instruction("OP_NOT", offset),
-        Some(OpCode::Constant) => constant_instruction("OP_CONSTANT", chunk, offset),
-        Some(OpCode::Nil) => simple_instruction("OP_NIL", offset),
-        Some(OpCode::False) => simple_instruction("OP_FALSE", offset),
-        Some(OpCode::True) => simple_instruction("OP_TRUE", offset),
-        Some(OpCode::Equal) => simple_instruction("OP_EQUAL", offset),
-        Some(OpCode::NotEqual) => simple_instruction("OP_NOTEQUAL", offset),
-        Some(OpCode::Greater) => simple_instruction("OP_GREATER", offset),
-        Some(OpCode::GreaterEqual) => simple_instruction("OP_GREATEREQUAL", offset),
-        Some(OpCode::Less) => simple_instruction("OP_LESS", offset),
-        Some(OpCode::LessEqual) => simple_instruction("OP_LESSEQUAL", offset),
-        None => {
-            println!("Unknown opcode {}", instruction);
-            offset + 1
+    let instruction = match Instruction::decode(chunk, offset) {
+        Ok(instruction) => instruction,
+        Err(VerifyError::InvalidOpcode(_, byte)) => {
+            text.push_str(&format!("Unknown opcode {}", byte));
+            return (text, offset + 1);
+        }
+        Err(VerifyError::MissingOperands(_, mnemonic)) => {
+            text.push_str(&format!("{} <missing operands>", mnemonic));
+            return (text, chunk.code.len());
+        }
+        Err(error) => unreachable!("decoding failed with {:?}", error),
+    };
+    let mnemonic = instruction.opcode().mnemonic();
+    let targets = instruction.jump_targets(offset);
+    match &instruction {
+        Instruction::Constant(index) => {
+            text.push_str(&format!("{:-16} {:4}", mnemonic, index));
+            if let Some(constant) = chunk.constants.get(*index as usize) {
+                text.push_str(&format!(" '{}'", constant));
+            }
+        }
+        Instruction::Jump(_) | Instruction::JumpIfFalse(_) => {
+            text.push_str(&format!("{:-16} {:4} -> {}", mnemonic, offset, targets[0]));
         }
+        Instruction::JumpTable { .. } => {
+            let (default, cases) = targets.split_last().unwrap();
+            text.push_str(&format!(
+                "{:-16} {:4} -> {:?} else {}",
+                mnemonic, offset, cases, default
+            ));
+        }
+        _ => text.push_str(mnemonic),
     }
-}
-
-fn simple_instruction(name: &str, offset: usize) -> usize {
-    println!("{}", name);
-    offset + 1
-}
-
-fn constant_instruction(name: &str, chunk: &Chunk, offset: usize) -> usize {
-    let constant = chunk.code[offset + 1];
-    println!(
-        "{:-16} {:4} '{}'",
-        name, constant, chunk.constants[constant as usize]
-    );
-    offset + 2
+    (text, offset + instruction.size())
 }
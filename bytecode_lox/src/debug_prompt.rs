@@ -0,0 +1,168 @@
+use std::io::{self, BufRead, Write};
+
+use bytecode_lox::{
+    chunk::Chunk,
+    debug::{disassemble_chunk, format_instruction},
+    vm::debugger::{Breakpoint, DebugClient, PauseReason, PausedVm, Resume},
+};
+
+const HELP: &str = "\
+Commands:
+    step, s              run one instruction
+    continue, c          run to the next breakpoint
+    stack                show the value stack, top last
+    frame, f             show the chunk, offset and line being run
+    disassemble, dis     show the whole chunk
+    break, b <line>      pause whenever <line> is entered
+    break, b @<offset>   pause before the instruction at <offset>
+    delete, d <target>   remove a breakpoint set with break
+    breakpoints, bl      list the breakpoints
+    help, h              show this message";
+
+/// Reads debugger commands from stdin for `lox --debug`.
+pub struct DebugPrompt {
+    lines: Vec<String>,
+    // stdin ran out, so nothing can resume a pause anymore
+    detached: bool,
+}
+
+impl DebugPrompt {
+    pub fn new(source: &str) -> Self {
+        Self {
+            lines: source.lines().map(str::to_string).collect(),
+            detached: false,
+        }
+    }
+
+    fn show_line(&self, line: u32) {
+        let text = (line as usize)
+            .checked_sub(1)
+            .and_then(|i| self.lines.get(i));
+        if let Some(text) = text {
+            println!("{:>4} | {}", line, text);
+        }
+    }
+}
+
+fn parse_breakpoint(argument: &str) -> Option<Breakpoint> {
+    match argument.strip_prefix('@') {
+        Some(offset) => offset.parse().ok().map(Breakpoint::Offset),
+        None => argument.parse().ok().map(Breakpoint::Line),
+    }
+}
+
+fn starts_instruction(chunk: &Chunk, offset: usize) -> bool {
+    let mut next = 0;
+    while next < offset && next < chunk.code.len() {
+        next = format_instruction(chunk, next).1;
+    }
+    next == offset && offset < chunk.code.len()
+}
+
+fn describe(breakpoint: Breakpoint) -> String {
+    match breakpoint {
+        Breakpoint::Offset(offset) => format!("offset {:04}", offset),
+        Breakpoint::Line(line) => format!("line {}", line),
+    }
+}
+
+impl DebugClient for DebugPrompt {
+    fn paused(&mut self, session: &mut PausedVm<'_>) -> Resume {
+        if self.detached {
+            return Resume::Continue;
+        }
+        let reason = match session.reason() {
+            PauseReason::Entry => "Paused before the first instruction",
+            PauseReason::Breakpoint => "Breakpoint",
+            PauseReason::Step => "Paused",
+        };
+        println!(
+            "{} at offset {:04}, line {}:",
+            reason,
+            session.ip(),
+            session.line()
+        );
+        self.show_line(session.line());
+        println!("{}", format_instruction(session.chunk(), session.ip()).0);
+
+        let stdin = io::stdin();
+        loop {
+            print!("(debug) ");
+            let _ = io::stdout().flush();
+            let mut input = String::new();
+            match stdin.lock().read_line(&mut input) {
+                Ok(0) | Err(_) => {
+                    self.detached = true;
+                    return Resume::Continue;
+                }
+                Ok(_) => {}
+            }
+            let input = input.trim();
+            let (command, argument) = match input.split_once(char::is_whitespace) {
+                Some((command, argument)) => (command, argument.trim()),
+                None => (input, ""),
+            };
+            match command {
+                "step" | "s" => return Resume::Step,
+                "continue" | "c" => return Resume::Continue,
+                "stack" => {
+                    if session.stack().is_empty() {
+                        println!("The stack is empty.");
+                    }
+                    for (i, value) in session.stack().iter().enumerate() {
+                        println!("{:4} [ {} ]", i, value);
+                    }
+                }
+                "frame" | "f" => {
+                    println!(
+                        "<script> at offset {:04} of {}, line {}",
+                        session.ip(),
+                        session.chunk().code.len(),
+                        session.line()
+                    );
+                    self.show_line(session.line());
+                    println!("{}", format_instruction(session.chunk(), session.ip()).0);
+                }
+                "disassemble" | "dis" => disassemble_chunk(session.chunk(), "<script>"),
+                "break" | "b" => match parse_breakpoint(argument) {
+                    Some(Breakpoint::Line(line))
+                        if session.chunk().offsets_of_line(line).is_empty() =>
+                    {
+                        println!("There is no code on line {}.", line)
+                    }
+                    Some(Breakpoint::Offset(offset))
+                        if !starts_instruction(session.chunk(), offset) =>
+                    {
+                        println!("No instruction starts at offset {}.", offset)
+                    }
+                    Some(breakpoint) => {
+                        session.breakpoints().insert(breakpoint);
+                        println!("Breakpoint set on {}.", describe(breakpoint));
+                    }
+                    None => println!("Expected a line number or @offset."),
+                },
+                "delete" | "d" => match parse_breakpoint(argument) {
+                    Some(breakpoint) => {
+                        if session.breakpoints().remove(&breakpoint) {
+                            println!("Breakpoint on {} removed.", describe(breakpoint));
+                        } else {
+                            println!("There is no breakpoint on {}.", describe(breakpoint));
+                        }
+                    }
+                    None => println!("Expected a line number or @offset."),
+                },
+                "breakpoints" | "bl" => {
+                    if session.breakpoints().is_empty() {
+                        println!("No breakpoints.");
+                    }
+                    for breakpoint in session.breakpoints().iter() {
+                        println!("{}", describe(*breakpoint));
+                    }
+                }
+                "help" | "h" => println!("{}", HELP),
+                "" => {}
+                _ => println!("Unknown command '{}'. Type 'help' for a list.", command),
+            }
+        }
+    }
+}
@@ -1,37 +1,114 @@
 use std::fmt::Display;
 
 use crate::{
+    sandbox::Limit,
     scanner::{Token, TokenKind},
-    value::Value,
 };
 
 #[derive(thiserror::Error, Debug)]
 pub enum InterpretError {
-    #[error(transparent)]
-    Compile(#[from] CompileError),
+    #[error("{}", join_lines(.0))]
+    Compile(Vec<CompileError>),
     #[error(transparent)]
     Runtime(#[from] RuntimeError),
 }
 
+fn join_lines(errors: &[CompileError]) -> String {
+    errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Why a script couldn't be built into an executable or a module.
+#[cfg(any(feature = "aot", feature = "wasm"))]
+#[derive(thiserror::Error, Debug)]
+pub enum BuildError {
+    #[error("{}", join_lines(.0))]
+    Compile(Vec<CompileError>),
+    #[error("Only scripts where every value is a number, a boolean or nil can be built.")]
+    Unsupported,
+    #[error("Scripts with 'and' or 'or' can't be built yet.")]
+    Branching,
+    #[error("Couldn't generate code: {0}")]
+    Codegen(String),
+    #[error("Couldn't link the executable: {0}")]
+    Link(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Why a chunk can't be run. Offsets are of the instruction at fault.
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum VerifyError {
+    #[error("[offset {0}] Byte '{1}' does not map to any op code.")]
+    InvalidOpcode(usize, u8),
+    #[error("[offset {0}] {1} is missing its operands.")]
+    MissingOperands(usize, &'static str),
+    #[error("[offset {0}] There is no constant {1}.")]
+    InvalidConstant(usize, u8),
+    #[error("[offset {0}] Jumps to {1}, which isn't the start of an instruction.")]
+    InvalidJump(usize, usize),
+    #[error("The chunk doesn't end with OP_RETURN.")]
+    MissingReturn,
+    #[error("[offset {0}] Pops more values than there are on the stack.")]
+    StackUnderflow(usize),
+    #[error("[offset {0}] Pushes more values than fit on the stack.")]
+    StackOverflow(usize),
+    #[error("[offset {0}] The stack has a different height on each path that gets here.")]
+    UnbalancedStack(usize),
+}
+
+/// Why a `ChunkBuilder` couldn't build its chunk. Offsets are of the jump
+/// at fault.
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum BuilderError {
+    #[error("Too many constants in one chunk.")]
+    TooManyConstants,
+    #[error("[offset {0}] Jumps to a label that was never placed.")]
+    UnplacedLabel(usize),
+    #[error("[offset {0}] Jumps back to {1}, but jumps only go forward.")]
+    BackwardJump(usize, usize),
+    #[error("[offset {0}] Jumps to {1}, which is too far to jump.")]
+    JumpTooFar(usize, usize),
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum CompileError {
     #[error("{0}")]
     ScanError(ErrorInfo),
     #[error("{0}")]
     ParseError(ErrorInfo),
+    #[error("{0}")]
+    JumpTooFar(ErrorInfo),
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum RuntimeError {
     #[error("Byte '{0}' does not map to any op code.")]
     InvalidOpcode(u8),
-    #[error("Operand for {0} must be number, but was {1}.")]
-    OperandMustBeNumber(String, Value),
+    // the operand is kept as text, the objects it could point to are freed
+    // together with the vm that returns the error
+    #[error("[line {0}] Operand must be a number, but was {1}.")]
+    OperandMustBeNumber(u32, String),
+    #[error("[line {0}] Operands must be numbers.")]
+    OperandsMustBeNumbers(u32),
+    #[error("[line {0}] Operands must be two numbers or two strings.")]
+    OperandsMustBeNumbersOrStrings(u32),
+    #[error("[line {0}] Division by zero.")]
+    DivisionByZero(u32),
+    #[error("[line {0}] '{1}' resulted in {2}.")]
+    NotFinite(u32, &'static str, f64),
+    #[error("[line {0}] Exceeded the sandbox limit of {1}.")]
+    LimitExceeded(u32, Limit),
 }
 
 #[derive(Debug)]
 pub struct ErrorInfo {
     line: usize,
+    // worked out like clox does, but not part of the message yet
+    #[allow(dead_code)]
     location: String,
     message: String,
 }
@@ -47,10 +124,7 @@ impl ErrorInfo {
         } else if token.kind == TokenKind::Error {
             ("".to_string(), token.lexeme.to_string())
         } else {
-            (
-                format!(" at '{}'", token.lexeme.to_string()),
-                message.to_string(),
-            )
+            (format!(" at '{}'", token.lexeme), message.to_string())
         };
         Self {
             line: token.line,
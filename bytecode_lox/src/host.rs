@@ -0,0 +1,13 @@
+/// Where the results printed by the vm go. Embedders that don't have a
+/// stdout, like the wasm playground, capture them through this.
+pub trait Output {
+    fn print(&mut self, line: &str);
+}
+
+pub struct StdOutput;
+
+impl Output for StdOutput {
+    fn print(&mut self, line: &str) {
+        println!("{}", line);
+    }
+}
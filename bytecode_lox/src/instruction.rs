@@ -0,0 +1,208 @@
+//! Instructions with their operands decoded, for the tools that read chunks,
+//! like the disassembler and the verifier, so how the operands are laid out
+//! in the bytes is only written down here. Jumps hold how far forward they
+//! jump, from the end of the instruction.
+
+use std::{convert::TryFrom, ops::Range};
+
+use crate::{
+    chunk::{Chunk, OpCode},
+    error::VerifyError,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instruction {
+    /// the index of the constant
+    Constant(u8),
+    Nil,
+    True,
+    False,
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Not,
+    Negate,
+    Dup,
+    Swap,
+    Pop,
+    Jump(u16),
+    JumpIfFalse(u16),
+    /// the distance for each case, then for none of them. there can't be
+    /// more than 255 cases
+    JumpTable {
+        cases: Vec<u16>,
+        default: u16,
+    },
+    Return,
+}
+
+impl Instruction {
+    pub fn opcode(&self) -> OpCode {
+        match self {
+            Instruction::Constant(_) => OpCode::Constant,
+            Instruction::Nil => OpCode::Nil,
+            Instruction::True => OpCode::True,
+            Instruction::False => OpCode::False,
+            Instruction::Equal => OpCode::Equal,
+            Instruction::NotEqual => OpCode::NotEqual,
+            Instruction::Greater => OpCode::Greater,
+            Instruction::GreaterEqual => OpCode::GreaterEqual,
+            Instruction::Less => OpCode::Less,
+            Instruction::LessEqual => OpCode::LessEqual,
+            Instruction::Add => OpCode::Add,
+            Instruction::Subtract => OpCode::Subtract,
+            Instruction::Multiply => OpCode::Multiply,
+            Instruction::Divide => OpCode::Divide,
+            Instruction::Not => OpCode::Not,
+            Instruction::Negate => OpCode::Negate,
+            Instruction::Dup => OpCode::Dup,
+            Instruction::Swap => OpCode::Swap,
+            Instruction::Pop => OpCode::Pop,
+            Instruction::Jump(_) => OpCode::Jump,
+            Instruction::JumpIfFalse(_) => OpCode::JumpIfFalse,
+            Instruction::JumpTable { .. } => OpCode::JumpTable,
+            Instruction::Return => OpCode::Return,
+        }
+    }
+
+    /// Reads the instruction at `offset`, which has to be in the chunk.
+    pub fn decode(chunk: &Chunk, offset: usize) -> Result<Instruction, VerifyError> {
+        let byte = chunk.code[offset];
+        let opcode = OpCode::from_u8(byte).ok_or(VerifyError::InvalidOpcode(offset, byte))?;
+        let byte_at = |i: usize| {
+            let byte = chunk.code.get(offset + i).copied();
+            byte.ok_or(VerifyError::MissingOperands(offset, opcode.mnemonic()))
+        };
+        let short_at = |i: usize| Ok(u16::from_be_bytes([byte_at(i)?, byte_at(i + 1)?]));
+        let instruction = match opcode {
+            OpCode::Constant => Instruction::Constant(byte_at(1)?),
+            OpCode::Nil => Instruction::Nil,
+            OpCode::True => Instruction::True,
+            OpCode::False => Instruction::False,
+            OpCode::Equal => Instruction::Equal,
+            OpCode::NotEqual => Instruction::NotEqual,
+            OpCode::Greater => Instruction::Greater,
+            OpCode::GreaterEqual => Instruction::GreaterEqual,
+            OpCode::Less => Instruction::Less,
+            OpCode::LessEqual => Instruction::LessEqual,
+            OpCode::Add => Instruction::Add,
+            OpCode::Subtract => Instruction::Subtract,
+            OpCode::Multiply => Instruction::Multiply,
+            OpCode::Divide => Instruction::Divide,
+            OpCode::Not => Instruction::Not,
+            OpCode::Negate => Instruction::Negate,
+            OpCode::Dup => Instruction::Dup,
+            OpCode::Swap => Instruction::Swap,
+            OpCode::Pop => Instruction::Pop,
+            OpCode::Jump => Instruction::Jump(short_at(1)?),
+            OpCode::JumpIfFalse => Instruction::JumpIfFalse(short_at(1)?),
+            OpCode::JumpTable => {
+                let count = byte_at(1)? as usize;
+                let cases = (0..count)
+                    .map(|case| short_at(2 + 2 * case))
+                    .collect::<Result<_, _>>()?;
+                Instruction::JumpTable {
+                    cases,
+                    default: short_at(2 + 2 * count)?,
+                }
+            }
+            OpCode::Return => Instruction::Return,
+        };
+        Ok(instruction)
+    }
+
+    /// The bytes of the instruction.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.opcode().as_u8()];
+        match self {
+            Instruction::Constant(index) => bytes.push(*index),
+            Instruction::Jump(distance) | Instruction::JumpIfFalse(distance) => {
+                bytes.extend(distance.to_be_bytes())
+            }
+            Instruction::JumpTable { cases, default } => {
+                let count = u8::try_from(cases.len()).expect("Too many cases in a jump table.");
+                bytes.push(count);
+                for distance in cases.iter().chain(Some(default)) {
+                    bytes.extend(distance.to_be_bytes());
+                }
+            }
+            _ => {}
+        }
+        bytes
+    }
+
+    /// Adds the instruction to `chunk`, compiled from the token on `line`
+    /// that spans `columns`.
+    pub fn encode(&self, chunk: &mut Chunk, line: usize, columns: Range<u32>) {
+        for byte in self.to_bytes() {
+            chunk.write(byte, line, columns.clone());
+        }
+    }
+
+    /// How many bytes the instruction takes.
+    pub fn size(&self) -> usize {
+        let table = match self {
+            Instruction::JumpTable { cases, .. } => 2 * (cases.len() + 1),
+            _ => 0,
+        };
+        1 + self.opcode().operand_count() + table
+    }
+
+    /// Where the instruction, if it's at `offset`, can jump to. For a
+    /// `JumpTable` each case in order and then the default.
+    pub fn jump_targets(&self, offset: usize) -> Vec<usize> {
+        let end = offset + self.size();
+        match self {
+            Instruction::Jump(distance) | Instruction::JumpIfFalse(distance) => {
+                vec![end + *distance as usize]
+            }
+            Instruction::JumpTable { cases, default } => cases
+                .iter()
+                .chain(Some(default))
+                .map(|distance| end + *distance as usize)
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+/// The instructions of a chunk with their offsets, in order, from
+/// `Chunk::instructions`. Stops after the first one that can't be decoded.
+pub struct Instructions<'chunk> {
+    chunk: &'chunk Chunk,
+    offset: usize,
+}
+
+impl<'chunk> Instructions<'chunk> {
+    pub(crate) fn new(chunk: &'chunk Chunk) -> Self {
+        Self { chunk, offset: 0 }
+    }
+}
+
+impl Iterator for Instructions<'_> {
+    type Item = Result<(usize, Instruction), VerifyError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.chunk.code.len() {
+            return None;
+        }
+        let offset = self.offset;
+        match Instruction::decode(self.chunk, offset) {
+            Ok(instruction) => {
+                self.offset += instruction.size();
+                Some(Ok((offset, instruction)))
+            }
+            Err(error) => {
+                self.offset = self.chunk.code.len();
+                Some(Err(error))
+            }
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! Compiles chunks to native code with cranelift, when everything they do
+//! is with numbers, booleans and nil, and runs them in place of the vm.
+
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, Linkage, Module};
+
+use crate::{
+    chunk::Chunk,
+    codegen::{self, Type},
+    value::Value,
+};
+
+/// A chunk compiled to a function, and the memory its code lives in.
+pub struct CompiledChunk {
+    module: Option<JITModule>,
+    function: extern "C" fn() -> f64,
+    result: Type,
+    instructions: u64,
+}
+
+impl CompiledChunk {
+    /// Runs the code, returning the value the chunk returns.
+    pub fn run(&self) -> Value {
+        let result = (self.function)();
+        match self.result {
+            Type::Number => Value::Number(result),
+            Type::Bool => Value::Bool(result != 0.0),
+            Type::Nil => Value::Nil,
+        }
+    }
+
+    /// How many instructions the vm would have run for the chunk.
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+}
+
+impl Drop for CompiledChunk {
+    fn drop(&mut self) {
+        if let Some(module) = self.module.take() {
+            // SAFETY: the function pointer into this memory is dropped along
+            // with self, so nothing can call it anymore
+            unsafe { module.free_memory() }
+        }
+    }
+}
+
+/// Compiles `chunk`, or returns `None` if it uses anything but numbers,
+/// booleans and nil, or would fail when run.
+pub fn compile(chunk: &Chunk) -> Option<CompiledChunk> {
+    let isa = codegen::native_isa(false)?;
+    let mut module = JITModule::new(JITBuilder::with_isa(isa, default_libcall_names()));
+
+    let mut context = module.make_context();
+    let (result, instructions) = codegen::translate(chunk, &mut context.func)?;
+
+    let id = module
+        .declare_function("chunk", Linkage::Local, &context.func.signature)
+        .ok()?;
+    module.define_function(id, &mut context).ok()?;
+    module.clear_context(&mut context);
+    module.finalize_definitions().ok()?;
+    let code = module.get_finalized_function(id);
+    // SAFETY: the function was declared above with no parameters and a
+    // single f64 result
+    let function = unsafe { std::mem::transmute::<*const u8, extern "C" fn() -> f64>(code) };
+    Some(CompiledChunk {
+        module: Some(module),
+        function,
+        result,
+        instructions,
+    })
+}
@@ -0,0 +1,22 @@
+#[cfg(feature = "aot")]
+pub mod aot;
+pub mod builder;
+pub mod chunk;
+#[cfg(any(feature = "jit", feature = "aot"))]
+mod codegen;
+pub mod compiler;
+pub mod coverage;
+pub mod debug;
+pub mod error;
+pub mod host;
+pub mod instruction;
+#[cfg(feature = "jit")]
+pub mod jit;
+pub mod sandbox;
+pub mod scanner;
+pub mod source_map;
+pub mod value;
+pub mod verifier;
+pub mod vm;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -1,42 +1,151 @@
-use error::InterpretError;
-use vm::VM;
+mod debug_prompt;
 
-mod chunk;
-mod compiler;
-#[cfg(any(feature = "debug_trace_execution", feature = "debug_print_code"))]
-mod debug;
-mod error;
-mod iterator;
-mod scanner;
-mod value;
-mod vm;
+#[cfg(any(feature = "aot", feature = "wasm"))]
+use bytecode_lox::error::BuildError;
+use bytecode_lox::{
+    compiler::Compiler,
+    coverage::Coverage,
+    error::InterpretError,
+    host::StdOutput,
+    source_map::SourceMap,
+    value::Objects,
+    vm::{debugger::Debugger, VM},
+};
+use debug_prompt::DebugPrompt;
 
-pub struct Lox {}
+pub struct Lox {
+    // division by zero, NaN and infinities are errors
+    strict_math: bool,
+    // runtime errors also dump the code around them and the stack
+    disassemble_on_error: bool,
+}
 
 fn handle_interpret_error(error: &InterpretError) {
+    eprintln!("{}", error);
     match error {
-        InterpretError::Compile(e) => {
-            eprintln!("{}", e);
-            std::process::exit(65);
-        }
-        InterpretError::Runtime(e) => {
-            eprintln!("{}", e);
-            std::process::exit(70);
+        InterpretError::Compile(_) => std::process::exit(65),
+        InterpretError::Runtime(_) => std::process::exit(70),
+    }
+}
+
+#[cfg(any(feature = "aot", feature = "wasm"))]
+fn handle_build_error(error: BuildError) -> ! {
+    eprintln!("{}", error);
+    match error {
+        BuildError::Compile(_) | BuildError::Unsupported | BuildError::Branching => {
+            std::process::exit(65)
         }
+        _ => std::process::exit(1),
     }
 }
 
 impl Lox {
-    pub fn run_file(path: &str) {
+    fn interpret(&self, source: String) -> Result<(), InterpretError> {
+        let objects = Objects::new();
+        let chunk = Compiler::compile(source, &objects).map_err(InterpretError::Compile)?;
+        let mut output = StdOutput;
+        let mut vm = VM::new(&chunk, objects, &mut output);
+        vm.set_strict_math(self.strict_math);
+        vm.set_disassemble_on_error(self.disassemble_on_error);
+        vm.run()
+    }
+
+    pub fn run_file(&self, path: &str) {
         let bytes = std::fs::read(path).unwrap();
-        let result = VM::interpret(String::from_utf8(bytes).unwrap());
+        let result = self.interpret(String::from_utf8(bytes).unwrap());
         if let Err(err) = result.as_ref() {
             handle_interpret_error(err);
         }
         result.unwrap();
     }
 
-    pub fn run_prompt() {
+    /// Runs the script one instruction at a time, pausing before the first.
+    pub fn debug_file(path: &str) {
+        let source = String::from_utf8(std::fs::read(path).unwrap()).unwrap();
+        let mut debugger = Debugger::new(DebugPrompt::new(&source)).with_stop_on_entry();
+        let result = VM::interpret_with_debugger(source, &mut StdOutput, &mut debugger);
+        if let Err(err) = result.as_ref() {
+            handle_interpret_error(err);
+        }
+    }
+
+    /// Runs the script counting how often each line runs, then prints the
+    /// source annotated with the counts to stderr, or with `lcov` writes them
+    /// to `lcov.info` instead.
+    pub fn run_with_coverage(path: &str, lcov: bool) {
+        let source = String::from_utf8(std::fs::read(path).unwrap()).unwrap();
+        let mut coverage = Coverage::new();
+        let result = VM::interpret_with_coverage(source.clone(), &mut StdOutput, &mut coverage);
+        if lcov {
+            std::fs::write("lcov.info", coverage.to_lcov(path)).unwrap();
+        } else {
+            eprint!("{}", coverage.annotate(&source));
+        }
+        if let Err(err) = result.as_ref() {
+            handle_interpret_error(err);
+        }
+    }
+
+    /// Runs the script, then writes where each instruction of its chunk came
+    /// from to `<script>.map`, as JSON.
+    pub fn run_with_source_map(path: &str) {
+        let source = String::from_utf8(std::fs::read(path).unwrap()).unwrap();
+        let mut source_map = SourceMap::new();
+        let result = VM::interpret_with_source_map(source, &mut StdOutput, &mut source_map);
+        std::fs::write(format!("{}.map", path), source_map.to_json(path)).unwrap();
+        if let Err(err) = result.as_ref() {
+            handle_interpret_error(err);
+        }
+    }
+
+    /// Runs the script, then prints how many objects it allocated to stderr.
+    /// The vm doesn't free any of them while it runs, so the final counts
+    /// are also the peak ones.
+    pub fn run_with_mem_stats(&self, path: &str) {
+        let source = String::from_utf8(std::fs::read(path).unwrap()).unwrap();
+        let objects = Objects::new();
+        let chunk = match Compiler::compile(source, &objects) {
+            Ok(chunk) => chunk,
+            Err(err) => return handle_interpret_error(&InterpretError::Compile(err)),
+        };
+        let mut output = StdOutput;
+        let mut vm = VM::new(&chunk, objects, &mut output);
+        vm.set_strict_math(self.strict_math);
+        let result = vm.run();
+        let stats = vm.heap_stats();
+        eprintln!("{:<14} {:>9} {:>9}", "", "peak", "final");
+        eprintln!(
+            "{:<14} {:>9} {:>9}",
+            "objects", stats.live_objects, stats.live_objects
+        );
+        let strings = vm.strings().len();
+        eprintln!("{:<14} {:>9} {:>9}", "strings", strings, strings);
+        eprintln!("{} bytes allocated", stats.bytes_allocated);
+        if let Err(err) = result.as_ref() {
+            handle_interpret_error(err);
+        }
+    }
+
+    /// Compiles the script into a standalone executable at `out`.
+    #[cfg(feature = "aot")]
+    pub fn build(path: &str, out: &str) {
+        let source = String::from_utf8(std::fs::read(path).unwrap()).unwrap();
+        if let Err(err) = bytecode_lox::aot::build(source, std::path::Path::new(out)) {
+            handle_build_error(err);
+        }
+    }
+
+    /// Compiles the script into a WebAssembly module at `out`, with the
+    /// runtime that runs it with node next to it.
+    #[cfg(feature = "wasm")]
+    pub fn build_wasm(path: &str, out: &str) {
+        let source = String::from_utf8(std::fs::read(path).unwrap()).unwrap();
+        if let Err(err) = bytecode_lox::wasm::build(source, std::path::Path::new(out)) {
+            handle_build_error(err);
+        }
+    }
+
+    pub fn run_prompt(&self) {
         let stdin = std::io::stdin();
         let mut stdout = std::io::stdout();
         use std::io::{BufRead, Write};
@@ -48,7 +157,7 @@ impl Lox {
             if reader.read_line(&mut line).unwrap() == 0 {
                 break;
             }
-            if let Err(error) = VM::interpret(line) {
+            if let Err(error) = self.interpret(line) {
                 handle_interpret_error(&error);
             }
         }
@@ -56,13 +165,49 @@ impl Lox {
 }
 
 fn main() {
-    let args = std::env::args().collect::<Vec<_>>();
-    if args.len() > 2 {
+    let mut args = std::env::args().collect::<Vec<_>>();
+    let strict_math = args.iter().any(|it| it == "--strict-math");
+    let disassemble_on_error = args.iter().any(|it| it == "--disassemble-on-error");
+    args.retain(|it| it != "--strict-math" && it != "--disassemble-on-error");
+    let lox = Lox {
+        strict_math,
+        disassemble_on_error,
+    };
+    if args.len() == 3 && args[1] == "--debug" {
+        Lox::debug_file(&args[2]);
+    } else if args.len() == 3 && args[1] == "--coverage" {
+        Lox::run_with_coverage(&args[2], false);
+    } else if args.len() == 3 && args[1] == "--coverage=lcov" {
+        Lox::run_with_coverage(&args[2], true);
+    } else if args.len() == 3 && args[1] == "--emit-source-map" {
+        Lox::run_with_source_map(&args[2]);
+    } else if args.len() == 3 && args[1] == "--mem-stats" {
+        lox.run_with_mem_stats(&args[2]);
+    } else if cfg!(feature = "aot") && args.len() == 5 && args[1] == "build" && args[3] == "-o" {
+        #[cfg(feature = "aot")]
+        Lox::build(&args[2], &args[4]);
+    } else if cfg!(feature = "wasm") && args.len() == 5 && args[1] == "wasm" && args[3] == "-o" {
+        #[cfg(feature = "wasm")]
+        Lox::build_wasm(&args[2], &args[4]);
+    } else if args.len() > 2 {
         println!("Usage: lox [script]");
+        println!("       lox --debug <script>");
+        println!("       lox --coverage[=lcov] <script>");
+        println!("       lox --emit-source-map <script>");
+        println!("       lox --mem-stats <script>");
+        if cfg!(feature = "aot") {
+            println!("       lox build <script> -o <out>");
+        }
+        if cfg!(feature = "wasm") {
+            println!("       lox wasm <script> -o <out.wasm>");
+        }
+        println!();
+        println!("--strict-math makes division by zero, NaN and infinities errors.");
+        println!("--disassemble-on-error shows the code around runtime errors and the stack.");
         std::process::exit(64);
     } else if args.len() == 2 {
-        Lox::run_file(&args[1]);
+        lox.run_file(&args[1]);
     } else {
-        Lox::run_prompt();
+        lox.run_prompt();
     }
 }
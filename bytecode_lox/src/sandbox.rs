@@ -0,0 +1,28 @@
+use std::fmt::Display;
+
+/// Limits for running untrusted scripts, see `VM::new_with_config`. The
+/// default configuration has no limits.
+///
+/// There is no builtin allowlist like the tree-walk interpreter has, the vm
+/// doesn't define any builtins yet.
+#[derive(Clone, Debug, Default)]
+pub struct SandboxConfig {
+    /// instructions executed in a single run
+    pub max_instructions: Option<u64>,
+    /// heap objects allocated at once
+    pub max_objects: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    Instructions(u64),
+    Objects(usize),
+}
+impl Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Limit::Instructions(max) => write!(f, "{} instructions", max),
+            Limit::Objects(max) => write!(f, "{} objects", max),
+        }
+    }
+}
@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 mod scanner;
 mod token;
 pub use scanner::Scanner;
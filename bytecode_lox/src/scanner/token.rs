@@ -1,60 +1,14 @@
-use lox_proc_macros::U8Enum;
+use std::borrow::Cow;
+
+pub use lox_frontend::TokenKind;
 
 #[derive(Clone, Debug)]
 pub struct Token<'scanner> {
     pub kind: TokenKind,
-    pub lexeme: &'scanner str,
+    /// the source of the token, except that identifiers are normalized to
+    /// NFC, and errors have their message
+    pub lexeme: Cow<'scanner, str>,
     pub line: usize,
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, U8Enum)]
-#[repr(u8)]
-pub enum TokenKind {
-    LeftParen,
-    RightParen,
-    LeftBrace,
-    RightBrace,
-    Comma,
-    Dot,
-    Minus,
-    Plus,
-    Semicolon,
-    Slash,
-    Star,
-
-    // One or two character tokens.
-    Bang,
-    BangEqual,
-    Equal,
-    EqualEqual,
-    Greater,
-    GreaterEqual,
-    Less,
-    LessEqual,
-
-    // Literals.
-    Identifier,
-    String,
-    Number,
-
-    // Keywords.
-    And,
-    Class,
-    Else,
-    False,
-    Fun,
-    For,
-    If,
-    Nil,
-    Or,
-    Print,
-    Return,
-    Super,
-    This,
-    True,
-    Var,
-    While,
-
-    Error,
-    Eof,
+    /// counted in grapheme clusters from 1
+    pub column: usize,
 }
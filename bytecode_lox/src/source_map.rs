@@ -0,0 +1,91 @@
+//! Source maps for `lox --emit-source-map`, which say where in the script
+//! each instruction of its chunk was compiled from, so tools that only see
+//! the vm's offsets can point at the source.
+
+use std::ops::Range;
+
+use crate::chunk::Chunk;
+
+/// Where the instruction at `offset`, which takes `length` bytes, was
+/// compiled from. Columns count grapheme clusters from 1, like the
+/// scanner's, and the end is excluded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mapping {
+    pub offset: usize,
+    pub length: usize,
+    pub line: u32,
+    pub columns: Range<u32>,
+}
+
+/// The mappings of a chunk's instructions, in order. Fill it with
+/// `VM::interpret_with_source_map`.
+#[derive(Default)]
+pub struct SourceMap {
+    mappings: Vec<Mapping>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn record(&mut self, chunk: &Chunk) {
+        let mut offset = 0;
+        while offset < chunk.code.len() {
+            let length = chunk.instruction_len(offset).unwrap_or(1);
+            self.mappings.push(Mapping {
+                offset,
+                length,
+                line: chunk.get_line(offset),
+                columns: chunk.get_columns(offset),
+            });
+            offset += length;
+        }
+    }
+
+    pub fn mappings(&self) -> &[Mapping] {
+        &self.mappings
+    }
+
+    /// The mappings as JSON, for the script at `path`.
+    pub fn to_json(&self, path: &str) -> String {
+        let mut json = format!(
+            "{{\n  \"version\": 1,\n  \"file\": {},\n",
+            json_string(path)
+        );
+        json.push_str("  \"mappings\": [");
+        for (i, mapping) in self.mappings.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            json.push_str(&format!(
+                "\n    {{ \"offset\": {}, \"length\": {}, \"line\": {}, \"column\": {}, \"endColumn\": {} }}",
+                mapping.offset,
+                mapping.length,
+                mapping.line,
+                mapping.columns.start,
+                mapping.columns.end
+            ));
+        }
+        if !self.mappings.is_empty() {
+            json.push_str("\n  ");
+        }
+        json.push_str("]\n}\n");
+        json
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
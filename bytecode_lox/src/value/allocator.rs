@@ -0,0 +1,34 @@
+use std::alloc::Layout;
+
+/// Where `Objects` gets the memory for the objects it allocates, so an
+/// embedder can give it an arena or one that tracks what it hands out.
+///
+/// # Safety
+///
+/// The same as `std::alloc::GlobalAlloc`: `alloc` returns memory that fits
+/// `layout`, or null when there isn't any, and memory stays valid until it's
+/// given to `dealloc` with the same layout. Layouts are never zero-sized.
+pub unsafe trait Allocator {
+    /// # Safety
+    ///
+    /// `layout` isn't zero-sized.
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8;
+    /// # Safety
+    ///
+    /// `pointer` came from `alloc` with the same `layout`, and isn't used
+    /// after.
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout);
+}
+
+/// The global allocator, which `Objects::new` uses.
+pub struct Global;
+
+unsafe impl Allocator for Global {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        std::alloc::alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        std::alloc::dealloc(pointer, layout)
+    }
+}
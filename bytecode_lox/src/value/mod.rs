@@ -1,4 +1,7 @@
+mod allocator;
 mod obj;
+#[allow(clippy::module_inception)]
 mod value;
-pub use obj::{Obj, Objects};
+pub use allocator::{Allocator, Global};
+pub use obj::{HeapStats, Obj, Objects};
 pub use value::Value;
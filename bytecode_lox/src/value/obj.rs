@@ -1,33 +1,138 @@
-use std::{cell::Cell, fmt::Display, mem, u8};
+use std::{
+    alloc::{handle_alloc_error, Layout},
+    cell::Cell,
+    fmt::Display,
+};
+
+use super::allocator::{Allocator, Global};
+
+// strings up to this long keep their characters in the object instead of
+// allocating them separately, which is most identifiers and literals. the
+// length takes another byte, so that's as big as a pointer to them and the
+// enum's tag
+const INLINE_MAX: usize = 22;
+
+// concatenations up to this long are copied into a flat string, a rope of
+// short strings costs more than copying them
+const FLAT_CONCAT_MAX: usize = 64;
+
+/// How much `Objects` has allocated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HeapStats {
+    /// objects that haven't been freed
+    pub live_objects: usize,
+    /// objects allocated so far, freed or not
+    pub allocations: usize,
+    /// bytes allocated so far, for the objects and what they point to
+    pub bytes_allocated: usize,
+}
 
 pub struct Objects {
     first: Cell<Option<Obj>>,
+    stats: Cell<HeapStats>,
+    allocator: Box<dyn Allocator>,
+}
+
+impl Default for Objects {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Objects {
     pub fn new() -> Self {
+        Self::with_allocator(Box::new(Global))
+    }
+
+    /// Objects whose memory comes from `allocator`.
+    pub fn with_allocator(allocator: Box<dyn Allocator>) -> Self {
         Self {
-            first: Cell::new(None)
+            first: Cell::new(None),
+            stats: Cell::new(HeapStats::default()),
+            allocator,
+        }
+    }
+
+    /// Number of objects allocated so far.
+    pub fn len(&self) -> usize {
+        self.stats.get().allocations
+    }
+
+    pub fn stats(&self) -> HeapStats {
+        self.stats.get()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// A copy of every string allocated so far, oldest first.
+    pub fn strings(&self) -> Vec<String> {
+        let mut strings = vec![];
+        let mut object = self.first.get();
+        while let Some(obj) = object {
+            if obj.is_string() {
+                strings.push(obj.to_string());
+            }
+            object = unsafe { (*obj.0).next };
         }
+        strings.reverse();
+        strings
     }
 
     pub fn string(&self, s: &str) -> Obj {
-        let obj = Obj::string(s, self.first.get());
-        self.first.set(Some(obj));
+        let obj = unsafe { Obj::string(s, self.first.get(), &*self.allocator) };
+        let heap_bytes = if s.len() > INLINE_MAX { s.len() } else { 0 };
+        self.allocated(obj, std::mem::size_of::<StringObj>() + heap_bytes);
+        obj
+    }
+
+    /// The strings `a` and `b` one after the other. Long results are ropes
+    /// that point at both, so building a string piece by piece doesn't copy
+    /// what's already there each time.
+    pub fn concat(&self, a: Obj, b: Obj) -> Obj {
+        debug_assert!(a.is_string() && b.is_string());
+        let len = a.string_len() + b.string_len();
+        if len <= FLAT_CONCAT_MAX {
+            return self.string(&format!("{}{}", a, b));
+        }
+        let obj = unsafe { Obj::rope(a, b, len, self.first.get(), &*self.allocator) };
+        self.allocated(obj, std::mem::size_of::<RopeObj>());
         obj
     }
+
+    // adds a new object, which took `bytes`, to the list and the stats
+    fn allocated(&self, obj: Obj, bytes: usize) {
+        self.first.set(Some(obj));
+        let stats = self.stats.get();
+        self.stats.set(HeapStats {
+            live_objects: stats.live_objects + 1,
+            allocations: stats.allocations + 1,
+            bytes_allocated: stats.bytes_allocated + bytes,
+        });
+    }
+
+    // memory for `layout` from the allocator, which can't be zero-sized
+    unsafe fn allocate(allocator: &dyn Allocator, layout: Layout) -> *mut u8 {
+        let pointer = allocator.alloc(layout);
+        if pointer.is_null() {
+            handle_alloc_error(layout);
+        }
+        pointer
+    }
 }
 
-unsafe fn drop_obj(obj: Obj) {
+unsafe fn drop_obj(obj: Obj, allocator: &dyn Allocator) {
     match (*obj.0).kind {
         ObjKind::String => {
-            let obj_pointer: *mut StringObj = mem::transmute(obj.0);
-            let obj_box = Box::from_raw(obj_pointer);
-            let slice_pointer: *mut [u8] = mem::transmute(obj_box.chars);
-            let slice_box = Box::from_raw(slice_pointer);
-            drop(slice_box);
-            drop(obj_box);
+            let string = obj.0 as *mut StringObj;
+            if let Chars::Heap(chars) = (*string).chars {
+                allocator.dealloc(chars as *mut u8, Layout::for_value(&*chars));
+            }
+            allocator.dealloc(string as *mut u8, Layout::new::<StringObj>());
         }
+        // the strings it points at are objects of their own
+        ObjKind::Rope => allocator.dealloc(obj.0 as *mut u8, Layout::new::<RopeObj>()),
     }
 }
 
@@ -35,14 +140,10 @@ impl Drop for Objects {
     fn drop(&mut self) {
         unsafe {
             let mut object = self.first.get();
-            loop {
-                if let Some(obj) = object {
-                    let next = (*obj.0).next;
-                    drop_obj(obj);
-                    object = next;
-                } else { 
-                    break
-                }
+            while let Some(obj) = object {
+                let next = (*obj.0).next;
+                drop_obj(obj, &*self.allocator);
+                object = next;
             }
         }
     }
@@ -53,64 +154,157 @@ impl Drop for Objects {
 pub struct Obj(*mut BaseObj);
 
 impl Obj {
-    fn string(s: &str, next: Option<Obj>) -> Self {
+    unsafe fn string(s: &str, next: Option<Obj>, allocator: &dyn Allocator) -> Self {
+        assert!(s.len() < u32::MAX as usize);
+
+        let chars = if s.len() <= INLINE_MAX {
+            let mut bytes = [0; INLINE_MAX];
+            bytes[..s.len()].copy_from_slice(s.as_bytes());
+            Chars::Inline {
+                len: s.len() as u8,
+                bytes,
+            }
+        } else {
+            let bytes = Objects::allocate(allocator, Layout::for_value(s.as_bytes()));
+            std::ptr::copy_nonoverlapping(s.as_ptr(), bytes, s.len());
+            Chars::Heap(std::ptr::slice_from_raw_parts(bytes, s.len()))
+        };
+
+        let obj = StringObj {
+            base: BaseObj {
+                kind: ObjKind::String,
+                next,
+            },
+            chars,
+            hash: hash_bytes(FNV_OFFSET_BASIS, s.as_bytes()),
+        };
+        let obj_pointer =
+            Objects::allocate(allocator, Layout::new::<StringObj>()) as *mut StringObj;
+        obj_pointer.write(obj);
+
+        Obj(obj_pointer as *mut BaseObj)
+    }
+
+    unsafe fn rope(
+        left: Obj,
+        right: Obj,
+        len: usize,
+        next: Option<Obj>,
+        allocator: &dyn Allocator,
+    ) -> Self {
+        let obj = RopeObj {
+            base: BaseObj {
+                kind: ObjKind::Rope,
+                next,
+            },
+            left,
+            right,
+            len,
+            hash: Cell::new(None),
+        };
+        let obj_pointer = Objects::allocate(allocator, Layout::new::<RopeObj>()) as *mut RopeObj;
+        obj_pointer.write(obj);
+
+        Obj(obj_pointer as *mut BaseObj)
+    }
+
+    /// Whether it's a string, flat or a rope.
+    pub fn is_string(&self) -> bool {
+        matches!(unsafe { (*self.0).kind }, ObjKind::String | ObjKind::Rope)
+    }
+
+    // the length in bytes of a string, flat or a rope
+    fn string_len(&self) -> usize {
         unsafe {
-            assert!(s.len() < u32::MAX as usize);
-
-            let byte_pointer = s.as_bytes().to_owned().into_boxed_slice();
-            let byte_pointer = Box::into_raw(byte_pointer);
-
-            let obj = StringObj {
-                base: BaseObj {
-                    kind: ObjKind::String,
-                    next,
-                },
-                chars: byte_pointer,
-            };
-            let obj_pointer = Box::new(obj);
-            let obj_pointer = Box::into_raw(obj_pointer);
-
-            Obj(mem::transmute(obj_pointer))
+            match (*self.0).kind {
+                ObjKind::String => (*self.0.cast::<StringObj>()).as_str().len(),
+                ObjKind::Rope => (*self.0.cast::<RopeObj>()).len,
+            }
         }
     }
 
+    // calls `f` with each flat piece of a string, in order. ropes can be as
+    // deep as they're long, so they're walked with a stack, not recursion
+    fn for_each_piece(&self, mut f: impl FnMut(&str) -> std::fmt::Result) -> std::fmt::Result {
+        let mut pending = vec![*self];
+        while let Some(obj) = pending.pop() {
+            unsafe {
+                match (*obj.0).kind {
+                    ObjKind::String => f((*obj.0.cast::<StringObj>()).as_str())?,
+                    ObjKind::Rope => {
+                        let rope = &*obj.0.cast::<RopeObj>();
+                        pending.push(rope.right);
+                        pending.push(rope.left);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The FNV-1a hash of a string's bytes, which is worked out once: when
+    /// a flat string is made, and the first time a rope's is asked for.
+    pub fn hash(&self) -> u32 {
+        unsafe {
+            match (*self.0).kind {
+                ObjKind::String => (*self.0.cast::<StringObj>()).hash,
+                ObjKind::Rope => {
+                    let rope = &*self.0.cast::<RopeObj>();
+                    if let Some(hash) = rope.hash.get() {
+                        return hash;
+                    }
+                    let mut hash = FNV_OFFSET_BASIS;
+                    let _ = self.for_each_piece(|piece| {
+                        hash = hash_bytes(hash, piece.as_bytes());
+                        Ok(())
+                    });
+                    rope.hash.set(Some(hash));
+                    hash
+                }
+            }
+        }
+    }
+
+    /// The characters of a flat string. `None` for ropes, which have to be
+    /// formatted to get theirs.
     pub fn as_string(&self) -> Option<&str> {
         unsafe {
-            let base: &BaseObj = mem::transmute(self.0);
+            let base = &*self.0;
             if base.kind == ObjKind::String {
-                let string: &StringObj = mem::transmute(self.0);
+                let string = &*(self.0 as *const StringObj);
                 Some(string.as_str())
             } else {
                 None
             }
         }
     }
+
+    pub fn type_name(&self) -> &'static str {
+        match unsafe { (*self.0).kind } {
+            ObjKind::String | ObjKind::Rope => "string",
+        }
+    }
 }
 
 impl Display for Obj {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        unsafe {
-            let base: &BaseObj = mem::transmute(self.0);
-            match base.kind {
-                ObjKind::String => {
-                    let string: &StringObj = mem::transmute(self.0);
-                    write!(f, "{}", string.as_str())
-                }
-            }
-        }
+        self.for_each_piece(|piece| f.write_str(piece))
     }
 }
 
 impl PartialEq for Obj {
     fn eq(&self, other: &Self) -> bool {
         unsafe {
-            let base_self: &BaseObj = mem::transmute(self.0);
-            let other_self: &BaseObj = mem::transmute(other.0);
+            let base_self = &*self.0;
+            let other_self = &*other.0;
             match (&base_self.kind, &other_self.kind) {
                 (ObjKind::String, ObjKind::String) => {
-                    let a: &StringObj = mem::transmute(self.0);
-                    let b: &StringObj = mem::transmute(other.0);
-                    a.as_str() == b.as_str()
+                    let a = &*(self.0 as *const StringObj);
+                    let b = &*(other.0 as *const StringObj);
+                    a.hash == b.hash && a.as_str() == b.as_str()
+                }
+                _ => {
+                    self.string_len() == other.string_len() && self.to_string() == other.to_string()
                 }
             }
         }
@@ -121,6 +315,7 @@ impl PartialEq for Obj {
 #[repr(C)]
 enum ObjKind {
     String,
+    Rope,
 }
 
 #[repr(C)]
@@ -132,16 +327,48 @@ struct BaseObj {
 #[repr(C)]
 struct StringObj {
     base: BaseObj,
-    chars: *const [u8],
+    chars: Chars,
+    hash: u32,
+}
+
+enum Chars {
+    Inline { len: u8, bytes: [u8; INLINE_MAX] },
+    Heap(*const [u8]),
+}
+
+#[repr(C)]
+struct RopeObj {
+    base: BaseObj,
+    left: Obj,
+    right: Obj,
+    len: usize,
+    // only worked out when it's needed, it takes going through every piece
+    hash: Cell<Option<u32>>,
+}
+
+const FNV_OFFSET_BASIS: u32 = 2166136261;
+const FNV_PRIME: u32 = 16777619;
+
+// `hash` carried on through `bytes`, so a string's hash can be worked out
+// a piece at a time
+fn hash_bytes(mut hash: u32, bytes: &[u8]) -> u32 {
+    for &byte in bytes {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
 }
 
 impl StringObj {
     unsafe fn as_str(&self) -> &str {
-        std::str::from_utf8_unchecked(mem::transmute(self.chars))
+        let bytes = match &self.chars {
+            Chars::Inline { len, bytes } => &bytes[..*len as usize],
+            Chars::Heap(chars) => &**chars,
+        };
+        std::str::from_utf8_unchecked(bytes)
     }
 }
 
-
 // TODO!
 // Each string requires two separate dynamic allocations—one for the ObjString
 // and a second for the character array. Accessing the characters from a value
@@ -154,9 +381,9 @@ impl StringObj {
 // When we create the ObjString for each string literal, we copy the characters
 // onto the heap. That way, when the string is later freed, we know it is safe
 // to free the characters too.
-// 
+//
 // This is a simpler approach but wastes some memory, which might be a problem
-// on very constrained devices. Instead, we could keep track of which ObjStrings 
-// own their character array and which are “constant strings” that just point 
-// back to the original source string or some other non-freeable location. Add 
+// on very constrained devices. Instead, we could keep track of which ObjStrings
+// own their character array and which are “constant strings” that just point
+// back to the original source string or some other non-freeable location. Add
 // support for this.
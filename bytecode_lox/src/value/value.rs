@@ -1,20 +1,15 @@
 use super::Obj;
 use std::fmt::Display;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub enum Value {
     Bool(bool),
+    #[default]
     Nil,
     Number(f64),
     Obj(Obj),
 }
 
-impl Default for Value {
-    fn default() -> Self {
-        Value::Nil
-    }
-}
-
 impl Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use Value::*;
@@ -47,4 +42,23 @@ impl Value {
             _ => None,
         }
     }
+
+    /// The object of a string, flat or a rope.
+    pub fn as_string_obj(&self) -> Option<Obj> {
+        match self {
+            Value::Obj(x) if x.is_string() => Some(*x),
+            _ => None,
+        }
+    }
+
+    /// Name of the value's type, as the tree-walk interpreter's `type()`
+    /// native reports it.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Bool(_) => "bool",
+            Value::Nil => "nil",
+            Value::Number(_) => "number",
+            Value::Obj(x) => x.type_name(),
+        }
+    }
 }
@@ -0,0 +1,108 @@
+//! Checks chunks before they're run, so the vm can trust every opcode it
+//! reads to be valid and followed by its operands. The instructions are read
+//! with `Instruction::decode`, so new opcodes are checked too. Jumps, and
+//! every entry of a jump table, have to land on an instruction, and every
+//! instruction has to find the values it pops on the stack.
+
+use crate::{chunk::Chunk, error::VerifyError, instruction::Instruction, vm::STACK_MAX};
+
+/// Checks that every byte of `chunk` is an opcode or one of its operands,
+/// that constants exist, that jumps land on an opcode, that the stack
+/// neither runs out of values nor overflows, and that it ends by returning.
+pub fn verify(chunk: &Chunk) -> Result<(), VerifyError> {
+    let mut instructions = vec![];
+    let mut jumps = vec![];
+    for instruction in chunk.instructions() {
+        let (offset, instruction) = instruction?;
+        if let Instruction::Constant(index) = instruction {
+            if index as usize >= chunk.constants.len() {
+                return Err(VerifyError::InvalidConstant(offset, index));
+            }
+        }
+        for target in instruction.jump_targets(offset) {
+            jumps.push((offset, target));
+        }
+        instructions.push((offset, instruction));
+    }
+    // offsets are in order, so a target is a start if the search finds it
+    for (offset, target) in jumps {
+        if instructions
+            .binary_search_by_key(&target, |(start, _)| *start)
+            .is_err()
+        {
+            return Err(VerifyError::InvalidJump(offset, target));
+        }
+    }
+    match instructions.last() {
+        Some((_, Instruction::Return)) => {}
+        _ => return Err(VerifyError::MissingReturn),
+    }
+    check_stack(chunk, &instructions)
+}
+
+// jumps only go forward, so by the time an instruction is checked every
+// path to it has been, and the height of the stack there is known. code
+// that nothing reaches can't run, so it isn't checked
+fn check_stack(chunk: &Chunk, instructions: &[(usize, Instruction)]) -> Result<(), VerifyError> {
+    let mut heights = vec![None; chunk.code.len()];
+    heights[0] = Some(0);
+    for (offset, instruction) in instructions {
+        let height = match heights[*offset] {
+            Some(height) => height,
+            None => continue,
+        };
+        let (pops, pushes) = stack_effect(instruction);
+        if height < pops {
+            return Err(VerifyError::StackUnderflow(*offset));
+        }
+        let after = height - pops + pushes;
+        if after > STACK_MAX {
+            return Err(VerifyError::StackOverflow(*offset));
+        }
+        let mut next = instruction.jump_targets(*offset);
+        if !matches!(
+            instruction,
+            Instruction::Jump(_) | Instruction::JumpTable { .. } | Instruction::Return
+        ) {
+            next.push(offset + instruction.size());
+        }
+        for target in next {
+            match heights[target] {
+                None => heights[target] = Some(after),
+                Some(height) if height != after => {
+                    return Err(VerifyError::UnbalancedStack(target))
+                }
+                Some(_) => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+// how many values the instruction pops, and then how many it pushes
+fn stack_effect(instruction: &Instruction) -> (usize, usize) {
+    match instruction {
+        Instruction::Constant(_) | Instruction::Nil | Instruction::True | Instruction::False => {
+            (0, 1)
+        }
+        Instruction::Equal
+        | Instruction::NotEqual
+        | Instruction::Greater
+        | Instruction::GreaterEqual
+        | Instruction::Less
+        | Instruction::LessEqual
+        | Instruction::Add
+        | Instruction::Subtract
+        | Instruction::Multiply
+        | Instruction::Divide => (2, 1),
+        Instruction::Not | Instruction::Negate => (1, 1),
+        Instruction::Dup => (1, 2),
+        Instruction::Swap => (2, 2),
+        Instruction::Pop => (1, 0),
+        Instruction::Jump(_) => (0, 0),
+        // the condition stays on the stack
+        Instruction::JumpIfFalse(_) => (1, 1),
+        Instruction::JumpTable { .. } => (1, 0),
+        Instruction::Return => (1, 0),
+    }
+}
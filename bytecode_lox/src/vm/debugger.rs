@@ -0,0 +1,139 @@
+//! Pausing the vm between instructions. Unlike the `debug_trace_execution`
+//! feature this is chosen at runtime: a `Debugger` given to
+//! `VM::interpret_with_debugger` stops at breakpoints and while stepping, and
+//! hands control to a `DebugClient`.
+
+use std::collections::BTreeSet;
+
+use crate::{chunk::Chunk, value::Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Breakpoint {
+    /// pause before the instruction at this offset
+    Offset(usize),
+    /// pause wherever execution enters this source line
+    Line(u32),
+}
+
+/// How to go on after a pause.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume {
+    /// run until the next breakpoint
+    Continue,
+    /// run a single instruction
+    Step,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseReason {
+    /// the vm is about to run its first instruction
+    Entry,
+    Breakpoint,
+    Step,
+}
+
+pub trait DebugClient {
+    /// Called when the vm pauses. Doesn't return until it should go on.
+    fn paused(&mut self, session: &mut PausedVm<'_>) -> Resume;
+}
+
+/// What a client can look at and change while the vm is paused.
+pub struct PausedVm<'a> {
+    chunk: &'a Chunk,
+    ip: usize,
+    stack: &'a [Value],
+    breakpoints: &'a mut BTreeSet<Breakpoint>,
+    reason: PauseReason,
+}
+
+impl PausedVm<'_> {
+    /// The chunk of the current call frame.
+    pub fn chunk(&self) -> &Chunk {
+        self.chunk
+    }
+
+    /// Offset of the instruction that runs next.
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    /// Source line of the instruction that runs next.
+    pub fn line(&self) -> u32 {
+        self.chunk.get_line(self.ip)
+    }
+
+    /// The value stack, bottom first.
+    pub fn stack(&self) -> &[Value] {
+        self.stack
+    }
+
+    pub fn reason(&self) -> PauseReason {
+        self.reason
+    }
+
+    pub fn breakpoints(&mut self) -> &mut BTreeSet<Breakpoint> {
+        self.breakpoints
+    }
+}
+
+pub struct Debugger {
+    client: Box<dyn DebugClient>,
+    breakpoints: BTreeSet<Breakpoint>,
+    stepping: bool,
+    stop_on_entry: bool,
+}
+
+impl Debugger {
+    pub fn new(client: impl DebugClient + 'static) -> Self {
+        Self {
+            client: Box::new(client),
+            breakpoints: BTreeSet::new(),
+            stepping: false,
+            stop_on_entry: false,
+        }
+    }
+
+    /// Pauses before the first instruction.
+    pub fn with_stop_on_entry(mut self) -> Self {
+        self.stop_on_entry = true;
+        self
+    }
+
+    pub fn breakpoints(&mut self) -> &mut BTreeSet<Breakpoint> {
+        &mut self.breakpoints
+    }
+
+    fn should_pause(&mut self, chunk: &Chunk, ip: usize) -> Option<PauseReason> {
+        if std::mem::take(&mut self.stop_on_entry) {
+            return Some(PauseReason::Entry);
+        }
+        if self.breakpoints.contains(&Breakpoint::Offset(ip)) {
+            return Some(PauseReason::Breakpoint);
+        }
+        let line = Breakpoint::Line(chunk.get_line(ip));
+        if self.breakpoints.contains(&line) && chunk.starts_line(ip) {
+            return Some(PauseReason::Breakpoint);
+        }
+        if self.stepping {
+            Some(PauseReason::Step)
+        } else {
+            None
+        }
+    }
+
+    /// Called by the vm before it runs the instruction at `ip`.
+    pub(crate) fn before_instruction(&mut self, chunk: &Chunk, ip: usize, stack: &[Value]) {
+        let reason = match self.should_pause(chunk, ip) {
+            Some(reason) => reason,
+            None => return,
+        };
+        let mut session = PausedVm {
+            chunk,
+            ip,
+            stack,
+            breakpoints: &mut self.breakpoints,
+            reason,
+        };
+        self.stepping = self.client.paused(&mut session) == Resume::Step;
+    }
+}
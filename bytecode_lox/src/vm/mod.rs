@@ -1,2 +1,5 @@
+pub mod debugger;
+#[allow(clippy::module_inception)]
 mod vm;
-pub use vm::VM;
+pub(crate) use vm::STACK_MAX;
+pub use vm::{Frame, VM};
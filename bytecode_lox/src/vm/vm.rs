@@ -1,34 +1,137 @@
-use std::{usize, vec};
-
 use crate::{
     chunk::{Chunk, OpCode},
     compiler::Compiler,
+    coverage::Coverage,
+    debug::format_instruction,
     error::{InterpretError, RuntimeError},
-    value::{Obj, Objects, Value},
+    host::Output,
+    sandbox::{Limit, SandboxConfig},
+    source_map::SourceMap,
+    value::{HeapStats, Objects, Value},
 };
 
+use super::debugger::Debugger;
+
 #[cfg(feature = "debug_trace_execution")]
 use crate::debug::disassemble_instruction;
 
-const STACK_MAX: usize = 256;
+/// A call frame, as `VM::frames` reports it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub name: String,
+    /// offset of the instruction that runs next
+    pub ip: usize,
+    pub line: u32,
+}
+
+pub(crate) const STACK_MAX: usize = 256;
+// how many instructions to show on each side of the one that failed
+const ERROR_CONTEXT: usize = 3;
+
 pub struct VM<'chunk> {
     stack: Vec<Value>,
     chunk: &'chunk Chunk,
     ip: usize,
 
     objects: Objects,
+    output: &'chunk mut dyn Output,
+    config: SandboxConfig,
+    instructions: u64,
+    debugger: Option<&'chunk mut Debugger>,
+    // how often the instruction at each offset ran, when collecting coverage
+    hits: Option<Vec<u64>>,
+    strict_math: bool,
+    disassemble_on_error: bool,
 }
 
 impl<'chunk> VM<'chunk> {
-    pub fn new(chunk: &'chunk Chunk, objects: Objects) -> Self {
+    pub fn new(chunk: &'chunk Chunk, objects: Objects, output: &'chunk mut dyn Output) -> Self {
+        Self::new_with_config(chunk, objects, output, SandboxConfig::default())
+    }
+
+    pub fn new_with_config(
+        chunk: &'chunk Chunk,
+        objects: Objects,
+        output: &'chunk mut dyn Output,
+        config: SandboxConfig,
+    ) -> Self {
         VM {
             chunk,
             ip: 0,
             stack: vec![],
             objects,
+            output,
+            config,
+            instructions: 0,
+            debugger: None,
+            hits: None,
+            strict_math: false,
+            disassemble_on_error: false,
         }
     }
 
+    /// Makes dividing by zero, and arithmetic that results in NaN or an
+    /// infinity, runtime errors instead of IEEE values.
+    pub fn set_strict_math(&mut self, strict_math: bool) {
+        self.strict_math = strict_math;
+    }
+
+    /// Writes the instructions around the one that fails, and the stack, to
+    /// stderr when running into a runtime error.
+    pub fn set_disassemble_on_error(&mut self, disassemble_on_error: bool) {
+        self.disassemble_on_error = disassemble_on_error;
+    }
+
+    /// Pauses at the debugger's breakpoints while running.
+    pub fn set_debugger(&mut self, debugger: Option<&'chunk mut Debugger>) {
+        self.debugger = debugger;
+    }
+
+    /// The value stack, bottom first.
+    pub fn stack(&self) -> &[Value] {
+        &self.stack
+    }
+
+    /// The chunk being run.
+    pub fn chunk(&self) -> &Chunk {
+        self.chunk
+    }
+
+    /// Offset of the instruction that runs next.
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    /// The call frames, innermost first. Scripts don't call functions yet,
+    /// so that's only the script's.
+    pub fn frames(&self) -> Vec<Frame> {
+        vec![Frame {
+            name: "<script>".to_string(),
+            ip: self.ip,
+            line: self.chunk.get_line(self.ip),
+        }]
+    }
+
+    /// Instructions run so far, counting those run as native code.
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    /// How much the vm has allocated.
+    pub fn heap_stats(&self) -> HeapStats {
+        self.objects.stats()
+    }
+
+    /// Objects allocated for each instruction run so far.
+    pub fn allocation_rate(&self) -> f64 {
+        self.objects.stats().allocations as f64 / self.instructions.max(1) as f64
+    }
+
+    /// A copy of every string the vm has allocated, oldest first.
+    pub fn strings(&self) -> Vec<String> {
+        self.objects.strings()
+    }
+
     fn _reset_stack(&mut self) {
         self.stack.clear();
     }
@@ -41,11 +144,87 @@ impl<'chunk> VM<'chunk> {
     fn pop(&mut self) -> Value {
         self.stack.pop().unwrap_or_default()
     }
-    fn peek(&mut self, distance: usize) -> Value {
-        self.stack[self.stack.len() - 1 - distance].clone()
+    fn peek(&self, distance: usize) -> &Value {
+        &self.stack[self.stack.len() - 1 - distance]
+    }
+    // instructions that only look at the value on top of the stack leave
+    // their result in its place, instead of popping it and pushing that
+    fn replace_top(&mut self, value: Value) {
+        if let Some(top) = self.stack.last_mut() {
+            *top = value;
+        }
+    }
+
+    // the result of the arithmetic `instruction`, unless strict math
+    // rejects it
+    fn check_math(
+        &self,
+        instruction: OpCode,
+        right: f64,
+        result: f64,
+    ) -> Result<f64, RuntimeError> {
+        if !self.strict_math {
+            return Ok(result);
+        }
+        let line = self.chunk.get_line(self.ip - 1);
+        if matches!(instruction, OpCode::Divide) && right == 0.0 {
+            return Err(RuntimeError::DivisionByZero(line));
+        }
+        if !result.is_finite() {
+            let operator = match instruction {
+                OpCode::Add => "+",
+                OpCode::Multiply => "*",
+                OpCode::Divide => "/",
+                _ => "-",
+            };
+            return Err(RuntimeError::NotFinite(line, operator, result));
+        }
+        Ok(result)
+    }
+
+    /// Runs the chunk, printing what it evaluates to.
+    pub fn run(&mut self) -> Result<(), InterpretError> {
+        let result = self.execute();
+        if result.is_err() && self.disassemble_on_error {
+            eprint!("{}", self.error_context());
+        }
+        result
+    }
+
+    // the instructions around the last one read, which is the one that
+    // failed, then the stack. scripts are the only frame there is
+    fn error_context(&self) -> String {
+        let mut starts = vec![];
+        let mut offset = 0;
+        while offset < self.chunk.code.len() {
+            starts.push(offset);
+            offset += self.chunk.instruction_len(offset).unwrap_or(1);
+        }
+        let failed = starts.iter().rposition(|&it| it < self.ip).unwrap_or(0);
+        let window =
+            failed.saturating_sub(ERROR_CONTEXT)..starts.len().min(failed + ERROR_CONTEXT + 1);
+
+        let mut text = String::from("== runtime error in <script> ==\n");
+        for i in window {
+            let marker = if i == failed { ">" } else { " " };
+            let (instruction, _) = format_instruction(self.chunk, starts[i]);
+            text.push_str(&format!("{} {}\n", marker, instruction));
+        }
+        text.push_str("stack: ");
+        for value in &self.stack {
+            text.push_str(&format!("[ {} ]", value));
+        }
+        text.push('\n');
+        text
     }
 
-    fn run(&mut self) -> Result<(), InterpretError> {
+    fn execute(&mut self) -> Result<(), InterpretError> {
+        #[cfg(feature = "jit")]
+        if let Some(value) = self.run_compiled() {
+            self.output.print(&value.to_string());
+            return Ok(());
+        }
+
         loop {
             #[cfg(feature = "debug_trace_execution")]
             {
@@ -57,43 +236,74 @@ impl<'chunk> VM<'chunk> {
                 disassemble_instruction(self.chunk, self.ip); // TODO! VERY slow!! makes the loop O(n^2)!
             }
 
+            if let Some(hits) = self.hits.as_mut() {
+                hits[self.ip] += 1;
+            }
+            if let Some(debugger) = self.debugger.as_mut() {
+                debugger.before_instruction(self.chunk, self.ip, &self.stack);
+            }
+
             macro_rules! read_byte {
                 () => {{
                     self.ip += 1;
                     self.chunk.code[self.ip - 1]
                 }};
             }
+            macro_rules! read_short {
+                () => {{
+                    self.ip += 2;
+                    u16::from_be_bytes([self.chunk.code[self.ip - 2], self.chunk.code[self.ip - 1]])
+                }};
+            }
             macro_rules! read_constant {
                 () => {
-                    self.chunk.constants[read_byte!() as usize].clone()
+                    self.chunk.constants[read_byte!() as usize]
                 };
             }
-            macro_rules! binary_op {
-                ($wrap:ident, $op:tt) => {{
-                    match (self.peek(0), self.peek(1)) {
-                        (Value::Number(a), Value::Number(b)) => {
-                            self.pop();
+            // line of the instruction currently being executed
+            macro_rules! current_line {
+                () => {
+                    self.chunk.get_line(self.ip - 1)
+                };
+            }
+            macro_rules! arithmetic_op {
+                ($instruction:expr, $op:tt) => {{
+                    match (self.peek(1), self.peek(0)) {
+                        (&Value::Number(a), &Value::Number(b)) => {
+                            let result = self.check_math($instruction, b, a $op b)?;
                             self.pop();
-                            self.push(Value::$wrap(a $op b));
-                        }
-                        (_a, _b) => {
-                            runtime_error!("Operands must be numbers.");
-                            return Err(RuntimeError::OperandMustBeNumber("idk".to_string(), Value::Nil).into())
+                            self.replace_top(Value::Number(result));
                         }
+                        _ => return Err(RuntimeError::OperandsMustBeNumbers(current_line!()).into()),
                     }
                 }};
             }
-
-            macro_rules! runtime_error {
-                ($args:tt) => {{
-                    eprint!("[line {}] ", self.chunk.get_line(self.ip));
-                    eprintln!($args);
+            macro_rules! binary_op {
+                ($wrap:ident, $op:tt) => {{
+                    match (self.peek(1), self.peek(0)) {
+                        (&Value::Number(a), &Value::Number(b)) => {
+                            self.pop();
+                            self.replace_top(Value::$wrap(a $op b));
+                        }
+                        _ => return Err(RuntimeError::OperandsMustBeNumbers(current_line!()).into()),
+                    }
                 }};
             }
 
             let opcode = read_byte!();
             let instruction = OpCode::from_u8(opcode).ok_or(RuntimeError::InvalidOpcode(opcode))?;
 
+            self.instructions += 1;
+            if let Some(max) = self.config.max_instructions {
+                if self.instructions > max {
+                    return Err(RuntimeError::LimitExceeded(
+                        current_line!(),
+                        Limit::Instructions(max),
+                    )
+                    .into());
+                }
+            }
+
             match instruction {
                 OpCode::Constant => {
                     let constant = read_constant!();
@@ -103,14 +313,14 @@ impl<'chunk> VM<'chunk> {
                 OpCode::False => self.push(Value::Bool(false)),
                 OpCode::True => self.push(Value::Bool(true)),
                 OpCode::Equal => {
-                    let a = self.pop();
-                    let b = self.pop();
-                    self.push(Value::Bool(a.equals(&b)));
+                    let equal = self.peek(1).equals(self.peek(0));
+                    self.pop();
+                    self.replace_top(Value::Bool(equal));
                 }
                 OpCode::NotEqual => {
-                    let a = self.pop();
-                    let b = self.pop();
-                    self.push(Value::Bool(!a.equals(&b)));
+                    let equal = self.peek(1).equals(self.peek(0));
+                    self.pop();
+                    self.replace_top(Value::Bool(!equal));
                 }
                 OpCode::Greater => binary_op!(Bool, >),
                 OpCode::GreaterEqual => binary_op!(Bool, >=),
@@ -118,55 +328,185 @@ impl<'chunk> VM<'chunk> {
                 OpCode::LessEqual => binary_op!(Bool, <=),
                 OpCode::Add => {
                     use Value::Number;
-                    if let (Some(b), Some(a)) = (self.peek(0).as_string(), self.peek(1).as_string())
+                    if let (Some(b), Some(a)) =
+                        (self.peek(0).as_string_obj(), self.peek(1).as_string_obj())
                     {
                         self.pop();
                         self.pop();
-                        // optimization: reduce this allocation by directly copying the existing slices into the
-                        // final allocated string
-                        let s = format!("{}{}", a, b);
-                        self.push(Value::Obj(self.objects.string(&s)))
-                    } else if let (Number(b), Number(a)) = (self.peek(0), self.peek(1)) {
-                        self.pop();
+                        if let Some(max) = self.config.max_objects {
+                            if self.objects.len() >= max {
+                                return Err(RuntimeError::LimitExceeded(
+                                    current_line!(),
+                                    Limit::Objects(max),
+                                )
+                                .into());
+                            }
+                        }
+                        self.push(Value::Obj(self.objects.concat(a, b)))
+                    } else if let (&Number(b), &Number(a)) = (self.peek(0), self.peek(1)) {
+                        let result = self.check_math(instruction, b, a + b)?;
                         self.pop();
-                        self.push(Value::Number(a + b));
+                        self.replace_top(Value::Number(result));
                     } else {
-                        runtime_error!("Operands must be two numbers or two strings.");
-                        todo!("should return err");
+                        return Err(
+                            RuntimeError::OperandsMustBeNumbersOrStrings(current_line!()).into(),
+                        );
                     }
                 }
-                OpCode::Subtract => binary_op!(Number, -),
-                OpCode::Multiply => binary_op!(Number, *),
-                OpCode::Divide => binary_op!(Number, /),
+                OpCode::Subtract => arithmetic_op!(OpCode::Subtract, -),
+                OpCode::Multiply => arithmetic_op!(OpCode::Multiply, *),
+                OpCode::Divide => arithmetic_op!(OpCode::Divide, /),
                 OpCode::Not => {
-                    let val = self.pop().is_falsey();
-                    self.push(Value::Bool(val));
+                    let falsey = self.peek(0).is_falsey();
+                    self.replace_top(Value::Bool(falsey));
                 }
                 OpCode::Negate => {
-                    if let Value::Number(number) = self.peek(0) {
-                        self.pop();
-                        self.push(Value::Number(-number))
+                    if let &Value::Number(number) = self.peek(0) {
+                        let result = self.check_math(instruction, number, -number)?;
+                        self.replace_top(Value::Number(result))
                     } else {
-                        runtime_error!("Operand must be a number.");
                         return Err(RuntimeError::OperandMustBeNumber(
-                            "unary negation".to_string(),
-                            self.peek(0),
+                            current_line!(),
+                            self.peek(0).to_string(),
                         )
                         .into());
                     }
                 }
+                OpCode::Dup => {
+                    let value = *self.peek(0);
+                    self.push(value);
+                }
+                OpCode::Swap => {
+                    let len = self.stack.len();
+                    self.stack.swap(len - 2, len - 1);
+                }
+                OpCode::Pop => {
+                    self.pop();
+                }
+                OpCode::Jump => {
+                    let distance = read_short!();
+                    self.ip += distance as usize;
+                }
+                OpCode::JumpIfFalse => {
+                    let distance = read_short!();
+                    if self.peek(0).is_falsey() {
+                        self.ip += distance as usize;
+                    }
+                }
+                OpCode::JumpTable => {
+                    let count = read_byte!() as usize;
+                    let table = self.ip;
+                    self.ip += 2 * (count + 1);
+                    // anything that isn't one of the cases takes the default,
+                    // which is the last entry
+                    let entry = match self.pop() {
+                        Value::Number(n) if n.fract() == 0.0 && n >= 0.0 && n < count as f64 => {
+                            n as usize
+                        }
+                        _ => count,
+                    };
+                    let distance = &self.chunk.code[table + 2 * entry..table + 2 * entry + 2];
+                    self.ip += u16::from_be_bytes([distance[0], distance[1]]) as usize;
+                }
                 OpCode::Return => {
-                    println!("{}", self.pop());
+                    let value = self.pop();
+                    self.output.print(&value.to_string());
                     return Ok(());
                 }
             }
         }
     }
 
-    pub fn interpret(source: String) -> Result<(), InterpretError> {
+    // runs the chunk as native code if it can be compiled. the debugger and
+    // coverage need to see each instruction, and compiled code doesn't check
+    // strict math, so they always interpret it.
+    #[cfg(feature = "jit")]
+    fn run_compiled(&mut self) -> Option<Value> {
+        if self.debugger.is_some() || self.hits.is_some() || self.strict_math {
+            return None;
+        }
+        let compiled = crate::jit::compile(self.chunk)?;
+        let instructions = self.instructions + compiled.instructions();
+        // the vm stops where the limit is hit, and reports its line
+        if self
+            .config
+            .max_instructions
+            .is_some_and(|max| instructions > max)
+        {
+            return None;
+        }
+        self.instructions = instructions;
+        Some(compiled.run())
+    }
+
+    pub fn interpret(source: String, output: &mut dyn Output) -> Result<(), InterpretError> {
+        Self::interpret_with_config(source, output, SandboxConfig::default())
+    }
+
+    pub fn interpret_with_config(
+        source: String,
+        output: &mut dyn Output,
+        config: SandboxConfig,
+    ) -> Result<(), InterpretError> {
+        let objects = Objects::new();
+        let chunk = Compiler::compile(source, &objects).map_err(InterpretError::Compile)?;
+        let mut vm = VM::new_with_config(&chunk, objects, output, config);
+        vm.run()
+    }
+
+    /// Runs like `interpret`, with strict math.
+    pub fn interpret_strict_math(
+        source: String,
+        output: &mut dyn Output,
+    ) -> Result<(), InterpretError> {
+        let objects = Objects::new();
+        let chunk = Compiler::compile(source, &objects).map_err(InterpretError::Compile)?;
+        let mut vm = VM::new(&chunk, objects, output);
+        vm.set_strict_math(true);
+        vm.run()
+    }
+
+    pub fn interpret_with_debugger(
+        source: String,
+        output: &mut dyn Output,
+        debugger: &mut Debugger,
+    ) -> Result<(), InterpretError> {
+        let objects = Objects::new();
+        let chunk = Compiler::compile(source, &objects).map_err(InterpretError::Compile)?;
+        let mut vm = VM::new(&chunk, objects, output);
+        vm.set_debugger(Some(debugger));
+        vm.run()
+    }
+
+    /// Runs like `interpret`, adding the lines that ran to `coverage`, even
+    /// when the script fails.
+    pub fn interpret_with_coverage(
+        source: String,
+        output: &mut dyn Output,
+        coverage: &mut Coverage,
+    ) -> Result<(), InterpretError> {
+        let objects = Objects::new();
+        let chunk = Compiler::compile(source, &objects).map_err(InterpretError::Compile)?;
+        let mut vm = VM::new(&chunk, objects, output);
+        vm.hits = Some(vec![0; chunk.code.len()]);
+        let result = vm.run();
+        if let Some(hits) = vm.hits.take() {
+            coverage.record(&chunk, &hits);
+        }
+        result
+    }
+
+    /// Runs like `interpret`, adding where each instruction came from to
+    /// `source_map` once the script compiles.
+    pub fn interpret_with_source_map(
+        source: String,
+        output: &mut dyn Output,
+        source_map: &mut SourceMap,
+    ) -> Result<(), InterpretError> {
         let objects = Objects::new();
-        let chunk = Compiler::compile(source, &objects)?;
-        let mut vm = VM::new(&chunk, objects);
+        let chunk = Compiler::compile(source, &objects).map_err(InterpretError::Compile)?;
+        source_map.record(&chunk);
+        let mut vm = VM::new(&chunk, objects, output);
         vm.run()
     }
 }
@@ -178,13 +518,3 @@ impl<'chunk> VM<'chunk> {
 // dynamically growing the stack as needed.
 //
 // What are the costs and benefits of doing so?
-
-// TODO!
-// To interpret OP_NEGATE, we pop the operand, negate the value, and then push
-// the result. That’s a simple implementation, but it increments and
-// decrements stackTop unnecessarily, since the stack ends up the same height
-// in the end. It might be faster to simply negate the value in place on the
-// stack and leave stackTop alone. Try that and see if you can measure a
-// performance difference.
-//
-// Are there other instructions where you can do a similar optimization?
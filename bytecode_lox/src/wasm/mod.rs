@@ -0,0 +1,559 @@
+//! Compiles scripts into standalone WebAssembly modules. Strings live in the
+//! module's memory, and the host only provides printing, through imports
+//! `runtime.js` implements for node.
+//!
+//! Like with native code, the types of the values on the stack are known
+//! while compiling, since the constants are. Numbers are f64s, booleans
+//! i32s and strings pointers to their length followed by their bytes. nil
+//! has no value. An operation on the wrong types compiles to reporting the
+//! error the vm would.
+
+use std::{collections::HashMap, path::Path};
+
+use wasm_encoder::{
+    BlockType, CodeSection, ConstExpr, DataSection, EntityType, ExportKind, ExportSection,
+    Function, FunctionSection, GlobalSection, GlobalType, ImportSection, Instruction, MemArg,
+    MemorySection, MemoryType, Module, TypeSection, ValType,
+};
+
+use crate::{
+    chunk::Chunk,
+    compiler::Compiler,
+    error::{BuildError, RuntimeError},
+    value::{Objects, Value},
+};
+
+/// Runs a module with node, providing its imports.
+pub const RUNTIME: &str = include_str!("runtime.js");
+
+// the imports from "lox", then the functions the module defines
+const PRINT_NUMBER: u32 = 0;
+const PRINT_STRING: u32 = 1;
+const ERROR: u32 = 2;
+const CONCAT: u32 = 3;
+const STRING_EQUAL: u32 = 4;
+const RUN: u32 = 5;
+
+// where the strings start, so no string is at 0
+const DATA_START: usize = 8;
+// the global with where the next string is allocated
+const HEAP: u32 = 0;
+// what `run` returns when the script fails, the vm's exit code for it
+const RUNTIME_ERROR: i32 = 70;
+
+/// Compiles `source` into a module at `out`, and writes the runtime that
+/// runs it next to it, with the same name and a `.js` extension.
+pub fn build(source: String, out: &Path) -> Result<(), BuildError> {
+    std::fs::write(out, compile(source)?)?;
+    std::fs::write(out.with_extension("js"), RUNTIME)?;
+    Ok(())
+}
+
+/// Compiles `source` into the bytes of a module that exports its memory
+/// and `run`, which prints what the script evaluates to and returns the
+/// exit code the vm would exit with.
+pub fn compile(source: String) -> Result<Vec<u8>, BuildError> {
+    let objects = Objects::new();
+    let chunk = Compiler::compile(source, &objects).map_err(BuildError::Compile)?;
+    let mut data = Data::default();
+    let run = translate(&chunk, &mut data)?;
+    let heap_start = (DATA_START + data.bytes.len()) as i32;
+
+    let mut types = TypeSection::new();
+    types.ty().function([ValType::F64], []);
+    types.ty().function([ValType::I32, ValType::I32], []);
+    types
+        .ty()
+        .function([ValType::I32, ValType::I32], [ValType::I32]);
+    types.ty().function([], [ValType::I32]);
+
+    let mut imports = ImportSection::new();
+    imports.import("lox", "print_number", EntityType::Function(0));
+    imports.import("lox", "print_string", EntityType::Function(1));
+    imports.import("lox", "error", EntityType::Function(1));
+
+    let mut functions = FunctionSection::new();
+    functions.function(2).function(2).function(3);
+
+    let mut memories = MemorySection::new();
+    memories.memory(MemoryType {
+        minimum: (heap_start as u64 >> 16) + 1,
+        maximum: None,
+        memory64: false,
+        shared: false,
+        page_size_log2: None,
+    });
+
+    let mut globals = GlobalSection::new();
+    let heap = GlobalType {
+        val_type: ValType::I32,
+        mutable: true,
+        shared: false,
+    };
+    globals.global(heap, &ConstExpr::i32_const(heap_start));
+
+    let mut exports = ExportSection::new();
+    exports.export("memory", ExportKind::Memory, 0);
+    exports.export("run", ExportKind::Func, RUN);
+
+    let mut code = CodeSection::new();
+    code.function(&concat())
+        .function(&string_equal())
+        .function(&run);
+
+    let mut data_section = DataSection::new();
+    data_section.active(0, &ConstExpr::i32_const(DATA_START as i32), data.bytes);
+
+    let mut module = Module::new();
+    module
+        .section(&types)
+        .section(&imports)
+        .section(&functions)
+        .section(&memories)
+        .section(&globals)
+        .section(&exports)
+        .section(&code)
+        .section(&data_section);
+    Ok(module.finish())
+}
+
+// the strings in the module's memory
+#[derive(Default)]
+struct Data {
+    bytes: Vec<u8>,
+    strings: HashMap<String, i32>,
+}
+
+impl Data {
+    // where `s` is in memory, adding it if it isn't yet
+    fn string(&mut self, s: &str) -> i32 {
+        if let Some(&ptr) = self.strings.get(s) {
+            return ptr;
+        }
+        let ptr = (DATA_START + self.bytes.len()) as i32;
+        self.bytes.extend((s.len() as u32).to_le_bytes());
+        self.bytes.extend(s.as_bytes());
+        while !self.bytes.len().is_multiple_of(4) {
+            self.bytes.push(0);
+        }
+        self.strings.insert(s.to_string(), ptr);
+        ptr
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Number,
+    Bool,
+    Nil,
+    Str,
+}
+
+fn mem(align: u32) -> MemArg {
+    MemArg {
+        offset: 0,
+        align,
+        memory_index: 0,
+    }
+}
+
+fn malformed() -> BuildError {
+    BuildError::Codegen("the chunk is malformed".into())
+}
+
+// a local of `run` to keep a value of `kind` in while `Dup` and `Swap`
+// move it around, the first or the second one of its type. nil has no
+// value to keep
+fn scratch(kind: Kind, second: bool) -> Option<u32> {
+    match kind {
+        Kind::Nil => None,
+        Kind::Number => Some(3 + second as u32),
+        Kind::Bool | Kind::Str => Some(1 + second as u32),
+    }
+}
+
+// `run`, which has a local to keep a string's pointer in while it's
+// printed, and the ones `scratch` picks from
+fn translate(chunk: &Chunk, data: &mut Data) -> Result<Function, BuildError> {
+    use crate::instruction::Instruction as Op;
+    use Instruction as I;
+    let mut f = Function::new([(3, ValType::I32), (2, ValType::F64)]);
+
+    let mut stack: Vec<Kind> = vec![];
+    for instruction in chunk.instructions() {
+        let (offset, instruction) = instruction.map_err(|_| malformed())?;
+        let line = chunk.get_line(offset);
+
+        let kind = match instruction {
+            Op::Constant(index) => {
+                match chunk.constants.get(index as usize).ok_or_else(malformed)? {
+                    Value::Number(n) => {
+                        f.instruction(&I::F64Const(*n));
+                        Kind::Number
+                    }
+                    Value::Bool(b) => {
+                        f.instruction(&I::I32Const(*b as i32));
+                        Kind::Bool
+                    }
+                    Value::Nil => Kind::Nil,
+                    value => {
+                        let s = value.as_string().ok_or_else(malformed)?;
+                        f.instruction(&I::I32Const(data.string(s)));
+                        Kind::Str
+                    }
+                }
+            }
+            Op::Nil => Kind::Nil,
+            Op::True | Op::False => {
+                f.instruction(&I::I32Const((instruction == Op::True) as i32));
+                Kind::Bool
+            }
+            Op::Equal | Op::NotEqual => {
+                let (b, a) = pop2(&mut stack)?;
+                match (a, b) {
+                    _ if a != b => {
+                        drop_value(&mut f, b);
+                        drop_value(&mut f, a);
+                        f.instruction(&I::I32Const(0));
+                    }
+                    (Kind::Number, _) => {
+                        f.instruction(&I::F64Eq);
+                    }
+                    (Kind::Bool, _) => {
+                        f.instruction(&I::I32Eq);
+                    }
+                    (Kind::Str, _) => {
+                        f.instruction(&I::Call(STRING_EQUAL));
+                    }
+                    (Kind::Nil, _) => {
+                        f.instruction(&I::I32Const(1));
+                    }
+                }
+                if instruction == Op::NotEqual {
+                    f.instruction(&I::I32Eqz);
+                }
+                Kind::Bool
+            }
+            Op::Greater | Op::GreaterEqual | Op::Less | Op::LessEqual => {
+                if pop2(&mut stack)? != (Kind::Number, Kind::Number) {
+                    let message = RuntimeError::OperandsMustBeNumbers(line).to_string();
+                    f.instruction(&I::I32Const(data.string(&message)));
+                    return Ok(fail(f));
+                }
+                f.instruction(&match instruction {
+                    Op::Greater => I::F64Gt,
+                    Op::GreaterEqual => I::F64Ge,
+                    Op::Less => I::F64Lt,
+                    _ => I::F64Le,
+                });
+                Kind::Bool
+            }
+            Op::Add => match pop2(&mut stack)? {
+                (Kind::Number, Kind::Number) => {
+                    f.instruction(&I::F64Add);
+                    Kind::Number
+                }
+                (Kind::Str, Kind::Str) => {
+                    f.instruction(&I::Call(CONCAT));
+                    Kind::Str
+                }
+                _ => {
+                    let message = RuntimeError::OperandsMustBeNumbersOrStrings(line).to_string();
+                    f.instruction(&I::I32Const(data.string(&message)));
+                    return Ok(fail(f));
+                }
+            },
+            Op::Subtract | Op::Multiply | Op::Divide => {
+                if pop2(&mut stack)? != (Kind::Number, Kind::Number) {
+                    let message = RuntimeError::OperandsMustBeNumbers(line).to_string();
+                    f.instruction(&I::I32Const(data.string(&message)));
+                    return Ok(fail(f));
+                }
+                f.instruction(&match instruction {
+                    Op::Subtract => I::F64Sub,
+                    Op::Multiply => I::F64Mul,
+                    _ => I::F64Div,
+                });
+                Kind::Number
+            }
+            Op::Not => {
+                match stack.pop().ok_or_else(malformed)? {
+                    Kind::Bool => {
+                        f.instruction(&I::I32Eqz);
+                    }
+                    Kind::Nil => {
+                        f.instruction(&I::I32Const(1));
+                    }
+                    kind => {
+                        drop_value(&mut f, kind);
+                        f.instruction(&I::I32Const(0));
+                    }
+                }
+                Kind::Bool
+            }
+            Op::Negate => match stack.pop().ok_or_else(malformed)? {
+                Kind::Number => {
+                    f.instruction(&I::F64Neg);
+                    Kind::Number
+                }
+                kind => {
+                    // the message has the operand in the middle, which is
+                    // only known when it runs
+                    let message = RuntimeError::OperandMustBeNumber(line, "\0".into()).to_string();
+                    let (before, after) = message.split_once('\0').ok_or_else(malformed)?;
+                    to_text(&mut f, data, kind);
+                    f.instruction(&I::LocalSet(0));
+                    f.instruction(&I::I32Const(data.string(before)));
+                    f.instruction(&I::LocalGet(0));
+                    f.instruction(&I::Call(CONCAT));
+                    f.instruction(&I::I32Const(data.string(after)));
+                    f.instruction(&I::Call(CONCAT));
+                    return Ok(fail(f));
+                }
+            },
+            Op::Dup => {
+                let kind = *stack.last().ok_or_else(malformed)?;
+                if let Some(local) = scratch(kind, false) {
+                    f.instruction(&I::LocalTee(local));
+                    f.instruction(&I::LocalGet(local));
+                }
+                kind
+            }
+            Op::Swap => {
+                let (b, a) = pop2(&mut stack)?;
+                let (b_local, a_local) = (scratch(b, false), scratch(a, true));
+                for &local in [b_local, a_local].iter().flatten() {
+                    f.instruction(&I::LocalSet(local));
+                }
+                for &local in [b_local, a_local].iter().flatten() {
+                    f.instruction(&I::LocalGet(local));
+                }
+                stack.push(b);
+                a
+            }
+            Op::Pop => {
+                let kind = stack.pop().ok_or_else(malformed)?;
+                drop_value(&mut f, kind);
+                continue;
+            }
+            Op::Jump(_) | Op::JumpIfFalse(_) | Op::JumpTable { .. } => {
+                return Err(BuildError::Branching)
+            }
+            Op::Return => {
+                match stack.pop().ok_or_else(malformed)? {
+                    Kind::Number => {
+                        f.instruction(&I::Call(PRINT_NUMBER));
+                    }
+                    kind => {
+                        to_text(&mut f, data, kind);
+                        print(&mut f, PRINT_STRING);
+                    }
+                }
+                f.instruction(&I::I32Const(0));
+                f.instruction(&I::Return);
+                f.instruction(&I::End);
+                return Ok(f);
+            }
+        };
+        stack.push(kind);
+    }
+    // the chunk ended without returning
+    Err(malformed())
+}
+
+fn pop2(stack: &mut Vec<Kind>) -> Result<(Kind, Kind), BuildError> {
+    let b = stack.pop().ok_or_else(malformed)?;
+    let a = stack.pop().ok_or_else(malformed)?;
+    Ok((b, a))
+}
+
+fn drop_value(f: &mut Function, kind: Kind) {
+    if kind != Kind::Nil {
+        f.instruction(&Instruction::Drop);
+    }
+}
+
+// replaces the value on top of the stack with the string the vm prints for
+// it. not for numbers, the host formats those.
+fn to_text(f: &mut Function, data: &mut Data, kind: Kind) {
+    match kind {
+        Kind::Bool => {
+            let (t, e) = (data.string("true"), data.string("false"));
+            f.instruction(&Instruction::If(BlockType::Result(ValType::I32)));
+            f.instruction(&Instruction::I32Const(t));
+            f.instruction(&Instruction::Else);
+            f.instruction(&Instruction::I32Const(e));
+            f.instruction(&Instruction::End);
+        }
+        Kind::Nil => {
+            f.instruction(&Instruction::I32Const(data.string("nil")));
+        }
+        Kind::Number | Kind::Str => {}
+    }
+}
+
+// passes the string on top of the stack to an import that takes a pointer
+// to its bytes and their length
+fn print(f: &mut Function, import: u32) {
+    f.instruction(&Instruction::LocalTee(0));
+    f.instruction(&Instruction::I32Const(4));
+    f.instruction(&Instruction::I32Add);
+    f.instruction(&Instruction::LocalGet(0));
+    f.instruction(&Instruction::I32Load(mem(2)));
+    f.instruction(&Instruction::Call(import));
+}
+
+// reports the message on top of the stack and ends `run`
+fn fail(mut f: Function) -> Function {
+    print(&mut f, ERROR);
+    f.instruction(&Instruction::I32Const(RUNTIME_ERROR));
+    f.instruction(&Instruction::Return);
+    f.instruction(&Instruction::End);
+    f
+}
+
+// (a, b) -> a string with the bytes of both, at the end of the heap, which
+// grows the memory when it's full
+fn concat() -> Function {
+    use Instruction as I;
+    let (a, b, a_len, b_len, result) = (0, 1, 2, 3, 4);
+    let mut f = Function::new([(3, ValType::I32)]);
+    let instructions = [
+        I::LocalGet(a),
+        I::I32Load(mem(2)),
+        I::LocalSet(a_len),
+        I::LocalGet(b),
+        I::I32Load(mem(2)),
+        I::LocalSet(b_len),
+        I::GlobalGet(HEAP),
+        I::LocalSet(result),
+        // the next string goes after this one, aligned to 4 bytes
+        I::LocalGet(result),
+        I::LocalGet(a_len),
+        I::I32Add,
+        I::LocalGet(b_len),
+        I::I32Add,
+        I::I32Const(4 + 3),
+        I::I32Add,
+        I::I32Const(-4),
+        I::I32And,
+        I::GlobalSet(HEAP),
+        I::GlobalGet(HEAP),
+        I::MemorySize(0),
+        I::I32Const(16),
+        I::I32Shl,
+        I::I32GtU,
+        I::If(BlockType::Empty),
+        // the pages the heap is missing, rounding up
+        I::GlobalGet(HEAP),
+        I::MemorySize(0),
+        I::I32Const(16),
+        I::I32Shl,
+        I::I32Sub,
+        I::I32Const(0xffff),
+        I::I32Add,
+        I::I32Const(16),
+        I::I32ShrU,
+        I::MemoryGrow(0),
+        I::I32Const(-1),
+        I::I32Eq,
+        I::If(BlockType::Empty),
+        I::Unreachable,
+        I::End,
+        I::End,
+        I::LocalGet(result),
+        I::LocalGet(a_len),
+        I::LocalGet(b_len),
+        I::I32Add,
+        I::I32Store(mem(2)),
+        I::LocalGet(result),
+        I::I32Const(4),
+        I::I32Add,
+        I::LocalGet(a),
+        I::I32Const(4),
+        I::I32Add,
+        I::LocalGet(a_len),
+        I::MemoryCopy {
+            src_mem: 0,
+            dst_mem: 0,
+        },
+        I::LocalGet(result),
+        I::I32Const(4),
+        I::I32Add,
+        I::LocalGet(a_len),
+        I::I32Add,
+        I::LocalGet(b),
+        I::I32Const(4),
+        I::I32Add,
+        I::LocalGet(b_len),
+        I::MemoryCopy {
+            src_mem: 0,
+            dst_mem: 0,
+        },
+        I::LocalGet(result),
+        I::End,
+    ];
+    for instruction in &instructions {
+        f.instruction(instruction);
+    }
+    f
+}
+
+// (a, b) -> whether the strings have the same bytes
+fn string_equal() -> Function {
+    use Instruction as I;
+    let (a, b, i) = (0, 1, 2);
+    let mut f = Function::new([(1, ValType::I32)]);
+    let byte = |string| {
+        [
+            I::LocalGet(string),
+            I::LocalGet(i),
+            I::I32Add,
+            I::I32Load8U(MemArg {
+                offset: 4,
+                ..mem(0)
+            }),
+        ]
+    };
+    let mut instructions = vec![
+        I::LocalGet(a),
+        I::I32Load(mem(2)),
+        I::LocalGet(b),
+        I::I32Load(mem(2)),
+        I::I32Ne,
+        I::If(BlockType::Empty),
+        I::I32Const(0),
+        I::Return,
+        I::End,
+        I::Block(BlockType::Empty),
+        I::Loop(BlockType::Empty),
+        // out of the block once every byte was compared
+        I::LocalGet(i),
+        I::LocalGet(a),
+        I::I32Load(mem(2)),
+        I::I32GeU,
+        I::BrIf(1),
+    ];
+    instructions.extend(byte(a));
+    instructions.extend(byte(b));
+    instructions.extend([
+        I::I32Ne,
+        I::If(BlockType::Empty),
+        I::I32Const(0),
+        I::Return,
+        I::End,
+        I::LocalGet(i),
+        I::I32Const(1),
+        I::I32Add,
+        I::LocalSet(i),
+        I::Br(0),
+        I::End,
+        I::End,
+        I::I32Const(1),
+        I::End,
+    ]);
+    for instruction in &instructions {
+        f.instruction(instruction);
+    }
+    f
+}
@@ -0,0 +1,48 @@
+// Runs a module compiled by `lox wasm` with node, providing the imports it
+// prints through. Runs the module given as the first argument, or the one
+// next to this file with the same name.
+"use strict";
+const fs = require("fs");
+
+const path = process.argv[2] || __filename.replace(/\.js$/, ".wasm");
+let memory;
+
+function text(ptr, len) {
+  return Buffer.from(memory.buffer, ptr, len).toString("utf8");
+}
+
+// formats numbers like the vm does, which is how Rust does
+function formatNumber(n) {
+  if (Number.isNaN(n)) return "NaN";
+  if (n === Infinity) return "inf";
+  if (n === -Infinity) return "-inf";
+  if (Object.is(n, -0)) return "-0";
+  const s = String(n);
+  const e = s.indexOf("e");
+  if (e < 0) return s;
+
+  // Rust never uses an exponent, so it's expanded
+  let mantissa = s.slice(0, e);
+  const exponent = Number(s.slice(e + 1));
+  const sign = mantissa.startsWith("-") ? "-" : "";
+  mantissa = mantissa.slice(sign.length);
+  const [int, frac = ""] = mantissa.split(".");
+  const digits = int + frac;
+  const point = int.length + exponent;
+  if (point <= 0) return sign + "0." + "0".repeat(-point) + digits;
+  if (point >= digits.length) return sign + digits + "0".repeat(point - digits.length);
+  return sign + digits.slice(0, point) + "." + digits.slice(point);
+}
+
+const imports = {
+  lox: {
+    print_number: (n) => console.log(formatNumber(n)),
+    print_string: (ptr, len) => console.log(text(ptr, len)),
+    error: (ptr, len) => console.error(text(ptr, len)),
+  },
+};
+
+WebAssembly.instantiate(fs.readFileSync(path), imports).then(({ instance }) => {
+  memory = instance.exports.memory;
+  process.exitCode = instance.exports.run();
+});
@@ -0,0 +1,51 @@
+//! The source map points each instruction at the token it was compiled
+//! from, which for an operator is the operator itself, not the end of its
+//! right operand.
+
+use bytecode_lox::host::Output;
+use bytecode_lox::source_map::SourceMap;
+use bytecode_lox::vm::VM;
+
+struct Discard;
+
+impl Output for Discard {
+    fn print(&mut self, _line: &str) {}
+}
+
+fn columns(source: &str) -> Vec<(usize, std::ops::Range<u32>)> {
+    let mut source_map = SourceMap::new();
+    VM::interpret_with_source_map(source.to_string(), &mut Discard, &mut source_map).unwrap();
+    source_map
+        .mappings()
+        .iter()
+        .map(|mapping| (mapping.offset, mapping.columns.clone()))
+        .collect()
+}
+
+#[test]
+fn maps_binary_operators_to_their_operator() {
+    let mappings = columns("1 + 2 * (3 - 1)");
+    // the four constants, then OP_SUBTRACT, OP_MULTIPLY and OP_ADD
+    assert_eq!(
+        mappings[..7],
+        [
+            (0, 1..2),
+            (2, 5..6),
+            (4, 10..11),
+            (6, 14..15),
+            (8, 12..13),
+            (9, 7..8),
+            (10, 3..4),
+        ]
+    );
+}
+
+#[test]
+fn maps_unary_operators_to_their_operator() {
+    let mappings = columns("!(-1 < 2)");
+    // 1, OP_NEGATE, 2, OP_LESS, OP_NOT
+    assert_eq!(
+        mappings[..5],
+        [(0, 4..5), (2, 3..4), (3, 8..9), (5, 6..7), (6, 1..2)]
+    );
+}
@@ -0,0 +1,17 @@
+//! Parses arbitrary text with the tree-walk parser and the bytecode compiler,
+//! which should report errors for invalid programs instead of panicking.
+//!
+//!     cargo +nightly fuzz run parser
+
+#![no_main]
+
+use bytecode_lox::{compiler::Compiler, value::Objects};
+use libfuzzer_sys::fuzz_target;
+use lox::{parser::Parser, scanner::Scanner};
+
+fuzz_target!(|source: &str| {
+    let _ = Parser::new(Scanner::new(source.to_string())).parse();
+
+    let objects = Objects::new();
+    let _ = Compiler::compile(source.to_string(), &objects);
+});
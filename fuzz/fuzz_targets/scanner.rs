@@ -0,0 +1,17 @@
+//! Scans arbitrary text with both scanners, which should report errors for
+//! anything they don't understand instead of panicking.
+//!
+//!     cargo +nightly fuzz run scanner
+
+#![no_main]
+
+use bytecode_lox::scanner::TokenKind;
+use libfuzzer_sys::fuzz_target;
+use lox::scanner::Scanner;
+
+fuzz_target!(|source: &str| {
+    let _ = Scanner::new(source.to_string()).scan_tokens();
+
+    let mut scanner = bytecode_lox::scanner::Scanner::new(source);
+    while scanner.scan().kind != TokenKind::Eof {}
+});
@@ -0,0 +1,67 @@
+//! Runs arbitrary bytes as chunks on the vm. Whatever the verifier lets
+//! through has to run without panicking, and the instruction budget stops
+//! it if it never ends.
+//!
+//!     cargo +nightly fuzz run vm
+
+#![no_main]
+
+use std::convert::TryInto;
+
+use bytecode_lox::{
+    chunk::Chunk,
+    host::Output,
+    sandbox::SandboxConfig,
+    value::{Objects, Value},
+    verifier::verify,
+    vm::VM,
+};
+use libfuzzer_sys::fuzz_target;
+
+struct Discard;
+
+impl Output for Discard {
+    fn print(&mut self, _line: &str) {}
+}
+
+// the first byte is how many constants there are. each of them is the next
+// eight bytes, a number when the first of them is even and a string of the
+// rest otherwise. what's left is the code
+fn chunk(data: &[u8], objects: &Objects) -> Option<Chunk> {
+    let (&count, data) = data.split_first()?;
+    let count = count as usize;
+    if data.len() < count * 8 {
+        return None;
+    }
+    let (constants, code) = data.split_at(count * 8);
+    let mut chunk = Chunk::new();
+    for bytes in constants.chunks(8) {
+        let value = if bytes[0] % 2 == 0 {
+            Value::Number(f64::from_be_bytes(bytes.try_into().unwrap()))
+        } else {
+            Value::Obj(objects.string(&String::from_utf8_lossy(&bytes[1..])))
+        };
+        chunk.add_constant(value);
+    }
+    for byte in code {
+        chunk.write(*byte, 1, 0..1);
+    }
+    Some(chunk)
+}
+
+fuzz_target!(|data: &[u8]| {
+    let objects = Objects::new();
+    let chunk = match chunk(data, &objects) {
+        Some(chunk) => chunk,
+        None => return,
+    };
+    if verify(&chunk).is_err() {
+        return;
+    }
+    let config = SandboxConfig {
+        max_instructions: Some(100_000),
+        max_objects: Some(10_000),
+    };
+    let mut output = Discard;
+    let _ = VM::new_with_config(&chunk, objects, &mut output, config).run();
+});
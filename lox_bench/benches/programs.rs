@@ -0,0 +1,27 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use lox_bench::{concatenate_pieces, programs, run_bytecode, run_tree_walk};
+
+fn bench_programs(c: &mut Criterion) {
+    for program in programs() {
+        let mut group = c.benchmark_group(program.name);
+        // a program that fails would only measure how fast it fails
+        run_tree_walk(&program.tree_walk).expect("benchmark failed on the tree-walk interpreter");
+        group.bench_function("tree-walk", |b| {
+            b.iter(|| run_tree_walk(&program.tree_walk))
+        });
+        if let Some(source) = &program.bytecode {
+            run_bytecode(source).expect("benchmark failed on the vm");
+            group.bench_function("bytecode", |b| b.iter(|| run_bytecode(source)));
+        }
+        group.finish();
+    }
+}
+
+fn bench_concatenation(c: &mut Criterion) {
+    c.bench_function("concatenate 100k pieces", |b| {
+        b.iter(|| concatenate_pieces(100_000))
+    });
+}
+
+criterion_group!(benches, bench_programs, bench_concatenation);
+criterion_main!(benches);
@@ -0,0 +1,93 @@
+//! Programs to benchmark both interpreters with, and functions that run them
+//! without printing anything. `cargo bench -p lox_bench` measures them all.
+
+use bytecode_lox::{error::InterpretError, value::Objects, vm::VM};
+use lox::{interpreter::Interpreter, LoxError};
+
+pub struct Program {
+    pub name: &'static str,
+    pub tree_walk: String,
+    /// the same program written for the vm, which only compiles single
+    /// expressions so far, or `None` if it can't be written yet
+    pub bytecode: Option<String>,
+}
+
+pub fn programs() -> Vec<Program> {
+    vec![
+        Program {
+            name: "fib",
+            tree_walk: include_str!("../programs/fib.lox").to_string(),
+            bytecode: None,
+        },
+        Program {
+            name: "zoo",
+            tree_walk: include_str!("../programs/zoo.lox").to_string(),
+            bytecode: None,
+        },
+        Program {
+            name: "strings",
+            tree_walk: include_str!("../programs/strings.lox").to_string(),
+            bytecode: None,
+        },
+        Program {
+            name: "closures",
+            tree_walk: include_str!("../programs/closures.lox").to_string(),
+            bytecode: None,
+        },
+        Program {
+            name: "variables",
+            tree_walk: include_str!("../programs/variables.lox").to_string(),
+            bytecode: None,
+        },
+        expression_program("arithmetic", "(1.5 * 2 - 1)"),
+        expression_program("concatenation", "\"lox \""),
+    ]
+}
+
+// the sum of many copies of `term`, which both backends can run
+fn expression_program(name: &'static str, term: &str) -> Program {
+    // a chunk has room for 256 constants, this stays below that
+    let expression = vec![term; 80].join(" + ");
+    Program {
+        name,
+        tree_walk: format!("print {};\n", expression),
+        // the line ends like it would in a file
+        bytecode: Some(format!("{}\n", expression)),
+    }
+}
+
+struct Discard;
+
+impl lox::host::Output for Discard {
+    fn print(&mut self, _line: &str) {}
+}
+
+impl bytecode_lox::host::Output for Discard {
+    fn print(&mut self, _line: &str) {}
+}
+
+pub fn run_tree_walk(source: &str) -> Result<(), LoxError> {
+    let mut interpreter = Interpreter::new();
+    // the programs are shared with the vm, which has the book's print
+    // statement
+    interpreter.set_compat_jlox(true);
+    interpreter.set_output(Box::new(Discard));
+    lox::run_source(&mut interpreter, source.to_string())
+}
+
+pub fn run_bytecode(source: &str) -> Result<(), InterpretError> {
+    VM::interpret(source.to_string(), &mut Discard)
+}
+
+/// Builds a string out of `count` pieces with the vm's strings, appending
+/// one at a time like a loop would, and returns its length. Scripts for the
+/// vm can't loop yet, or have enough constants for that many pieces.
+pub fn concatenate_pieces(count: usize) -> usize {
+    let objects = Objects::new();
+    let piece = objects.string("lox ");
+    let mut text = objects.string("");
+    for _ in 0..count {
+        text = objects.concat(text, piece);
+    }
+    text.to_string().len()
+}
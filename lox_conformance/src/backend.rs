@@ -0,0 +1,131 @@
+use std::{cell::RefCell, rc::Rc, thread};
+
+use bytecode_lox::{error::InterpretError, vm::VM};
+use lox::{interpreter::Interpreter, sandbox::SandboxConfig, LoxError};
+
+// big enough for the deepest recursion the sandbox limits allow
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    TreeWalk,
+    Bytecode,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 2] = [Backend::TreeWalk, Backend::Bytecode];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::TreeWalk => "tree-walk",
+            Backend::Bytecode => "bytecode",
+        }
+    }
+
+    /// How the upstream suite names the implementation this backend
+    /// follows, in expectations meant for only one of them.
+    pub fn dialect(self) -> &'static str {
+        match self {
+            Backend::TreeWalk => "java",
+            Backend::Bytecode => "c",
+        }
+    }
+
+    /// Runs `source` on its own thread, so a panic or a deep recursion can't
+    /// take the harness down.
+    pub fn run(self, source: &str) -> Outcome {
+        let source = source.to_string();
+        let handle = thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(move || match self {
+                Backend::TreeWalk => run_tree_walk(source),
+                Backend::Bytecode => run_bytecode(source),
+            })
+            .expect("failed to spawn a thread for the test");
+        match handle.join() {
+            Ok(outcome) => outcome,
+            Err(panic) => {
+                let message = panic
+                    .downcast_ref::<&str>()
+                    .map(|it| it.to_string())
+                    .or_else(|| panic.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                Outcome {
+                    output: vec![],
+                    errors: vec![format!("panicked: {}", message)],
+                    exit_code: 101,
+                }
+            }
+        }
+    }
+}
+
+/// What a script printed, the errors it reported and the exit code the
+/// interpreter's binary would have finished with.
+pub struct Outcome {
+    pub output: Vec<String>,
+    pub errors: Vec<String>,
+    pub exit_code: i32,
+}
+
+#[derive(Clone, Default)]
+struct Capture(Rc<RefCell<Vec<String>>>);
+
+impl lox::host::Output for Capture {
+    fn print(&mut self, line: &str) {
+        self.0.borrow_mut().push(line.to_string());
+    }
+}
+
+impl bytecode_lox::host::Output for Capture {
+    fn print(&mut self, line: &str) {
+        self.0.borrow_mut().push(line.to_string());
+    }
+}
+
+fn run_tree_walk(source: String) -> Outcome {
+    let capture = Capture::default();
+    let config = SandboxConfig {
+        max_steps: Some(100_000_000),
+        max_environments: Some(10_000),
+        ..SandboxConfig::default()
+    };
+    let mut interpreter = Interpreter::new_with_config(config);
+    interpreter.set_compat_jlox(true);
+    interpreter.set_output(Box::new(capture.clone()));
+    let result = lox::run_source(&mut interpreter, source);
+    let exit_code = match &result {
+        Ok(()) => 0,
+        Err(LoxError::Runtime(_)) => 70,
+        Err(_) => 65,
+    };
+    finish(capture, result.err().map(|e| e.to_string()), exit_code)
+}
+
+fn run_bytecode(source: String) -> Outcome {
+    let mut capture = Capture::default();
+    let config = bytecode_lox::sandbox::SandboxConfig {
+        max_instructions: Some(100_000_000),
+        ..Default::default()
+    };
+    let result = VM::interpret_with_config(source, &mut capture, config);
+    let exit_code = match &result {
+        Ok(()) => 0,
+        Err(InterpretError::Compile(_)) => 65,
+        Err(InterpretError::Runtime(_)) => 70,
+    };
+    finish(capture, result.err().map(|e| e.to_string()), exit_code)
+}
+
+fn finish(capture: Capture, error: Option<String>, exit_code: i32) -> Outcome {
+    let output = capture.0.borrow().clone();
+    Outcome {
+        output,
+        errors: error
+            .iter()
+            .flat_map(|e| e.lines())
+            .map(str::to_string)
+            .collect(),
+        exit_code,
+    }
+}
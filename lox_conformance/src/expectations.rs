@@ -0,0 +1,146 @@
+//! The expectations the upstream suite writes as comments in each test:
+//!
+//! ```lox
+//! print 1; // expect: 1
+//! var a = -"s"; // expect runtime error: Operand must be a number.
+//! fun f( // Error at end: Expect parameter name.
+//! // [line 3] Error at 'x': Already a variable with this name in this scope.
+//! // [c line 4] Error at 'y': Only the bytecode backend reports this.
+//! ```
+
+use std::collections::BTreeSet;
+
+use crate::backend::{Backend, Outcome};
+
+pub struct Expectations {
+    output: Vec<String>,
+    // the dialect the error is limited to, if any, and the error
+    compile_errors: Vec<(Option<String>, String)>,
+    // the message and the line it's reported on
+    runtime_error: Option<(String, usize)>,
+}
+
+impl Expectations {
+    /// The expectations in `source`, or `None` if it isn't a test.
+    pub fn parse(source: &str) -> Option<Self> {
+        let mut expectations = Expectations {
+            output: vec![],
+            compile_errors: vec![],
+            runtime_error: None,
+        };
+        for (i, line) in source.lines().enumerate() {
+            let line_number = i + 1;
+            let comment = match line.find("//") {
+                Some(start) => line[start + 2..].trim_start(),
+                None => continue,
+            };
+            if comment.starts_with("nontest") {
+                return None;
+            }
+            if let Some(output) = comment.strip_prefix("expect:") {
+                let output = output.strip_prefix(' ').unwrap_or(output);
+                expectations.output.push(output.to_string());
+            } else if let Some(message) = comment.strip_prefix("expect runtime error: ") {
+                expectations.runtime_error = Some((message.to_string(), line_number));
+            } else if comment.starts_with("Error") {
+                let error = format!("[line {}] {}", line_number, comment);
+                expectations.compile_errors.push((None, error));
+            } else if let Some(error) = parse_error_line(comment) {
+                expectations.compile_errors.push(error);
+            }
+        }
+        Some(expectations)
+    }
+
+    /// The ways `outcome` differs from what was expected on `backend`.
+    pub fn check(&self, backend: Backend, outcome: &Outcome) -> Vec<String> {
+        let mut failures = vec![];
+
+        let compile_errors = self
+            .compile_errors
+            .iter()
+            .filter(|(dialect, _)| dialect.as_deref().is_none_or(|it| it == backend.dialect()))
+            .map(|(_, error)| error.clone())
+            .collect::<BTreeSet<_>>();
+
+        let expected_exit_code = if self.runtime_error.is_some() {
+            70
+        } else if !compile_errors.is_empty() {
+            65
+        } else {
+            0
+        };
+
+        let errors = outcome
+            .errors
+            .iter()
+            .map(|it| it.replace("[Line ", "[line "))
+            .collect::<Vec<_>>();
+        if let Some((message, line)) = &self.runtime_error {
+            let text = errors.join("\n");
+            if !text.contains(message.as_str()) {
+                failures.push(format!("Expected runtime error '{}'.", message));
+            } else if !text.contains(&format!("[line {}]", line)) {
+                failures.push(format!("Expected the runtime error on line {}.", line));
+            }
+        } else {
+            let found = errors
+                .iter()
+                .filter_map(|it| normalize_error(it))
+                .collect::<BTreeSet<_>>();
+            for missing in compile_errors.difference(&found) {
+                failures.push(format!("Missing expected error: {}", missing));
+            }
+            for error in &errors {
+                match normalize_error(error) {
+                    Some(error) if compile_errors.contains(&error) => {}
+                    _ => failures.push(format!("Unexpected error: {}", error)),
+                }
+            }
+        }
+
+        for (i, expected) in self.output.iter().enumerate() {
+            match outcome.output.get(i) {
+                Some(actual) if actual == expected => {}
+                Some(actual) => {
+                    failures.push(format!("Expected output '{}', got '{}'.", expected, actual))
+                }
+                None => failures.push(format!("Missing expected output '{}'.", expected)),
+            }
+        }
+        for extra in outcome.output.iter().skip(self.output.len()) {
+            failures.push(format!("Got unexpected output '{}'.", extra));
+        }
+
+        if outcome.exit_code != expected_exit_code {
+            failures.push(format!(
+                "Expected exit code {}, got {}.",
+                expected_exit_code, outcome.exit_code
+            ));
+        }
+        failures
+    }
+}
+
+// `[line 3] Error...`, `[java line 3] Error...` or `[c line 3] Error...`
+fn parse_error_line(comment: &str) -> Option<(Option<String>, String)> {
+    let rest = comment.strip_prefix('[')?;
+    let (dialect, rest) = match rest.split_once(' ') {
+        Some((dialect, rest)) if dialect != "line" => (Some(dialect.to_string()), rest),
+        _ => (None, rest),
+    };
+    let rest = rest.strip_prefix("line ")?;
+    let (line, error) = rest.split_once("] ")?;
+    line.parse::<usize>().ok()?;
+    if !error.starts_with("Error") {
+        return None;
+    }
+    Some((dialect, format!("[line {}] {}", line, error)))
+}
+
+// a reported compile error in the form the suite expects, if it is one
+fn normalize_error(error: &str) -> Option<String> {
+    let start = error.find("[line ")?;
+    let (_, error) = parse_error_line(&error[start..])?;
+    Some(error)
+}
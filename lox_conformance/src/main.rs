@@ -0,0 +1,154 @@
+//! Runs the test suite of the craftinginterpreters repository, the `test`
+//! directory, against both interpreters and prints how many tests of each
+//! chapter's directory pass on each backend.
+//!
+//! With `--baseline <file>` it fails if a test that passed when the baseline
+//! was written with `--write-baseline <file>` doesn't pass anymore, so it can
+//! guard against regressions before either backend passes everything.
+
+mod backend;
+mod expectations;
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use backend::Backend;
+use expectations::Expectations;
+
+// directories of the suite for the book's intermediate chapters, which test
+// a token dump or an AST printer instead of running programs
+const SKIPPED: [&str; 3] = ["benchmark", "expressions", "scanning"];
+
+#[derive(Default)]
+struct Tally {
+    passed: usize,
+    total: usize,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let mut dir = None;
+    let mut baseline = None;
+    let mut write_baseline = None;
+    let mut verbose = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--baseline" => baseline = args.next(),
+            "--write-baseline" => write_baseline = args.next(),
+            "--verbose" | "-v" => verbose = true,
+            _ if dir.is_none() => dir = Some(arg),
+            _ => usage(),
+        }
+    }
+    let dir = Path::new(dir.unwrap_or_else(|| usage()));
+
+    // a test that makes a backend panic fails, and the outcome has the message
+    std::panic::set_hook(Box::new(|_| {}));
+
+    let mut chapters: BTreeMap<String, [Tally; 2]> = BTreeMap::new();
+    // `<backend> <path>` of every test that passed
+    let mut passing = BTreeSet::new();
+    for path in lox::find_files(dir, ".lox")? {
+        let relative = path.strip_prefix(dir)?;
+        let components = relative
+            .components()
+            .map(|it| it.as_os_str().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        let chapter = match components.as_slice() {
+            [chapter, _, ..] => chapter.clone(),
+            _ => "(top level)".to_string(),
+        };
+        if SKIPPED.contains(&chapter.as_str()) {
+            continue;
+        }
+        let name = components.join("/");
+
+        let source = std::fs::read_to_string(&path)?;
+        let expectations = match Expectations::parse(&source) {
+            Some(expectations) => expectations,
+            None => continue,
+        };
+        let tallies = chapters.entry(chapter).or_default();
+        for (backend, tally) in Backend::ALL.iter().zip(tallies.iter_mut()) {
+            let outcome = backend.run(&source);
+            let failures = expectations.check(*backend, &outcome);
+            tally.total += 1;
+            if failures.is_empty() {
+                tally.passed += 1;
+                passing.insert(format!("{} {}", backend.name(), name));
+            } else if verbose {
+                println!("FAIL {} > {}", backend.name(), name);
+                for failure in &failures {
+                    println!("    {}", failure);
+                }
+            }
+        }
+    }
+
+    print_table(&chapters);
+
+    if let Some(file) = write_baseline {
+        let lines = passing.iter().map(|it| format!("{}\n", it));
+        std::fs::write(file, lines.collect::<String>())?;
+    }
+    if let Some(file) = baseline {
+        let expected = std::fs::read_to_string(file)?;
+        let regressions = expected
+            .lines()
+            .filter(|it| !it.is_empty() && !passing.contains(*it))
+            .collect::<Vec<_>>();
+        if !regressions.is_empty() {
+            println!();
+            println!(
+                "{} tests passed in the baseline but fail now:",
+                regressions.len()
+            );
+            for regression in regressions {
+                println!("    {}", regression);
+            }
+            std::process::exit(1);
+        }
+    }
+    Ok(())
+}
+
+fn print_table(chapters: &BTreeMap<String, [Tally; 2]>) {
+    let mut totals = [Tally::default(), Tally::default()];
+    print!("{:<24}", "chapter");
+    for backend in &Backend::ALL {
+        print!("{:>18}", backend.name());
+    }
+    println!();
+    for (chapter, tallies) in chapters {
+        print!("{:<24}", chapter);
+        for (tally, total) in tallies.iter().zip(totals.iter_mut()) {
+            print_rate(tally);
+            total.passed += tally.passed;
+            total.total += tally.total;
+        }
+        println!();
+    }
+    print!("{:<24}", "total");
+    for total in &totals {
+        print_rate(total);
+    }
+    println!();
+}
+
+fn print_rate(tally: &Tally) {
+    let percent = if tally.total == 0 {
+        100.0
+    } else {
+        tally.passed as f64 * 100.0 / tally.total as f64
+    };
+    let count = format!("{}/{}", tally.passed, tally.total);
+    print!("{:>12} {:>4.0}%", count, percent);
+}
+
+fn usage() -> ! {
+    println!("Usage: lox-conformance [--verbose] [--baseline <file>] [--write-baseline <file>] <test dir>");
+    std::process::exit(64);
+}
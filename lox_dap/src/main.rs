@@ -0,0 +1,421 @@
+//! Debug adapter for Lox, speaking the Debug Adapter Protocol over stdin and
+//! stdout. It runs one script with the tree-walk interpreter and lets the
+//! editor set breakpoints by line, step through statements, look at the
+//! scopes of every frame and evaluate expressions while paused.
+
+mod protocol;
+
+use std::{cell::RefCell, collections::BTreeSet, io, rc::Rc};
+
+use lox::{
+    debugger::{DebugClient, Debugger, PauseReason, PausedSession, Resume},
+    environment::Environment,
+    host::{Input, Output},
+    interpreter::{CancelHandle, Interpreter, InterpreterError},
+    value::RuntimeValue,
+    LoxError,
+};
+use serde_json::{json, Value};
+
+use protocol::Connection;
+
+const THREAD_ID: u64 = 1;
+
+fn main() -> anyhow::Result<()> {
+    let connection = Rc::new(RefCell::new(Connection::stdio()));
+
+    // everything the editor sends before `configurationDone`
+    let mut program = None;
+    let mut stop_on_entry = false;
+    let mut breakpoints = BTreeSet::new();
+    loop {
+        let request = match connection.borrow().recv() {
+            Some(request) => request,
+            None => return Ok(()),
+        };
+        let mut connection = connection.borrow_mut();
+        match request["command"].as_str().unwrap_or_default() {
+            "initialize" => {
+                connection.respond(
+                    &request,
+                    json!({
+                        "supportsConfigurationDoneRequest": true,
+                        "supportsEvaluateForHovers": true,
+                    }),
+                );
+                connection.event("initialized", json!({}));
+            }
+            "launch" => {
+                let arguments = &request["arguments"];
+                let path = arguments["program"].as_str().unwrap_or_default();
+                match std::fs::read_to_string(path) {
+                    Ok(source) => {
+                        program = Some((path.to_string(), source));
+                        stop_on_entry = arguments["stopOnEntry"].as_bool().unwrap_or(false);
+                        connection.respond(&request, json!({}));
+                    }
+                    Err(e) => {
+                        connection.respond_error(&request, &format!("Can't read '{}': {}", path, e))
+                    }
+                }
+            }
+            "setBreakpoints" => {
+                let body = set_breakpoints(&request, &mut breakpoints);
+                connection.respond(&request, body);
+            }
+            "threads" => connection.respond(&request, threads_body()),
+            "configurationDone" => {
+                connection.respond(&request, json!({}));
+                break;
+            }
+            "disconnect" => {
+                connection.respond(&request, json!({}));
+                return Ok(());
+            }
+            command => {
+                connection.respond_error(&request, &format!("Unsupported request '{}'.", command))
+            }
+        }
+    }
+
+    let (path, source) = match program {
+        Some(program) => program,
+        None => {
+            let mut connection = connection.borrow_mut();
+            connection.event(
+                "output",
+                json!({ "category": "stderr", "output": "No program was launched.\n" }),
+            );
+            connection.event("terminated", json!({}));
+            return Ok(());
+        }
+    };
+
+    let mut interpreter = Interpreter::new();
+    interpreter.set_output(Box::new(EventOutput(connection.clone())));
+    interpreter.set_input(Box::new(NoInput));
+    let client = Client {
+        connection: connection.clone(),
+        cancel: interpreter.cancel_handle(),
+        path,
+        handles: vec![],
+    };
+    let mut debugger = Debugger::new(client);
+    *debugger.breakpoints() = breakpoints;
+    if stop_on_entry {
+        debugger = debugger.with_stop_on_entry();
+    }
+    interpreter.set_hooks(Some(Box::new(debugger)));
+
+    let exit_code = match lox::run_source(&mut interpreter, source) {
+        Ok(()) => 0,
+        Err(LoxError::Runtime(InterpreterError::Exit(code))) => code,
+        Err(LoxError::Runtime(InterpreterError::Cancelled)) => 0,
+        Err(e) => {
+            connection.borrow_mut().event(
+                "output",
+                json!({ "category": "stderr", "output": format!("{}\n", e) }),
+            );
+            1
+        }
+    };
+
+    let mut connection = connection.borrow_mut();
+    connection.event("exited", json!({ "exitCode": exit_code }));
+    connection.event("terminated", json!({}));
+    while let Some(request) = connection.recv() {
+        if request["command"] == "disconnect" {
+            connection.respond(&request, json!({}));
+            break;
+        }
+        connection.respond_error(&request, "The program has ended.");
+    }
+    Ok(())
+}
+
+// `print` shows up in the editor's debug console
+struct EventOutput(Rc<RefCell<Connection>>);
+
+impl Output for EventOutput {
+    fn print(&mut self, line: &str) {
+        self.0.borrow_mut().event(
+            "output",
+            json!({ "category": "stdout", "output": format!("{}\n", line) }),
+        );
+    }
+}
+
+// stdin carries the protocol, so there is nothing for `readLine` to read
+struct NoInput;
+
+impl Input for NoInput {
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        Ok(None)
+    }
+}
+
+// something the editor can expand, identified by its position plus one
+enum Handle {
+    Scope(Environment),
+    Value(RuntimeValue),
+}
+
+struct Client {
+    connection: Rc<RefCell<Connection>>,
+    cancel: CancelHandle,
+    path: String,
+    // only valid while paused, the editor asks for them again each time
+    handles: Vec<Handle>,
+}
+
+impl DebugClient for Client {
+    fn poll(&mut self, breakpoints: &mut BTreeSet<usize>) -> bool {
+        let mut pause = false;
+        let mut connection = self.connection.borrow_mut();
+        while let Ok(request) = connection.try_recv() {
+            match request["command"].as_str().unwrap_or_default() {
+                "setBreakpoints" => {
+                    let body = set_breakpoints(&request, breakpoints);
+                    connection.respond(&request, body);
+                }
+                "threads" => connection.respond(&request, threads_body()),
+                "pause" => {
+                    connection.respond(&request, json!({}));
+                    pause = true;
+                }
+                "disconnect" => {
+                    self.cancel.cancel();
+                    connection.respond(&request, json!({}));
+                }
+                _ => connection.respond_error(&request, "The program is running."),
+            }
+        }
+        pause
+    }
+
+    fn paused(&mut self, session: &mut PausedSession<'_>) -> Resume {
+        let reason = match session.reason() {
+            PauseReason::Entry => "entry",
+            PauseReason::Breakpoint => "breakpoint",
+            PauseReason::Step => "step",
+            PauseReason::Pause => "pause",
+        };
+        self.connection.borrow_mut().event(
+            "stopped",
+            json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true }),
+        );
+        self.handles.clear();
+
+        loop {
+            let request = match self.connection.borrow().recv() {
+                Some(request) => request,
+                None => {
+                    self.cancel.cancel();
+                    return Resume::Continue;
+                }
+            };
+            let arguments = &request["arguments"];
+            let resume = match request["command"].as_str().unwrap_or_default() {
+                "continue" => Some(Resume::Continue),
+                "next" => Some(Resume::StepOver),
+                "stepIn" => Some(Resume::StepIn),
+                "stepOut" => Some(Resume::StepOut),
+                "disconnect" => {
+                    self.cancel.cancel();
+                    Some(Resume::Continue)
+                }
+                "stackTrace" => {
+                    let body = self.stack_trace(session);
+                    self.connection.borrow_mut().respond(&request, body);
+                    None
+                }
+                "scopes" => {
+                    let frame = arguments["frameId"].as_u64().unwrap_or(0) as usize;
+                    let body = self.scopes(session, frame);
+                    self.connection.borrow_mut().respond(&request, body);
+                    None
+                }
+                "variables" => {
+                    let reference = arguments["variablesReference"].as_u64().unwrap_or(0);
+                    let body = self.variables(reference as usize);
+                    self.connection.borrow_mut().respond(&request, body);
+                    None
+                }
+                "evaluate" => {
+                    let frame = arguments["frameId"].as_u64().unwrap_or(0) as usize;
+                    let expression = arguments["expression"].as_str().unwrap_or_default();
+                    match session.evaluate(frame, expression) {
+                        Ok(value) => {
+                            let body = json!({
+                                "result": display(&value),
+                                "variablesReference": self.handle_for(&value),
+                            });
+                            self.connection.borrow_mut().respond(&request, body);
+                        }
+                        Err(e) => self
+                            .connection
+                            .borrow_mut()
+                            .respond_error(&request, &e.to_string()),
+                    }
+                    None
+                }
+                "setBreakpoints" => {
+                    let body = set_breakpoints(&request, session.breakpoints());
+                    self.connection.borrow_mut().respond(&request, body);
+                    None
+                }
+                "threads" => {
+                    self.connection
+                        .borrow_mut()
+                        .respond(&request, threads_body());
+                    None
+                }
+                "pause" => {
+                    self.connection.borrow_mut().respond(&request, json!({}));
+                    None
+                }
+                command => {
+                    self.connection
+                        .borrow_mut()
+                        .respond_error(&request, &format!("Unsupported request '{}'.", command));
+                    None
+                }
+            };
+            if let Some(resume) = resume {
+                let body = if resume == Resume::Continue {
+                    json!({ "allThreadsContinued": true })
+                } else {
+                    json!({})
+                };
+                self.connection.borrow_mut().respond(&request, body);
+                return resume;
+            }
+        }
+    }
+}
+
+impl Client {
+    fn stack_trace(&self, session: &PausedSession<'_>) -> Value {
+        let frames = session
+            .frames()
+            .iter()
+            .enumerate()
+            .map(|(id, frame)| {
+                json!({
+                    "id": id,
+                    "name": frame.name,
+                    "line": frame.line,
+                    "column": 1,
+                    "source": { "path": self.path },
+                })
+            })
+            .collect::<Vec<_>>();
+        json!({ "stackFrames": frames, "totalFrames": frames.len() })
+    }
+
+    // a scope for each environment from the frame's innermost one out
+    fn scopes(&mut self, session: &PausedSession<'_>, frame: usize) -> Value {
+        let mut scopes = vec![];
+        let mut environment = session.frames().get(frame).map(|it| it.environment.clone());
+        while let Some(current) = environment {
+            environment = current.enclosing();
+            let name = if environment.is_none() {
+                "Globals"
+            } else if scopes.is_empty() {
+                "Locals"
+            } else {
+                "Closure"
+            };
+            self.handles.push(Handle::Scope(current));
+            scopes.push(json!({
+                "name": name,
+                "variablesReference": self.handles.len(),
+                "expensive": false,
+            }));
+        }
+        json!({ "scopes": scopes })
+    }
+
+    fn variables(&mut self, reference: usize) -> Value {
+        let children = match reference.checked_sub(1).and_then(|i| self.handles.get(i)) {
+            Some(Handle::Scope(environment)) => {
+                // the natives would bury the script's own globals
+                let is_globals = environment.enclosing().is_none();
+                environment
+                    .values()
+                    .into_iter()
+                    .filter(|(_, value)| {
+                        !(is_globals && matches!(value, RuntimeValue::BuiltInFunction(_)))
+                    })
+                    .map(|(name, value)| (name.to_string(), value))
+                    .collect()
+            }
+            Some(Handle::Value(RuntimeValue::List(list))) => list
+                .items()
+                .into_iter()
+                .enumerate()
+                .map(|(i, value)| (format!("[{}]", i), value))
+                .collect(),
+            Some(Handle::Value(RuntimeValue::Map(map))) => map
+                .entries()
+                .into_iter()
+                .map(|(key, value)| (display(&key.to_value()), value))
+                .collect(),
+            Some(Handle::Value(RuntimeValue::Instance(instance))) => instance.fields(),
+            _ => vec![],
+        };
+        let variables = children
+            .into_iter()
+            .map(|(name, value)| {
+                json!({
+                    "name": name,
+                    "value": display(&value),
+                    "type": value.type_name(),
+                    "variablesReference": self.handle_for(&value),
+                })
+            })
+            .collect::<Vec<_>>();
+        json!({ "variables": variables })
+    }
+
+    // lists, maps and instances can be expanded in the editor
+    fn handle_for(&mut self, value: &RuntimeValue) -> usize {
+        match value {
+            RuntimeValue::List(_) | RuntimeValue::Map(_) | RuntimeValue::Instance(_) => {
+                self.handles.push(Handle::Value(value.clone()));
+                self.handles.len()
+            }
+            _ => 0,
+        }
+    }
+}
+
+fn display(value: &RuntimeValue) -> String {
+    match value {
+        RuntimeValue::Str(s) => format!("\"{}\"", s),
+        value => value.to_string(),
+    }
+}
+
+// there is only one source, so every breakpoint is taken to be in it
+fn set_breakpoints(request: &Value, breakpoints: &mut BTreeSet<usize>) -> Value {
+    let lines = request["arguments"]["breakpoints"]
+        .as_array()
+        .map(|breakpoints| {
+            breakpoints
+                .iter()
+                .filter_map(|it| it["line"].as_u64())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    *breakpoints = lines.iter().map(|line| *line as usize).collect();
+    let breakpoints = lines
+        .iter()
+        .map(|line| json!({ "verified": true, "line": line }))
+        .collect::<Vec<_>>();
+    json!({ "breakpoints": breakpoints })
+}
+
+fn threads_body() -> Value {
+    json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] })
+}
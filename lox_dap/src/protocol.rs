@@ -0,0 +1,118 @@
+//! The wire format of the Debug Adapter Protocol: JSON messages with a
+//! `Content-Length` header, over stdin and stdout.
+
+use std::{
+    io::{self, BufRead, Write},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+
+use serde_json::{json, Value};
+
+pub struct Connection {
+    receiver: Receiver<Value>,
+    seq: u64,
+}
+
+impl Connection {
+    /// Starts reading requests from stdin on another thread, so they can be
+    /// checked for while the script runs.
+    pub fn stdio() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let stdin = io::stdin();
+            let mut stdin = stdin.lock();
+            while let Ok(Some(message)) = read_message(&mut stdin) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { receiver, seq: 0 }
+    }
+
+    /// The next request, or `None` once the client is gone.
+    pub fn recv(&self) -> Option<Value> {
+        self.receiver.recv().ok()
+    }
+
+    /// The next request if one already arrived.
+    pub fn try_recv(&self) -> Result<Value, TryRecvError> {
+        self.receiver.try_recv()
+    }
+
+    pub fn respond(&mut self, request: &Value, body: Value) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": true,
+            "body": body,
+        }));
+    }
+
+    pub fn respond_error(&mut self, request: &Value, message: &str) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": false,
+            "message": message,
+        }));
+    }
+
+    pub fn event(&mut self, event: &str, body: Value) {
+        self.send(json!({
+            "type": "event",
+            "event": event,
+            "body": body,
+        }));
+    }
+
+    fn send(&mut self, mut message: Value) {
+        self.seq += 1;
+        message["seq"] = self.seq.into();
+        let content = message.to_string();
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        // if stdout is gone so is the client, and the next read ends things
+        let _ = write!(
+            stdout,
+            "Content-Length: {}\r\n\r\n{}",
+            content.len(),
+            content
+        );
+        let _ = stdout.flush();
+    }
+}
+
+fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let length = match length {
+        Some(length) => length,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Missing Content-Length header.",
+            ))
+        }
+    };
+    let mut content = vec![0; length];
+    reader.read_exact(&mut content)?;
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
@@ -0,0 +1,67 @@
+#ifndef LOX_H
+#define LOX_H
+
+#include <stdbool.h>
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct LoxVm LoxVm;
+typedef struct LoxValue LoxValue;
+
+typedef enum LoxValueType {
+    LOX_NIL,
+    LOX_BOOL,
+    LOX_NUMBER,
+    LOX_STRING,
+    LOX_FUNCTION,
+    LOX_CLASS,
+    LOX_INSTANCE,
+    LOX_LIST,
+    LOX_MAP,
+} LoxValueType;
+
+#define LOX_OK 0
+#define LOX_COMPILE_ERROR 65
+#define LOX_RUNTIME_ERROR 70
+
+/* Arguments are borrowed for the duration of the call. Return a new value,
+ * or NULL to raise a runtime error. */
+typedef LoxValue *(*LoxNativeFn)(void *userdata, const LoxValue *const *args, size_t argc);
+
+LoxVm *lox_new(void);
+void lox_free(LoxVm *vm);
+
+/* Runs source in the vm, keeping globals between calls. Returns one of the
+ * LOX_* result codes. */
+int lox_eval(LoxVm *vm, const char *source);
+/* Message of the last failed lox_eval, or NULL. Valid until the next eval. */
+const char *lox_last_error(const LoxVm *vm);
+
+/* Returns a copy of the global, or NULL if it is not defined. */
+LoxValue *lox_get_global(const LoxVm *vm, const char *name);
+void lox_set_global(LoxVm *vm, const char *name, const LoxValue *value);
+int lox_register_fn(LoxVm *vm, const char *name, size_t arity, LoxNativeFn function, void *userdata);
+
+/* Every LoxValue returned to the host must be released with lox_value_free. */
+LoxValue *lox_value_nil(void);
+LoxValue *lox_value_bool(bool value);
+LoxValue *lox_value_number(double value);
+LoxValue *lox_value_string(const char *value);
+void lox_value_free(LoxValue *value);
+
+LoxValueType lox_value_type(const LoxValue *value);
+bool lox_value_as_bool(const LoxValue *value);
+/* NaN if the value is not a number. */
+double lox_value_as_number(const LoxValue *value);
+/* The value formatted as print would. Release it with lox_string_free. */
+char *lox_value_to_string(const LoxValue *value);
+void lox_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,295 @@
+//! C bindings for embedding the tree-walk interpreter. See `include/lox.h`
+//! for the matching declarations.
+//!
+//! Every `LoxValue*` handed to the host is owned by it and must be released
+//! with `lox_value_free`. Strings returned by `lox_value_to_string` must be
+//! released with `lox_string_free`.
+
+use std::{
+    ffi::{CStr, CString},
+    os::raw::{c_char, c_int, c_void},
+    panic::{catch_unwind, AssertUnwindSafe},
+    ptr,
+};
+
+use lox::{
+    interpreter::{Interpreter, InterpreterError},
+    value::{BuiltInFunction, RuntimeValue},
+    LoxError,
+};
+
+pub struct LoxVm {
+    interpreter: Interpreter,
+    last_error: Option<CString>,
+}
+
+pub struct LoxValue(RuntimeValue);
+
+#[repr(C)]
+pub enum LoxValueType {
+    Nil,
+    Bool,
+    Number,
+    String,
+    Function,
+    Class,
+    Instance,
+    List,
+    Map,
+}
+
+pub const LOX_OK: c_int = 0;
+pub const LOX_COMPILE_ERROR: c_int = 65;
+pub const LOX_RUNTIME_ERROR: c_int = 70;
+
+pub type LoxNativeFn = unsafe extern "C" fn(
+    userdata: *mut c_void,
+    args: *const *const LoxValue,
+    argc: usize,
+) -> *mut LoxValue;
+
+// the host is responsible for making its userdata safe to use from
+// wherever it calls into the interpreter.
+struct UserData(*mut c_void);
+unsafe impl Send for UserData {}
+unsafe impl Sync for UserData {}
+
+fn into_raw(value: RuntimeValue) -> *mut LoxValue {
+    Box::into_raw(Box::new(LoxValue(value)))
+}
+
+unsafe fn str_arg<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        None
+    } else {
+        CStr::from_ptr(s).to_str().ok()
+    }
+}
+
+fn c_string(s: String) -> CString {
+    CString::new(s.replace('\0', "\\0")).unwrap_or_default()
+}
+
+#[no_mangle]
+pub extern "C" fn lox_new() -> *mut LoxVm {
+    Box::into_raw(Box::new(LoxVm {
+        interpreter: Interpreter::new(),
+        last_error: None,
+    }))
+}
+
+/// # Safety
+/// `vm` must come from `lox_new` and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn lox_free(vm: *mut LoxVm) {
+    if !vm.is_null() {
+        drop(Box::from_raw(vm));
+    }
+}
+
+/// Runs `source` in the vm. Globals are kept between calls.
+///
+/// # Safety
+/// `vm` must come from `lox_new` and `source` must be a nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn lox_eval(vm: *mut LoxVm, source: *const c_char) -> c_int {
+    let vm = &mut *vm;
+    let source = match str_arg(source) {
+        Some(s) => s.to_string(),
+        None => {
+            vm.last_error = Some(c_string("Source is not valid UTF-8.".into()));
+            return LOX_COMPILE_ERROR;
+        }
+    };
+
+    let interpreter = &mut vm.interpreter;
+    let result = catch_unwind(AssertUnwindSafe(|| lox::run_source(interpreter, source)));
+    let (code, message) = match result {
+        Ok(Ok(())) => (LOX_OK, None),
+        Ok(Err(e @ LoxError::Runtime(_))) => (LOX_RUNTIME_ERROR, Some(e.to_string())),
+        Ok(Err(e)) => (LOX_COMPILE_ERROR, Some(e.to_string())),
+        Err(_) => (
+            LOX_RUNTIME_ERROR,
+            Some("Internal interpreter error.".into()),
+        ),
+    };
+    vm.last_error = message.map(c_string);
+    code
+}
+
+/// Message of the last failed `lox_eval`, or NULL. The pointer is valid
+/// until the next call to `lox_eval`.
+///
+/// # Safety
+/// `vm` must come from `lox_new`.
+#[no_mangle]
+pub unsafe extern "C" fn lox_last_error(vm: *const LoxVm) -> *const c_char {
+    match &(*vm).last_error {
+        Some(e) => e.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Returns a copy of the global `name`, or NULL if it is not defined.
+///
+/// # Safety
+/// `vm` must come from `lox_new` and `name` must be a nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn lox_get_global(vm: *const LoxVm, name: *const c_char) -> *mut LoxValue {
+    match str_arg(name).and_then(|name| (*vm).interpreter.get_global(name)) {
+        Some(value) => into_raw(value),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Defines the global `name` with a copy of `value`.
+///
+/// # Safety
+/// `vm` must come from `lox_new`, `name` must be a nul terminated string and
+/// `value` a live value.
+#[no_mangle]
+pub unsafe extern "C" fn lox_set_global(
+    vm: *mut LoxVm,
+    name: *const c_char,
+    value: *const LoxValue,
+) {
+    if let Some(name) = str_arg(name) {
+        (*vm).interpreter.define_global(name, (*value).0.clone());
+    }
+}
+
+/// Registers `function` as the global native `name`. The arguments passed
+/// to it are borrowed for the duration of the call. It must return a new
+/// value, or NULL to raise a runtime error.
+///
+/// # Safety
+/// `vm` must come from `lox_new` and `name` must be a nul terminated string.
+/// `userdata` is passed back untouched to `function`.
+#[no_mangle]
+pub unsafe extern "C" fn lox_register_fn(
+    vm: *mut LoxVm,
+    name: *const c_char,
+    arity: usize,
+    function: LoxNativeFn,
+    userdata: *mut c_void,
+) -> c_int {
+    let name = match str_arg(name) {
+        Some(name) => name.to_string(),
+        None => return LOX_COMPILE_ERROR,
+    };
+    let userdata = UserData(userdata);
+    let args = (0..arity).map(|_| "_").collect();
+    let native_name = name.clone();
+    let native = BuiltInFunction::new(&name, args, move |_, args| {
+        let args = args.into_iter().map(LoxValue).collect::<Vec<_>>();
+        let arg_pointers = args
+            .iter()
+            .map(|it| it as *const LoxValue)
+            .collect::<Vec<_>>();
+        let result = function(userdata.0, arg_pointers.as_ptr(), arg_pointers.len());
+        if result.is_null() {
+            Err(InterpreterError::NativeFunction(
+                native_name.clone(),
+                "Host function failed.".into(),
+            ))
+        } else {
+            Ok(Box::from_raw(result).0)
+        }
+    });
+    (*vm)
+        .interpreter
+        .define_global(&name, RuntimeValue::BuiltInFunction(native));
+    LOX_OK
+}
+
+#[no_mangle]
+pub extern "C" fn lox_value_nil() -> *mut LoxValue {
+    into_raw(RuntimeValue::Nil)
+}
+
+#[no_mangle]
+pub extern "C" fn lox_value_bool(value: bool) -> *mut LoxValue {
+    into_raw(RuntimeValue::Bool(value))
+}
+
+#[no_mangle]
+pub extern "C" fn lox_value_number(value: f64) -> *mut LoxValue {
+    into_raw(RuntimeValue::Float(value))
+}
+
+/// # Safety
+/// `value` must be a nul terminated UTF-8 string.
+#[no_mangle]
+pub unsafe extern "C" fn lox_value_string(value: *const c_char) -> *mut LoxValue {
+    match str_arg(value) {
+        Some(s) => into_raw(RuntimeValue::Str(s.into())),
+        None => ptr::null_mut(),
+    }
+}
+
+/// # Safety
+/// `value` must be NULL or a value that was not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn lox_value_free(value: *mut LoxValue) {
+    if !value.is_null() {
+        drop(Box::from_raw(value));
+    }
+}
+
+/// # Safety
+/// `value` must be a live value.
+#[no_mangle]
+pub unsafe extern "C" fn lox_value_type(value: *const LoxValue) -> LoxValueType {
+    match &(*value).0 {
+        RuntimeValue::Nil => LoxValueType::Nil,
+        RuntimeValue::Bool(_) => LoxValueType::Bool,
+        RuntimeValue::Float(_) | RuntimeValue::BigInt(_) => LoxValueType::Number,
+        RuntimeValue::Str(_) => LoxValueType::String,
+        RuntimeValue::BuiltInFunction(_) | RuntimeValue::UserFunction(_) => LoxValueType::Function,
+        RuntimeValue::Class(_) => LoxValueType::Class,
+        RuntimeValue::Instance(_) => LoxValueType::Instance,
+        RuntimeValue::List(_) => LoxValueType::List,
+        RuntimeValue::Map(_) => LoxValueType::Map,
+    }
+}
+
+/// Lox truthiness of the value.
+///
+/// # Safety
+/// `value` must be a live value.
+#[no_mangle]
+pub unsafe extern "C" fn lox_value_as_bool(value: *const LoxValue) -> bool {
+    (*value).0.is_truthy()
+}
+
+/// The number held by `value`, or NaN if it is not a number. Integers
+/// of `--bignum` are rounded to the closest double.
+///
+/// # Safety
+/// `value` must be a live value.
+#[no_mangle]
+pub unsafe extern "C" fn lox_value_as_number(value: *const LoxValue) -> f64 {
+    match &(*value).0 {
+        RuntimeValue::Float(x) => *x,
+        RuntimeValue::BigInt(x) => x.to_f64(),
+        _ => f64::NAN,
+    }
+}
+
+/// The value formatted as `print` would. Free it with `lox_string_free`.
+///
+/// # Safety
+/// `value` must be a live value.
+#[no_mangle]
+pub unsafe extern "C" fn lox_value_to_string(value: *const LoxValue) -> *mut c_char {
+    c_string((*value).0.to_string()).into_raw()
+}
+
+/// # Safety
+/// `s` must be NULL or come from `lox_value_to_string`.
+#[no_mangle]
+pub unsafe extern "C" fn lox_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
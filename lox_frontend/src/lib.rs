@@ -0,0 +1,10 @@
+//! The scanner and tokens that both interpreters read Lox with, so they
+//! agree on what a script's tokens are, where each one is, and which
+//! scripts can't be scanned at all. Each interpreter wraps the tokens with
+//! what it needs of them, like the values of literals.
+
+mod scanner;
+mod token;
+
+pub use scanner::{ScanError, Scanner};
+pub use token::{identifier_name, Token, TokenKind};
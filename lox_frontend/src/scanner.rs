@@ -0,0 +1,273 @@
+use std::{error::Error, fmt::Display};
+
+use unicode_ident::{is_xid_continue, is_xid_start};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::token::{Token, TokenKind};
+
+/// Splits the source into tokens as they're asked for, ending with an `Eof`
+/// token. Errors are returned in place of the token they'd have been, and
+/// scanning carries on after them, for whoever wants to report more than
+/// one. Spans are byte offsets into the source.
+pub struct Scanner<S> {
+    source: S,
+    finished: bool,
+
+    // byte offsets into the source, always on character boundaries. the
+    // token being scanned is the slice between them.
+    start: usize,
+    current: usize,
+    line: usize,
+    keep_comments: bool,
+    print_keyword: bool,
+}
+
+impl<S: AsRef<str>> Scanner<S> {
+    pub fn new(source: S) -> Self {
+        Self {
+            source,
+            finished: false,
+            start: 0,
+            current: 0,
+            line: 1,
+            keep_comments: false,
+            print_keyword: false,
+        }
+    }
+
+    /// Also produces a `Comment` token for each comment. Parsers don't
+    /// expect them, these tokens are for tools that reproduce the source.
+    pub fn with_comments(mut self) -> Self {
+        self.keep_comments = true;
+        self
+    }
+
+    /// Scans `print` as the keyword of the book's print statement, instead
+    /// of as a name.
+    pub fn with_print_keyword(mut self) -> Self {
+        self.print_keyword = true;
+        self
+    }
+
+    pub fn source(&self) -> &str {
+        self.source.as_ref()
+    }
+
+    /// Where the last token or error starts on its line, counting grapheme
+    /// clusters from 1.
+    pub fn last_column(&self) -> usize {
+        self.column(self.start)
+    }
+
+    // `None` for whitespace, and for comments unless they're kept
+    fn scan_token(&mut self) -> Result<Option<TokenKind>, ScanError> {
+        let Some(c) = self.advance() else {
+            return Ok(None);
+        };
+        let kind = match c {
+            '(' => TokenKind::LeftParen,
+            ')' => TokenKind::RightParen,
+            '{' => TokenKind::LeftBrace,
+            '}' => TokenKind::RightBrace,
+            ':' => TokenKind::Colon,
+            ',' => TokenKind::Comma,
+            '.' => TokenKind::Dot,
+            '-' => TokenKind::Minus,
+            '+' => TokenKind::Plus,
+            ';' => TokenKind::Semicolon,
+            '*' => TokenKind::Star,
+            '!' if self.check('=') => {
+                if self.check('=') {
+                    TokenKind::BangEqualEqual
+                } else {
+                    TokenKind::BangEqual
+                }
+            }
+            '!' => TokenKind::Bang,
+            '=' if self.check('=') => {
+                if self.check('=') {
+                    TokenKind::EqualEqualEqual
+                } else {
+                    TokenKind::EqualEqual
+                }
+            }
+            '=' => TokenKind::Equal,
+            '<' if self.check('=') => TokenKind::LessEqual,
+            '<' => TokenKind::Less,
+            '>' if self.check('=') => TokenKind::GreaterEqual,
+            '>' => TokenKind::Greater,
+            '/' if self.check('/') => {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.advance();
+                }
+                if !self.keep_comments {
+                    return Ok(None);
+                }
+                TokenKind::Comment
+            }
+            '/' => TokenKind::Slash,
+            ' ' | '\r' | '\t' => return Ok(None),
+            '\n' => {
+                self.line += 1;
+                return Ok(None);
+            }
+            '"' => self.string()?,
+            c if c.is_ascii_digit() => self.number(),
+            c if c == '_' || is_xid_start(c) => self.identifier(),
+            _ => {
+                // the whole of what looks like one character, accents and
+                // all, instead of each mark being another error
+                let grapheme = self.source()[self.start..].graphemes(true).next();
+                let grapheme = grapheme.unwrap_or_default().to_string();
+                self.current = self.start + grapheme.len();
+                return Err(ScanError::UnexpectedCharacter(grapheme, self.line));
+            }
+        };
+        Ok(Some(kind))
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.current += c.len_utf8();
+        Some(c)
+    }
+
+    fn check(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.current += expected.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.char_at(self.current)
+    }
+
+    fn peek_next(&self) -> Option<char> {
+        let next = self.current + self.peek()?.len_utf8();
+        self.char_at(next)
+    }
+
+    // everything the scanner looks for is ascii, so a byte is only decoded
+    // into a whole character when it isn't one
+    fn char_at(&self, index: usize) -> Option<char> {
+        let byte = *self.source().as_bytes().get(index)?;
+        if byte.is_ascii() {
+            Some(byte as char)
+        } else {
+            self.source()[index..].chars().next()
+        }
+    }
+
+    fn string(&mut self) -> Result<TokenKind, ScanError> {
+        loop {
+            match self.advance() {
+                Some('"') => return Ok(TokenKind::String),
+                Some('\n') => self.line += 1,
+                Some(_) => {}
+                None => return Err(ScanError::UnterminatedString(self.line)),
+            }
+        }
+    }
+
+    fn number(&mut self) -> TokenKind {
+        self.digits();
+        if self.peek() == Some('.') && self.peek_next().is_some_and(|c| c.is_ascii_digit()) {
+            self.advance();
+            self.digits();
+        }
+        TokenKind::Number
+    }
+
+    fn digits(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.advance();
+        }
+    }
+
+    fn identifier(&mut self) -> TokenKind {
+        while self.peek().is_some_and(is_xid_continue) {
+            self.advance();
+        }
+        let lexeme = &self.source()[self.start..self.current];
+        TokenKind::keyword(lexeme, self.print_keyword).unwrap_or(TokenKind::Identifier)
+    }
+
+    fn make_token(&self, kind: TokenKind) -> Token {
+        Token {
+            kind,
+            span: self.start..self.current,
+            line: self.line,
+            column: self.column(self.start),
+        }
+    }
+
+    // the column of the byte at `index`, counting back to the start of its
+    // line
+    fn column(&self, index: usize) -> usize {
+        let source = self.source();
+        let line_start = source[..index].rfind('\n').map_or(0, |it| it + 1);
+        source[line_start..index].graphemes(true).count() + 1
+    }
+}
+
+impl<S: AsRef<str>> Iterator for Scanner<S> {
+    type Item = Result<Token, ScanError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        while self.current < self.source().len() {
+            self.start = self.current;
+            match self.scan_token() {
+                Ok(Some(kind)) => return Some(Ok(self.make_token(kind))),
+                Ok(None) => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        self.finished = true;
+        self.start = self.current;
+        Some(Ok(self.make_token(TokenKind::Eof)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanError {
+    /// the character, with any that combine with it
+    UnexpectedCharacter(String, usize),
+    UnterminatedString(usize),
+}
+
+impl ScanError {
+    pub fn line(&self) -> usize {
+        match self {
+            ScanError::UnexpectedCharacter(_, line) | ScanError::UnterminatedString(line) => *line,
+        }
+    }
+
+    /// What went wrong, without where.
+    pub fn message(&self) -> &'static str {
+        match self {
+            ScanError::UnexpectedCharacter(..) => "Unexpected character.",
+            ScanError::UnterminatedString(_) => "Unterminated string.",
+        }
+    }
+}
+
+impl Display for ScanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScanError::UnexpectedCharacter(c, line) => {
+                write!(f, "[Line {}] Unexpected character '{}'.", line, c)
+            }
+            ScanError::UnterminatedString(line) => {
+                write!(f, "[Line {}] Unterminated string.", line)
+            }
+        }
+    }
+}
+
+impl Error for ScanError {}
@@ -0,0 +1,125 @@
+use std::{borrow::Cow, ops::Range};
+
+use lox_proc_macros::U8Enum;
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, U8Enum)]
+#[repr(u8)]
+pub enum TokenKind {
+    LeftParen,
+    RightParen,
+    LeftBrace,
+    RightBrace,
+    Colon,
+    Comma,
+    Dot,
+    Minus,
+    Plus,
+    Semicolon,
+    Slash,
+    Star,
+
+    // One to three character tokens.
+    Bang,
+    BangEqual,
+    BangEqualEqual,
+    Equal,
+    EqualEqual,
+    EqualEqualEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+
+    // Literals.
+    Identifier,
+    String,
+    Number,
+
+    // Keywords.
+    And,
+    Break,
+    Breakpoint,
+    Class,
+    Continue,
+    Else,
+    False,
+    Fun,
+    For,
+    If,
+    Nil,
+    Or,
+    Print,
+    Return,
+    Super,
+    This,
+    True,
+    Var,
+    While,
+
+    // only produced by 'Scanner::with_comments', for the formatter
+    Comment,
+    // never produced by 'Scanner', which returns a 'ScanError' instead. for
+    // scanners that hand errors to the parser as tokens
+    Error,
+
+    Eof,
+}
+
+impl TokenKind {
+    /// The keyword spelled `lexeme`, if it's one. `print` is only a keyword
+    /// for scanners that have the book's print statement.
+    pub fn keyword(lexeme: &str, print: bool) -> Option<TokenKind> {
+        let kind = match lexeme {
+            "and" => TokenKind::And,
+            "break" => TokenKind::Break,
+            "breakpoint" => TokenKind::Breakpoint,
+            "class" => TokenKind::Class,
+            "continue" => TokenKind::Continue,
+            "else" => TokenKind::Else,
+            "false" => TokenKind::False,
+            "for" => TokenKind::For,
+            "fun" => TokenKind::Fun,
+            "if" => TokenKind::If,
+            "nil" => TokenKind::Nil,
+            "or" => TokenKind::Or,
+            "print" if print => TokenKind::Print,
+            "return" => TokenKind::Return,
+            "super" => TokenKind::Super,
+            "this" => TokenKind::This,
+            "true" => TokenKind::True,
+            "var" => TokenKind::Var,
+            "while" => TokenKind::While,
+            _ => return None,
+        };
+        Some(kind)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// the bytes of the source the token was scanned from
+    pub span: Range<usize>,
+    pub line: usize,
+    /// where the token starts on its line, counting grapheme clusters from
+    /// 1, as a reader would
+    pub column: usize,
+}
+
+impl Token {
+    /// The source of the token.
+    pub fn lexeme<'source>(&self, source: &'source str) -> &'source str {
+        &source[self.span.clone()]
+    }
+}
+
+/// The name an identifier's lexeme stands for: the lexeme in Unicode's NFC,
+/// so names that look the same are the same.
+pub fn identifier_name(lexeme: &str) -> Cow<'_, str> {
+    if is_nfc(lexeme) {
+        Cow::Borrowed(lexeme)
+    } else {
+        Cow::Owned(lexeme.nfc().collect())
+    }
+}
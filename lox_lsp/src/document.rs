@@ -0,0 +1,335 @@
+use lox::{
+    analysis::find_unreachable_code,
+    ast::{Expr, FunctionStmt, Stmt},
+    interpreter::Interpreter,
+    parser::Parser,
+    resolver::{Reference, Resolver},
+    scanner::{ScanError, Scanner},
+    token::Token,
+    LoxError,
+};
+use lsp_types::{Diagnostic, DiagnosticSeverity, DocumentSymbol, Position, Range, SymbolKind};
+
+/// An open file and everything the language server knows about it, worked
+/// out again every time it changes.
+pub struct Document {
+    lines: LineIndex,
+    pub diagnostics: Vec<Diagnostic>,
+    statements: Vec<Stmt>,
+    references: Vec<Reference>,
+    declarations: Vec<Declaration>,
+}
+
+// a name that's introduced somewhere, and how it's shown on hover
+struct Declaration {
+    name: Token,
+    signature: String,
+}
+
+impl Document {
+    pub fn new(text: &str) -> Self {
+        let mut document = Self {
+            lines: LineIndex::new(text),
+            diagnostics: vec![],
+            statements: vec![],
+            references: vec![],
+            declarations: vec![],
+        };
+
+        let statements = match Parser::new(Scanner::new(text.to_string())).parse() {
+            Ok(statements) => statements,
+            Err(LoxError::Scan(e)) => {
+                let (line, message) = match e {
+                    ScanError::UnexpectedCharacter(c, line) => {
+                        (line, format!("Unexpected character '{}'.", c))
+                    }
+                    ScanError::UnterminatedString(line) => (line, "Unterminated string.".into()),
+                };
+                let range = document.lines.line_range(line);
+                document.error(range, message);
+                return document;
+            }
+            Err(LoxError::Parse(errors)) => {
+                for e in errors {
+                    let range = document.lines.token_range(e.token());
+                    document.error(range, e.message().into());
+                }
+                return document;
+            }
+            Err(_) => unreachable!("parsing only fails with scan or parse errors"),
+        };
+
+        let mut interpreter = Interpreter::new();
+        let mut resolver = Resolver::new(&mut interpreter);
+        resolver.resolve(&statements);
+        let resolution = resolver.finish();
+        for e in &resolution.errors {
+            let range = document.lines.token_range(e.token());
+            document.error(range, e.message().into());
+        }
+        let warnings = resolution
+            .warnings
+            .iter()
+            .chain(&find_unreachable_code(&statements))
+            .map(|warning| {
+                let start = document.lines.token_range(warning.token()).start;
+                let end = document.lines.line_range(*warning.lines().end()).end;
+                Diagnostic {
+                    range: Range { start, end },
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    source: Some("lox".into()),
+                    message: warning.message().into(),
+                    ..Default::default()
+                }
+            })
+            .collect::<Vec<_>>();
+        document.diagnostics.extend(warnings);
+
+        collect_declarations(&statements, &mut document.declarations);
+        document.references = resolution.references;
+        document.statements = statements;
+        document
+    }
+
+    fn error(&mut self, range: Range, message: String) {
+        self.diagnostics.push(Diagnostic {
+            range,
+            severity: Some(DiagnosticSeverity::ERROR),
+            source: Some("lox".into()),
+            message,
+            ..Default::default()
+        });
+    }
+
+    /// Where the variable at `position` was declared.
+    pub fn definition(&self, position: Position) -> Option<Range> {
+        let offset = self.lines.offset(position);
+        if let Some(reference) = self.references.iter().find(|it| contains(&it.name, offset)) {
+            return Some(self.lines.token_range(&reference.declaration));
+        }
+        self.declarations
+            .iter()
+            .find(|it| contains(&it.name, offset))
+            .map(|it| self.lines.token_range(&it.name))
+    }
+
+    /// The signature of what's declared or used at `position`, and the range
+    /// of the name there.
+    pub fn hover(&self, position: Position) -> Option<(String, Range)> {
+        let offset = self.lines.offset(position);
+        let (name, declaration) = match self.references.iter().find(|it| contains(&it.name, offset))
+        {
+            Some(reference) => (&reference.name, &reference.declaration),
+            None => {
+                let declaration = self
+                    .declarations
+                    .iter()
+                    .find(|it| contains(&it.name, offset))?;
+                (&declaration.name, &declaration.name)
+            }
+        };
+        let declaration = self
+            .declarations
+            .iter()
+            .find(|it| it.name.scanner_index == declaration.scanner_index)?;
+        Some((declaration.signature.clone(), self.lines.token_range(name)))
+    }
+
+    /// The functions, classes and variables declared at the top level, with
+    /// the methods of each class.
+    pub fn symbols(&self) -> Vec<DocumentSymbol> {
+        self.statements
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::Function(fun) => Some(self.symbol(&fun.name, SymbolKind::FUNCTION, vec![])),
+                Stmt::Var { name, .. } => Some(self.symbol(name, SymbolKind::VARIABLE, vec![])),
+                Stmt::Class { name, methods, .. } => {
+                    let methods = methods
+                        .iter()
+                        .map(|method| {
+                            let kind = if method.name.lexeme == "init" {
+                                SymbolKind::CONSTRUCTOR
+                            } else {
+                                SymbolKind::METHOD
+                            };
+                            self.symbol(&method.name, kind, vec![])
+                        })
+                        .collect();
+                    Some(self.symbol(name, SymbolKind::CLASS, methods))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn symbol(
+        &self,
+        name: &Token,
+        kind: SymbolKind,
+        children: Vec<DocumentSymbol>,
+    ) -> DocumentSymbol {
+        let range = self.lines.token_range(name);
+        let detail = self
+            .declarations
+            .iter()
+            .find(|it| it.name.scanner_index == name.scanner_index)
+            .map(|it| it.signature.clone());
+        #[allow(deprecated)]
+        DocumentSymbol {
+            name: name.lexeme.to_string(),
+            detail,
+            kind,
+            tags: None,
+            deprecated: None,
+            range,
+            selection_range: range,
+            children: Some(children),
+        }
+    }
+}
+
+fn contains(token: &Token, offset: usize) -> bool {
+    let span = token.span();
+    (span.start..=span.end).contains(&offset)
+}
+
+fn collect_declarations(statements: &[Stmt], declarations: &mut Vec<Declaration>) {
+    for stmt in statements {
+        match stmt {
+            Stmt::Var { name, .. } => declarations.push(Declaration {
+                name: name.clone(),
+                signature: format!("var {}", name.lexeme),
+            }),
+            Stmt::Function(fun) => collect_function("fun ", fun, declarations),
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+                statics,
+            } => {
+                let signature = match superclass {
+                    Some(Expr::Variable {
+                        name: superclass, ..
+                    }) => format!("class {} < {}", name.lexeme, superclass.lexeme),
+                    _ => format!("class {}", name.lexeme),
+                };
+                declarations.push(Declaration {
+                    name: name.clone(),
+                    signature,
+                });
+                for field in statics {
+                    declarations.push(Declaration {
+                        name: field.name.clone(),
+                        signature: format!("static {}.{}", name.lexeme, field.name.lexeme),
+                    });
+                }
+                for method in methods {
+                    collect_function(&format!("{}.", name.lexeme), method, declarations);
+                }
+            }
+            Stmt::Block { statements } => collect_declarations(statements, declarations),
+            Stmt::While { body, .. } => {
+                collect_declarations(std::slice::from_ref(body), declarations)
+            }
+            Stmt::If {
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                collect_declarations(std::slice::from_ref(then_branch), declarations);
+                if let Some(branch) = else_branch {
+                    collect_declarations(std::slice::from_ref(branch), declarations);
+                }
+            }
+            Stmt::Expression { .. }
+            | Stmt::Print { .. }
+            | Stmt::Return { .. }
+            | Stmt::Break { .. }
+            | Stmt::Continue { .. }
+            | Stmt::Breakpoint { .. } => {}
+        }
+    }
+}
+
+fn collect_function(prefix: &str, fun: &FunctionStmt, declarations: &mut Vec<Declaration>) {
+    let params = fun
+        .params
+        .iter()
+        .map(|it| it.lexeme.as_str())
+        .collect::<Vec<_>>();
+    declarations.push(Declaration {
+        name: fun.name.clone(),
+        signature: format!("{}{}({})", prefix, fun.name.lexeme, params.join(", ")),
+    });
+    for param in &fun.params {
+        declarations.push(Declaration {
+            name: param.clone(),
+            signature: format!("parameter {}", param.lexeme),
+        });
+    }
+    collect_declarations(&fun.body, declarations);
+}
+
+// converts between the scanner's byte offsets and LSP positions, which count
+// UTF-16 code units
+struct LineIndex {
+    text: String,
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    fn new(text: &str) -> Self {
+        let mut line_starts = vec![0];
+        for (i, c) in text.char_indices() {
+            if c == '\n' {
+                line_starts.push(i + 1);
+            }
+        }
+        Self {
+            text: text.to_string(),
+            line_starts,
+        }
+    }
+
+    fn position(&self, offset: usize) -> Position {
+        let line = self.line_starts.partition_point(|start| *start <= offset) - 1;
+        let start = self.line_starts[line];
+        let end = offset.min(self.text.len());
+        let character = self.text[start..end]
+            .chars()
+            .map(|c| c.len_utf16())
+            .sum::<usize>();
+        Position::new(line as u32, character as u32)
+    }
+
+    fn offset(&self, position: Position) -> usize {
+        let start = match self.line_starts.get(position.line as usize) {
+            Some(start) => *start,
+            None => return self.text.len(),
+        };
+        let mut units = 0;
+        for (i, c) in self.text[start..].char_indices() {
+            if units >= position.character as usize || c == '\n' {
+                return start + i;
+            }
+            units += c.len_utf16();
+        }
+        self.text.len()
+    }
+
+    fn token_range(&self, token: &Token) -> Range {
+        let span = token.span();
+        Range::new(self.position(span.start), self.position(span.end))
+    }
+
+    // the whole of a line, numbered from 1 like the scanner does
+    fn line_range(&self, line: usize) -> Range {
+        let line = line.saturating_sub(1).min(self.line_starts.len() - 1);
+        let start = self.line_starts[line];
+        let end = self
+            .line_starts
+            .get(line + 1)
+            .map_or(self.text.len(), |next| next - 1);
+        Range::new(self.position(start), self.position(end))
+    }
+}
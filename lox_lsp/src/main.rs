@@ -0,0 +1,186 @@
+//! Language server for Lox, speaking LSP over stdin and stdout. It keeps
+//! the text of every open file and analyzes it again on each change with the
+//! tree-walk interpreter's scanner, parser and resolver.
+
+mod document;
+
+use std::collections::HashMap;
+
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+        PublishDiagnostics,
+    },
+    request::{DocumentSymbolRequest, GotoDefinition, HoverRequest, Request as _},
+    DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
+    DocumentSymbolParams, DocumentSymbolResponse, GotoDefinitionParams, GotoDefinitionResponse,
+    Hover, HoverContents, HoverParams, HoverProviderCapability, Location, MarkupContent,
+    MarkupKind, OneOf, PublishDiagnosticsParams, ServerCapabilities, TextDocumentSyncCapability,
+    TextDocumentSyncKind, Url,
+};
+
+use document::Document;
+
+fn main() -> anyhow::Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        ..Default::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    Server::default().run(&connection)?;
+    // the writer thread only ends once the connection's sender is gone
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
+
+#[derive(Default)]
+struct Server {
+    documents: HashMap<Url, Document>,
+}
+
+impl Server {
+    fn run(&mut self, connection: &Connection) -> anyhow::Result<()> {
+        for message in &connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    let response = self.handle_request(request);
+                    connection.sender.send(Message::Response(response))?;
+                }
+                Message::Notification(notification) => {
+                    if let Some(params) = self.handle_notification(notification)? {
+                        let notification =
+                            Notification::new(PublishDiagnostics::METHOD.into(), params);
+                        connection
+                            .sender
+                            .send(Message::Notification(notification))?;
+                    }
+                }
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    // returns the diagnostics to publish when a document changed
+    fn handle_notification(
+        &mut self,
+        notification: Notification,
+    ) -> anyhow::Result<Option<PublishDiagnosticsParams>> {
+        let (uri, document) = match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let document = Document::new(&params.text_document.text);
+                (params.text_document.uri, Some(document))
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                // with full sync the last change has the whole text
+                let document = params
+                    .content_changes
+                    .last()
+                    .map(|change| Document::new(&change.text));
+                match document {
+                    Some(document) => (params.text_document.uri, Some(document)),
+                    None => return Ok(None),
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                (params.text_document.uri, None)
+            }
+            _ => return Ok(None),
+        };
+
+        let diagnostics = match document {
+            Some(document) => {
+                let diagnostics = document.diagnostics.clone();
+                self.documents.insert(uri.clone(), document);
+                diagnostics
+            }
+            None => {
+                self.documents.remove(&uri);
+                vec![]
+            }
+        };
+        Ok(Some(PublishDiagnosticsParams::new(uri, diagnostics, None)))
+    }
+
+    fn handle_request(&mut self, request: Request) -> Response {
+        let result = match request.method.as_str() {
+            GotoDefinition::METHOD => self.definition(request.params),
+            HoverRequest::METHOD => self.hover(request.params),
+            DocumentSymbolRequest::METHOD => self.symbols(request.params),
+            _ => {
+                return method_not_found(request.id, &request.method);
+            }
+        };
+        match result {
+            Ok(value) => Response::new_ok(request.id, value),
+            Err(e) => Response::new_err(
+                request.id,
+                lsp_server::ErrorCode::InvalidParams as i32,
+                e.to_string(),
+            ),
+        }
+    }
+
+    fn definition(&self, params: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+        let params: GotoDefinitionParams = serde_json::from_value(params)?;
+        let uri = params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+        let response = self
+            .documents
+            .get(&uri)
+            .and_then(|document| document.definition(position))
+            .map(|range| GotoDefinitionResponse::Scalar(Location::new(uri, range)));
+        Ok(serde_json::to_value(response)?)
+    }
+
+    fn hover(&self, params: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+        let params: HoverParams = serde_json::from_value(params)?;
+        let position = params.text_document_position_params;
+        let response = self
+            .documents
+            .get(&position.text_document.uri)
+            .and_then(|document| document.hover(position.position))
+            .map(|(signature, range)| Hover {
+                contents: HoverContents::Markup(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: format!("```lox\n{}\n```", signature),
+                }),
+                range: Some(range),
+            });
+        Ok(serde_json::to_value(response)?)
+    }
+
+    fn symbols(&self, params: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+        let params: DocumentSymbolParams = serde_json::from_value(params)?;
+        let response = self
+            .documents
+            .get(&params.text_document.uri)
+            .map(|document| DocumentSymbolResponse::Nested(document.symbols()));
+        Ok(serde_json::to_value(response)?)
+    }
+}
+
+fn method_not_found(id: RequestId, method: &str) -> Response {
+    Response::new_err(
+        id,
+        lsp_server::ErrorCode::MethodNotFound as i32,
+        format!("Unsupported method '{}'.", method),
+    )
+}
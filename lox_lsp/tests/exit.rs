@@ -0,0 +1,77 @@
+//! The server ends when the client asks it to, or when its input closes,
+//! so editors don't leave one running every time they restart it.
+
+use std::{
+    io::{Read, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde_json::{json, Value};
+
+fn server() -> Child {
+    Command::new(env!("CARGO_BIN_EXE_lox-lsp"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap()
+}
+
+fn send(stdin: &mut ChildStdin, message: Value) {
+    let body = message.to_string();
+    write!(stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+    stdin.flush().unwrap();
+}
+
+fn initialize(stdin: &mut ChildStdin) {
+    send(
+        stdin,
+        json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"capabilities": {}}}),
+    );
+    send(
+        stdin,
+        json!({"jsonrpc": "2.0", "method": "initialized", "params": {}}),
+    );
+}
+
+// whether `child` exits successfully within a few seconds. it's killed if
+// it doesn't
+fn exits(mut child: Child) -> bool {
+    // the responses have to be read for the server to get to the end
+    let mut stdout = child.stdout.take().unwrap();
+    thread::spawn(move || stdout.read_to_end(&mut vec![]));
+
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(10) {
+        if let Some(status) = child.try_wait().unwrap() {
+            return status.success();
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    child.kill().unwrap();
+    false
+}
+
+#[test]
+fn exits_after_shutdown_and_exit() {
+    let mut child = server();
+    let mut stdin = child.stdin.take().unwrap();
+    initialize(&mut stdin);
+    send(
+        &mut stdin,
+        json!({"jsonrpc": "2.0", "id": 2, "method": "shutdown"}),
+    );
+    send(&mut stdin, json!({"jsonrpc": "2.0", "method": "exit"}));
+    assert!(exits(child));
+}
+
+#[test]
+fn exits_when_its_input_closes() {
+    let mut child = server();
+    let mut stdin = child.stdin.take().unwrap();
+    initialize(&mut stdin);
+    drop(stdin);
+    assert!(exits(child));
+}
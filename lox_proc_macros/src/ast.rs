@@ -0,0 +1,529 @@
+//! `make_ast!`, which takes the enums and structs of a syntax tree and
+//! writes what every node needs from the shape of its fields: the tokens
+//! it was parsed from, a `Visitor` with a method per variant, and `walk_*`
+//! functions that visit a node's children.
+//!
+//! Fields are told apart by their types. `Token`s are tokens, the types
+//! given to the macro are nodes, `Box`, `Option` and `Vec` of those are
+//! looked through, and anything else, like ids, is skipped.
+//!
+//! Each node also gets a constructor, `new` for structs and `new_variant`
+//! for the variants of enums, which boxes its children itself. A named field
+//! marked `#[span]` holds the node's span, for nodes whose source goes
+//! further than their tokens, like the braces around a function's body: the
+//! constructor takes it like any other field, and `span()` returns it
+//! instead of working it out.
+
+use std::collections::HashMap;
+
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+    parse::{Parse, ParseStream},
+    Fields, GenericArgument, Item, PathArguments, Type,
+};
+
+pub struct Ast {
+    items: Vec<Item>,
+    // the field marked `#[span]` of each node that has one, by the node's
+    // path, like `Expr::Binary`
+    span_fields: HashMap<String, Ident>,
+}
+
+impl Parse for Ast {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut items = vec![];
+        let mut span_fields = HashMap::new();
+        while !input.is_empty() {
+            let mut item: Item = input.parse()?;
+            match &mut item {
+                Item::Enum(e) => {
+                    for variant in &mut e.variants {
+                        let path = format!("{}::{}", e.ident, variant.ident);
+                        if let Some(field) = take_span_field(&mut variant.fields)? {
+                            span_fields.insert(path, field);
+                        }
+                    }
+                }
+                Item::Struct(s) => {
+                    if let Some(field) = take_span_field(&mut s.fields)? {
+                        span_fields.insert(s.ident.to_string(), field);
+                    }
+                }
+                _ => return Err(input.error("expected an enum or a struct")),
+            }
+            items.push(item);
+        }
+        Ok(Ast { items, span_fields })
+    }
+}
+
+// what a field holds, as far as walking the tree goes
+enum Shape {
+    Token,
+    // the node's type, and whether it's an enum
+    Node(Ident, bool),
+    Boxed(Box<Shape>),
+    Optional(Box<Shape>),
+    Many(Box<Shape>),
+    Other,
+}
+
+// the name of the field marked `#[span]`, removing the mark, which isn't a
+// real attribute
+fn take_span_field(fields: &mut Fields) -> syn::Result<Option<Ident>> {
+    let mut found = None;
+    for field in fields.iter_mut() {
+        let before = field.attrs.len();
+        field.attrs.retain(|attr| !attr.path.is_ident("span"));
+        if field.attrs.len() == before {
+            continue;
+        }
+        let name = match &field.ident {
+            Some(name) => name.clone(),
+            None => return Err(syn::Error::new_spanned(field, "a span field needs a name")),
+        };
+        if found.is_some() {
+            return Err(syn::Error::new_spanned(
+                name,
+                "a node can only have one span field",
+            ));
+        }
+        found = Some(name);
+    }
+    Ok(found)
+}
+
+// a variant of an enum, or a struct, with what its fields are bound to
+struct Node {
+    pattern: TokenStream,
+    // how to build it from a value for each field
+    construct: TokenStream,
+    fields: Vec<(Ident, Shape, Type)>,
+    span: Option<Ident>,
+}
+
+impl Ast {
+    fn is_enum(&self, name: &Ident) -> Option<bool> {
+        self.items.iter().find_map(|item| match item {
+            Item::Enum(e) if e.ident == *name => Some(true),
+            Item::Struct(s) if s.ident == *name => Some(false),
+            _ => None,
+        })
+    }
+
+    fn shape(&self, ty: &Type) -> Shape {
+        let segment = match ty {
+            Type::Path(path) => match path.path.segments.last() {
+                Some(segment) => segment,
+                None => return Shape::Other,
+            },
+            _ => return Shape::Other,
+        };
+        let inner = match &segment.arguments {
+            PathArguments::AngleBracketed(args) => match args.args.first() {
+                Some(GenericArgument::Type(inner)) => Some(inner),
+                _ => None,
+            },
+            _ => None,
+        };
+        match (segment.ident.to_string().as_str(), inner) {
+            ("Box", Some(inner)) => Shape::Boxed(Box::new(self.shape(inner))),
+            ("Option", Some(inner)) => Shape::Optional(Box::new(self.shape(inner))),
+            ("Vec", Some(inner)) => Shape::Many(Box::new(self.shape(inner))),
+            ("Token", None) => Shape::Token,
+            _ => match self.is_enum(&segment.ident) {
+                Some(is_enum) => Shape::Node(segment.ident.clone(), is_enum),
+                None => Shape::Other,
+            },
+        }
+    }
+
+    fn node(&self, path: TokenStream, key: String, fields: &Fields) -> Node {
+        let mut bound = vec![];
+        let pattern = match fields {
+            Fields::Named(named) => {
+                for field in &named.named {
+                    let name = field.ident.clone().unwrap();
+                    bound.push((name, self.shape(&field.ty), field.ty.clone()));
+                }
+                let names = bound.iter().map(|(name, _, _)| name);
+                quote!(#path { #(#names),* })
+            }
+            Fields::Unnamed(unnamed) => {
+                for (i, field) in unnamed.unnamed.iter().enumerate() {
+                    let name = match &field.ty {
+                        Type::Path(ty) if unnamed.unnamed.len() == 1 => {
+                            let ty = &ty.path.segments.last().unwrap().ident;
+                            format_ident!("{}", snake_case(&ty.to_string()))
+                        }
+                        _ => format_ident!("field{}", i),
+                    };
+                    bound.push((name, self.shape(&field.ty), field.ty.clone()));
+                }
+                let names = bound.iter().map(|(name, _, _)| name);
+                quote!(#path ( #(#names),* ))
+            }
+            Fields::Unit => quote!(#path),
+        };
+        let values = bound.iter().map(|(name, shape, _)| {
+            let value = construct(quote!(#name), shape);
+            match fields {
+                Fields::Named(_) => quote!(#name: #value),
+                _ => value,
+            }
+        });
+        let construct = match fields {
+            Fields::Named(_) => quote!(#path { #(#values),* }),
+            Fields::Unnamed(_) => quote!(#path ( #(#values),* )),
+            Fields::Unit => quote!(#path),
+        };
+        Node {
+            pattern,
+            construct,
+            fields: bound,
+            span: self.span_fields.get(&key).cloned(),
+        }
+    }
+
+    // the variants of an enum, or the struct itself
+    fn nodes(&self, item: &Item) -> (Ident, Vec<(Ident, Node)>) {
+        match item {
+            Item::Enum(e) => {
+                let name = e.ident.clone();
+                let nodes = e
+                    .variants
+                    .iter()
+                    .map(|variant| {
+                        let ident = &variant.ident;
+                        let key = format!("{}::{}", name, ident);
+                        let node = self.node(quote!(#name::#ident), key, &variant.fields);
+                        (variant.ident.clone(), node)
+                    })
+                    .collect();
+                (name, nodes)
+            }
+            Item::Struct(s) => {
+                let name = s.ident.clone();
+                let node = self.node(quote!(#name), name.to_string(), &s.fields);
+                (name.clone(), vec![(name, node)])
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn expand(&self) -> TokenStream {
+        let items = &self.items;
+        let mut impls = vec![];
+        let mut visit_methods = vec![];
+        let mut walks = vec![];
+        for item in items {
+            let (name, nodes) = self.nodes(item);
+            let is_enum = matches!(item, Item::Enum(_));
+            let snake = snake_case(&name.to_string());
+
+            let span_arms = nodes.iter().map(|(_, node)| {
+                let pattern = &node.pattern;
+                let pushes = match &node.span {
+                    Some(span) => quote!(spans.push(#span.clone());),
+                    None => {
+                        let pushes = node
+                            .fields
+                            .iter()
+                            .map(|(field, shape, _)| push_spans(quote!(#field), shape));
+                        quote!(#(#pushes)*)
+                    }
+                };
+                quote!(#pattern => { #pushes })
+            });
+            let constructors = nodes.iter().map(|(variant, node)| {
+                let (function, doc) = if is_enum {
+                    let function = format_ident!("new_{}", snake_case(&variant.to_string()));
+                    (
+                        function,
+                        format!("An `{}::{}` of the given fields.", name, variant),
+                    )
+                } else {
+                    (
+                        format_ident!("new"),
+                        format!("A `{}` of the given fields.", name),
+                    )
+                };
+                let params = node.fields.iter().map(|(field, shape, ty)| {
+                    let ty = constructor_type(shape, ty);
+                    quote!(#field: #ty)
+                });
+                let construct = &node.construct;
+                quote! {
+                    #[doc = #doc]
+                    pub fn #function(#(#params),*) -> Self {
+                        #construct
+                    }
+                }
+            });
+            let token_arms = nodes.iter().map(|(_, node)| {
+                let pattern = &node.pattern;
+                let pushes = node
+                    .fields
+                    .iter()
+                    .map(|(field, shape, _)| push_tokens(quote!(#field), shape));
+                quote!(#pattern => { #(#pushes)* })
+            });
+            impls.push(quote! {
+                impl #name {
+                    #(#constructors)*
+
+                    /// Every token the node was parsed from, in order, except
+                    /// for keywords and punctuation that aren't kept in the
+                    /// tree.
+                    pub fn tokens(&self) -> Vec<&Token> {
+                        let mut tokens = vec![];
+                        self.push_tokens(&mut tokens);
+                        tokens
+                    }
+
+                    /// The bytes of the source from the node's first token to
+                    /// the end of its last, or `None` if it has no tokens.
+                    /// Nodes with a span field cover what it says.
+                    pub fn span(&self) -> Option<std::ops::Range<usize>> {
+                        let mut spans = vec![];
+                        self.push_spans(&mut spans);
+                        let start = spans.iter().map(|it| it.start).min()?;
+                        let end = spans.iter().map(|it| it.end).max()?;
+                        Some(start..end)
+                    }
+
+                    #[allow(unused_variables)]
+                    fn push_spans(&self, spans: &mut Vec<std::ops::Range<usize>>) {
+                        match self {
+                            #(#span_arms)*
+                        }
+                    }
+
+                    #[allow(unused_variables)]
+                    fn push_tokens<'s>(&'s self, tokens: &mut Vec<&'s Token>) {
+                        match self {
+                            #(#token_arms)*
+                        }
+                    }
+                }
+            });
+
+            let walk = format_ident!("walk_{}", snake);
+            let child_arms = nodes.iter().map(|(_, node)| {
+                let pattern = &node.pattern;
+                let visits = node
+                    .fields
+                    .iter()
+                    .map(|(field, shape, _)| visit_children(quote!(#field), shape));
+                quote!(#pattern => { #(#visits)* })
+            });
+            let doc = format!(
+                "Visits the children of a `{}` in order, returning what each visit returned.",
+                name
+            );
+            walks.push(quote! {
+                #[doc = #doc]
+                #[allow(unused_variables)]
+                pub fn #walk<R, V: Visitor<R> + ?Sized>(visitor: &mut V, node: &#name) -> Vec<R> {
+                    let mut results = vec![];
+                    match node {
+                        #(#child_arms)*
+                    }
+                    results
+                }
+            });
+
+            if !is_enum {
+                continue;
+            }
+            let visit = format_ident!("visit_{}", snake);
+            let mut dispatch = vec![];
+            for (variant, node) in &nodes {
+                let method = format_ident!("visit_{}_{}", snake_case(&variant.to_string()), snake);
+                let params = node.fields.iter().map(|(field, shape, ty)| {
+                    let ty = param_type(shape, ty);
+                    quote!(#field: #ty)
+                });
+                let args = node
+                    .fields
+                    .iter()
+                    .map(|(field, shape, _)| argument(quote!(#field), shape));
+                let pattern = &node.pattern;
+                visit_methods.push(quote! {
+                    fn #method(&mut self, #(#params),*) -> R;
+                });
+                dispatch.push(quote!(#pattern => self.#method(#(#args),*),));
+            }
+            let doc = format!("Calls the method for the kind of `{}` it is.", name);
+            visit_methods.push(quote! {
+                #[doc = #doc]
+                #[allow(unused_variables)]
+                fn #visit(&mut self, node: &#name) -> R {
+                    match node {
+                        #(#dispatch)*
+                    }
+                }
+            });
+        }
+
+        quote! {
+            #(#items)*
+            #(#impls)*
+
+            /// Something done to each kind of node, returning an `R` for it.
+            /// `walk_*` visits a node's children, for visitors that go
+            /// through the whole tree.
+            pub trait Visitor<R> {
+                #(#visit_methods)*
+            }
+
+            #(#walks)*
+        }
+    }
+}
+
+fn push_tokens(value: TokenStream, shape: &Shape) -> TokenStream {
+    match shape {
+        Shape::Token => quote!(tokens.push(#value);),
+        Shape::Node(..) => quote!(#value.push_tokens(tokens);),
+        Shape::Boxed(inner) => push_tokens(value, inner),
+        Shape::Optional(inner) => {
+            let inner = push_tokens(quote!(it), inner);
+            quote!(if let Some(it) = #value { #inner })
+        }
+        Shape::Many(inner) => {
+            let inner = push_tokens(quote!(it), inner);
+            quote!(for it in #value { #inner })
+        }
+        Shape::Other => quote!(),
+    }
+}
+
+// children with a span of their own cover it, which may be more than
+// their tokens
+fn push_spans(value: TokenStream, shape: &Shape) -> TokenStream {
+    match shape {
+        Shape::Token => quote!(spans.push(#value.span());),
+        Shape::Node(..) => quote!(spans.extend(#value.span());),
+        Shape::Boxed(inner) => push_spans(value, inner),
+        Shape::Optional(inner) => {
+            let inner = push_spans(quote!(it), inner);
+            quote!(if let Some(it) = #value { #inner })
+        }
+        Shape::Many(inner) => {
+            let inner = push_spans(quote!(it), inner);
+            quote!(for it in #value { #inner })
+        }
+        Shape::Other => quote!(),
+    }
+}
+
+// enums are children, structs are looked into, since they have no
+// `Visitor` method of their own
+fn visit_children(value: TokenStream, shape: &Shape) -> TokenStream {
+    match shape {
+        Shape::Node(name, true) => {
+            let visit = format_ident!("visit_{}", snake_case(&name.to_string()));
+            quote!(results.push(visitor.#visit(#value));)
+        }
+        Shape::Node(name, false) => {
+            let walk = format_ident!("walk_{}", snake_case(&name.to_string()));
+            quote!(results.extend(#walk(visitor, #value));)
+        }
+        Shape::Boxed(inner) => visit_children(value, inner),
+        Shape::Optional(inner) => {
+            let inner = visit_children(quote!(it), inner);
+            quote!(if let Some(it) = #value { #inner })
+        }
+        Shape::Many(inner) => {
+            let inner = visit_children(quote!(it), inner);
+            quote!(for it in #value { #inner })
+        }
+        Shape::Token | Shape::Other => quote!(),
+    }
+}
+
+// fields are passed to visitors by reference, without the box
+fn param_type(shape: &Shape, ty: &Type) -> TokenStream {
+    match shape {
+        Shape::Boxed(_) => {
+            let inner = inner_type(ty);
+            quote!(&#inner)
+        }
+        Shape::Many(_) => {
+            let inner = inner_type(ty);
+            quote!(&[#inner])
+        }
+        Shape::Optional(boxed) if matches!(**boxed, Shape::Boxed(_)) => {
+            let inner = inner_type(&inner_type(ty));
+            quote!(Option<&#inner>)
+        }
+        Shape::Optional(_) => {
+            let inner = inner_type(ty);
+            quote!(Option<&#inner>)
+        }
+        _ => quote!(&#ty),
+    }
+}
+
+// constructors take children without the box, and box them
+fn constructor_type(shape: &Shape, ty: &Type) -> TokenStream {
+    match shape {
+        Shape::Boxed(_) => {
+            let inner = inner_type(ty);
+            quote!(#inner)
+        }
+        Shape::Optional(boxed) if matches!(**boxed, Shape::Boxed(_)) => {
+            let inner = inner_type(&inner_type(ty));
+            quote!(Option<#inner>)
+        }
+        _ => quote!(#ty),
+    }
+}
+
+fn construct(value: TokenStream, shape: &Shape) -> TokenStream {
+    match shape {
+        Shape::Boxed(_) => quote!(Box::new(#value)),
+        Shape::Optional(boxed) if matches!(**boxed, Shape::Boxed(_)) => {
+            quote!(#value.map(Box::new))
+        }
+        _ => value,
+    }
+}
+
+fn argument(value: TokenStream, shape: &Shape) -> TokenStream {
+    match shape {
+        Shape::Boxed(_) => quote!(&**#value),
+        Shape::Optional(boxed) if matches!(**boxed, Shape::Boxed(_)) => {
+            quote!(#value.as_deref())
+        }
+        Shape::Optional(_) => quote!(#value.as_ref()),
+        _ => value,
+    }
+}
+
+// the type inside `Box<T>`, `Option<T>` or `Vec<T>`
+fn inner_type(ty: &Type) -> Type {
+    match ty {
+        Type::Path(path) => match &path.path.segments.last().unwrap().arguments {
+            PathArguments::AngleBracketed(args) => match args.args.first() {
+                Some(GenericArgument::Type(inner)) => inner.clone(),
+                _ => ty.clone(),
+            },
+            _ => ty.clone(),
+        },
+        _ => ty.clone(),
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
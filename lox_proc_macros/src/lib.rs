@@ -1,28 +1,172 @@
 use proc_macro::TokenStream;
 use quote::quote;
 
+mod ast;
+mod native;
+
+/// Writes the given syntax tree types, and for all of them the tokens and
+/// spans of their nodes, a `Visitor` trait and `walk_*` functions.
+#[proc_macro]
+pub fn make_ast(input: TokenStream) -> TokenStream {
+    let ast = syn::parse_macro_input!(input as ast::Ast);
+    ast.expand().into()
+}
+
+/// Makes the function a native of the tree-walk interpreter, registered
+/// under its own name or the one given with `#[lox_native(name = "...")]`.
+/// Its parameters are the native's arguments, checked and converted before
+/// it's called.
+#[proc_macro_attribute]
+pub fn lox_native(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = syn::parse_macro_input!(args as syn::AttributeArgs);
+    let function = syn::parse_macro_input!(input as syn::ItemFn);
+    native::expand(args, function)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+/// For fieldless enums that are stored as bytes: `COUNT`, `VARIANTS` in
+/// order, `as_u8`, `from_u8`, `name` and `TryFrom<u8>`.
 #[proc_macro_derive(U8Enum)]
 pub fn derive_enum_variant_count(input: TokenStream) -> TokenStream {
     let syn_item: syn::DeriveInput = syn::parse(input).unwrap();
-    let len = match syn_item.data {
-        syn::Data::Enum(enum_item) => enum_item.variants.len(),
-        _ => panic!("U8Enum only works on Enums"),
+    let enum_item = match syn_item.data {
+        syn::Data::Enum(enum_item) => enum_item,
+        _ => {
+            let error = syn::Error::new_spanned(&syn_item.ident, "U8Enum only works on enums");
+            return error.to_compile_error().into();
+        }
     };
+    // `from_u8` looks the byte up in `VARIANTS`, which only gives back what
+    // `as_u8` made it from if the variants are numbered from 0 and hold
+    // nothing
+    for variant in &enum_item.variants {
+        let error = if !matches!(variant.fields, syn::Fields::Unit) {
+            Some("U8Enum variants can't hold data")
+        } else if variant.discriminant.is_some() {
+            Some("U8Enum variants can't have explicit discriminants")
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            return syn::Error::new_spanned(variant, error)
+                .to_compile_error()
+                .into();
+        }
+    }
+    let len = enum_item.variants.len();
     let enum_name = syn_item.ident;
+    let variants = enum_item.variants.iter().map(|it| &it.ident);
+    let names = variants.clone().map(|it| it.to_string());
+    let variants_again = variants.clone();
     let expanded = quote! {
         impl #enum_name {
             pub const COUNT: usize = #len;
+            pub const VARIANTS: [Self; #len] = [#(Self::#variants),*];
             pub fn as_u8(&self) -> u8 {
                 *self as u8
             }
             pub fn from_u8(byte: u8) -> Option<Self> {
-                if byte as usize >= Self::COUNT {
-                    None
-                } else {
-                    unsafe { std::mem::transmute(byte) }
+                Self::VARIANTS.get(byte as usize).copied()
+            }
+            /// The variant's name, as it's written in the source.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    #(Self::#variants_again => #names,)*
+                }
+            }
+        }
+
+        impl std::convert::TryFrom<u8> for #enum_name {
+            /// the byte, which is no variant
+            type Error = u8;
+
+            fn try_from(byte: u8) -> Result<Self, u8> {
+                Self::from_u8(byte).ok_or(byte)
+            }
+        }
+    };
+    expanded.into()
+}
+
+/// For opcodes: `mnemonic`, which is `OP_` and the variant's name in upper
+/// case, and `operand_count`, the bytes that follow the opcode, 0 unless
+/// it's given with `#[opcode(operands = 1)]`. `#[opcode(mnemonic = "..")]`
+/// gives another mnemonic.
+#[proc_macro_derive(OpcodeInfo, attributes(opcode))]
+pub fn derive_opcode_info(input: TokenStream) -> TokenStream {
+    let syn_item: syn::DeriveInput = syn::parse(input).unwrap();
+    let enum_item = match syn_item.data {
+        syn::Data::Enum(enum_item) => enum_item,
+        _ => {
+            let error = syn::Error::new_spanned(&syn_item.ident, "OpcodeInfo only works on enums");
+            return error.to_compile_error().into();
+        }
+    };
+    let mut mnemonics = vec![];
+    let mut operands = vec![];
+    for variant in &enum_item.variants {
+        let mut mnemonic = format!("OP_{}", variant.ident.to_string().to_uppercase());
+        let mut count = 0usize;
+        for attr in variant.attrs.iter().filter(|it| it.path.is_ident("opcode")) {
+            if let Err(error) = opcode_attribute(attr, &mut mnemonic, &mut count) {
+                return error.to_compile_error().into();
+            }
+        }
+        let ident = &variant.ident;
+        mnemonics.push(quote!(Self::#ident => #mnemonic,));
+        operands.push(quote!(Self::#ident => #count,));
+    }
+    let enum_name = syn_item.ident;
+    let expanded = quote! {
+        impl #enum_name {
+            /// The opcode's name in disassembly.
+            pub fn mnemonic(&self) -> &'static str {
+                match self {
+                    #(#mnemonics)*
+                }
+            }
+            /// How many bytes of operands follow the opcode.
+            pub fn operand_count(&self) -> usize {
+                match self {
+                    #(#operands)*
                 }
             }
         }
     };
     expanded.into()
 }
+
+fn opcode_attribute(
+    attr: &syn::Attribute,
+    mnemonic: &mut String,
+    count: &mut usize,
+) -> syn::Result<()> {
+    let list = match attr.parse_meta()? {
+        syn::Meta::List(list) => list,
+        meta => return Err(syn::Error::new_spanned(meta, "expected #[opcode(..)]")),
+    };
+    for nested in &list.nested {
+        match nested {
+            syn::NestedMeta::Meta(syn::Meta::NameValue(value)) => match &value.lit {
+                syn::Lit::Int(n) if value.path.is_ident("operands") => {
+                    *count = n.base10_parse()?;
+                }
+                syn::Lit::Str(s) if value.path.is_ident("mnemonic") => *mnemonic = s.value(),
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        value,
+                        "expected `operands = <count>` or `mnemonic = \"<name>\"`",
+                    ))
+                }
+            },
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    nested,
+                    "expected `operands = <count>` or `mnemonic = \"<name>\"`",
+                ))
+            }
+        }
+    }
+    Ok(())
+}
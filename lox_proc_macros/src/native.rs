@@ -0,0 +1,112 @@
+//! `#[lox_native]`, which turns a Rust function into a native function of
+//! the tree-walk interpreter. The function keeps its typed parameters, and
+//! a wrapper that takes the script's arguments converts them with
+//! `stdlib::Arg`, so a wrong argument is reported with the native's name
+//! and the argument's position, and converts what it returns with
+//! `stdlib::Return`. The wrapper is submitted to the registry that
+//! `Interpreter::new` defines its natives from.
+//!
+//! A first parameter of `&mut Interpreter` is passed the interpreter, and
+//! isn't an argument of the native.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    AttributeArgs, FnArg, ItemFn, Lit, Meta, NestedMeta, Pat, PatType, Type, TypePath,
+    TypeReference,
+};
+
+pub fn expand(args: AttributeArgs, function: ItemFn) -> syn::Result<TokenStream> {
+    let ident = &function.sig.ident;
+    let mut name = ident.to_string();
+    for arg in &args {
+        match arg {
+            NestedMeta::Meta(Meta::NameValue(pair)) if pair.path.is_ident("name") => {
+                match &pair.lit {
+                    Lit::Str(lit) => name = lit.value(),
+                    lit => return Err(syn::Error::new_spanned(lit, "expected a string")),
+                }
+            }
+            _ => return Err(syn::Error::new_spanned(arg, "expected `name = \"...\"`")),
+        }
+    }
+
+    let mut params = function.sig.inputs.iter().peekable();
+    let takes_interpreter = match params.peek() {
+        Some(FnArg::Typed(PatType { ty, .. })) => is_interpreter(ty),
+        _ => false,
+    };
+    let mut passed = vec![];
+    if takes_interpreter {
+        params.next();
+        passed.push(quote!(interpreter));
+    }
+    let mut arg_names = vec![];
+    let mut conversions = vec![];
+    for (index, param) in params.enumerate() {
+        let (pat, ty) = match param {
+            FnArg::Typed(PatType { pat, ty, .. }) => (pat, ty),
+            FnArg::Receiver(receiver) => {
+                return Err(syn::Error::new_spanned(
+                    receiver,
+                    "natives can't take `self`",
+                ))
+            }
+        };
+        let param = match &**pat {
+            Pat::Ident(pat) => &pat.ident,
+            pat => {
+                return Err(syn::Error::new_spanned(
+                    pat,
+                    "native parameters must be plain names, which the script sees",
+                ))
+            }
+        };
+        let variable = format_ident!("arg{}", index);
+        arg_names.push(param.to_string());
+        conversions.push(quote! {
+            let #variable = <#ty as crate::stdlib::Arg>::from_arg(#name, &args, #index)?;
+        });
+        passed.push(quote!(#variable));
+    }
+
+    Ok(quote! {
+        #function
+
+        const _: () = {
+            #[allow(unused_variables)]
+            fn native(
+                interpreter: &mut crate::interpreter::Interpreter,
+                args: Vec<crate::value::RuntimeValue>,
+            ) -> Result<crate::value::RuntimeValue, crate::interpreter::InterpreterError> {
+                #(#conversions)*
+                crate::stdlib::Return::into_result(#ident(#(#passed),*))
+            }
+
+            ::inventory::submit! {
+                crate::stdlib::Registered {
+                    name: #name,
+                    args: &[#(#arg_names),*],
+                    function: native,
+                }
+            }
+        };
+    })
+}
+
+fn is_interpreter(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(TypeReference {
+            mutability: Some(_),
+            elem,
+            ..
+        }) => match &**elem {
+            Type::Path(TypePath { path, .. }) => path
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == "Interpreter"),
+            _ => false,
+        },
+        _ => false,
+    }
+}
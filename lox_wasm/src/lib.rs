@@ -0,0 +1,117 @@
+//! Bindings for running both interpreters from javascript, meant for a
+//! browser playground. Build with `wasm-pack build lox_wasm --target web`.
+//!
+//! ```js
+//! const { output, errors } = run("println(1 + 2);", Backend.TreeWalk, line => console.log(line));
+//! ```
+
+use std::{cell::RefCell, io, rc::Rc, time::Duration};
+
+use bytecode_lox::vm::VM;
+use js_sys::{Array, Date, Function, Object, Reflect};
+use lox::{interpreter::Interpreter, sandbox::SandboxConfig};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub enum Backend {
+    TreeWalk,
+    Bytecode,
+}
+
+/// Collects everything the program prints, forwarding each line to the
+/// callback as soon as it is produced.
+struct Capture {
+    lines: Rc<RefCell<Vec<String>>>,
+    callback: Option<Function>,
+}
+
+impl Capture {
+    fn line(&mut self, line: &str) {
+        self.lines.borrow_mut().push(line.to_string());
+        if let Some(callback) = &self.callback {
+            // an exception thrown by the callback shouldn't stop the script
+            let _ = callback.call1(&JsValue::NULL, &JsValue::from_str(line));
+        }
+    }
+}
+
+impl lox::host::Output for Capture {
+    fn print(&mut self, line: &str) {
+        self.line(line);
+    }
+}
+
+impl bytecode_lox::host::Output for Capture {
+    fn print(&mut self, line: &str) {
+        self.line(line);
+    }
+}
+
+// std::time::SystemTime is not available on wasm32-unknown-unknown
+struct JsClock;
+
+impl lox::host::Clock for JsClock {
+    fn now(&self) -> Duration {
+        Duration::from_secs_f64(Date::now() / 1000.0)
+    }
+
+    // the browser can't block the thread, so this waits actively
+    fn sleep(&self, duration: Duration) {
+        let end = Date::now() + duration.as_secs_f64() * 1000.0;
+        while Date::now() < end {}
+    }
+}
+
+// there is no stdin in the browser, readLine always gets nil
+struct NoInput;
+
+impl lox::host::Input for NoInput {
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        Ok(None)
+    }
+}
+
+/// Runs `source` on the chosen backend and returns `{ output, errors }`,
+/// where `output` is everything that was printed and `errors` is an array
+/// with one string per reported error.
+#[wasm_bindgen]
+pub fn run(source: &str, backend: Backend, on_output: Option<Function>) -> JsValue {
+    let lines = Rc::new(RefCell::new(vec![]));
+    let mut capture = Capture {
+        lines: lines.clone(),
+        callback: on_output,
+    };
+
+    let error = match backend {
+        Backend::TreeWalk => {
+            // there is no file system to give access to in the browser
+            let mut interpreter = Interpreter::new_with_config(SandboxConfig::default());
+            interpreter.set_output(Box::new(capture));
+            interpreter.set_input(Box::new(NoInput));
+            interpreter.set_clock(Box::new(JsClock));
+            lox::run_source(&mut interpreter, source.to_string())
+                .err()
+                .map(|e| e.to_string())
+        }
+        Backend::Bytecode => VM::interpret(source.to_string(), &mut capture)
+            .err()
+            .map(|e| e.to_string()),
+    };
+
+    let errors = Array::new();
+    for line in error.iter().flat_map(|e| e.lines()) {
+        errors.push(&JsValue::from_str(line));
+    }
+    let output = lines
+        .borrow()
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect::<String>();
+
+    let result = Object::new();
+    // setting a property on a fresh plain object can't fail
+    Reflect::set(&result, &"output".into(), &output.into()).unwrap();
+    Reflect::set(&result, &"errors".into(), &errors).unwrap();
+    result.into()
+}
@@ -0,0 +1,117 @@
+//! Checks that need the whole syntax tree but not the scopes the resolver
+//! tracks. For now that's finding code that can never run.
+
+use crate::{
+    ast::{Expr, Stmt},
+    resolver::Warning,
+    token::TokenKind,
+};
+
+/// Finds statements after an unconditional `return`, and the bodies and
+/// branches that a constant condition keeps from ever running.
+pub fn find_unreachable_code(statements: &[Stmt]) -> Vec<Warning> {
+    let mut warnings = vec![];
+    check_statements(statements, &mut warnings);
+    warnings
+}
+
+fn check_statements(statements: &[Stmt], warnings: &mut Vec<Warning>) {
+    for (i, stmt) in statements.iter().enumerate() {
+        check_stmt(stmt, warnings);
+        if always_returns(stmt) && i + 1 < statements.len() {
+            unreachable(&statements[i + 1..], warnings);
+            return;
+        }
+    }
+}
+
+fn check_stmt(stmt: &Stmt, warnings: &mut Vec<Warning>) {
+    match stmt {
+        Stmt::Block { statements } => check_statements(statements, warnings),
+        Stmt::Function(fun) => check_statements(&fun.body, warnings),
+        Stmt::Class { methods, .. } => {
+            for method in methods {
+                check_statements(&method.body, warnings);
+            }
+        }
+        Stmt::While {
+            condition, body, ..
+        } => {
+            if truthiness(condition) == Some(false) {
+                unreachable(std::slice::from_ref(body), warnings);
+            } else {
+                check_stmt(body, warnings);
+            }
+        }
+        Stmt::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            let truthy = truthiness(condition);
+            if truthy == Some(false) {
+                unreachable(std::slice::from_ref(then_branch), warnings);
+            } else {
+                check_stmt(then_branch, warnings);
+            }
+            if let Some(branch) = else_branch {
+                if truthy == Some(true) {
+                    unreachable(std::slice::from_ref(branch), warnings);
+                } else {
+                    check_stmt(branch, warnings);
+                }
+            }
+        }
+        Stmt::Expression { .. }
+        | Stmt::Print { .. }
+        | Stmt::Return { .. }
+        | Stmt::Var { .. }
+        | Stmt::Break { .. }
+        | Stmt::Continue { .. }
+        | Stmt::Breakpoint { .. } => {}
+    }
+}
+
+// whether the statements after this one can never run, because it always
+// returns or leaves the iteration of the loop it's in
+fn always_returns(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::Return { .. } | Stmt::Break { .. } | Stmt::Continue { .. } => true,
+        Stmt::Block { statements } => statements.iter().any(always_returns),
+        Stmt::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => match truthiness(condition) {
+            Some(true) => always_returns(then_branch),
+            Some(false) => else_branch.as_deref().is_some_and(always_returns),
+            None => {
+                always_returns(then_branch) && else_branch.as_deref().is_some_and(always_returns)
+            }
+        },
+        _ => false,
+    }
+}
+
+// whether the condition is a literal, and so always truthy or always falsey
+fn truthiness(condition: &Expr) -> Option<bool> {
+    match condition {
+        Expr::Grouping { expression } => truthiness(expression),
+        Expr::Literal { value } => Some(!matches!(value.kind, TokenKind::False | TokenKind::Nil)),
+        _ => None,
+    }
+}
+
+// one warning that spans all of the given statements
+fn unreachable(statements: &[Stmt], warnings: &mut Vec<Warning>) {
+    let tokens = statements.iter().flat_map(Stmt::tokens).collect::<Vec<_>>();
+    let first = tokens.iter().min_by_key(|it| it.scanner_index);
+    let last = tokens.iter().max_by_key(|it| it.scanner_index);
+    if let (Some(first), Some(last)) = (first, last) {
+        warnings.push(Warning::new(
+            (*first).clone(),
+            last.line,
+            "Unreachable code.",
+        ));
+    }
+}
@@ -1,97 +1,175 @@
-// use lox_derive_ast::make_ast;
+use std::{
+    ops::Range,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use lox_proc_macros::make_ast;
+
 use crate::token::Token;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub enum Expr {
-    Binary {
-        left: Box<Expr>,
-        operator: Token,
-        right: Box<Expr>,
-    },
-    Call {
-        callee: Box<Expr>,
-        paren: Token,
-        arguments: Vec<Expr>,
-    },
-    Get {
-        object: Box<Expr>,
-        name: Token,
-    },
-    Grouping {
-        expression: Box<Expr>,
-    },
-    Literal {
-        value: Token,
-    },
-    Logical {
-        left: Box<Expr>,
-        operator: Token,
-        right: Box<Expr>,
-    },
-    Set {
-        object: Box<Expr>,
-        name: Token,
-        value: Box<Expr>,
-    },
-    Super {
-        keyword: Token,
-        method: Token,
-    },
-    This {
-        keyword: Token,
-    },
-    Unary {
-        operator: Token,
-        right: Box<Expr>,
-    },
-    Variable {
-        name: Token,
-    },
-    Assign {
-        name: Token,
-        value: Box<Expr>,
-    },
+/// Identifies an expression the resolver resolves, so the interpreter can
+/// find how far up its variable lives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    /// An id no other node has, even one from another parse, like a
+    /// previous line of the REPL.
+    pub fn next() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        NodeId(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
 }
 
-#[derive(Debug, Clone)]
-pub struct FunctionStmt {
-    pub name: Token,
-    pub params: Vec<Token>,
-    pub body: Vec<Stmt>,
+// each node is declared once here, and what walks the tree is generated
+// from its fields
+make_ast! {
+    #[derive(Debug, Clone)]
+    pub enum Expr {
+        Binary {
+            left: Box<Expr>,
+            operator: Token,
+            right: Box<Expr>,
+        },
+        Call {
+            callee: Box<Expr>,
+            paren: Token,
+            arguments: Vec<Expr>,
+        },
+        Get {
+            object: Box<Expr>,
+            name: Token,
+        },
+        Grouping {
+            expression: Box<Expr>,
+        },
+        Literal {
+            value: Token,
+        },
+        Logical {
+            left: Box<Expr>,
+            operator: Token,
+            right: Box<Expr>,
+        },
+        Set {
+            object: Box<Expr>,
+            name: Token,
+            value: Box<Expr>,
+        },
+        Super {
+            id: NodeId,
+            keyword: Token,
+            method: Token,
+        },
+        This {
+            id: NodeId,
+            keyword: Token,
+        },
+        Unary {
+            operator: Token,
+            right: Box<Expr>,
+        },
+        Variable {
+            id: NodeId,
+            name: Token,
+        },
+        Assign {
+            id: NodeId,
+            name: Token,
+            value: Box<Expr>,
+        },
+        /// an anonymous class, like `class < Base { ... }`
+        Class {
+            keyword: Token,
+            /// an `Expr::Variable`
+            superclass: Option<Box<Expr>>,
+            methods: Vec<FunctionStmt>,
+            statics: Vec<StaticField>,
+        },
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct FunctionStmt {
+        pub name: Token,
+        pub params: Vec<Token>,
+        pub body: Vec<Stmt>,
+        /// from the name to the brace that closes the body
+        #[span]
+        pub span: Range<usize>,
+    }
+
+    /// `static name = value;` in a class body
+    #[derive(Debug, Clone)]
+    pub struct StaticField {
+        pub name: Token,
+        pub value: Expr,
+    }
+
+    #[derive(Debug, Clone)]
+    pub enum Stmt {
+        Expression {
+            expression: Expr,
+        },
+        Print {
+            expression: Expr,
+        },
+        Return {
+            keyword: Token,
+            value: Option<Expr>,
+        },
+        Var {
+            name: Token,
+            initializer: Option<Expr>,
+        },
+        /// `for` loops are `While`s with the increment, which runs after
+        /// the body, even when it `continue`s
+        While {
+            label: Option<Token>,
+            condition: Expr,
+            body: Box<Stmt>,
+            increment: Option<Expr>,
+        },
+        /// `label` is the loop to leave, or the innermost one without it
+        Break {
+            keyword: Token,
+            label: Option<Token>,
+        },
+        /// `label` is the loop to go on with, or the innermost one without
+        /// it
+        Continue {
+            keyword: Token,
+            label: Option<Token>,
+        },
+        Block {
+            statements: Vec<Stmt>,
+        },
+        Class {
+            name: Token,
+            /// an `Expr::Variable`
+            superclass: Option<Expr>,
+            methods: Vec<FunctionStmt>,
+            statics: Vec<StaticField>,
+        },
+        Function(FunctionStmt),
+        If {
+            condition: Expr,
+            then_branch: Box<Stmt>,
+            else_branch: Option<Box<Stmt>>,
+        },
+        /// pauses the script when it runs under a debugger, does nothing
+        /// otherwise
+        Breakpoint {
+            keyword: Token,
+        },
+    }
 }
 
-#[derive(Debug, Clone)]
-pub enum Stmt {
-    Expression {
-        expression: Expr,
-    },
-    Print {
-        expression: Expr,
-    },
-    Return {
-        keyword: Token,
-        value: Option<Expr>,
-    },
-    Var {
-        name: Token,
-        initializer: Option<Expr>,
-    },
-    While {
-        condition: Expr,
-        body: Box<Stmt>,
-    },
-    Block {
-        statements: Vec<Stmt>,
-    },
-    Class {
-        name: Token,
-        superclass: Option<Token>,
-        methods: Vec<FunctionStmt>,
-    },
-    Function(FunctionStmt),
-    If {
-        condition: Expr,
-        then_branch: Box<Stmt>,
-        else_branch: Option<Box<Stmt>>,
-    },
+impl Stmt {
+    /// The line the statement starts on, or `None` for an empty block.
+    pub fn line(&self) -> Option<usize> {
+        self.tokens()
+            .into_iter()
+            .min_by_key(|it| it.scanner_index)
+            .map(|it| it.line)
+    }
 }
@@ -0,0 +1,117 @@
+//! `lox bench`: runs a script a number of times on one of the interpreters,
+//! with what it prints thrown away, and reports how long the runs took and
+//! how much work each of them did.
+
+use std::{
+    fmt::Display,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use bytecode_lox::{compiler::Compiler, error::InterpretError, value::Objects, vm::VM};
+use lox::{ast::Stmt, hooks::InterpreterHooks, interpreter::Interpreter};
+
+/// How one run went.
+pub struct Measurement {
+    pub time: Duration,
+    /// instructions the vm ran, or statements the tree-walk interpreter
+    /// executed
+    pub work: u64,
+}
+
+struct Discard;
+
+impl lox::host::Output for Discard {
+    fn print(&mut self, _line: &str) {}
+}
+
+impl bytecode_lox::host::Output for Discard {
+    fn print(&mut self, _line: &str) {}
+}
+
+#[derive(Clone, Default)]
+struct StatementCounter(Arc<AtomicU64>);
+
+impl InterpreterHooks for StatementCounter {
+    fn on_statement(&mut self, _interpreter: &mut Interpreter, _stmt: &Stmt) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Runs `source` once on `interpreter`, which should be fresh.
+pub fn measure_tree_walk(
+    mut interpreter: Interpreter,
+    source: &str,
+) -> Result<Measurement, lox::LoxError> {
+    let counter = StatementCounter::default();
+    interpreter.set_hooks(Some(Box::new(counter.clone())));
+    interpreter.set_output(Box::new(Discard));
+    let start = Instant::now();
+    lox::run_source(&mut interpreter, source.to_string())?;
+    Ok(Measurement {
+        time: start.elapsed(),
+        work: counter.0.load(Ordering::Relaxed),
+    })
+}
+
+/// Compiles and runs `source` once on the vm.
+pub fn measure_vm(source: &str, strict_math: bool) -> Result<Measurement, InterpretError> {
+    let start = Instant::now();
+    let objects = Objects::new();
+    let chunk = Compiler::compile(source.to_string(), &objects).map_err(InterpretError::Compile)?;
+    let mut output = Discard;
+    let mut vm = VM::new(&chunk, objects, &mut output);
+    vm.set_strict_math(strict_math);
+    vm.run()?;
+    Ok(Measurement {
+        time: start.elapsed(),
+        work: vm.instructions(),
+    })
+}
+
+/// The times of the runs, and the work of the last one.
+pub struct Summary {
+    pub runs: usize,
+    pub mean: Duration,
+    pub median: Duration,
+    pub stddev: Duration,
+    pub work: u64,
+}
+
+impl Summary {
+    /// `measurements` can't be empty.
+    pub fn new(measurements: &[Measurement]) -> Self {
+        let runs = measurements.len();
+        let mut seconds = measurements
+            .iter()
+            .map(|it| it.time.as_secs_f64())
+            .collect::<Vec<_>>();
+        seconds.sort_by(f64::total_cmp);
+        let mean = seconds.iter().sum::<f64>() / runs as f64;
+        let median = if runs.is_multiple_of(2) {
+            (seconds[runs / 2 - 1] + seconds[runs / 2]) / 2.0
+        } else {
+            seconds[runs / 2]
+        };
+        let variance = seconds.iter().map(|it| (it - mean).powi(2)).sum::<f64>() / runs as f64;
+        Summary {
+            runs,
+            mean: Duration::from_secs_f64(mean),
+            median: Duration::from_secs_f64(median),
+            stddev: Duration::from_secs_f64(variance.sqrt()),
+            work: measurements[runs - 1].work,
+        }
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "runs    {}", self.runs)?;
+        writeln!(f, "mean    {:.3?}", self.mean)?;
+        writeln!(f, "median  {:.3?}", self.median)?;
+        write!(f, "stddev  {:.3?}", self.stddev)
+    }
+}
@@ -0,0 +1,117 @@
+//! Line coverage for `lox --coverage`: which lines with statements ran, and
+//! how often.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::{ast::Stmt, hooks::InterpreterHooks, interpreter::Interpreter};
+
+/// Counts the statements that run on each line. Install a clone with
+/// `Interpreter::set_hooks` and read the counts from this one afterwards.
+#[derive(Clone, Default)]
+pub struct Coverage(Arc<Mutex<BTreeMap<usize, u64>>>);
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The hits of every line of `statements` that has a statement, including
+    /// the lines that never ran.
+    pub fn report(&self, statements: &[Stmt]) -> CoverageReport {
+        let mut lines = BTreeMap::new();
+        executable_lines(statements, &mut lines);
+        for (line, hits) in self.0.lock().unwrap().iter() {
+            lines.insert(*line, *hits);
+        }
+        CoverageReport { lines }
+    }
+}
+
+impl InterpreterHooks for Coverage {
+    fn on_statement(&mut self, _interpreter: &mut Interpreter, stmt: &Stmt) {
+        // a block only groups statements, which are counted on their own
+        if let Stmt::Block { .. } = stmt {
+            return;
+        }
+        if let Some(line) = stmt.line() {
+            *self.0.lock().unwrap().entry(line).or_insert(0) += 1;
+        }
+    }
+}
+
+fn executable_lines(statements: &[Stmt], lines: &mut BTreeMap<usize, u64>) {
+    for stmt in statements {
+        match stmt {
+            Stmt::Block { statements } => {
+                executable_lines(statements, lines);
+                continue;
+            }
+            Stmt::While { body, .. } => executable_lines(std::slice::from_ref(body), lines),
+            Stmt::If {
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                executable_lines(std::slice::from_ref(then_branch), lines);
+                if let Some(else_branch) = else_branch {
+                    executable_lines(std::slice::from_ref(else_branch), lines);
+                }
+            }
+            Stmt::Function(function) => executable_lines(&function.body, lines),
+            Stmt::Class { methods, .. } => {
+                for method in methods {
+                    executable_lines(&method.body, lines);
+                }
+            }
+            _ => {}
+        }
+        if let Some(line) = stmt.line() {
+            lines.insert(line, 0);
+        }
+    }
+}
+
+/// How often each line with a statement ran.
+pub struct CoverageReport {
+    lines: BTreeMap<usize, u64>,
+}
+
+impl CoverageReport {
+    /// Hits by line, for the lines with statements.
+    pub fn lines(&self) -> &BTreeMap<usize, u64> {
+        &self.lines
+    }
+
+    /// The report as an lcov tracefile for the script at `path`.
+    pub fn to_lcov(&self, path: &str) -> String {
+        let mut lcov = format!("TN:\nSF:{}\n", path);
+        for (line, hits) in &self.lines {
+            lcov.push_str(&format!("DA:{},{}\n", line, hits));
+        }
+        let hit = self.lines.values().filter(|hits| **hits > 0).count();
+        lcov.push_str(&format!(
+            "LF:{}\nLH:{}\nend_of_record\n",
+            self.lines.len(),
+            hit
+        ));
+        lcov
+    }
+
+    /// `source` with the hits of each line in front of it, `-` for lines
+    /// without statements and `#####` for the ones that never ran.
+    pub fn annotate(&self, source: &str) -> String {
+        let mut annotated = String::new();
+        for (i, text) in source.lines().enumerate() {
+            let count = match self.lines.get(&(i + 1)) {
+                Some(0) => "#####".to_string(),
+                Some(hits) => hits.to_string(),
+                None => "-".to_string(),
+            };
+            annotated.push_str(&format!("{:>9}:{:>5}: {}\n", count, i + 1, text));
+        }
+        annotated
+    }
+}
@@ -0,0 +1,101 @@
+use std::io::{self, BufRead, Write};
+
+use lox::debugger::{DebugClient, PauseReason, PausedSession, Resume};
+
+const HELP: &str = "\
+Commands:
+    step, s            run to the next statement, going into calls
+    next, n            run to the next statement in this function
+    continue, c        run to the next breakpoint
+    print, p <expr>    evaluate an expression in the current scope
+    backtrace, bt      show the active function calls
+    break, b <line>    pause whenever <line> is reached
+    delete, d <line>   remove the breakpoint on <line>
+    help, h            show this message";
+
+/// Reads debugger commands from stdin for `lox --debug`.
+pub struct DebugPrompt {
+    lines: Vec<String>,
+    // stdin ran out, so nothing can resume a pause anymore
+    detached: bool,
+}
+
+impl DebugPrompt {
+    pub fn new(source: &str) -> Self {
+        Self {
+            lines: source.lines().map(str::to_string).collect(),
+            detached: false,
+        }
+    }
+
+    fn show_line(&self, line: usize) {
+        if let Some(text) = line.checked_sub(1).and_then(|i| self.lines.get(i)) {
+            println!("{:>4} | {}", line, text);
+        }
+    }
+}
+
+impl DebugClient for DebugPrompt {
+    fn paused(&mut self, session: &mut PausedSession<'_>) -> Resume {
+        if self.detached {
+            return Resume::Continue;
+        }
+        let reason = match session.reason() {
+            PauseReason::Entry => "Paused before the first statement",
+            PauseReason::Breakpoint => "Breakpoint",
+            PauseReason::Step | PauseReason::Pause => "Paused",
+        };
+        println!("{} at line {}:", reason, session.line());
+        self.show_line(session.line());
+
+        let stdin = io::stdin();
+        loop {
+            print!("(debug) ");
+            let _ = io::stdout().flush();
+            let mut input = String::new();
+            match stdin.lock().read_line(&mut input) {
+                Ok(0) | Err(_) => {
+                    self.detached = true;
+                    return Resume::Continue;
+                }
+                Ok(_) => {}
+            }
+            let input = input.trim();
+            let (command, argument) = match input.split_once(char::is_whitespace) {
+                Some((command, argument)) => (command, argument.trim()),
+                None => (input, ""),
+            };
+            match command {
+                "step" | "s" => return Resume::StepIn,
+                "next" | "n" => return Resume::StepOver,
+                "continue" | "c" => return Resume::Continue,
+                "print" | "p" => match session.evaluate(0, argument) {
+                    Ok(value) => println!("{}", value),
+                    Err(e) => println!("{}", e),
+                },
+                "backtrace" | "bt" => {
+                    for (i, frame) in session.frames().iter().enumerate() {
+                        println!("#{} {} at line {}", i, frame.name, frame.line);
+                    }
+                }
+                "break" | "b" | "delete" | "d" => match argument.parse::<usize>() {
+                    Ok(line) => {
+                        let breakpoints = session.breakpoints();
+                        if command.starts_with('b') {
+                            breakpoints.insert(line);
+                            println!("Breakpoint set on line {}.", line);
+                        } else if breakpoints.remove(&line) {
+                            println!("Breakpoint on line {} removed.", line);
+                        } else {
+                            println!("There is no breakpoint on line {}.", line);
+                        }
+                    }
+                    Err(_) => println!("Expected a line number."),
+                },
+                "help" | "h" => println!("{}", HELP),
+                "" => {}
+                _ => println!("Unknown command '{}'. Type 'help' for a list.", command),
+            }
+        }
+    }
+}
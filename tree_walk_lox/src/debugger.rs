@@ -0,0 +1,255 @@
+//! Pausing and stepping through a script, built on `InterpreterHooks`. The
+//! `Debugger` tracks the call stack, breakpoints and stepping, and a
+//! `DebugClient` decides what happens while execution is paused, like
+//! answering the requests of an editor or reading commands from a terminal.
+
+use std::collections::BTreeSet;
+
+use crate::{
+    ast::Stmt,
+    environment::Environment,
+    hooks::InterpreterHooks,
+    interpreter::{Interpreter, InterpreterError},
+    value::RuntimeValue,
+    LoxError,
+};
+
+/// How to go on after a pause.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume {
+    /// run until the next breakpoint
+    Continue,
+    /// pause at the next statement, even inside a function it calls
+    StepIn,
+    /// pause at the next statement of this function or of its callers
+    StepOver,
+    /// pause once the current function returns
+    StepOut,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseReason {
+    /// the script is about to run its first statement
+    Entry,
+    Breakpoint,
+    Step,
+    /// the client asked for it while the script was running
+    Pause,
+}
+
+/// A function call that's in progress, or the script itself.
+#[derive(Clone)]
+pub struct Frame {
+    pub name: String,
+    /// the line running in this frame, which is the line of the call for
+    /// every frame but the innermost
+    pub line: usize,
+    /// the innermost scope of the frame
+    pub environment: Environment,
+}
+
+pub trait DebugClient {
+    /// Called before every statement while the script runs, to take care of
+    /// requests that arrive meanwhile. Returns whether to pause.
+    fn poll(&mut self, breakpoints: &mut BTreeSet<usize>) -> bool {
+        let _ = breakpoints;
+        false
+    }
+
+    /// Called when execution pauses. Doesn't return until the script should
+    /// go on.
+    fn paused(&mut self, session: &mut PausedSession<'_>) -> Resume;
+}
+
+/// What a client can look at and change while the script is paused.
+pub struct PausedSession<'a> {
+    interpreter: &'a mut Interpreter,
+    frames: Vec<Frame>,
+    reason: PauseReason,
+    breakpoints: &'a mut BTreeSet<usize>,
+}
+
+impl PausedSession<'_> {
+    /// The active frames, innermost first. The last one is the script.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn reason(&self) -> PauseReason {
+        self.reason
+    }
+
+    /// The line execution is paused at.
+    pub fn line(&self) -> usize {
+        self.frames[0].line
+    }
+
+    pub fn breakpoints(&mut self) -> &mut BTreeSet<usize> {
+        self.breakpoints
+    }
+
+    /// Evaluates an expression in the scope of the frame at `frame`.
+    pub fn evaluate(&mut self, frame: usize, source: &str) -> Result<RuntimeValue, LoxError> {
+        let environment = match self.frames.get(frame) {
+            Some(frame) => frame.environment.clone(),
+            None => self.interpreter.globals().clone(),
+        };
+        self.interpreter.evaluate_in(source, &environment)
+    }
+}
+
+// a call the debugger saw start and not return yet
+struct Call {
+    name: String,
+    line: usize,
+    caller_environment: Environment,
+}
+
+struct Step {
+    resume: Resume,
+    depth: usize,
+    line: usize,
+}
+
+/// Hooks that pause the script at breakpoints and while stepping, and hand
+/// control to a `DebugClient`. Install it with `Interpreter::set_hooks`.
+pub struct Debugger<C: DebugClient> {
+    client: C,
+    breakpoints: BTreeSet<usize>,
+    calls: Vec<Call>,
+    line: usize,
+    // depth and line of the last statement, so a line with many statements
+    // only stops at a breakpoint once
+    previous: Option<(usize, usize)>,
+    step: Option<Step>,
+    stop_on_entry: bool,
+}
+
+impl<C: DebugClient> Debugger<C> {
+    pub fn new(client: C) -> Self {
+        Self {
+            client,
+            breakpoints: BTreeSet::new(),
+            calls: vec![],
+            line: 0,
+            previous: None,
+            step: None,
+            stop_on_entry: false,
+        }
+    }
+
+    /// Pauses before the first statement.
+    pub fn with_stop_on_entry(mut self) -> Self {
+        self.stop_on_entry = true;
+        self
+    }
+
+    /// The lines to pause at.
+    pub fn breakpoints(&mut self) -> &mut BTreeSet<usize> {
+        &mut self.breakpoints
+    }
+
+    fn should_pause(&mut self, line: usize, depth: usize, stmt: &Stmt) -> Option<PauseReason> {
+        let moved = self.previous != Some((depth, line));
+        self.previous = Some((depth, line));
+
+        if self.client.poll(&mut self.breakpoints) {
+            return Some(PauseReason::Pause);
+        }
+        if std::mem::take(&mut self.stop_on_entry) {
+            return Some(PauseReason::Entry);
+        }
+        if let Stmt::Breakpoint { .. } = stmt {
+            return Some(PauseReason::Breakpoint);
+        }
+        if moved && self.breakpoints.contains(&line) {
+            return Some(PauseReason::Breakpoint);
+        }
+        let step = self.step.as_ref()?;
+        let stepped = match step.resume {
+            Resume::Continue => false,
+            Resume::StepIn => (depth, line) != (step.depth, step.line),
+            Resume::StepOver => depth < step.depth || (depth == step.depth && line != step.line),
+            Resume::StepOut => depth < step.depth,
+        };
+        if stepped {
+            Some(PauseReason::Step)
+        } else {
+            None
+        }
+    }
+
+    fn frames(&self, interpreter: &Interpreter) -> Vec<Frame> {
+        let mut frames = vec![];
+        let mut line = self.line;
+        let mut environment = interpreter.environment().clone();
+        for call in self.calls.iter().rev() {
+            frames.push(Frame {
+                name: call.name.clone(),
+                line,
+                environment,
+            });
+            line = call.line;
+            environment = call.caller_environment.clone();
+        }
+        frames.push(Frame {
+            name: "<script>".into(),
+            line,
+            environment,
+        });
+        frames
+    }
+}
+
+impl<C: DebugClient> InterpreterHooks for Debugger<C> {
+    fn on_statement(&mut self, interpreter: &mut Interpreter, stmt: &Stmt) {
+        let line = match stmt.line() {
+            Some(line) => line,
+            None => return,
+        };
+        self.line = line;
+        let depth = self.calls.len();
+        let reason = match self.should_pause(line, depth, stmt) {
+            Some(reason) => reason,
+            None => return,
+        };
+
+        let mut session = PausedSession {
+            frames: self.frames(interpreter),
+            interpreter,
+            reason,
+            breakpoints: &mut self.breakpoints,
+        };
+        let resume = self.client.paused(&mut session);
+        self.step = match resume {
+            Resume::Continue => None,
+            resume => Some(Step {
+                resume,
+                depth,
+                line,
+            }),
+        };
+    }
+
+    fn on_function_enter(
+        &mut self,
+        interpreter: &mut Interpreter,
+        name: &str,
+        _args: &[RuntimeValue],
+    ) {
+        self.calls.push(Call {
+            name: name.to_string(),
+            line: self.line,
+            caller_environment: interpreter.environment().clone(),
+        });
+    }
+
+    fn on_function_exit(
+        &mut self,
+        _interpreter: &mut Interpreter,
+        _name: &str,
+        _result: &Result<RuntimeValue, InterpreterError>,
+    ) {
+        self.calls.pop();
+    }
+}
@@ -0,0 +1,246 @@
+//! Errors and warnings shown with the line of source they're about, and the
+//! part of it they point at underlined:
+//!
+//! ```text
+//! error: Expect ';' after expression.
+//!  --> script.lox:2:9
+//!   |
+//! 2 | print(a)
+//!   |         ^
+//! ```
+//!
+//! In a terminal errors are red, warnings yellow and where they are cyan.
+
+use std::{env, fmt::Write, io::IsTerminal, ops::Range};
+
+use crate::{
+    interpreter::{InterpreterError, StatementError},
+    parser::ParserError,
+    resolver::{ResolverError, Warning},
+    scanner::ScanError,
+    token::Token,
+    LoxError,
+};
+
+const RESET: &str = "\x1b[0m";
+const ERROR: &str = "\x1b[1;31m";
+const WARNING: &str = "\x1b[1;33m";
+const SPAN: &str = "\x1b[36m";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// what went wrong, without where
+    pub message: String,
+    /// of the source, `None` if it isn't about any of it
+    pub line: Option<usize>,
+    /// bytes of the source it points at, which can be empty, like the end
+    /// of the source is
+    pub span: Option<Range<usize>>,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message.into())
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message.into())
+    }
+
+    fn new(severity: Severity, message: String) -> Self {
+        Self {
+            severity,
+            message,
+            line: None,
+            span: None,
+        }
+    }
+
+    pub fn at_line(mut self, line: usize) -> Self {
+        self.line = Some(line);
+        self
+    }
+
+    /// Points at the source `token` was scanned from.
+    pub fn at_token(mut self, token: &Token) -> Self {
+        self.line = Some(token.line);
+        self.span = Some(token.scanner_index..token.scanner_index + token.length);
+        self
+    }
+}
+
+impl From<&ScanError> for Diagnostic {
+    fn from(error: &ScanError) -> Self {
+        let message = match error {
+            ScanError::UnexpectedCharacter(c, _) => format!("Unexpected character '{}'.", c),
+            _ => error.message().to_string(),
+        };
+        Diagnostic::error(message).at_line(error.line())
+    }
+}
+
+impl From<&ParserError> for Diagnostic {
+    fn from(error: &ParserError) -> Self {
+        Diagnostic::error(error.message()).at_token(error.token())
+    }
+}
+
+impl From<&ResolverError> for Diagnostic {
+    fn from(error: &ResolverError) -> Self {
+        Diagnostic::error(error.message()).at_token(error.token())
+    }
+}
+
+impl From<&Warning> for Diagnostic {
+    fn from(warning: &Warning) -> Self {
+        Diagnostic::warning(warning.message()).at_token(warning.token())
+    }
+}
+
+impl From<&InterpreterError> for Diagnostic {
+    fn from(error: &InterpreterError) -> Self {
+        let diagnostic = Diagnostic::error(error.to_string().trim_end());
+        match error.token() {
+            // natives make tokens that aren't anywhere in the source
+            Some(token) if token.line > 0 => diagnostic.at_token(token),
+            _ => diagnostic,
+        }
+    }
+}
+
+impl From<&StatementError> for Diagnostic {
+    fn from(error: &StatementError) -> Self {
+        let diagnostic = Diagnostic::from(&error.error);
+        match error.line {
+            Some(line) if diagnostic.line.is_none() => diagnostic.at_line(line),
+            _ => diagnostic,
+        }
+    }
+}
+
+/// A diagnostic for each of the errors in `error`.
+pub fn diagnostics(error: &LoxError) -> Vec<Diagnostic> {
+    match error {
+        LoxError::Scan(e) => vec![e.into()],
+        LoxError::Parse(errors) => errors.iter().map(Diagnostic::from).collect(),
+        LoxError::Resolve(errors) => errors.iter().map(Diagnostic::from).collect(),
+        LoxError::Runtime(e) => vec![e.into()],
+    }
+}
+
+/// Whether what's written to `stream` should have colors: only when it's a
+/// terminal, and `NO_COLOR` isn't set to something.
+pub fn use_color(stream: &impl IsTerminal) -> bool {
+    let no_color = env::var_os("NO_COLOR").is_some_and(|it| !it.is_empty());
+    !no_color && stream.is_terminal()
+}
+
+/// Renders diagnostics about a source named `name`, like the path of a
+/// script.
+pub struct Renderer<'a> {
+    source: &'a str,
+    name: &'a str,
+    color: bool,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(source: &'a str, name: &'a str) -> Self {
+        Self {
+            source,
+            name,
+            color: false,
+        }
+    }
+
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// The diagnostic, ending with a newline.
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let mut out = String::new();
+        let (label, color) = match diagnostic.severity {
+            Severity::Error => ("error", ERROR),
+            Severity::Warning => ("warning", WARNING),
+        };
+        self.paint(&mut out, color, label);
+        writeln!(out, ": {}", diagnostic.message).unwrap();
+
+        let Some(line) = diagnostic.line.filter(|it| *it > 0) else {
+            return out;
+        };
+        if let Some(span) = &diagnostic.span {
+            if !self.is_on_line(span, line) {
+                return out;
+            }
+        }
+        let text = self.source.lines().nth(line - 1).unwrap_or("");
+        let gutter = " ".repeat(line.to_string().len());
+        // the columns the span covers, counting characters from 0, and only
+        // on its first line
+        let columns = diagnostic.span.as_ref().map(|span| {
+            let line_start = self.line_start(span.start);
+            let start = self.source[line_start..span.start].chars().count();
+            let end = span.end.min(line_start + text.len()).max(span.start);
+            let width = self.source[span.start..end].chars().count().max(1);
+            (start, width)
+        });
+
+        let location = match columns {
+            Some((start, _)) => format!("{}:{}:{}", self.name, line, start + 1),
+            None => format!("{}:{}", self.name, line),
+        };
+        write!(out, "{}", gutter).unwrap();
+        self.paint(&mut out, SPAN, "-->");
+        writeln!(out, " {}", location).unwrap();
+        self.gutter(&mut out, &gutter);
+        out.push('\n');
+        self.paint(&mut out, SPAN, &format!("{} |", line));
+        writeln!(out, " {}", text).unwrap();
+        if let Some((start, width)) = columns {
+            self.gutter(&mut out, &gutter);
+            out.push_str(&" ".repeat(start + 1));
+            self.paint(&mut out, SPAN, &"^".repeat(width));
+            out.push('\n');
+        }
+        out
+    }
+
+    // whether `span` is in the source and starts on `line`. a runtime error
+    // can point at a function another source defined, like an earlier line
+    // of the REPL, which this one can't show
+    fn is_on_line(&self, span: &Range<usize>, line: usize) -> bool {
+        let in_source = self.source.get(span.clone()).is_some();
+        match self.source.get(..span.start) {
+            Some(before) if in_source => before.matches('\n').count() + 1 == line,
+            _ => false,
+        }
+    }
+
+    // the byte offset of the start of the line `offset` is on
+    fn line_start(&self, offset: usize) -> usize {
+        self.source[..offset].rfind('\n').map_or(0, |it| it + 1)
+    }
+
+    fn gutter(&self, out: &mut String, gutter: &str) {
+        self.paint(out, SPAN, &format!("{} |", gutter));
+    }
+
+    fn paint(&self, out: &mut String, color: &str, text: &str) {
+        if self.color {
+            out.push_str(color);
+            out.push_str(text);
+            out.push_str(RESET);
+        } else {
+            out.push_str(text);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! `lox difftest`: runs a script on the tree-walk interpreter and on the
+//! bytecode vm and compares what each of them printed, the kind of error it
+//! stopped with and its exit code.
+//!
+//! The bytecode compiler only takes a single expression, and the vm prints
+//! its value. The tree-walk interpreter runs that expression as
+//! `println(<expression>);`, and scripts the bytecode compiler can't compile
+//! are unsupported rather than compared.
+
+use std::{
+    cell::RefCell,
+    fmt::Display,
+    panic::{self, AssertUnwindSafe},
+    rc::Rc,
+};
+
+use bytecode_lox::{error::InterpretError, vm::VM};
+
+use crate::{
+    interpreter::{Interpreter, InterpreterError},
+    LoxError,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    Compile,
+    Runtime,
+    /// the interpreter itself crashed
+    Panic,
+}
+
+impl Display for ErrorClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorClass::Compile => write!(f, "compile error"),
+            ErrorClass::Runtime => write!(f, "runtime error"),
+            ErrorClass::Panic => write!(f, "panic"),
+        }
+    }
+}
+
+/// How a run of a script ended.
+pub struct Outcome {
+    pub output: Vec<String>,
+    pub error: Option<(ErrorClass, String)>,
+    /// 65 for compile errors and 70 for runtime errors like the bytecode
+    /// binary, or the code given to the `exit` native
+    pub exit_code: i32,
+}
+
+#[derive(Clone, Default)]
+struct Capture(Rc<RefCell<Vec<String>>>);
+
+impl Capture {
+    fn lines(&self) -> Vec<String> {
+        self.0.borrow().clone()
+    }
+}
+
+impl crate::host::Output for Capture {
+    fn print(&mut self, line: &str) {
+        self.0.borrow_mut().push(line.to_string());
+    }
+}
+
+impl bytecode_lox::host::Output for Capture {
+    fn print(&mut self, line: &str) {
+        self.0.borrow_mut().push(line.to_string());
+    }
+}
+
+/// What running a script on both backends found.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Agreed,
+    /// the ways they differ, one line each
+    Diverged(Vec<String>),
+    /// the bytecode compiler can't compile the script, with its error
+    Unsupported(String),
+}
+
+/// Runs `source` on the bytecode vm and, if it compiles there, as the same
+/// expression on the tree-walk interpreter.
+pub fn run_both(source: &str) -> Comparison {
+    let bytecode = run_bytecode(source);
+    if let Some((ErrorClass::Compile, message)) = &bytecode.error {
+        return Comparison::Unsupported(message.clone());
+    }
+    // on a line of its own, so a comment at the end doesn't hide the `)`
+    let tree_walk = run_tree_walk(&format!("println(\n{}\n);", source));
+    match compare(&tree_walk, &bytecode) {
+        differences if differences.is_empty() => Comparison::Agreed,
+        differences => Comparison::Diverged(differences),
+    }
+}
+
+pub fn run_tree_walk(source: &str) -> Outcome {
+    let capture = Capture::default();
+    let result = catch_panic(|| {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_output(Box::new(capture.clone()));
+        crate::run_source(&mut interpreter, source.to_string())
+    });
+    let (error, exit_code) = match result {
+        Ok(Ok(())) => (None, 0),
+        Ok(Err(LoxError::Runtime(InterpreterError::Exit(code)))) => (None, code),
+        Ok(Err(e @ LoxError::Runtime(_))) => (Some((ErrorClass::Runtime, e.to_string())), 70),
+        Ok(Err(e)) => (Some((ErrorClass::Compile, e.to_string())), 65),
+        Err(message) => (Some((ErrorClass::Panic, message)), 101),
+    };
+    Outcome {
+        output: capture.lines(),
+        error,
+        exit_code,
+    }
+}
+
+pub fn run_bytecode(source: &str) -> Outcome {
+    let capture = Capture::default();
+    let result = catch_panic(|| VM::interpret(source.to_string(), &mut capture.clone()));
+    let (error, exit_code) = match result {
+        Ok(Ok(())) => (None, 0),
+        Ok(Err(e @ InterpretError::Compile(_))) => (Some((ErrorClass::Compile, e.to_string())), 65),
+        Ok(Err(e @ InterpretError::Runtime(_))) => (Some((ErrorClass::Runtime, e.to_string())), 70),
+        Err(message) => (Some((ErrorClass::Panic, message)), 101),
+    };
+    Outcome {
+        output: capture.lines(),
+        error,
+        exit_code,
+    }
+}
+
+// runs `f` without letting a panic escape or print, returning its message
+fn catch_panic<T>(f: impl FnOnce() -> T) -> Result<T, String> {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    panic::set_hook(hook);
+    result.map_err(|panic| {
+        panic
+            .downcast_ref::<&str>()
+            .map(|it| it.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_default()
+    })
+}
+
+/// The ways the two runs of a script differ, one line each.
+pub fn compare(tree_walk: &Outcome, bytecode: &Outcome) -> Vec<String> {
+    let mut differences = vec![];
+
+    let lines = tree_walk.output.len().max(bytecode.output.len());
+    if let Some(i) = (0..lines).find(|i| tree_walk.output.get(*i) != bytecode.output.get(*i)) {
+        differences.push(format!("stdout differs at line {}:", i + 1));
+        differences.push(format!(
+            "    tree-walk: {}",
+            show_line(&tree_walk.output, i)
+        ));
+        differences.push(format!("    bytecode:  {}", show_line(&bytecode.output, i)));
+    }
+
+    let classes = (
+        tree_walk.error.as_ref().map(|(class, _)| *class),
+        bytecode.error.as_ref().map(|(class, _)| *class),
+    );
+    if classes.0 != classes.1 {
+        differences.push("errors differ:".to_string());
+        differences.push(format!("    tree-walk: {}", show_error(&tree_walk.error)));
+        differences.push(format!("    bytecode:  {}", show_error(&bytecode.error)));
+    }
+
+    if tree_walk.exit_code != bytecode.exit_code {
+        differences.push(format!(
+            "exit codes differ: tree-walk {}, bytecode {}",
+            tree_walk.exit_code, bytecode.exit_code
+        ));
+    }
+    differences
+}
+
+fn show_line(output: &[String], i: usize) -> String {
+    match output.get(i) {
+        Some(line) => format!("{:?}", line),
+        None => "<no output>".to_string(),
+    }
+}
+
+fn show_error(error: &Option<(ErrorClass, String)>) -> String {
+    match error {
+        // only the first line, a compile error can have a lot of them
+        Some((class, message)) => {
+            format!("{}: {}", class, message.lines().next().unwrap_or_default())
+        }
+        None => "no error".to_string(),
+    }
+}
@@ -0,0 +1,283 @@
+//! Graphviz export of the syntax tree, for rendering parse trees with
+//! `dot -Tsvg`. Every statement and expression becomes a node labeled with
+//! its kind and the lexemes of its tokens, and edges are labeled with the
+//! field they come from.
+
+use std::fmt::Write;
+
+use crate::{
+    ast::{Expr, FunctionStmt, StaticField, Stmt},
+    token::Token,
+};
+
+/// Returns the DOT source of a graph with one tree per statement.
+pub fn to_dot(statements: &[Stmt]) -> String {
+    let mut graph = Graph::default();
+    graph.out.push_str("digraph ast {\n");
+    graph
+        .out
+        .push_str("    node [shape=box, fontname=monospace];\n");
+    for stmt in statements {
+        graph.stmt(stmt);
+    }
+    graph.out.push_str("}\n");
+    graph.out
+}
+
+#[derive(Default)]
+struct Graph {
+    out: String,
+    next_id: usize,
+}
+
+impl Graph {
+    fn node(&mut self, label: &str) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        writeln!(self.out, "    n{} [label=\"{}\"];", id, escape(label)).unwrap();
+        id
+    }
+
+    fn edge(&mut self, from: usize, to: usize, label: &str) {
+        if label.is_empty() {
+            writeln!(self.out, "    n{} -> n{};", from, to).unwrap();
+        } else {
+            writeln!(
+                self.out,
+                "    n{} -> n{} [label=\"{}\"];",
+                from,
+                to,
+                escape(label)
+            )
+            .unwrap();
+        }
+    }
+
+    fn token(&mut self, parent: usize, token: &Token, label: &str) {
+        let id = self.node(&token.lexeme);
+        self.edge(parent, id, label);
+    }
+
+    fn block(&mut self, parent: usize, statements: &[Stmt], label: &str) {
+        for stmt in statements {
+            let id = self.stmt(stmt);
+            self.edge(parent, id, label);
+        }
+    }
+
+    fn function(&mut self, kind: &str, fun: &FunctionStmt) -> usize {
+        let id = self.node(&format!("{} {}", kind, fun.name.lexeme));
+        for param in &fun.params {
+            self.token(id, param, "param");
+        }
+        self.block(id, &fun.body, "body");
+        id
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> usize {
+        match stmt {
+            Stmt::Expression { expression } => {
+                let id = self.node("Expression");
+                let child = self.expr(expression);
+                self.edge(id, child, "");
+                id
+            }
+            Stmt::Print { expression } => {
+                let id = self.node("Print");
+                let child = self.expr(expression);
+                self.edge(id, child, "");
+                id
+            }
+            Stmt::Breakpoint { .. } => self.node("Breakpoint"),
+            Stmt::Return { value, .. } => {
+                let id = self.node("Return");
+                if let Some(value) = value {
+                    let child = self.expr(value);
+                    self.edge(id, child, "");
+                }
+                id
+            }
+            Stmt::Var { name, initializer } => {
+                let id = self.node(&format!("Var {}", name.lexeme));
+                if let Some(initializer) = initializer {
+                    let child = self.expr(initializer);
+                    self.edge(id, child, "");
+                }
+                id
+            }
+            Stmt::While {
+                label,
+                condition,
+                body,
+                increment,
+            } => {
+                let id = self.node(&labeled("While", label));
+                let child = self.expr(condition);
+                self.edge(id, child, "condition");
+                let child = self.stmt(body);
+                self.edge(id, child, "body");
+                if let Some(increment) = increment {
+                    let child = self.expr(increment);
+                    self.edge(id, child, "increment");
+                }
+                id
+            }
+            Stmt::Break { label, .. } => self.node(&labeled("Break", label)),
+            Stmt::Continue { label, .. } => self.node(&labeled("Continue", label)),
+            Stmt::Block { statements } => {
+                let id = self.node("Block");
+                self.block(id, statements, "");
+                id
+            }
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+                statics,
+            } => self.class(
+                &format!("Class {}", name.lexeme),
+                superclass.as_ref(),
+                methods,
+                statics,
+            ),
+            Stmt::Function(fun) => self.function("Fun", fun),
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                let id = self.node("If");
+                let child = self.expr(condition);
+                self.edge(id, child, "condition");
+                let child = self.stmt(then_branch);
+                self.edge(id, child, "then");
+                if let Some(branch) = else_branch {
+                    let child = self.stmt(branch);
+                    self.edge(id, child, "else");
+                }
+                id
+            }
+        }
+    }
+
+    fn class(
+        &mut self,
+        label: &str,
+        superclass: Option<&Expr>,
+        methods: &[FunctionStmt],
+        statics: &[StaticField],
+    ) -> usize {
+        let id = self.node(label);
+        if let Some(Expr::Variable {
+            name: superclass, ..
+        }) = superclass
+        {
+            self.token(id, superclass, "superclass");
+        }
+        for field in statics {
+            let child = self.expr(&field.value);
+            self.edge(id, child, &format!("static {}", field.name.lexeme));
+        }
+        for method in methods {
+            let child = self.function("Method", method);
+            self.edge(id, child, "");
+        }
+        id
+    }
+
+    fn expr(&mut self, expr: &Expr) -> usize {
+        match expr {
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            }
+            | Expr::Logical {
+                left,
+                operator,
+                right,
+            } => {
+                let id = self.node(&operator.lexeme);
+                let child = self.expr(left);
+                self.edge(id, child, "");
+                let child = self.expr(right);
+                self.edge(id, child, "");
+                id
+            }
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                let id = self.node("Call");
+                let child = self.expr(callee);
+                self.edge(id, child, "callee");
+                for argument in arguments {
+                    let child = self.expr(argument);
+                    self.edge(id, child, "argument");
+                }
+                id
+            }
+            Expr::Get { object, name } => {
+                let id = self.node(&format!(".{}", name.lexeme));
+                let child = self.expr(object);
+                self.edge(id, child, "");
+                id
+            }
+            Expr::Grouping { expression } => {
+                let id = self.node("( )");
+                let child = self.expr(expression);
+                self.edge(id, child, "");
+                id
+            }
+            Expr::Literal { value } => self.node(&value.lexeme),
+            Expr::Set {
+                object,
+                name,
+                value,
+            } => {
+                let id = self.node(&format!(".{} =", name.lexeme));
+                let child = self.expr(object);
+                self.edge(id, child, "object");
+                let child = self.expr(value);
+                self.edge(id, child, "value");
+                id
+            }
+            Expr::Super { method, .. } => self.node(&format!("super.{}", method.lexeme)),
+            Expr::This { .. } => self.node("this"),
+            Expr::Unary { operator, right } => {
+                let id = self.node(&operator.lexeme);
+                let child = self.expr(right);
+                self.edge(id, child, "");
+                id
+            }
+            Expr::Variable { name, .. } => self.node(&name.lexeme),
+            Expr::Assign { name, value, .. } => {
+                let id = self.node(&format!("{} =", name.lexeme));
+                let child = self.expr(value);
+                self.edge(id, child, "");
+                id
+            }
+            Expr::Class {
+                superclass,
+                methods,
+                statics,
+                ..
+            } => self.class("Class", superclass.as_deref(), methods, statics),
+        }
+    }
+}
+
+// string literals keep their quotes in the label
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+// a loop or a jump out of one, with the loop's label
+fn labeled(kind: &str, label: &Option<Token>) -> String {
+    match label {
+        Some(label) => format!("{} {}", kind, label.lexeme),
+        None => kind.to_string(),
+    }
+}
@@ -1,50 +1,163 @@
-use std::{
-    collections::HashMap,
-    sync::{Arc, Mutex},
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rustc_hash::FxHashMap;
+
+use crate::{
+    gc::{self, Node, Trace},
+    shared::{Cell, Gc, Weak},
+    symbol::Symbol,
+    value::RuntimeValue,
 };
 
-use crate::value::RuntimeValue;
+struct Variables {
+    // in the order they were defined, which in a local scope is the order
+    // the resolver numbered their slots in
+    names: Vec<Symbol>,
+    values: Vec<RuntimeValue>,
+    // the slot of each name, only kept for the globals since they're the
+    // only variables looked up by name
+    slots: Option<FxHashMap<Symbol, usize>>,
+}
+impl Variables {
+    fn new(by_name: bool) -> Self {
+        Self {
+            names: vec![],
+            values: vec![],
+            slots: if by_name {
+                Some(FxHashMap::default())
+            } else {
+                None
+            },
+        }
+    }
+    fn slot_of(&self, name: Symbol) -> Option<usize> {
+        match &self.slots {
+            Some(slots) => slots.get(&name).copied(),
+            None => self.names.iter().position(|it| *it == name),
+        }
+    }
+}
+
+// shared by an environment and all of its descendants
+#[derive(Default)]
+struct Heap {
+    // how many of them are alive
+    live: AtomicUsize,
+    // all of them, so the cycle collector can find the ones that are only
+    // alive because they refer to each other
+    environments: Cell<Vec<Weak<EnvironmentStorage>>>,
+}
+impl Heap {
+    fn register(&self, environment: &Gc<EnvironmentStorage>) {
+        let live = self.live.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut environments = self.environments.borrow_mut();
+        // a dropped environment keeps its allocation until it's pruned
+        if environments.len() >= 2 * live.max(32) {
+            environments.retain(|it| it.strong_count() > 0);
+        }
+        environments.push(Gc::downgrade(environment));
+    }
+}
 
 struct EnvironmentStorage {
-    values: Mutex<HashMap<String, RuntimeValue>>,
+    variables: Cell<Variables>,
     enclosing: Option<Environment>,
+    heap: Gc<Heap>,
+}
+impl Drop for EnvironmentStorage {
+    fn drop(&mut self) {
+        self.heap.live.fetch_sub(1, Ordering::Relaxed);
+    }
 }
 #[derive(Clone)]
-pub struct Environment(Arc<EnvironmentStorage>);
+pub struct Environment(Gc<EnvironmentStorage>);
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Environment {
     pub fn new() -> Self {
-        Self(
-            EnvironmentStorage {
-                values: HashMap::new().into(),
-                enclosing: None,
-            }
-            .into(),
-        )
+        let heap = Gc::new(Heap::default());
+        let storage = Gc::new(EnvironmentStorage {
+            variables: Variables::new(true).into(),
+            enclosing: None,
+            heap: heap.clone(),
+        });
+        heap.register(&storage);
+        Self(storage)
     }
     pub fn child(&self) -> Self {
-        Self(
-            EnvironmentStorage {
-                values: HashMap::new().into(),
-                enclosing: Some(self.clone()),
-            }
-            .into(),
-        )
+        let storage = Gc::new(EnvironmentStorage {
+            variables: Variables::new(false).into(),
+            enclosing: Some(self.clone()),
+            heap: self.0.heap.clone(),
+        });
+        self.0.heap.register(&storage);
+        Self(storage)
+    }
+    /// Number of environments alive in the tree this one belongs to.
+    pub fn live_count(&self) -> usize {
+        self.0.heap.live.load(Ordering::Relaxed)
+    }
+    /// Every environment alive in the tree this one belongs to.
+    pub(crate) fn all(&self) -> Vec<Environment> {
+        let mut environments = self.0.heap.environments.borrow_mut();
+        environments.retain(|it| it.strong_count() > 0);
+        environments
+            .iter()
+            .filter_map(Weak::upgrade)
+            .map(Environment)
+            .collect()
     }
     pub fn enclosing(&self) -> Option<Environment> {
         self.0.enclosing.clone()
     }
-    pub fn define(&self, name: &str, value: RuntimeValue) {
-        self.0
-            .values
-            .lock()
-            .unwrap()
-            .insert(name.to_string(), value);
-    }
-    pub fn assign(&self, name: &str, value: RuntimeValue) -> Option<RuntimeValue> {
-        let mut values = self.0.values.lock().unwrap();
-        if values.contains_key(name) {
-            values.insert(name.to_string(), value)
+    /// snapshot of the variables defined in this environment, without the
+    /// enclosing ones, sorted by name so the order is stable
+    pub fn values(&self) -> Vec<(Symbol, RuntimeValue)> {
+        let variables = self.0.variables.borrow();
+        let mut values = variables
+            .names
+            .iter()
+            .copied()
+            .zip(variables.values.iter().cloned())
+            .collect::<Vec<_>>();
+        values.sort_by_key(|(name, _)| *name);
+        values
+    }
+    /// How many environments up the chain `name` is defined, and its slot
+    /// there.
+    pub fn locate(&self, name: Symbol) -> Option<(usize, usize)> {
+        if let Some(slot) = self.0.variables.borrow().slot_of(name) {
+            Some((0, slot))
+        } else {
+            let enclosing = self.0.enclosing.as_ref()?;
+            let (depth, slot) = enclosing.locate(name)?;
+            Some((depth + 1, slot))
+        }
+    }
+    /// Adds a variable in the next slot. Defining a global again replaces
+    /// it instead.
+    pub fn define(&self, name: Symbol, value: RuntimeValue) {
+        let mut variables = self.0.variables.borrow_mut();
+        let next = variables.values.len();
+        if let Some(slots) = &mut variables.slots {
+            if let Some(&slot) = slots.get(&name) {
+                variables.values[slot] = value;
+                return;
+            }
+            slots.insert(name, next);
+        }
+        variables.names.push(name);
+        variables.values.push(value);
+    }
+    pub fn assign(&self, name: Symbol, value: RuntimeValue) -> Option<RuntimeValue> {
+        let mut variables = self.0.variables.borrow_mut();
+        if let Some(slot) = variables.slot_of(name) {
+            Some(std::mem::replace(&mut variables.values[slot], value))
         } else if let Some(enclosing) = &self.0.enclosing {
             enclosing.assign(name, value)
         } else {
@@ -54,59 +167,64 @@ impl Environment {
     pub fn assign_at(
         &self,
         distance: usize,
-        name: &str,
+        slot: usize,
         value: RuntimeValue,
     ) -> Option<RuntimeValue> {
-        if distance > 0 {
-            self.ancestor(distance)
-                .0
-                .values
-                .lock()
-                .unwrap()
-                .insert(name.to_string(), value)
-        } else {
-            self.0
-                .values
-                .lock()
-                .unwrap()
-                .insert(name.to_string(), value)
-        }
+        let mut variables = self.ancestor(distance).0.variables.borrow_mut();
+        let current = variables.values.get_mut(slot)?;
+        Some(std::mem::replace(current, value))
     }
-    pub fn get(&self, name: &str) -> Option<RuntimeValue> {
-        let mut value = self.0.values.lock().unwrap().get(name).cloned();
-        if value.is_none() {
-            if let Some(enclosing) = &self.0.enclosing {
-                value = enclosing.get(name);
-            }
+    pub fn get(&self, name: Symbol) -> Option<RuntimeValue> {
+        let variables = self.0.variables.borrow();
+        match variables.slot_of(name) {
+            Some(slot) => Some(variables.values[slot].clone()),
+            None => self.0.enclosing.as_ref()?.get(name),
         }
-        value
     }
-    pub fn get_at(&self, distance: usize, name: &str) -> Option<RuntimeValue> {
-        if distance > 0 {
-            self.ancestor(distance)
+    pub fn get_at(&self, distance: usize, slot: usize) -> Option<RuntimeValue> {
+        let variables = self.ancestor(distance).0.variables.borrow();
+        variables.values.get(slot).cloned()
+    }
+
+    fn ancestor(&self, distance: usize) -> &Environment {
+        let mut environment = self;
+        for _ in 0..distance {
+            environment = environment
                 .0
-                .values
-                .lock()
-                .unwrap()
-                .get(name)
-                .cloned()
-        } else {
-            self.0.values.lock().unwrap().get(name).cloned()
+                .enclosing
+                .as_ref()
+                .expect("resolved deeper than the environments go");
         }
+        environment
     }
+}
 
-    fn ancestor(&self, distance: usize) -> Environment {
-        assert!(distance > 0);
-        assert!(self.0.enclosing.is_some());
-
-        // yuck
-        let mut env = self.0.enclosing.clone();
-        for _ in 0..(distance - 1) {
-            let some = env.take().unwrap();
-            if let Some(enclosing) = some.0.enclosing.as_ref() {
-                env = Some(enclosing.clone())
-            }
+impl Trace for Environment {
+    fn address(&self) -> usize {
+        Gc::as_ptr(&self.0) as *const () as usize
+    }
+    fn strong_count(&self) -> usize {
+        Gc::strong_count(&self.0)
+    }
+    fn trace(&self, children: &mut Vec<Node>) -> bool {
+        let variables = match self.0.variables.try_borrow() {
+            Some(variables) => variables,
+            None => return false,
+        };
+        if let Some(enclosing) = &self.0.enclosing {
+            children.push(Node::Environment(enclosing.clone()));
         }
-        env.unwrap()
+        for value in &variables.values {
+            gc::trace_value(value, children);
+        }
+        true
+    }
+    fn clear(&self) {
+        // dropped after the borrow ends, dropping values can come back here
+        let cleared = self.0.variables.try_borrow_mut().map(|mut variables| {
+            let by_name = variables.slots.is_some();
+            std::mem::replace(&mut *variables, Variables::new(by_name))
+        });
+        drop(cleared);
     }
 }
@@ -0,0 +1,283 @@
+//! Source formatter behind `lox fmt`. The script is parsed to make sure it
+//! is valid, then its tokens are printed again with canonical indentation
+//! and spacing. Working on tokens instead of the syntax tree is what keeps
+//! the comments, which the parser throws away.
+
+use crate::{
+    parser::Parser,
+    scanner::Scanner,
+    symbol::Symbol,
+    token::{Token, TokenKind},
+    LoxError,
+};
+
+const INDENT: &str = "  ";
+/// Lines longer than this are wrapped at their arguments when possible.
+pub const MAX_WIDTH: usize = 100;
+
+/// Returns `source` formatted, or the errors that make it invalid. With
+/// `compat_jlox` the source is in jlox's dialect, with `print` statements.
+pub fn format_source(source: &str, compat_jlox: bool) -> Result<String, LoxError> {
+    let scanner = || {
+        let scanner = Scanner::new(source.to_string());
+        if compat_jlox {
+            scanner.with_compat_jlox()
+        } else {
+            scanner
+        }
+    };
+    Parser::new(scanner()).parse()?;
+
+    let mut tokens = scanner().with_comments().scan_tokens()?;
+    // identifiers are written as they were, not in the NFC of their lexemes
+    for token in tokens.iter_mut() {
+        if token.kind == TokenKind::Identifier {
+            token.lexeme = Symbol::intern(&source[token.span()]);
+        }
+    }
+    Ok(Formatter::default().format(&tokens))
+}
+
+#[derive(Default)]
+struct Formatter<'t> {
+    lines: Vec<String>,
+    // tokens of the line being built and whether each one is a unary operator
+    line: Vec<(&'t Token, bool)>,
+    indent: usize,
+    // the indentation of the line being built, fixed by its first token
+    line_indent: usize,
+    // the previous line was cut short by a comment in the middle of a
+    // statement, so this one is indented as its continuation
+    continued: bool,
+    parens: usize,
+    // the line is complete, but a comment after it on the same line of the
+    // source still belongs to it
+    pending_break: bool,
+    last_line: usize,
+}
+
+impl<'t> Formatter<'t> {
+    fn format(mut self, tokens: &'t [Token]) -> String {
+        let mut tokens = tokens.iter().peekable();
+        while let Some(token) = tokens.next() {
+            if token.kind == TokenKind::Eof {
+                break;
+            }
+            if self.pending_break {
+                if token.kind == TokenKind::Comment && token.line == self.last_line {
+                    self.flush(Some(token));
+                    self.last_line = token.line;
+                    continue;
+                }
+                self.flush(None);
+            }
+            if self.line.is_empty() && token.kind != TokenKind::RightBrace {
+                self.blank_line_before(token);
+            }
+
+            match token.kind {
+                // a comment in the middle of a statement ends its line
+                TokenKind::Comment if !self.line.is_empty() => {
+                    self.flush(Some(token));
+                    self.continued = true;
+                }
+                TokenKind::Comment => {
+                    self.push_line(token.lexeme.trim_end().to_string());
+                }
+                TokenKind::LeftBrace => {
+                    self.push(token);
+                    if tokens.peek().map(|it| it.kind) == Some(TokenKind::RightBrace) {
+                        // an empty block stays on one line: `class A {}`
+                        let close = tokens.next().unwrap();
+                        self.push(close);
+                        self.end_block_line(tokens.peek().map(|it| it.kind));
+                    } else {
+                        self.pending_break = true;
+                        self.indent += 1;
+                    }
+                }
+                TokenKind::RightBrace => {
+                    if !self.line.is_empty() {
+                        self.flush(None);
+                    }
+                    self.indent = self.indent.saturating_sub(1);
+                    self.push(token);
+                    self.end_block_line(tokens.peek().map(|it| it.kind));
+                }
+                TokenKind::Semicolon => {
+                    self.push(token);
+                    // the semicolons in a for loop's clauses don't end lines
+                    if self.parens == 0 {
+                        self.pending_break = true;
+                    }
+                }
+                TokenKind::LeftParen => {
+                    self.parens += 1;
+                    self.push(token);
+                }
+                TokenKind::RightParen => {
+                    self.parens = self.parens.saturating_sub(1);
+                    self.push(token);
+                }
+                _ => self.push(token),
+            }
+            self.last_line = token.line;
+        }
+        if !self.line.is_empty() {
+            self.flush(None);
+        }
+
+        let mut out = self.lines.join("\n");
+        out.push('\n');
+        out
+    }
+
+    // `} else` stays together, and so does the end of a class expression
+    // with what follows it, as in `};` or `})`. anything else after a
+    // closing brace starts a new line
+    fn end_block_line(&mut self, next: Option<TokenKind>) {
+        use TokenKind::*;
+        if !matches!(next, Some(Else | Semicolon | RightParen | Comma | Dot)) {
+            self.pending_break = true;
+        }
+    }
+
+    // keeps one blank line where the source had one or more between two
+    // statements, but never right after an opening brace
+    fn blank_line_before(&mut self, token: &Token) {
+        let after_open_brace = self.lines.last().is_none_or(|line| line.ends_with('{'));
+        let last_is_blank = self.lines.last().is_some_and(|line| line.is_empty());
+        if token.line > self.last_line + 1 && !after_open_brace && !last_is_blank {
+            self.lines.push(String::new());
+        }
+    }
+
+    fn push(&mut self, token: &'t Token) {
+        let unary = match token.kind {
+            TokenKind::Bang => true,
+            TokenKind::Minus => self.line.last().is_none_or(|(prev, _)| {
+                !matches!(
+                    prev.kind,
+                    TokenKind::Identifier
+                        | TokenKind::Number
+                        | TokenKind::String
+                        | TokenKind::True
+                        | TokenKind::False
+                        | TokenKind::Nil
+                        | TokenKind::This
+                        | TokenKind::RightParen
+                )
+            }),
+            _ => false,
+        };
+        if self.line.is_empty() {
+            self.line_indent = self.indent + self.continued as usize;
+            self.continued = false;
+        }
+        self.line.push((token, unary));
+    }
+
+    fn push_line(&mut self, text: String) {
+        self.lines
+            .push(format!("{}{}", INDENT.repeat(self.indent), text));
+    }
+
+    fn flush(&mut self, comment: Option<&Token>) {
+        let line = std::mem::take(&mut self.line);
+        self.pending_break = false;
+        let mut wrapped = wrap(&line, self.line_indent);
+        if let Some(comment) = comment {
+            let last = wrapped.last_mut().unwrap();
+            last.push(' ');
+            last.push_str(comment.lexeme.trim_end());
+        }
+        self.lines.extend(wrapped);
+    }
+}
+
+fn space_between(prev: (&Token, bool), next: &Token) -> bool {
+    use TokenKind::*;
+    let (prev, prev_unary) = prev;
+    if prev_unary || matches!(prev.kind, LeftParen | Dot) {
+        return false;
+    }
+    match next.kind {
+        Colon | Comma | Semicolon | RightParen | Dot => false,
+        RightBrace => prev.kind != LeftBrace,
+        // calls and declarations, but not `if (`, `while (` and the like
+        LeftParen => !matches!(prev.kind, Identifier | RightParen | This | Super),
+        _ => true,
+    }
+}
+
+fn render(tokens: &[(&Token, bool)]) -> String {
+    let mut out = String::new();
+    for (i, (token, _)) in tokens.iter().enumerate() {
+        if i > 0 && space_between(tokens[i - 1], token) {
+            out.push(' ');
+        }
+        out.push_str(&token.lexeme);
+    }
+    out
+}
+
+// renders the tokens as one line if it fits. otherwise the first group of
+// parentheses with commas in it gets one argument per line:
+//
+//     call(
+//       first,
+//       second
+//     );
+fn wrap(tokens: &[(&Token, bool)], indent: usize) -> Vec<String> {
+    let prefix = INDENT.repeat(indent);
+    let line = render(tokens);
+    if prefix.len() + line.chars().count() <= MAX_WIDTH {
+        return vec![format!("{}{}", prefix, line)];
+    }
+    let (open, close, commas) = match split_point(tokens) {
+        Some(split) => split,
+        None => return vec![format!("{}{}", prefix, line)],
+    };
+
+    let mut lines = vec![format!("{}{}", prefix, render(&tokens[..=open]))];
+    let mut start = open + 1;
+    for end in commas.into_iter().chain(std::iter::once(close)) {
+        let mut argument = wrap(&tokens[start..end], indent + 1);
+        if end != close {
+            argument.last_mut().unwrap().push(',');
+        }
+        lines.extend(argument);
+        start = end + 1;
+    }
+    lines.extend(wrap(&tokens[close..], indent));
+    lines
+}
+
+// the first '(' with commas directly inside it, its matching ')' and the
+// commas in between
+fn split_point(tokens: &[(&Token, bool)]) -> Option<(usize, usize, Vec<usize>)> {
+    for (open, (token, _)) in tokens.iter().enumerate() {
+        if token.kind != TokenKind::LeftParen {
+            continue;
+        }
+        let mut depth = 0;
+        let mut commas = vec![];
+        for (i, (token, _)) in tokens.iter().enumerate().skip(open) {
+            match token.kind {
+                TokenKind::LeftParen => depth += 1,
+                TokenKind::RightParen => {
+                    depth -= 1;
+                    if depth == 0 {
+                        if !commas.is_empty() {
+                            return Some((open, i, commas));
+                        }
+                        break;
+                    }
+                }
+                TokenKind::Comma if depth == 1 => commas.push(i),
+                _ => {}
+            }
+        }
+    }
+    None
+}
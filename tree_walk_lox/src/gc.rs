@@ -0,0 +1,146 @@
+//! Frees the environments that are only alive because of references to each
+//! other, like the one a function is defined in, which holds the function,
+//! whose closure is that same environment.
+//!
+//! Reference counting can't free those, so this does what CPython's
+//! collector does: it subtracts the references that the environments and the
+//! values reachable from them hold to each other from their reference
+//! counts. Whatever still has references left is referred to from outside,
+//! by the interpreter, and is alive along with everything it reaches. The
+//! rest is garbage, and emptying it breaks its cycles.
+
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    environment::Environment,
+    value::{ClassDefinition, ClassInstance, List, Map, RuntimeValue, UserFunction},
+};
+
+/// Something that holds references to other values and can be part of a
+/// cycle.
+pub(crate) trait Trace {
+    /// Where its shared state lives, which tells copies of it apart from
+    /// other values.
+    fn address(&self) -> usize;
+    fn strong_count(&self) -> usize;
+    /// Pushes a node for each reference it holds, or returns false if it's
+    /// borrowed and can't be looked at right now.
+    fn trace(&self, children: &mut Vec<Node>) -> bool;
+    /// Drops the values it holds.
+    fn clear(&self);
+}
+
+pub(crate) enum Node {
+    Environment(Environment),
+    Function(UserFunction),
+    Class(ClassDefinition),
+    Instance(ClassInstance),
+    List(List),
+    Map(Map),
+}
+impl Node {
+    fn as_trace(&self) -> &dyn Trace {
+        match self {
+            Node::Environment(it) => it,
+            Node::Function(it) => it,
+            Node::Class(it) => it,
+            Node::Instance(it) => it,
+            Node::List(it) => it,
+            Node::Map(it) => it,
+        }
+    }
+}
+
+/// Pushes a node for `value` if it can hold references.
+pub(crate) fn trace_value(value: &RuntimeValue, children: &mut Vec<Node>) {
+    let node = match value {
+        RuntimeValue::UserFunction(it) => Node::Function(it.clone()),
+        RuntimeValue::Class(it) => Node::Class(it.clone()),
+        RuntimeValue::Instance(it) => Node::Instance(it.clone()),
+        RuntimeValue::List(it) => Node::List(it.clone()),
+        RuntimeValue::Map(it) => Node::Map(it.clone()),
+        // natives are opaque, so whatever they capture is kept alive
+        RuntimeValue::Bool(_)
+        | RuntimeValue::Float(_)
+        | RuntimeValue::BigInt(_)
+        | RuntimeValue::Str(_)
+        | RuntimeValue::BuiltInFunction(_)
+        | RuntimeValue::Nil => return,
+    };
+    children.push(node);
+}
+
+struct Entry {
+    node: Node,
+    // references from outside of the environments and their values
+    outside: usize,
+    children: Vec<usize>,
+}
+
+/// Frees the unreachable environments in the tree `environment` belongs
+/// to, and returns how many there were. Collects nothing if some value is
+/// borrowed, since it can't know what that value refers to.
+pub(crate) fn collect(environment: &Environment) -> usize {
+    let mut entries: FxHashMap<usize, Entry> = FxHashMap::default();
+    let mut pending = environment
+        .all()
+        .into_iter()
+        .map(Node::Environment)
+        .collect::<Vec<_>>();
+    let mut children = vec![];
+    while let Some(node) = pending.pop() {
+        let address = node.as_trace().address();
+        if entries.contains_key(&address) {
+            continue;
+        }
+        if !node.as_trace().trace(&mut children) {
+            return 0;
+        }
+        let addresses = children.iter().map(|it| it.as_trace().address());
+        let entry = Entry {
+            node,
+            outside: 0,
+            children: addresses.collect(),
+        };
+        entries.insert(address, entry);
+        pending.append(&mut children);
+    }
+
+    // the entries hold the only copies made while tracing, one each
+    for entry in entries.values_mut() {
+        entry.outside = entry.node.as_trace().strong_count() - 1;
+    }
+    let references = entries
+        .values()
+        .flat_map(|it| it.children.clone())
+        .collect::<Vec<_>>();
+    for child in references {
+        if let Some(entry) = entries.get_mut(&child) {
+            entry.outside = entry.outside.saturating_sub(1);
+        }
+    }
+
+    let mut alive = entries
+        .iter()
+        .filter(|(_, it)| it.outside > 0)
+        .map(|(address, _)| *address)
+        .collect::<Vec<_>>();
+    let mut reachable = FxHashSet::default();
+    while let Some(address) = alive.pop() {
+        if reachable.insert(address) {
+            alive.extend(&entries[&address].children);
+        }
+    }
+
+    let mut freed = 0;
+    for (address, entry) in &entries {
+        if reachable.contains(address) {
+            continue;
+        }
+        if let Node::Environment(_) = entry.node {
+            freed += 1;
+        }
+        entry.node.as_trace().clear();
+    }
+    freed
+}
@@ -0,0 +1,93 @@
+//! Syntax highlighting for the REPL. A line is colored by the tokens the
+//! scanner makes of it, so the colors always agree with how it will be read.
+//! `highlight` has the shape of rustyline's `Highlighter::highlight` hook,
+//! which the REPL's line editor calls on every keystroke.
+
+use std::borrow::Cow;
+
+use crate::{
+    scanner::{ScanError, Scanner},
+    token::TokenKind,
+};
+
+const RESET: &str = "\x1b[0m";
+const KEYWORD: &str = "\x1b[35m";
+const STRING: &str = "\x1b[32m";
+const NUMBER: &str = "\x1b[33m";
+const COMMENT: &str = "\x1b[90m";
+
+#[derive(Default)]
+pub struct Highlighter {
+    compat_jlox: bool,
+}
+
+impl Highlighter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Colors `print` as the keyword of the book's print statement, like
+    /// `Scanner::with_compat_jlox` scans it.
+    pub fn with_compat_jlox(mut self) -> Self {
+        self.compat_jlox = true;
+        self
+    }
+
+    /// `line` with ANSI colors around its keywords, strings, numbers and
+    /// comments. A string that isn't closed yet is colored to the end of the
+    /// line, as it usually is while it's being typed, and anything after
+    /// another error is left as it is.
+    pub fn highlight<'l>(&self, line: &'l str) -> Cow<'l, str> {
+        let mut scanner = Scanner::new(line.to_string()).with_comments();
+        if self.compat_jlox {
+            scanner = scanner.with_compat_jlox();
+        }
+
+        let mut highlighted = String::new();
+        let mut end = 0;
+        for token in scanner {
+            match token {
+                Ok(token) => {
+                    let Some(color) = color(token.kind) else {
+                        continue;
+                    };
+                    let span = token.span();
+                    highlighted.push_str(&line[end..span.start]);
+                    paint(&mut highlighted, color, &line[span.clone()]);
+                    end = span.end;
+                }
+                Err(ScanError::UnterminatedString(_)) => {
+                    if let Some(quote) = line[end..].find('"') {
+                        highlighted.push_str(&line[end..end + quote]);
+                        paint(&mut highlighted, STRING, &line[end + quote..]);
+                        end = line.len();
+                    }
+                }
+                Err(ScanError::UnexpectedCharacter(..)) => {}
+            }
+        }
+        if highlighted.is_empty() {
+            return Cow::Borrowed(line);
+        }
+        highlighted.push_str(&line[end..]);
+        Cow::Owned(highlighted)
+    }
+}
+
+fn color(kind: TokenKind) -> Option<&'static str> {
+    use TokenKind::*;
+    match kind {
+        And | Break | Breakpoint | Class | Continue | Else | False | Fun | For | If | Nil | Or
+        | Print | Return | Super | This | True | Var | While => Some(KEYWORD),
+        String => Some(STRING),
+        Number => Some(NUMBER),
+        Comment => Some(COMMENT),
+        _ => None,
+    }
+}
+
+fn paint(out: &mut String, color: &str, text: &str) {
+    out.push_str(color);
+    out.push_str(text);
+    out.push_str(RESET);
+}
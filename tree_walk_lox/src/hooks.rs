@@ -0,0 +1,40 @@
+use crate::{
+    ast::Stmt,
+    interpreter::{Interpreter, InterpreterError},
+    value::RuntimeValue,
+};
+
+/// Callbacks an embedder can install with `Interpreter::set_hooks` to
+/// observe execution, for profilers, debuggers or audit logs.
+///
+/// Hooks are not called again while one of them is running, so they can
+/// use the interpreter they receive without seeing their own activity.
+#[allow(unused_variables)]
+pub trait InterpreterHooks {
+    /// called before a statement is executed
+    fn on_statement(&mut self, interpreter: &mut Interpreter, stmt: &Stmt) {}
+
+    /// called before a function, native or class is called
+    fn on_function_enter(
+        &mut self,
+        interpreter: &mut Interpreter,
+        name: &str,
+        args: &[RuntimeValue],
+    ) {
+    }
+
+    /// called after a call returns, with the value it produced
+    fn on_function_exit(
+        &mut self,
+        interpreter: &mut Interpreter,
+        name: &str,
+        result: &Result<RuntimeValue, InterpreterError>,
+    ) {
+    }
+
+    /// called when a `var` statement defines a variable
+    fn on_define(&mut self, interpreter: &mut Interpreter, name: &str, value: &RuntimeValue) {}
+
+    /// called when a variable is assigned a new value
+    fn on_assign(&mut self, interpreter: &mut Interpreter, name: &str, value: &RuntimeValue) {}
+}
@@ -0,0 +1,64 @@
+use std::{
+    io::{self, BufRead},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Where the output of `print` goes. Embedders that don't have a stdout,
+/// like the wasm playground, capture it through this.
+pub trait Output {
+    fn print(&mut self, line: &str);
+}
+
+/// Where `readLine` reads from.
+pub trait Input {
+    /// the next line without its line ending, or `None` at the end of input
+    fn read_line(&mut self) -> io::Result<Option<String>>;
+}
+
+/// Source of wall clock time for the time natives.
+pub trait Clock {
+    /// time elapsed since the unix epoch
+    fn now(&self) -> Duration;
+    /// blocks the script for `duration`
+    fn sleep(&self, duration: Duration);
+}
+
+pub struct StdOutput;
+
+impl Output for StdOutput {
+    fn print(&mut self, line: &str) {
+        println!("{}", line);
+    }
+}
+
+pub struct StdInput;
+
+impl Input for StdInput {
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+        Ok(Some(line))
+    }
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
@@ -0,0 +1,110 @@
+//! Where the REPL reads its lines from. In a terminal that's a line editor,
+//! which highlights what's typed as it's typed and keeps a history to go back
+//! through. Otherwise it's plain lines of stdin.
+
+use std::{
+    borrow::Cow,
+    io::{self, IsTerminal, Write},
+};
+
+use lox::highlight::Highlighter;
+use rustyline::{
+    completion::Completer,
+    error::ReadlineError,
+    highlight::{self, CmdKind},
+    hint::Hinter,
+    history::DefaultHistory,
+    validate::Validator,
+    Editor, Helper,
+};
+
+use crate::interrupt;
+
+pub enum Line {
+    /// with its newline
+    Read(String),
+    /// Ctrl-C was pressed, so what was typed so far is dropped
+    Interrupted,
+    Eof,
+}
+
+pub enum Input {
+    Editor(Box<Editor<LoxHelper, DefaultHistory>>),
+    Plain,
+}
+
+impl Input {
+    pub fn new(compat_jlox: bool) -> anyhow::Result<Self> {
+        if !io::stdin().is_terminal() {
+            return Ok(Input::Plain);
+        }
+        let mut highlighter = Highlighter::new();
+        if compat_jlox {
+            highlighter = highlighter.with_compat_jlox();
+        }
+        let mut editor = Editor::new()?;
+        editor.set_helper(Some(LoxHelper(highlighter)));
+        Ok(Input::Editor(Box::new(editor)))
+    }
+
+    pub fn read_line(&mut self, prompt: &str) -> anyhow::Result<Line> {
+        match self {
+            Input::Editor(editor) => match editor.readline(prompt) {
+                Ok(line) => {
+                    if !line.trim().is_empty() {
+                        editor.add_history_entry(line.as_str())?;
+                    }
+                    Ok(Line::Read(line + "\n"))
+                }
+                // the editor reads Ctrl-C as a key, so the signal handler
+                // never sees it
+                Err(ReadlineError::Interrupted) => {
+                    interrupt::pressed_in_editor();
+                    Ok(Line::Interrupted)
+                }
+                Err(ReadlineError::Eof) => Ok(Line::Eof),
+                Err(e) => Err(e.into()),
+            },
+            Input::Plain => {
+                print!("{}", prompt);
+                io::stdout().flush()?;
+                let mut line = String::new();
+                match interrupt::read_line(&mut io::stdin().lock(), &mut line) {
+                    Ok(0) => Ok(Line::Eof),
+                    Ok(_) => Ok(Line::Read(line)),
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => Ok(Line::Interrupted),
+                    Err(e) => Err(e.into()),
+                }
+            }
+        }
+    }
+}
+
+/// Colors the line being edited with `lox::highlight`.
+pub struct LoxHelper(Highlighter);
+
+impl highlight::Highlighter for LoxHelper {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        self.0.highlight(line)
+    }
+
+    // any key can start or end a string or a comment, which changes the
+    // colors of everything after it
+    fn highlight_char(&self, _line: &str, _pos: usize, _kind: CmdKind) -> bool {
+        true
+    }
+}
+
+impl Completer for LoxHelper {
+    type Candidate = String;
+}
+
+impl Hinter for LoxHelper {
+    type Hint = String;
+}
+
+// lines are put together by the REPL until their braces close, so each one
+// is accepted as it is
+impl Validator for LoxHelper {}
+
+impl Helper for LoxHelper {}
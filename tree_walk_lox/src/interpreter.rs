@@ -1,59 +1,508 @@
 use crate::{
-    ast::{Expr, Stmt},
+    ast::{Expr, FunctionStmt, NodeId, StaticField, Stmt},
     environment::Environment,
+    gc,
+    hooks::InterpreterHooks,
+    host::{Clock, Input, Output, StdInput, StdOutput, SystemClock},
+    parser::Parser,
+    runtime,
+    sandbox::{Limit, SandboxConfig},
+    scanner::Scanner,
+    stdlib::{self, Rng},
+    symbol::{self, Symbol},
+    testing::TestReport,
     token::{Token, TokenKind},
-    value::{BuiltInFunction, ClassDefinition, RuntimeValue, UserFunction},
+    value::{
+        exact, integer_literal, BuiltInFunction, CallableValue, ClassDefinition, ClassInstance,
+        RuntimeValue, UserFunction,
+    },
+    LoxError,
 };
+use rustc_hash::FxHashMap;
 use std::{
-    collections::HashMap,
     error::Error,
     fmt::Display,
-    time::{SystemTime, UNIX_EPOCH},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
 
+// the fewest environments alive before cycles are collected
+const COLLECTION_THRESHOLD: usize = 1024;
+
+/// Stops a running interpreter from another thread. The script fails with
+/// `InterpreterError::Cancelled` before its next statement.
+///
+/// A cancelled interpreter stays cancelled until `reset` is called.
+#[derive(Clone, Default)]
+pub struct CancelHandle(Arc<AtomicBool>);
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
 pub struct Interpreter {
     globals: Environment,
     environment: Environment,
-    locals: HashMap<Expr, usize>,
+    // how many environments up and in which slot each resolved variable is
+    locals: FxHashMap<NodeId, (usize, usize)>,
+    output: Box<dyn Output>,
+    // what `print` wrote since the last line ended
+    partial_line: String,
+    input: Box<dyn Input>,
+    clock: Box<dyn Clock>,
+    hooks: Option<Box<dyn InterpreterHooks>>,
+    cancel: CancelHandle,
+    config: SandboxConfig,
+    steps: u64,
+    // how many environments can be alive before the next collection
+    next_collection: usize,
+    rng: Rng,
+    tests: TestReport,
+    compat_jlox: bool,
+    bignum: bool,
+    strict_math: bool,
+    loose_equality: bool,
+    continue_on_error: bool,
+    // the errors top-level statements failed with, when they don't stop the
+    // script
+    errors: Vec<StatementError>,
+}
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 impl Interpreter {
     pub fn new() -> Self {
+        Self::new_with_config(SandboxConfig::unrestricted())
+    }
+
+    pub fn new_with_config(config: SandboxConfig) -> Self {
         let globals = Environment::new();
-        globals.define(
-            "clock",
-            RuntimeValue::BuiltInFunction(BuiltInFunction::new("clock", vec![], |_, _| {
-                Ok(RuntimeValue::Float(
-                    SystemTime::now()
-                        .duration_since(UNIX_EPOCH)
-                        .map_err(|_| InterpreterError::Internal)?
-                        .as_millis() as f64,
-                ))
-            })),
-        );
-
-        Self {
+        let mut interpreter = Self {
             globals: globals.clone(),
             environment: globals,
-            locals: HashMap::new(),
+            locals: FxHashMap::default(),
+            output: Box::new(StdOutput),
+            partial_line: String::new(),
+            input: Box::new(StdInput),
+            clock: Box::new(SystemClock),
+            hooks: None,
+            cancel: CancelHandle::default(),
+            config,
+            steps: 0,
+            next_collection: COLLECTION_THRESHOLD,
+            rng: Rng::default(),
+            tests: TestReport::default(),
+            compat_jlox: false,
+            bignum: false,
+            strict_math: false,
+            loose_equality: false,
+            continue_on_error: false,
+            errors: vec![],
+        };
+
+        stdlib::define_all(&mut interpreter);
+
+        interpreter
+    }
+
+    pub fn config(&self) -> &SandboxConfig {
+        &self.config
+    }
+
+    // defines the builtin unless the sandbox leaves it out
+    pub(crate) fn define_builtin(&mut self, function: BuiltInFunction) {
+        let name = function.name().to_string();
+        if self.config.allows_builtin(&name) {
+            self.define_global(&name, RuntimeValue::BuiltInFunction(function));
         }
     }
 
-    pub fn interpret(&mut self, statements: &[Stmt]) {
-        for statement in statements {
-            match self.execute(statement) {
-                Ok(_) => {}
-                Err(e) => {
-                    eprintln!("{}", e);
-                    break;
+    fn count_step(&mut self) -> Result<(), InterpreterError> {
+        self.steps += 1;
+        match self.config.max_steps {
+            Some(max) if self.steps > max => {
+                Err(InterpreterError::LimitExceeded(Limit::Steps(max)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn set_output(&mut self, output: Box<dyn Output>) {
+        self.output = output;
+    }
+
+    pub fn set_input(&mut self, input: Box<dyn Input>) {
+        self.input = input;
+    }
+
+    /// The value as `print` shows it: what its `toString()` method returns,
+    /// for an instance whose class has one, or its usual text otherwise.
+    /// Lists and maps show their items the usual way.
+    pub fn stringify(&mut self, value: &RuntimeValue) -> Result<String, InterpreterError> {
+        match self.call_to_string(value)? {
+            Some(text) => Ok(text),
+            None => Ok(value.to_string()),
+        }
+    }
+
+    // what the instance's `toString()` returns, if it has one. jlox has no
+    // such method
+    fn call_to_string(&mut self, value: &RuntimeValue) -> Result<Option<String>, InterpreterError> {
+        let method = match value {
+            RuntimeValue::Instance(instance) if !self.compat_jlox => {
+                match instance.class().find_method("toString") {
+                    Some(method) => RuntimeValue::UserFunction(method.bind(instance)),
+                    None => return Ok(None),
                 }
             }
+            _ => return Ok(None),
+        };
+        let text = runtime::call(&method, vec![], self)?;
+        Ok(Some(text.to_string()))
+    }
+
+    // a string added to an instance with `toString()`, on either side
+    fn concatenate(
+        &mut self,
+        left: &RuntimeValue,
+        right: &RuntimeValue,
+    ) -> Result<Option<String>, InterpreterError> {
+        match (left, right) {
+            (RuntimeValue::Str(left), right @ RuntimeValue::Instance(_)) => Ok(self
+                .call_to_string(right)?
+                .map(|right| format!("{}{}", left, right))),
+            (left @ RuntimeValue::Instance(_), RuntimeValue::Str(right)) => Ok(self
+                .call_to_string(left)?
+                .map(|left| format!("{}{}", left, right))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Prints a whole line, after whatever `print` left on the current one.
+    pub fn print_line(&mut self, text: &str) {
+        let line = std::mem::take(&mut self.partial_line) + text;
+        self.output.print(&line);
+    }
+
+    /// Adds to the current line without ending it, which `Output` only
+    /// gets once something does.
+    pub(crate) fn write(&mut self, text: &str) {
+        self.partial_line.push_str(text);
+    }
+
+    /// Ends the line `print` left unfinished, if it did.
+    pub fn end_line(&mut self) {
+        if !self.partial_line.is_empty() {
+            self.print_line("");
+        }
+    }
+
+    /// Parses scripts as the book's jlox does, where `print` is a statement
+    /// instead of a native.
+    pub fn set_compat_jlox(&mut self, compat_jlox: bool) {
+        self.compat_jlox = compat_jlox;
+    }
+
+    pub fn compat_jlox(&self) -> bool {
+        self.compat_jlox
+    }
+
+    /// Keeps whole numbers exact however large they get, instead of
+    /// rounding them once they no longer fit in a float.
+    pub fn set_bignum(&mut self, bignum: bool) {
+        self.bignum = bignum;
+    }
+
+    pub fn bignum(&self) -> bool {
+        self.bignum
+    }
+
+    /// Makes dividing by zero, and arithmetic that results in NaN or an
+    /// infinity, runtime errors instead of IEEE values.
+    pub fn set_strict_math(&mut self, strict_math: bool) {
+        self.strict_math = strict_math;
+    }
+
+    pub fn strict_math(&self) -> bool {
+        self.strict_math
+    }
+
+    /// Makes `==` and `!=` treat a string that reads as a number as equal to
+    /// that number. `===` and `!==` always compare strictly.
+    pub fn set_loose_equality(&mut self, loose_equality: bool) {
+        self.loose_equality = loose_equality;
+    }
+
+    pub fn loose_equality(&self) -> bool {
+        self.loose_equality
+    }
+
+    /// Makes a top-level statement that fails with a runtime error go on to
+    /// the next one, instead of stopping the script, with the error kept for
+    /// `take_errors`. Exiting, cancelling and sandbox limits still stop it.
+    pub fn set_continue_on_error(&mut self, continue_on_error: bool) {
+        self.continue_on_error = continue_on_error;
+    }
+
+    pub fn continue_on_error(&self) -> bool {
+        self.continue_on_error
+    }
+
+    /// The errors kept by `set_continue_on_error` since the last call, in
+    /// the order they happened.
+    pub fn take_errors(&mut self) -> Vec<StatementError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    // the result of `operator`, unless strict math rejects it
+    fn check_math(
+        &self,
+        operator: &Token,
+        right: &RuntimeValue,
+        result: RuntimeValue,
+    ) -> Result<RuntimeValue, InterpreterError> {
+        if !self.strict_math {
+            return Ok(result);
+        }
+        match (&result, right) {
+            (_, RuntimeValue::Float(x)) if operator.kind == TokenKind::Slash && *x == 0.0 => {
+                Err(InterpreterError::DivisionByZero(operator.clone()))
+            }
+            (RuntimeValue::Float(x), _) if !x.is_finite() => {
+                Err(InterpreterError::NotFinite(operator.clone(), *x))
+            }
+            _ => Ok(result),
+        }
+    }
+
+    /// A scanner for `source`, in the dialect the interpreter runs.
+    pub fn scanner(&self, source: String) -> Scanner {
+        let scanner = Scanner::new(source);
+        if self.compat_jlox {
+            scanner.with_compat_jlox()
+        } else {
+            scanner
+        }
+    }
+
+    pub(crate) fn input(&mut self) -> &mut dyn Input {
+        self.input.as_mut()
+    }
+
+    pub(crate) fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    pub(crate) fn rng(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
+
+    /// What the `test` and `expect` natives recorded so far.
+    pub fn test_report(&self) -> &TestReport {
+        &self.tests
+    }
+
+    pub(crate) fn tests(&mut self) -> &mut TestReport {
+        &mut self.tests
+    }
+
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Installs hooks that observe execution, returning the previous ones.
+    pub fn set_hooks(
+        &mut self,
+        hooks: Option<Box<dyn InterpreterHooks>>,
+    ) -> Option<Box<dyn InterpreterHooks>> {
+        std::mem::replace(&mut self.hooks, hooks)
+    }
+
+    /// Frees the environments that are only kept alive by cycles, like a
+    /// function stored in the environment it closes over, and returns how
+    /// many there were. It also happens on its own as environments pile up.
+    pub fn collect_garbage(&mut self) -> usize {
+        let freed = gc::collect(&self.globals);
+        self.next_collection = (2 * self.globals.live_count()).max(COLLECTION_THRESHOLD);
+        freed
+    }
+
+    /// How many environments are alive, including the global one.
+    pub fn live_environments(&self) -> usize {
+        self.globals.live_count()
+    }
+
+    /// How many environments have to be alive for the next collection to
+    /// happen on its own.
+    pub fn next_collection(&self) -> usize {
+        self.next_collection
+    }
+
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    // the hooks are taken out while they run, so anything they do with the
+    // interpreter doesn't trigger them again.
+    fn with_hooks(&mut self, f: impl FnOnce(&mut dyn InterpreterHooks, &mut Interpreter)) {
+        if let Some(mut hooks) = self.hooks.take() {
+            f(hooks.as_mut(), self);
+            self.hooks = Some(hooks);
+        }
+    }
+
+    pub fn interpret(&mut self, statements: &[Stmt]) -> Result<(), InterpreterError> {
+        self.steps = 0;
+        let result = statements
+            .iter()
+            .try_for_each(|it| self.execute_top_level(it));
+        self.end_line();
+        result
+    }
+
+    fn execute_top_level(&mut self, stmt: &Stmt) -> Result<(), InterpreterError> {
+        match self.execute(stmt) {
+            Err(error) if self.continue_on_error && !error.stops_script() => {
+                self.end_line();
+                self.errors.push(StatementError {
+                    line: stmt.line(),
+                    error,
+                });
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Runs one top-level statement, for an `ExecutionSession`. The first
+    /// one starts counting steps again, like `interpret` does.
+    pub(crate) fn interpret_one(
+        &mut self,
+        stmt: &Stmt,
+        first: bool,
+    ) -> Result<(), InterpreterError> {
+        if first {
+            self.steps = 0;
+        }
+        let result = self.execute(stmt);
+        if result.is_err() {
+            self.end_line();
+        }
+        result
+    }
+
+    pub fn define_global(&mut self, name: &str, value: RuntimeValue) {
+        self.globals.define(Symbol::intern(name), value);
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<RuntimeValue> {
+        self.globals.get(Symbol::intern(name))
+    }
+
+    pub fn globals(&self) -> &Environment {
+        &self.globals
+    }
+
+    /// The environment of the code that is running, the innermost scope.
+    pub fn environment(&self) -> &Environment {
+        &self.environment
+    }
+
+    /// Evaluates the expression in `source` as if it was written where
+    /// `environment` is the innermost scope, for debuggers inspecting a
+    /// paused script. Variables are looked up by name through the chain of
+    /// environments, since the expression was never resolved.
+    pub fn evaluate_in(
+        &mut self,
+        source: &str,
+        environment: &Environment,
+    ) -> Result<RuntimeValue, LoxError> {
+        let expr = Parser::new(self.scanner(source.to_string())).parse_expression()?;
+
+        // the expression is thrown away afterwards, and so is what it
+        // resolved to
+        let mut resolved = vec![];
+        self.resolve_in(&expr, environment, &mut resolved);
+        let previous = std::mem::replace(&mut self.environment, environment.clone());
+        let result = self.evaluate(&expr);
+        self.environment = previous;
+        for id in resolved {
+            self.locals.remove(&id);
+        }
+        Ok(result?)
+    }
+
+    fn resolve_in(&mut self, expr: &Expr, environment: &Environment, resolved: &mut Vec<NodeId>) {
+        let name = match expr {
+            Expr::Variable { id, name } | Expr::This { id, keyword: name } => Some((*id, name)),
+            Expr::Assign { id, name, value } => {
+                self.resolve_in(value, environment, resolved);
+                Some((*id, name))
+            }
+            Expr::Super { id, keyword, .. } => Some((*id, keyword)),
+            Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+                self.resolve_in(left, environment, resolved);
+                self.resolve_in(right, environment, resolved);
+                None
+            }
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                self.resolve_in(callee, environment, resolved);
+                for argument in arguments {
+                    self.resolve_in(argument, environment, resolved);
+                }
+                None
+            }
+            Expr::Get { object, .. } => {
+                self.resolve_in(object, environment, resolved);
+                None
+            }
+            Expr::Set { object, value, .. } => {
+                self.resolve_in(object, environment, resolved);
+                self.resolve_in(value, environment, resolved);
+                None
+            }
+            Expr::Grouping { expression }
+            | Expr::Unary {
+                right: expression, ..
+            } => {
+                self.resolve_in(expression, environment, resolved);
+                None
+            }
+            // the methods of a class would need resolving like a whole
+            // script, which evaluating an expression doesn't do
+            Expr::Literal { .. } | Expr::Class { .. } => None,
+        };
+        if let Some((id, name)) = name {
+            if let Some(location) = environment.locate(name.lexeme) {
+                self.locals.insert(id, location);
+                resolved.push(id);
+            }
         }
     }
 
     fn evaluate(&mut self, expr: &Expr) -> Result<RuntimeValue, InterpreterError> {
+        self.count_step()?;
         match expr {
-            Expr::Literal { value } => Ok(value.literal.clone()),
-            Expr::Variable { name } => self.look_up_variable(name, expr),
+            Expr::Literal { value } => match integer_literal(value) {
+                Some(big) if self.bignum => Ok(big),
+                _ => Ok(value.literal.clone()),
+            },
+            Expr::Variable { id, name } => self.look_up_variable(name, *id),
             Expr::Call {
                 callee,
                 paren,
@@ -66,30 +515,39 @@ impl Interpreter {
                     .collect::<Result<Vec<RuntimeValue>, InterpreterError>>()?;
 
                 if let Some(callable) = callee.as_callable() {
-                    if arguments.len() != callable.arity() {
-                        Err(InterpreterError::FunctionArity(
+                    if !callable.accepts(arguments.len()) {
+                        Err(InterpreterError::arity(
                             paren.clone(),
-                            callable.arity(),
+                            callable,
                             arguments.len(),
                         ))
                     } else {
-                        callable.call(self, arguments)
+                        let name = callable.name();
+                        self.with_hooks(|hooks, interpreter| {
+                            hooks.on_function_enter(interpreter, name, &arguments)
+                        });
+                        let result = callable.call(self, arguments);
+                        self.with_hooks(|hooks, interpreter| {
+                            hooks.on_function_exit(interpreter, name, &result)
+                        });
+                        result
                     }
                 } else {
                     Err(InterpreterError::NotCallable(callee))
                 }
             }
-            Expr::This { keyword } => self.look_up_variable(keyword, expr),
-            Expr::Super { method, .. } => {
-                let distance = *self.locals.get(expr).unwrap();
+            Expr::This { id, keyword } => self.look_up_variable(keyword, *id),
+            Expr::Super { id, method, .. } => {
+                // `super` and `this` are alone in their environments
+                let (distance, _) = *self.locals.get(id).unwrap();
 
-                let superclass = self.environment.get_at(distance, "super");
+                let superclass = self.environment.get_at(distance, 0);
                 let superclass = match superclass {
                     Some(RuntimeValue::Class(sc)) => sc,
                     _ => unreachable!("'super' can only be class."),
                 };
 
-                let object = self.environment.get_at(distance - 1, "this");
+                let object = self.environment.get_at(distance - 1, 0);
                 let object = match object {
                     Some(RuntimeValue::Instance(i)) => i,
                     _ => unreachable!("'this' can only be instance."),
@@ -101,50 +559,82 @@ impl Interpreter {
 
                 Ok(RuntimeValue::UserFunction(method.bind(&object)))
             }
-            Expr::Get { object, name } => {
-                let object = self.evaluate(object)?;
-                if let RuntimeValue::Instance(instance) = object {
-                    instance
-                        .get(name)
-                        .ok_or_else(|| InterpreterError::UndefinedProperty(name.clone()))
-                } else {
-                    Err(InterpreterError::MustAccessValueOnInstances)
+            Expr::Get {
+                object: object_expr,
+                name,
+            } => {
+                let object = self.evaluate(object_expr)?;
+                match object {
+                    RuntimeValue::Instance(instance) => {
+                        self.check_private(object_expr, name, &instance)?;
+                        instance
+                            .get(&name.lexeme)
+                            .ok_or_else(|| InterpreterError::UndefinedProperty(name.clone()))
+                    }
+                    RuntimeValue::Class(class) => class
+                        .get_static(&name.lexeme)
+                        .ok_or_else(|| InterpreterError::UndefinedProperty(name.clone())),
+                    RuntimeValue::List(list) => stdlib::list_method(&list, &name.lexeme)
+                        .ok_or_else(|| InterpreterError::UndefinedProperty(name.clone())),
+                    RuntimeValue::Map(map) => stdlib::map_method(&map, &name.lexeme)
+                        .ok_or_else(|| InterpreterError::UndefinedProperty(name.clone())),
+                    RuntimeValue::Str(s) => stdlib::string_method(&s, &name.lexeme)
+                        .ok_or_else(|| InterpreterError::UndefinedProperty(name.clone())),
+                    number @ (RuntimeValue::Float(_) | RuntimeValue::BigInt(_)) => {
+                        stdlib::number_method(&number, &name.lexeme)
+                            .ok_or_else(|| InterpreterError::UndefinedProperty(name.clone()))
+                    }
+                    _ => Err(InterpreterError::MustAccessValueOnInstances),
                 }
             }
             Expr::Set {
                 name,
-                object,
+                object: object_expr,
                 value,
             } => {
-                let object = self.evaluate(object)?;
-                if let RuntimeValue::Instance(instance) = object {
-                    let value = self.evaluate(value)?;
-                    instance.set(name, value.clone());
-                    Ok(value)
-                } else {
-                    Err(InterpreterError::MustAccessValueOnInstances)
+                let object = self.evaluate(object_expr)?;
+                match object {
+                    RuntimeValue::Instance(instance) => {
+                        self.check_private(object_expr, name, &instance)?;
+                        if instance.is_frozen() {
+                            return Err(InterpreterError::FrozenInstance(
+                                name.clone(),
+                                instance.class().name().to_string(),
+                            ));
+                        }
+                        let value = self.evaluate(value)?;
+                        instance.set(&name.lexeme, value.clone());
+                        Ok(value)
+                    }
+                    RuntimeValue::Class(class) => {
+                        let value = self.evaluate(value)?;
+                        class.set_static(&name.lexeme, value.clone());
+                        Ok(value)
+                    }
+                    _ => Err(InterpreterError::MustAccessValueOnInstances),
                 }
             }
             Expr::Grouping { expression } => self.evaluate(expression),
             Expr::Unary { operator, right } => {
                 let right = self.evaluate(right)?;
                 match operator.kind {
-                    TokenKind::Minus => match right {
-                        RuntimeValue::Float(f) => Ok(RuntimeValue::Float(-f)),
-                        v => Err(InterpreterError::UnaryMinusOperandMustBeNumber(v)),
-                    },
-                    TokenKind::Bang => Ok(RuntimeValue::Bool(!right.is_truthy())),
+                    TokenKind::Minus => {
+                        let result = runtime::negate(&right)?;
+                        self.check_math(operator, &right, result)
+                    }
+                    TokenKind::Bang => Ok(runtime::not(&right)),
                     _ => Err(InterpreterError::Internal),
                 }
             }
-            Expr::Assign { name, value } => {
+            Expr::Assign { id, name, value } => {
                 let value = self.evaluate(value)?;
-                let distance = self.locals.get(expr);
-                if let Some(distance) = distance {
-                    self.environment
-                        .assign_at(*distance, &name.lexeme, value.clone());
+                self.with_hooks(|hooks, interpreter| {
+                    hooks.on_assign(interpreter, &name.lexeme, &value)
+                });
+                if let Some(&(distance, slot)) = self.locals.get(id) {
+                    self.environment.assign_at(distance, slot, value.clone());
                 } else {
-                    self.globals.assign(&name.lexeme, value.clone());
+                    self.globals.assign(name.lexeme, value.clone());
                 }
                 Ok(value)
             }
@@ -156,72 +646,61 @@ impl Interpreter {
                 let left = self.evaluate(left)?;
                 let right = self.evaluate(right)?;
 
+                if self.bignum {
+                    if let Some(result) = exact(operator.kind, &left, &right) {
+                        return Ok(result);
+                    }
+                }
+                if matches!(operator.kind, TokenKind::EqualEqual | TokenKind::BangEqual) {
+                    if let Some(equal) = self.instance_equals(&left, &right)? {
+                        let not = operator.kind == TokenKind::BangEqual;
+                        return Ok(RuntimeValue::Bool(equal != not));
+                    }
+                }
                 match operator.kind {
                     TokenKind::Minus => {
-                        if let (RuntimeValue::Float(l), RuntimeValue::Float(r)) = (&left, &right) {
-                            Ok(RuntimeValue::Float(l - r))
-                        } else {
-                            Err(InterpreterError::OperandsMustBeNumbers)
-                        }
+                        self.check_math(operator, &right, runtime::subtract(&left, &right)?)
                     }
                     TokenKind::Slash => {
-                        if let (RuntimeValue::Float(l), RuntimeValue::Float(r)) = (&left, &right) {
-                            Ok(RuntimeValue::Float(l / r))
-                        } else {
-                            Err(InterpreterError::OperandsMustBeNumbers)
-                        }
+                        self.check_math(operator, &right, runtime::divide(&left, &right)?)
                     }
                     TokenKind::Star => {
-                        if let (RuntimeValue::Float(l), RuntimeValue::Float(r)) = (&left, &right) {
-                            Ok(RuntimeValue::Float(l * r))
-                        } else {
-                            Err(InterpreterError::OperandsMustBeNumbers)
-                        }
+                        self.check_math(operator, &right, runtime::multiply(&left, &right)?)
                     }
                     TokenKind::Plus => {
-                        if let (RuntimeValue::Float(l), RuntimeValue::Float(r)) = (&left, &right) {
-                            Ok(RuntimeValue::Float(l + r))
-                        } else if let (RuntimeValue::Str(l), RuntimeValue::Str(r)) = (&left, &right)
-                        {
-                            let s = l.to_string() + r;
-                            Ok(RuntimeValue::Str(s.as_str().into()))
-                        } else {
-                            Err(InterpreterError::OperandsMustBeNumbersOrStr)
+                        if let Some(text) = self.concatenate(&left, &right)? {
+                            return Ok(RuntimeValue::Str(text.into()));
                         }
+                        self.check_math(operator, &right, runtime::add(&left, &right)?)
                     }
-                    TokenKind::Greater => {
-                        if let (RuntimeValue::Float(l), RuntimeValue::Float(r)) = (&left, &right) {
-                            Ok(RuntimeValue::Bool(l > r))
-                        } else {
-                            Err(InterpreterError::OperandsMustBeNumbers)
-                        }
+                    TokenKind::Greater => runtime::greater(&left, &right),
+                    TokenKind::GreaterEqual => runtime::greater_equal(&left, &right),
+                    TokenKind::Less => runtime::less(&left, &right),
+                    TokenKind::LessEqual => runtime::less_equal(&left, &right),
+                    TokenKind::BangEqual if self.loose_equality => {
+                        Ok(RuntimeValue::Bool(!runtime::loosely_equal(&left, &right)))
                     }
-                    TokenKind::GreaterEqual => {
-                        if let (RuntimeValue::Float(l), RuntimeValue::Float(r)) = (&left, &right) {
-                            Ok(RuntimeValue::Bool(l >= r))
-                        } else {
-                            Err(InterpreterError::OperandsMustBeNumbers)
-                        }
+                    TokenKind::EqualEqual if self.loose_equality => {
+                        Ok(RuntimeValue::Bool(runtime::loosely_equal(&left, &right)))
                     }
-                    TokenKind::Less => {
-                        if let (RuntimeValue::Float(l), RuntimeValue::Float(r)) = (&left, &right) {
-                            Ok(RuntimeValue::Bool(l < r))
-                        } else {
-                            Err(InterpreterError::OperandsMustBeNumbers)
-                        }
+                    TokenKind::BangEqual | TokenKind::BangEqualEqual => {
+                        Ok(runtime::not_equal(&left, &right))
                     }
-                    TokenKind::LessEqual => {
-                        if let (RuntimeValue::Float(l), RuntimeValue::Float(r)) = (&left, &right) {
-                            Ok(RuntimeValue::Bool(l <= r))
-                        } else {
-                            Err(InterpreterError::OperandsMustBeNumbers)
-                        }
+                    TokenKind::EqualEqual | TokenKind::EqualEqualEqual => {
+                        Ok(runtime::equal(&left, &right))
                     }
-                    TokenKind::BangEqual => Ok(RuntimeValue::Bool(!left.equals(&right))),
-                    TokenKind::EqualEqual => Ok(RuntimeValue::Bool(left.equals(&right))),
                     _ => Err(InterpreterError::Internal),
                 }
             }
+            Expr::Class {
+                superclass,
+                methods,
+                statics,
+                ..
+            } => {
+                let class = self.class(None, superclass.as_deref(), methods, statics)?;
+                Ok(RuntimeValue::Class(class))
+            }
             Expr::Logical {
                 left,
                 operator,
@@ -242,13 +721,23 @@ impl Interpreter {
     }
 
     fn execute(&mut self, stmt: &Stmt) -> Result<(), InterpreterError> {
+        // every loop iteration and function body goes through here, so
+        // checking once per statement is enough to stop any script.
+        if self.cancel.is_cancelled() {
+            return Err(InterpreterError::Cancelled);
+        }
+        self.count_step()?;
+        self.with_hooks(|hooks, interpreter| hooks.on_statement(interpreter, stmt));
         match stmt {
             Stmt::Expression { expression } => {
                 self.evaluate(expression)?;
             }
+            // the debugger's hooks already saw it
+            Stmt::Breakpoint { .. } => {}
             Stmt::Print { expression } => {
                 let value = self.evaluate(expression)?;
-                println!("{}", value.to_string());
+                let text = self.stringify(&value)?;
+                self.print_line(&text);
             }
             Stmt::Return { value, .. } => {
                 let value = if let Some(v) = value {
@@ -264,7 +753,10 @@ impl Interpreter {
                 } else {
                     RuntimeValue::Nil
                 };
-                self.environment.define(&name.lexeme, value);
+                self.with_hooks(|hooks, interpreter| {
+                    hooks.on_define(interpreter, &name.lexeme, &value)
+                });
+                self.environment.define(name.lexeme, value);
             }
             Stmt::Block { statements } => {
                 self.execute_block(statements, &self.environment.child())?;
@@ -280,58 +772,143 @@ impl Interpreter {
                     self.execute(else_branch)?;
                 }
             }
-            Stmt::While { condition, body } => {
+            Stmt::While {
+                label,
+                condition,
+                body,
+                increment,
+            } => {
+                // a `break` or `continue` is for this loop if it has this
+                // loop's label, or no label at all
+                let targets = |target: &Option<Symbol>| {
+                    target.is_none_or(|target| label.as_ref().is_some_and(|it| it.lexeme == target))
+                };
                 while self.evaluate(condition)?.is_truthy() {
-                    self.execute(body)?;
+                    match self.execute(body) {
+                        Err(InterpreterError::Break(target)) if targets(&target) => break,
+                        Err(InterpreterError::Continue(target)) if targets(&target) => {}
+                        result => result?,
+                    }
+                    if let Some(increment) = increment {
+                        self.evaluate(increment)?;
+                    }
                 }
             }
+            Stmt::Break { label, .. } => {
+                return Err(InterpreterError::Break(label.as_ref().map(|it| it.lexeme)));
+            }
+            Stmt::Continue { label, .. } => {
+                return Err(InterpreterError::Continue(
+                    label.as_ref().map(|it| it.lexeme),
+                ));
+            }
             Stmt::Function(fun) => {
                 let function = UserFunction::new(fun, &self.environment, false);
                 self.environment
-                    .define(&fun.name.lexeme, RuntimeValue::UserFunction(function));
+                    .define(fun.name.lexeme, RuntimeValue::UserFunction(function));
             }
             Stmt::Class {
                 name,
                 superclass,
                 methods,
+                statics,
             } => {
-                let superclass = if let Some(sc) = superclass {
-                    let result = self.evaluate(&Expr::Variable { name: sc.clone() })?;
-                    match result {
-                        RuntimeValue::Class(cd) => Some(cd),
-                        _ => return Err(InterpreterError::SuperClassMustBeClass(sc.clone())),
-                    }
-                } else {
-                    None
-                };
-
-                self.environment.define(&name.lexeme, RuntimeValue::Nil);
+                self.environment.define(name.lexeme, RuntimeValue::Nil);
+                let class = self.class(Some(name), superclass.as_ref(), methods, statics)?;
+                self.environment
+                    .assign(name.lexeme, RuntimeValue::Class(class));
+            }
+        };
+        Ok(())
+    }
 
-                if let Some(sc) = &superclass {
-                    self.environment = self.environment.child();
-                    self.environment
-                        .define("super", RuntimeValue::Class(sc.clone()));
-                }
+    // `==` and `!=` on two instances ask the left one's `equals(other)`
+    // method, if its class has one, instead of comparing identity. `===`
+    // and `!==` never do, and jlox has no such method
+    fn instance_equals(
+        &mut self,
+        left: &RuntimeValue,
+        right: &RuntimeValue,
+    ) -> Result<Option<bool>, InterpreterError> {
+        let (left, right) = match (left, right) {
+            (RuntimeValue::Instance(left), RuntimeValue::Instance(_)) if !self.compat_jlox => {
+                (left, right)
+            }
+            _ => return Ok(None),
+        };
+        let method = match left.class().find_method("equals") {
+            Some(method) => RuntimeValue::UserFunction(method.bind(left)),
+            None => return Ok(None),
+        };
+        let result = runtime::call(&method, vec![right.clone()], self)?;
+        Ok(Some(result.is_truthy()))
+    }
 
-                let mut class_methods = HashMap::new();
-                for method in methods {
-                    let is_initializer = method.name.lexeme == "this";
-                    let function = UserFunction::new(method, &self.environment, is_initializer);
-                    class_methods.insert(method.name.lexeme.clone(), function);
-                }
+    // fields and methods whose names start with `_` are private: only the
+    // methods of the instance's classes can use them, through `this`. jlox
+    // has no such convention
+    fn check_private(
+        &self,
+        object: &Expr,
+        name: &Token,
+        instance: &ClassInstance,
+    ) -> Result<(), InterpreterError> {
+        if self.compat_jlox || !name.lexeme.starts_with('_') || matches!(object, Expr::This { .. })
+        {
+            Ok(())
+        } else {
+            Err(InterpreterError::PrivateProperty(
+                name.clone(),
+                instance.class().name().to_string(),
+            ))
+        }
+    }
 
-                let class = RuntimeValue::Class(ClassDefinition::new(
-                    name,
-                    superclass.clone(),
-                    class_methods,
-                ));
-                if superclass.is_some() {
-                    self.environment = self.environment.enclosing().unwrap();
+    // a class declared by a statement, or an anonymous one from an
+    // expression
+    fn class(
+        &mut self,
+        name: Option<&Token>,
+        superclass: Option<&Expr>,
+        methods: &[FunctionStmt],
+        statics: &[StaticField],
+    ) -> Result<ClassDefinition, InterpreterError> {
+        let superclass = if let Some(sc) = superclass {
+            match (self.evaluate(sc)?, sc) {
+                (RuntimeValue::Class(cd), _) => Some(cd),
+                (_, Expr::Variable { name, .. }) => {
+                    return Err(InterpreterError::SuperClassMustBeClass(name.clone()))
                 }
-                self.environment.assign(&name.lexeme, class);
+                _ => unreachable!("The parser only makes variables superclasses."),
             }
+        } else {
+            None
         };
-        Ok(())
+
+        let mut static_values = FxHashMap::default();
+        for field in statics {
+            let value = self.evaluate(&field.value)?;
+            static_values.insert(field.name.lexeme.to_string(), value);
+        }
+
+        if let Some(sc) = &superclass {
+            self.environment = self.environment.child();
+            self.environment
+                .define(*symbol::SUPER, RuntimeValue::Class(sc.clone()));
+        }
+
+        let mut class_methods = FxHashMap::default();
+        for method in methods {
+            let is_initializer = method.name.lexeme == "init";
+            let function = UserFunction::new(method, &self.environment, is_initializer);
+            class_methods.insert(method.name.lexeme.to_string(), function);
+        }
+
+        let class = ClassDefinition::new(name, superclass.clone(), class_methods, static_values);
+        if superclass.is_some() {
+            self.environment = self.environment.enclosing().unwrap();
+        }
+        Ok(class)
     }
 
     pub fn execute_block(
@@ -339,6 +916,18 @@ impl Interpreter {
         statements: &[Stmt],
         environment: &Environment,
     ) -> Result<(), InterpreterError> {
+        if environment.live_count() >= self.next_collection {
+            self.collect_garbage();
+        }
+        if let Some(max) = self.config.max_environments {
+            // it might only be over the limit because of cycles
+            if environment.live_count() > max {
+                self.collect_garbage();
+            }
+            if environment.live_count() > max {
+                return Err(InterpreterError::LimitExceeded(Limit::Environments(max)));
+            }
+        }
         let previous = self.environment.clone();
         self.environment = environment.clone();
         for statement in statements {
@@ -353,20 +942,19 @@ impl Interpreter {
         Ok(())
     }
 
-    pub fn resolve(&mut self, expr: &Expr, depth: usize) {
-        self.locals.insert(expr.clone(), depth);
+    pub fn resolve(&mut self, id: NodeId, depth: usize, slot: usize) {
+        self.locals.insert(id, (depth, slot));
     }
 
     fn look_up_variable(
         &mut self,
         name: &Token,
-        expr: &Expr,
+        id: NodeId,
     ) -> Result<RuntimeValue, InterpreterError> {
-        let distance = self.locals.get(expr);
-        let look_up = if let Some(distance) = distance {
-            self.environment.get_at(*distance, &name.lexeme)
+        let look_up = if let Some(&(distance, slot)) = self.locals.get(&id) {
+            self.environment.get_at(distance, slot)
         } else {
-            self.globals.get(&name.lexeme)
+            self.globals.get(name.lexeme)
         };
         look_up.ok_or_else(|| InterpreterError::UndefinedVariable(name.clone()))
     }
@@ -380,11 +968,23 @@ pub enum InterpreterError {
     OperandsMustBeNumbersOrStr,
     UndefinedVariable(Token),
     UndefinedProperty(Token),
+    PrivateProperty(Token, String),
+    FrozenInstance(Token, String),
     NotCallable(RuntimeValue),
     FunctionArity(Token, usize, usize),
+    FunctionMinArity(Token, usize, usize),
     MustAccessValueOnInstances,
     SuperClassMustBeClass(Token),
+    DivisionByZero(Token),
+    NotFinite(Token, f64),
+    NativeFunction(String, String),
+    Cancelled,
+    Exit(i32),
+    LimitExceeded(Limit),
     Return(RuntimeValue),
+    // the label of the loop to leave or go on with, if it has one
+    Break(Option<Symbol>),
+    Continue(Option<Symbol>),
 }
 impl Display for InterpreterError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -402,6 +1002,16 @@ impl Display for InterpreterError {
             InterpreterError::UndefinedProperty(tok) => {
                 write!(f, "Undefined property '{}'.", tok.lexeme)
             }
+            InterpreterError::PrivateProperty(tok, class) => write!(
+                f,
+                "'{}' is private to class {}, use it through 'this' in its methods.",
+                tok.lexeme, class
+            ),
+            InterpreterError::FrozenInstance(tok, class) => write!(
+                f,
+                "Can't set '{}' on a frozen instance of {}.",
+                tok.lexeme, class
+            ),
             InterpreterError::UndefinedVariable(tok) => {
                 write!(f, "Undefined variable '{}'.", tok.lexeme)
             }
@@ -411,14 +1021,93 @@ impl Display for InterpreterError {
             InterpreterError::FunctionArity(_at, expected, got) => {
                 write!(f, "Expected {} arguments but got {}.", expected, got)
             }
+            InterpreterError::FunctionMinArity(_at, expected, got) => {
+                write!(
+                    f,
+                    "Expected at least {} arguments but got {}.",
+                    expected, got
+                )
+            }
             InterpreterError::MustAccessValueOnInstances => {
                 write!(f, "Only instances have properties.")
             }
             InterpreterError::SuperClassMustBeClass(tok) => {
                 write!(f, "Superclass '{}' must be class.", tok.lexeme)
             }
+            InterpreterError::DivisionByZero(_) => write!(f, "Division by zero."),
+            InterpreterError::NotFinite(operator, x) => {
+                write!(f, "'{}' resulted in {}.", operator.lexeme, x)
+            }
+            InterpreterError::NativeFunction(name, message) => {
+                write!(f, "{}: {}", name, message)
+            }
+            InterpreterError::LimitExceeded(limit) => {
+                write!(f, "Exceeded the sandbox limit of {}.", limit)
+            }
+            InterpreterError::Exit(code) => write!(f, "Exited with code {}.", code),
+            InterpreterError::Cancelled => write!(f, "Execution was cancelled."),
             InterpreterError::Return(_) => write!(f, "INTERNAL ERROR: Return was not caught."),
+            InterpreterError::Break(_) => write!(f, "INTERNAL ERROR: Break was not caught."),
+            InterpreterError::Continue(_) => {
+                write!(f, "INTERNAL ERROR: Continue was not caught.")
+            }
         }
     }
 }
 impl Error for InterpreterError {}
+
+/// A runtime error a top-level statement failed with, kept by
+/// `Interpreter::set_continue_on_error`.
+#[derive(Debug)]
+pub struct StatementError {
+    /// where the statement starts, `None` for an empty block
+    pub line: Option<usize>,
+    pub error: InterpreterError,
+}
+impl Display for StatementError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "[Line {}] {}", line, self.error),
+            None => write!(f, "{}", self.error),
+        }
+    }
+}
+impl Error for StatementError {}
+impl InterpreterError {
+    /// Whether the error stops the whole script wherever it happens, even
+    /// where other errors are caught, like in a `test`.
+    pub fn stops_script(&self) -> bool {
+        matches!(
+            self,
+            InterpreterError::Exit(_)
+                | InterpreterError::Cancelled
+                | InterpreterError::LimitExceeded(_)
+        )
+    }
+
+    /// The token the error happened at, for the errors that know it.
+    pub fn token(&self) -> Option<&Token> {
+        match self {
+            InterpreterError::UndefinedVariable(token)
+            | InterpreterError::UndefinedProperty(token)
+            | InterpreterError::PrivateProperty(token, _)
+            | InterpreterError::FrozenInstance(token, _)
+            | InterpreterError::FunctionArity(token, ..)
+            | InterpreterError::FunctionMinArity(token, ..)
+            | InterpreterError::SuperClassMustBeClass(token)
+            | InterpreterError::DivisionByZero(token)
+            | InterpreterError::NotFinite(token, _) => Some(token),
+            _ => None,
+        }
+    }
+
+    /// The error for calling `callable` with `got` arguments it doesn't
+    /// accept.
+    pub(crate) fn arity(at: Token, callable: &dyn CallableValue, got: usize) -> Self {
+        if callable.is_variadic() {
+            InterpreterError::FunctionMinArity(at, callable.arity(), got)
+        } else {
+            InterpreterError::FunctionArity(at, callable.arity(), got)
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Ctrl-C in the REPL. While something runs it cancels the interpreter, so
+//! the script stops with an error and the prompt comes back. At the prompt
+//! it drops what was typed so far, and pressed again it exits.
+
+use std::{
+    io::{self, BufRead},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+};
+
+use lox::interpreter::CancelHandle;
+
+// the handle of the REPL's interpreter
+static CANCEL: OnceLock<CancelHandle> = OnceLock::new();
+// whether the REPL is running something, rather than waiting at the prompt
+static RUNNING: AtomicBool = AtomicBool::new(false);
+// whether Ctrl-C was pressed at the prompt since the last line was read
+static PRESSED_AT_PROMPT: AtomicBool = AtomicBool::new(false);
+
+/// Makes Ctrl-C cancel the interpreter of `handle` instead of ending the
+/// process. Only the first call does anything.
+pub fn install(handle: CancelHandle) {
+    if CANCEL.set(handle).is_err() {
+        return;
+    }
+    // without SA_RESTART, which `signal` sets, so a read waiting at the
+    // prompt fails with EINTR instead of going on waiting
+    #[cfg(unix)]
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        action.sa_flags = 0;
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(libc::SIGINT, &action, std::ptr::null_mut());
+    }
+}
+
+/// Like `BufRead::read_line`, except that Ctrl-C makes it fail with
+/// `ErrorKind::Interrupted`, where `read_line` would go back to waiting.
+pub fn read_line(reader: &mut impl BufRead, line: &mut String) -> io::Result<usize> {
+    let mut bytes = vec![];
+    loop {
+        let available = reader.fill_buf()?;
+        if available.is_empty() {
+            break;
+        }
+        let (taken, done) = match available.iter().position(|&b| b == b'\n') {
+            Some(end) => (end + 1, true),
+            None => (available.len(), false),
+        };
+        bytes.extend_from_slice(&available[..taken]);
+        reader.consume(taken);
+        if done {
+            break;
+        }
+    }
+    let read = String::from_utf8(bytes)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Input isn't valid UTF-8."))?;
+    line.push_str(&read);
+    Ok(read.len())
+}
+
+/// Marks the REPL as running what was typed, or as back at the prompt.
+pub fn set_running(running: bool) {
+    RUNNING.store(running, Ordering::SeqCst);
+}
+
+/// Ctrl-C at the prompt of the line editor, which reads it as a key instead
+/// of a signal. Like the handler, it exits if it was the second time.
+pub fn pressed_in_editor() {
+    if PRESSED_AT_PROMPT.swap(true, Ordering::SeqCst) {
+        std::process::exit(130);
+    }
+    println!("(Press Ctrl-C again to exit)");
+}
+
+/// Whether Ctrl-C was pressed at the prompt since this was last called, in
+/// which case what was typed before it is dropped.
+pub fn take_pressed_at_prompt() -> bool {
+    PRESSED_AT_PROMPT.swap(false, Ordering::SeqCst)
+}
+
+// only does what's safe in a signal handler: atomics and raw writes
+#[cfg(unix)]
+extern "C" fn on_interrupt(_signal: libc::c_int) {
+    if RUNNING.load(Ordering::SeqCst) {
+        if let Some(handle) = CANCEL.get() {
+            handle.cancel();
+        }
+    } else if PRESSED_AT_PROMPT.swap(true, Ordering::SeqCst) {
+        unsafe { libc::_exit(130) };
+    } else {
+        // the REPL prints the prompt again once the read is interrupted
+        let message = b"\n(Press Ctrl-C again to exit)\n";
+        unsafe { libc::write(libc::STDOUT_FILENO, message.as_ptr().cast(), message.len()) };
+    }
+}
@@ -0,0 +1,170 @@
+use std::{
+    error::Error,
+    ffi::OsStr,
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use ast::Stmt;
+use interpreter::{Interpreter, InterpreterError};
+use parser::{Parser, ParserError};
+use resolver::{Resolver, ResolverError, Warning};
+use scanner::ScanError;
+
+pub mod analysis;
+pub mod ast;
+pub mod coverage;
+pub mod debugger;
+pub mod diagnostic;
+pub mod difftest;
+pub mod dot;
+pub mod environment;
+pub mod format;
+mod gc;
+pub mod highlight;
+pub mod hooks;
+pub mod host;
+pub mod interpreter;
+pub mod memory;
+pub mod parser;
+pub mod profile;
+pub mod resolver;
+pub mod runtime;
+pub mod sandbox;
+pub mod scanner;
+pub mod session;
+pub mod shared;
+mod stdlib;
+pub mod symbol;
+pub mod testing;
+pub mod token;
+pub mod transpile;
+pub mod value;
+
+#[derive(Debug)]
+pub enum LoxError {
+    Scan(ScanError),
+    Parse(Vec<ParserError>),
+    Resolve(Vec<ResolverError>),
+    Runtime(InterpreterError),
+}
+impl Display for LoxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoxError::Scan(e) => write!(f, "{}", e),
+            LoxError::Parse(errors) => {
+                let lines = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+                write!(f, "{}", lines.join("\n"))
+            }
+            LoxError::Resolve(errors) => {
+                let lines = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+                write!(f, "{}", lines.join("\n"))
+            }
+            LoxError::Runtime(e) => write!(f, "{}", e),
+        }
+    }
+}
+impl Error for LoxError {}
+impl From<ScanError> for LoxError {
+    fn from(e: ScanError) -> Self {
+        LoxError::Scan(e)
+    }
+}
+impl From<Vec<ParserError>> for LoxError {
+    fn from(errors: Vec<ParserError>) -> Self {
+        LoxError::Parse(errors)
+    }
+}
+impl From<Vec<ResolverError>> for LoxError {
+    fn from(errors: Vec<ResolverError>) -> Self {
+        LoxError::Resolve(errors)
+    }
+}
+impl From<InterpreterError> for LoxError {
+    fn from(e: InterpreterError) -> Self {
+        LoxError::Runtime(e)
+    }
+}
+
+/// Scans, parses, resolves and runs `source` with the given interpreter.
+/// Globals defined by the script stay in the interpreter, so it can be
+/// called again with more source.
+pub fn run_source(interpreter: &mut Interpreter, source: String) -> Result<(), LoxError> {
+    let statements = prepare_source(interpreter, source)?;
+    interpreter.interpret(&statements)?;
+
+    Ok(())
+}
+
+/// Scans, parses and resolves `source` for the given interpreter to run.
+pub fn prepare_source(
+    interpreter: &mut Interpreter,
+    source: String,
+) -> Result<Vec<Stmt>, LoxError> {
+    let statements = Parser::new(interpreter.scanner(source)).parse()?;
+
+    let mut resolver = Resolver::new(interpreter);
+    resolver.resolve(&statements);
+    let resolution = resolver.finish();
+    if !resolution.errors.is_empty() {
+        return Err(resolution.errors.into());
+    }
+    Ok(statements)
+}
+
+/// Scans, parses and resolves `source` without running it, returning the
+/// warnings the resolver and the analysis passes found, in source order.
+/// With `compat_jlox` the source is in jlox's dialect, with `print`
+/// statements.
+pub fn lint_source(source: String, compat_jlox: bool) -> Result<Vec<Warning>, LoxError> {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_compat_jlox(compat_jlox);
+    let statements = Parser::new(interpreter.scanner(source)).parse()?;
+
+    let mut resolver = Resolver::new(&mut interpreter);
+    resolver.resolve(&statements);
+    let resolution = resolver.finish();
+    if !resolution.errors.is_empty() {
+        return Err(resolution.errors.into());
+    }
+    let mut warnings = resolution.warnings;
+    warnings.extend(analysis::find_unreachable_code(&statements));
+    warnings.sort_by_key(|it| it.token().scanner_index);
+    Ok(warnings)
+}
+
+/// The name of the script that runs before the others, in the same globals,
+/// with the helpers a user wants everywhere.
+pub const PRELUDE_FILE: &str = "prelude.lox";
+
+/// The first `prelude.lox` in the directories of `search_path`, which are
+/// separated as in `PATH`, like they are in `LOX_PATH`.
+pub fn find_prelude(search_path: &OsStr) -> Option<PathBuf> {
+    std::env::split_paths(search_path)
+        .map(|dir| dir.join(PRELUDE_FILE))
+        .find(|path| path.is_file())
+}
+
+/// The files in `dir` and its subdirectories whose names end with `suffix`,
+/// sorted by path.
+pub fn find_files(dir: &Path, suffix: &str) -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.ends_with(suffix))
+            {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
@@ -1,72 +1,632 @@
-use interpreter::Interpreter;
-use parser::Parser;
-use resolver::Resolver;
-use scanner::Scanner;
-
-mod ast;
-mod environment;
-mod interpreter;
-mod parser;
-mod resolver;
-mod scanner;
-mod token;
-mod value;
-
-struct Lox {}
+mod bench;
+mod debug_prompt;
+mod input;
+mod interrupt;
 
-impl Lox {
-    pub fn new() -> Self {
-        Self {}
-    }
+use std::path::{Path, PathBuf};
 
-    pub fn run(&mut self, source: String) -> anyhow::Result<()> {
-        let tokens = Scanner::new(source).scan_tokens()?;
-        let statements = Parser::new(tokens).parse()?;
+use debug_prompt::DebugPrompt;
+use input::{Input, Line};
+use lox::{
+    coverage::Coverage,
+    debugger::Debugger,
+    diagnostic::{diagnostics, use_color, Diagnostic, Renderer},
+    difftest::{self, Comparison},
+    dot::to_dot,
+    format::format_source,
+    interpreter::{Interpreter, InterpreterError},
+    memory::{MemoryReport, MemoryStats, MemoryTracker},
+    parser::Parser,
+    profile::Profiler,
+    scanner::Scanner,
+    testing::find_test_files,
+    LoxError,
+};
+
+// how running a script, or what was typed, went
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Clean,
+    RuntimeErrors,
+    // it didn't run at all
+    CompileErrors,
+}
 
+struct Lox {
+    // scripts are run as the book's jlox runs them, with `print` as a
+    // statement
+    compat_jlox: bool,
+    // whole numbers are kept exact however large they get
+    bignum: bool,
+    // division by zero, NaN and infinities are errors
+    strict_math: bool,
+    // `==` equates numbers with strings that read as them
+    loose_equality: bool,
+    // a failing top-level statement is reported and the next one runs
+    continue_on_error: bool,
+    // runs in every interpreter before anything else does
+    prelude: Option<PathBuf>,
+}
+
+impl Lox {
+    fn interpreter(&self) -> anyhow::Result<Interpreter> {
         let mut interpreter = Interpreter::new();
-        let mut resolver = Resolver::new(&mut interpreter);
-        resolver.resolve(&statements);
-        interpreter.interpret(&statements);
+        interpreter.set_compat_jlox(self.compat_jlox);
+        interpreter.set_bignum(self.bignum);
+        interpreter.set_strict_math(self.strict_math);
+        interpreter.set_loose_equality(self.loose_equality);
+        if let Some(path) = &self.prelude {
+            let source = std::fs::read_to_string(path)
+                .map_err(|e| anyhow::anyhow!("Can't read the prelude {}: {}", path.display(), e))?;
+            lox::run_source(&mut interpreter, source)
+                .map_err(|e| anyhow::anyhow!("In the prelude {}:\n{}", path.display(), e))?;
+        }
+        // only the script goes on after errors, the prelude has to work
+        interpreter.set_continue_on_error(self.continue_on_error);
+        Ok(interpreter)
+    }
+
+    /// Runs `source`, read from `name`, like the path of a script.
+    pub fn run(&mut self, source: String, name: &str) -> anyhow::Result<()> {
+        let mut interpreter = self.interpreter()?;
+        self.run_with(&mut interpreter, source, name)
+    }
 
+    fn run_with(
+        &mut self,
+        interpreter: &mut Interpreter,
+        source: String,
+        name: &str,
+    ) -> anyhow::Result<()> {
+        if self.run_and_report(interpreter, source, name) == Outcome::CompileErrors {
+            std::process::exit(65);
+        }
         Ok(())
     }
 
+    // runs `source`, printing the errors it had to stderr
+    fn run_and_report(
+        &mut self,
+        interpreter: &mut Interpreter,
+        source: String,
+        name: &str,
+    ) -> Outcome {
+        // a Ctrl-C that came too late for the last run isn't for this one
+        interpreter.cancel_handle().reset();
+        interrupt::set_running(true);
+        let result = lox::run_source(interpreter, source.clone());
+        interrupt::set_running(false);
+
+        let renderer = Renderer::new(&source, name).with_color(use_color(&std::io::stderr()));
+        let errors = interpreter.take_errors();
+        for error in &errors {
+            eprint!("{}", renderer.render(&error.into()));
+        }
+        match result {
+            Ok(()) if errors.is_empty() => Outcome::Clean,
+            Ok(()) => Outcome::RuntimeErrors,
+            Err(LoxError::Runtime(InterpreterError::Exit(code))) => std::process::exit(code),
+            Err(LoxError::Runtime(InterpreterError::Cancelled)) => {
+                eprintln!("Interrupted.");
+                Outcome::RuntimeErrors
+            }
+            Err(error) => {
+                for diagnostic in diagnostics(&error) {
+                    eprint!("{}", renderer.render(&diagnostic));
+                }
+                match error {
+                    LoxError::Runtime(_) => Outcome::RuntimeErrors,
+                    _ => Outcome::CompileErrors,
+                }
+            }
+        }
+    }
+
     pub fn run_file(&mut self, path: &str) -> anyhow::Result<()> {
         let bytes = std::fs::read(path)?;
-        self.run(std::str::from_utf8(&bytes)?.into())
+        self.run(std::str::from_utf8(&bytes)?.into(), path)
+    }
+
+    /// Runs the script under the debugger, pausing before the first
+    /// statement so breakpoints can be set.
+    pub fn debug_file(&mut self, path: &str) -> anyhow::Result<()> {
+        let source = std::fs::read_to_string(path)?;
+        let mut interpreter = self.interpreter()?;
+        let debugger = Debugger::new(DebugPrompt::new(&source)).with_stop_on_entry();
+        interpreter.set_hooks(Some(Box::new(debugger)));
+        self.run_with(&mut interpreter, source, path)
+    }
+
+    /// Runs the script counting how often each line runs, then prints the
+    /// source annotated with the counts to stderr, or with `lcov` writes them
+    /// to `lcov.info` instead.
+    pub fn run_with_coverage(&mut self, path: &str, lcov: bool) -> anyhow::Result<()> {
+        let source = std::fs::read_to_string(path)?;
+        let mut interpreter = self.interpreter()?;
+        let coverage = Coverage::new();
+        interpreter.set_hooks(Some(Box::new(coverage.clone())));
+        self.run_with(&mut interpreter, source.clone(), path)?;
+
+        // it ran, so it parses
+        let statements = Parser::new(interpreter.scanner(source.clone()))
+            .parse()
+            .map_err(report)?;
+        let report = coverage.report(&statements);
+        if lcov {
+            std::fs::write("lcov.info", report.to_lcov(path))?;
+        } else {
+            eprint!("{}", report.annotate(&source));
+        }
+        Ok(())
+    }
+
+    /// Runs the script timing the calls it makes, then prints how long each
+    /// function took to stderr, or with `collapsed` writes the time of each
+    /// chain of calls to `profile.folded` instead, for flamegraphs.
+    pub fn run_with_profile(&mut self, path: &str, collapsed: bool) -> anyhow::Result<()> {
+        let source = std::fs::read_to_string(path)?;
+        let mut interpreter = self.interpreter()?;
+        let profiler = Profiler::new();
+        interpreter.set_hooks(Some(Box::new(profiler.clone())));
+        self.run_with(&mut interpreter, source, path)?;
+
+        let report = profiler.report();
+        if collapsed {
+            std::fs::write("profile.folded", report.to_collapsed())?;
+        } else {
+            eprint!("{}", report);
+        }
+        Ok(())
+    }
+
+    /// Runs the script keeping track of the environments, instances and
+    /// strings it keeps alive, then prints their peak and final counts to
+    /// stderr.
+    pub fn run_with_mem_stats(&mut self, path: &str) -> anyhow::Result<()> {
+        let source = std::fs::read_to_string(path)?;
+        let mut interpreter = self.interpreter()?;
+        let tracker = MemoryTracker::new();
+        interpreter.set_hooks(Some(Box::new(tracker.clone())));
+        self.run_with(&mut interpreter, source, path)?;
+
+        let last = MemoryStats::of(&interpreter);
+        let report = MemoryReport {
+            peak: tracker.peak(last),
+            last,
+            next_collection: interpreter.next_collection(),
+        };
+        eprint!("{}", report);
+        Ok(())
+    }
+
+    /// Runs every test file under `dir`, printing each test as it's checked.
+    /// Returns whether all of them passed.
+    pub fn run_tests(&mut self, dir: &str) -> anyhow::Result<bool> {
+        let (mut passed, mut failed) = (0, 0);
+        for path in find_test_files(Path::new(dir))? {
+            let source = std::fs::read_to_string(&path)?;
+            let mut interpreter = self.interpreter()?;
+            let result = lox::run_source(&mut interpreter, source);
+
+            for case in &interpreter.test_report().cases {
+                if case.passed() {
+                    passed += 1;
+                    println!("PASS {} > {}", path.display(), case.name);
+                } else {
+                    failed += 1;
+                    println!("FAIL {} > {}", path.display(), case.name);
+                    for failure in &case.failures {
+                        println!("    {}", failure);
+                    }
+                }
+            }
+            // an error outside of a test stops the rest of the file
+            if let Err(e) = result {
+                failed += 1;
+                println!("FAIL {}", path.display());
+                for line in e.to_string().lines() {
+                    println!("    {}", line);
+                }
+            }
+        }
+        println!();
+        println!("{} passed, {} failed", passed, failed);
+        Ok(failed == 0)
+    }
+
+    /// Runs the script `warmup` times, then `runs` more times that are
+    /// measured, on the vm or on this interpreter, and prints how long they
+    /// took.
+    pub fn bench(
+        &mut self,
+        path: &str,
+        runs: usize,
+        warmup: usize,
+        vm: bool,
+    ) -> anyhow::Result<()> {
+        let source = std::fs::read_to_string(path)?;
+        let mut measurements = vec![];
+        for run in 0..warmup + runs {
+            let measurement = if vm {
+                bench::measure_vm(&source, self.strict_math)
+                    .map_err(|e| anyhow::anyhow!("{}", e))?
+            } else {
+                bench::measure_tree_walk(self.interpreter()?, &source).map_err(report)?
+            };
+            if run >= warmup {
+                measurements.push(measurement);
+            }
+        }
+        let summary = bench::Summary::new(&measurements);
+        let (backend, work) = if vm {
+            ("the vm", "instructions run")
+        } else {
+            ("the tree-walk interpreter", "statements executed")
+        };
+        println!("{} on {}, after {} warmup runs", path, backend, warmup);
+        println!("{}", summary);
+        println!("{} per run: {}", work, summary.work);
+        Ok(())
+    }
+
+    /// Runs the given files, and the `.lox` files in the given directories,
+    /// on both interpreters and prints where they disagree. Files the
+    /// bytecode compiler can't compile are skipped. Returns whether they
+    /// agreed on every other file.
+    pub fn difftest(&mut self, paths: &[String]) -> anyhow::Result<bool> {
+        let (mut agreed, mut diverged, mut skipped) = (0, 0, 0);
+        for file in collect_files(paths)? {
+            let source = std::fs::read_to_string(&file)?;
+            match difftest::run_both(&source) {
+                Comparison::Agreed => {
+                    agreed += 1;
+                    println!("SAME {}", file.display());
+                }
+                Comparison::Diverged(differences) => {
+                    diverged += 1;
+                    println!("DIFF {}", file.display());
+                    for difference in &differences {
+                        println!("    {}", difference);
+                    }
+                }
+                Comparison::Unsupported(error) => {
+                    skipped += 1;
+                    println!("SKIP {}", file.display());
+                    // only the first line, a compile error can have a lot
+                    println!("    {}", error.lines().next().unwrap_or_default());
+                }
+            }
+        }
+        println!();
+        println!(
+            "{} agreed, {} diverged, {} skipped",
+            agreed, diverged, skipped
+        );
+        Ok(diverged == 0)
+    }
+
+    /// Formats the given files, and the `.lox` files in the given
+    /// directories. With `check`, only lists the files that aren't formatted.
+    /// Returns false if a file couldn't be parsed, or if one wasn't
+    /// formatted with `check`.
+    pub fn format(&mut self, paths: &[String], check: bool) -> anyhow::Result<bool> {
+        let mut success = true;
+        for file in collect_files(paths)? {
+            let source = std::fs::read_to_string(&file)?;
+            let formatted = match format_source(&source, self.compat_jlox) {
+                Ok(formatted) => formatted,
+                Err(e) => {
+                    eprintln!("{}: {}", file.display(), e);
+                    success = false;
+                    continue;
+                }
+            };
+            if formatted == source {
+                continue;
+            }
+            if check {
+                println!("{}", file.display());
+                success = false;
+            } else {
+                std::fs::write(&file, formatted)?;
+            }
+        }
+        Ok(success)
+    }
+
+    /// Prints the warnings for the given files, and for the `.lox` files in
+    /// the given directories. Returns whether there were none.
+    pub fn lint(&mut self, paths: &[String]) -> anyhow::Result<bool> {
+        let mut clean = true;
+        for file in collect_files(paths)? {
+            let source = std::fs::read_to_string(&file)?;
+            let name = file.display().to_string();
+            let renderer = Renderer::new(&source, &name).with_color(use_color(&std::io::stdout()));
+            let diagnostics = match lox::lint_source(source.clone(), self.compat_jlox) {
+                Ok(warnings) => warnings.iter().map(Diagnostic::from).collect(),
+                Err(e) => diagnostics(&e),
+            };
+            for diagnostic in &diagnostics {
+                print!("{}", renderer.render(diagnostic));
+            }
+            clean &= diagnostics.is_empty();
+        }
+        Ok(clean)
+    }
+
+    /// Writes the syntax tree of `script` as a Graphviz graph to `out`, or
+    /// next to the script with a `.dot` extension.
+    pub fn export_dot(&mut self, script: &str, out: Option<&str>) -> anyhow::Result<()> {
+        let source = std::fs::read_to_string(script)?;
+        let mut scanner = Scanner::new(source);
+        if self.compat_jlox {
+            scanner = scanner.with_compat_jlox();
+        }
+        let statements = Parser::new(scanner).parse().map_err(report)?;
+        let out = match out {
+            Some(out) => PathBuf::from(out),
+            None => Path::new(script).with_extension("dot"),
+        };
+        std::fs::write(out, to_dot(&statements))?;
+        Ok(())
+    }
+
+    /// Writes `script` as the source of a Rust program to `out`, or to
+    /// stdout.
+    pub fn transpile(&mut self, script: &str, out: Option<&str>) -> anyhow::Result<()> {
+        let source = std::fs::read_to_string(script)?;
+        let rust = lox::transpile::transpile(&source).map_err(|e| anyhow::anyhow!("{}", e))?;
+        match out {
+            Some(out) => std::fs::write(out, rust)?,
+            None => print!("{}", rust),
+        }
+        Ok(())
     }
 
     pub fn run_prompt(&mut self) -> anyhow::Result<()> {
-        let stdin = std::io::stdin();
-        let mut stdout = std::io::stdout();
-        use std::io::{BufRead, Write};
+        let mut input = Input::new(self.compat_jlox)?;
+        // lines are put together until their braces and parentheses close,
+        // so a function or class typed or pasted over several lines runs as
+        // one. a blank line runs what there is anyway
+        let mut source = String::new();
+        let mut interpreter = self.interpreter()?;
+        // a mistake in one statement shouldn't lose the rest of what was
+        // typed
+        interpreter.set_continue_on_error(true);
+        interrupt::install(interpreter.cancel_handle());
+        // what ran without errors, for `:save`
+        let mut history = String::new();
         loop {
-            print!("> ");
-            stdout.flush()?;
-            let mut line = String::new();
-            let mut reader = stdin.lock();
-            if reader.read_line(&mut line)? == 0 {
+            let prompt = if source.is_empty() { "> " } else { "... " };
+            let (line, eof) = match input.read_line(prompt)? {
+                Line::Read(line) => (line, false),
+                // Ctrl-C drops what was typed so far
+                Line::Interrupted => {
+                    source.clear();
+                    continue;
+                }
+                Line::Eof => (String::new(), true),
+            };
+            // it can also come just as the line was read
+            if interrupt::take_pressed_at_prompt() {
+                source.clear();
+            }
+            if let Some(command) = line.trim().strip_prefix(':').filter(|_| source.is_empty()) {
+                if let Err(error) = self.run_command(command, &mut interpreter, &mut history) {
+                    println!("{}", error);
+                }
+                continue;
+            }
+            let blank = line.trim().is_empty();
+            source.push_str(&line);
+            if !eof && !blank && is_incomplete(&source) {
+                continue;
+            }
+            if !source.trim().is_empty() {
+                let input = std::mem::take(&mut source);
+                if self.run_and_report(&mut interpreter, input.clone(), "<repl>") == Outcome::Clean
+                {
+                    push_input(&mut history, &input);
+                }
+            }
+            source.clear();
+            if eof {
                 break;
             }
-            if let Err(error) = self.run(line) {
-                println!("{}", error);
+        }
+        Ok(())
+    }
+
+    // `:save <file>` writes what ran without errors in the session to the
+    // file, as a script, and `:replay <file>` runs a file as if it had been
+    // typed
+    fn run_command(
+        &mut self,
+        command: &str,
+        interpreter: &mut Interpreter,
+        history: &mut String,
+    ) -> anyhow::Result<()> {
+        let (name, path) = command.split_once(' ').unwrap_or((command, ""));
+        match (name, path.trim()) {
+            ("save", path) if !path.is_empty() => std::fs::write(path, &history)
+                .map_err(|e| anyhow::anyhow!("Can't write {}: {}", path, e))?,
+            ("replay", path) if !path.is_empty() => {
+                let source = std::fs::read_to_string(path)
+                    .map_err(|e| anyhow::anyhow!("Can't read {}: {}", path, e))?;
+                if self.run_and_report(interpreter, source.clone(), path) == Outcome::Clean {
+                    push_input(history, &source);
+                }
             }
+            _ => println!("Commands: :save <file>, :replay <file>"),
         }
         Ok(())
     }
 }
 
+// adds an input to the session's history, a line apart from the next one
+fn push_input(history: &mut String, input: &str) {
+    history.push_str(input.trim_end());
+    history.push('\n');
+}
+
+// whether `source` has braces or parentheses left open, or a string that
+// isn't closed, so more lines of it are coming. other scan errors are left
+// for running it to report
+fn is_incomplete(source: &str) -> bool {
+    use lox::{scanner::ScanError, token::TokenKind};
+    let mut depth = 0;
+    for token in Scanner::new(source.to_string()) {
+        match token.map(|it| it.kind) {
+            Ok(TokenKind::LeftBrace | TokenKind::LeftParen) => depth += 1,
+            Ok(TokenKind::RightBrace | TokenKind::RightParen) => depth -= 1,
+            Ok(_) => {}
+            Err(ScanError::UnterminatedString(_)) => return true,
+            Err(_) => return false,
+        }
+    }
+    depth > 0
+}
+
 fn main() -> anyhow::Result<()> {
-    let args = std::env::args().collect::<Vec<_>>();
-    if args.len() > 2 {
-        println!("Usage: lox [script]");
-        std::process::exit(64);
-    } else if args.len() == 2 {
-        let mut lox = Lox::new();
-        lox.run_file(&args[1])?;
-    } else {
-        let mut lox = Lox::new();
-        lox.run_prompt()?;
+    let mut args = std::env::args().collect::<Vec<_>>();
+    let mut lox = Lox {
+        compat_jlox: take_flag(&mut args, "--compat-jlox"),
+        bignum: take_flag(&mut args, "--bignum"),
+        strict_math: take_flag(&mut args, "--strict-math"),
+        loose_equality: take_flag(&mut args, "--loose-equality"),
+        continue_on_error: take_flag(&mut args, "--continue-on-error"),
+        prelude: None,
+    };
+    let no_prelude = take_flag(&mut args, "--no-prelude");
+    lox.prelude = match take_value(&mut args, "--prelude") {
+        Some(path) => Some(PathBuf::from(path)),
+        None if no_prelude => None,
+        None => std::env::var_os("LOX_PATH").and_then(|it| lox::find_prelude(&it)),
+    };
+    match args.get(1).map(String::as_str) {
+        Some("test") if args.len() <= 3 => {
+            let dir = args.get(2).map(String::as_str).unwrap_or(".");
+            if !lox.run_tests(dir)? {
+                std::process::exit(1);
+            }
+        }
+        Some("fmt") => {
+            let check = args[2..].iter().any(|it| it == "--check");
+            let paths = args[2..]
+                .iter()
+                .filter(|it| *it != "--check")
+                .cloned()
+                .collect::<Vec<_>>();
+            if paths.is_empty() {
+                usage();
+            }
+            if !lox.format(&paths, check)? {
+                std::process::exit(1);
+            }
+        }
+        Some("--debug") if args.len() == 3 => lox.debug_file(&args[2])?,
+        Some("--coverage") if args.len() == 3 => lox.run_with_coverage(&args[2], false)?,
+        Some("--coverage=lcov") if args.len() == 3 => lox.run_with_coverage(&args[2], true)?,
+        Some("--profile") if args.len() == 3 => lox.run_with_profile(&args[2], false)?,
+        Some("--profile=collapsed") if args.len() == 3 => lox.run_with_profile(&args[2], true)?,
+        Some("--mem-stats") if args.len() == 3 => lox.run_with_mem_stats(&args[2])?,
+        Some("lint") if args.len() > 2 => {
+            if !lox.lint(&args[2..])? {
+                std::process::exit(1);
+            }
+        }
+        Some("difftest") if args.len() > 2 => {
+            if !lox.difftest(&args[2..])? {
+                std::process::exit(1);
+            }
+        }
+        Some("bench") => {
+            let vm = take_flag(&mut args, "--vm");
+            let runs = take_count(&mut args, "--runs")?.unwrap_or(10);
+            let warmup = take_count(&mut args, "--warmup")?.unwrap_or(2);
+            if args.len() != 3 || runs == 0 {
+                usage();
+            }
+            lox.bench(&args[2], runs, warmup, vm)?
+        }
+        Some("dot") if (3..=4).contains(&args.len()) => {
+            lox.export_dot(&args[2], args.get(3).map(String::as_str))?
+        }
+        Some("transpile") if (3..=4).contains(&args.len()) => {
+            lox.transpile(&args[2], args.get(3).map(String::as_str))?
+        }
+        Some(script) if args.len() == 2 => lox.run_file(script)?,
+        None => lox.run_prompt()?,
+        _ => usage(),
     }
     Ok(())
 }
+
+// errors can hold values, which can't go to other threads like an
+// `anyhow::Error` needs, so only their message is kept
+fn report(error: impl Into<LoxError>) -> anyhow::Error {
+    anyhow::anyhow!("{}", error.into())
+}
+
+// the given files, and the `.lox` files in the given directories
+fn collect_files(paths: &[String]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for path in paths.iter().map(Path::new) {
+        if path.is_dir() {
+            files.extend(lox::find_files(path, ".lox")?);
+        } else {
+            files.push(path.to_path_buf());
+        }
+    }
+    Ok(files)
+}
+
+// whether `flag` was passed, removing it so it can go anywhere
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let passed = args.iter().any(|it| it == flag);
+    args.retain(|it| it != flag);
+    passed
+}
+
+// the value given as `name=<value>`, removing it so it can go anywhere
+fn take_value(args: &mut Vec<String>, name: &str) -> Option<String> {
+    let prefix = format!("{}=", name);
+    let index = args.iter().position(|it| it.starts_with(&prefix))?;
+    Some(args.remove(index)[prefix.len()..].to_string())
+}
+
+// the number given as `name=<count>`, removing it so it can go anywhere
+fn take_count(args: &mut Vec<String>, name: &str) -> anyhow::Result<Option<usize>> {
+    let Some(value) = take_value(args, name) else {
+        return Ok(None);
+    };
+    let count = value
+        .parse()
+        .map_err(|_| anyhow::anyhow!("{} needs a number, not '{}'", name, value))?;
+    Ok(Some(count))
+}
+
+fn usage() -> ! {
+    println!("Usage: lox [script]");
+    println!("       lox --debug <script>");
+    println!("       lox --coverage[=lcov] <script>");
+    println!("       lox --profile[=collapsed] <script>");
+    println!("       lox --mem-stats <script>");
+    println!("       lox test [dir]");
+    println!("       lox fmt [--check] <paths>");
+    println!("       lox lint <paths>");
+    println!("       lox dot <script> [out]");
+    println!("       lox difftest <paths>");
+    println!("       lox bench [--vm] [--runs=<n>] [--warmup=<n>] <script>");
+    println!("       lox transpile <script> [out]");
+    println!();
+    println!("--compat-jlox runs scripts with `print` as a statement, like the book.");
+    println!("--bignum keeps integers exact past 2^53, instead of rounding them.");
+    println!("--strict-math makes division by zero, NaN and infinities errors.");
+    println!("--loose-equality makes \"1\" == 1 true, === still compares strictly.");
+    println!("--continue-on-error reports a failing statement and runs the next one.");
+    println!("--prelude=<file> runs the file first, in the same globals as the script.");
+    println!("    Otherwise the first prelude.lox in the directories of LOX_PATH runs,");
+    println!("    unless --no-prelude is given.");
+    std::process::exit(64);
+}
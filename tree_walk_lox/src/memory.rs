@@ -0,0 +1,160 @@
+//! Memory use for `lox --mem-stats`: how many environments, instances and
+//! strings a script keeps alive, at its peak and when it ends.
+//!
+//! Environments and instances count themselves as they're made and dropped.
+//! Strings are plain shared `str`s that don't, so they're counted by walking
+//! the values the environments hold, which is too slow to do before every
+//! statement. Their peak is only sampled, and strings nothing holds on to,
+//! like the temporaries of an expression, aren't seen.
+
+use std::{
+    fmt::Display,
+    sync::{Arc, Mutex},
+};
+
+use rustc_hash::FxHashSet;
+
+use crate::{
+    ast::Stmt,
+    environment::Environment,
+    gc::Trace,
+    hooks::InterpreterHooks,
+    interpreter::Interpreter,
+    shared::Gc,
+    value::{ClassInstance, RuntimeValue},
+};
+
+// statements between two counts of the strings
+const STRING_SAMPLE_INTERVAL: u64 = 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// including the global one
+    pub environments: usize,
+    /// of every interpreter in the process
+    pub instances: usize,
+    /// the distinct ones held by variables, fields, lists and maps
+    pub strings: usize,
+}
+
+impl MemoryStats {
+    /// What `interpreter` keeps alive right now.
+    pub fn of(interpreter: &Interpreter) -> Self {
+        Self {
+            environments: interpreter.live_environments(),
+            instances: ClassInstance::live_count(),
+            strings: count_strings(interpreter.globals()),
+        }
+    }
+
+    /// The larger of each count.
+    pub fn max(self, other: MemoryStats) -> Self {
+        Self {
+            environments: self.environments.max(other.environments),
+            instances: self.instances.max(other.instances),
+            strings: self.strings.max(other.strings),
+        }
+    }
+}
+
+/// Keeps the peak counts while a script runs. Install a clone with
+/// `Interpreter::set_hooks` and read the peak from this one afterwards.
+#[derive(Clone, Default)]
+pub struct MemoryTracker(Arc<Mutex<Tracked>>);
+
+#[derive(Default)]
+struct Tracked {
+    peak: MemoryStats,
+    statements: u64,
+}
+
+impl MemoryTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The highest counts seen so far, with `last`, the counts after the
+    /// script, as the peak if nothing was higher.
+    pub fn peak(&self, last: MemoryStats) -> MemoryStats {
+        self.0.lock().unwrap().peak.max(last)
+    }
+}
+
+impl InterpreterHooks for MemoryTracker {
+    fn on_statement(&mut self, interpreter: &mut Interpreter, _stmt: &Stmt) {
+        let mut tracked = self.0.lock().unwrap();
+        let mut now = MemoryStats {
+            environments: interpreter.live_environments(),
+            instances: ClassInstance::live_count(),
+            strings: 0,
+        };
+        if tracked.statements.is_multiple_of(STRING_SAMPLE_INTERVAL) {
+            now.strings = count_strings(interpreter.globals());
+        }
+        tracked.statements += 1;
+        tracked.peak = tracked.peak.max(now);
+    }
+}
+
+/// The peak and final counts of a script.
+pub struct MemoryReport {
+    pub peak: MemoryStats,
+    pub last: MemoryStats,
+    /// how many environments can be alive before the next collection
+    pub next_collection: usize,
+}
+
+impl Display for MemoryReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rows = [
+            (
+                "environments",
+                self.peak.environments,
+                self.last.environments,
+            ),
+            ("instances", self.peak.instances, self.last.instances),
+            ("strings", self.peak.strings, self.last.strings),
+        ];
+        writeln!(f, "{:<14} {:>9} {:>9}", "", "peak", "final")?;
+        for (name, peak, last) in rows {
+            writeln!(f, "{:<14} {:>9} {:>9}", name, peak, last)?;
+        }
+        writeln!(
+            f,
+            "the next collection runs at {} environments",
+            self.next_collection
+        )
+    }
+}
+
+// the distinct strings reachable from the environments in the tree
+// `environment` belongs to
+fn count_strings(environment: &Environment) -> usize {
+    let mut strings = FxHashSet::default();
+    let mut seen = FxHashSet::default();
+    let mut values = environment
+        .all()
+        .iter()
+        .flat_map(|it| it.values())
+        .map(|(_, value)| value)
+        .collect::<Vec<_>>();
+    while let Some(value) = values.pop() {
+        match &value {
+            RuntimeValue::Str(s) => {
+                strings.insert(Gc::as_ptr(s) as *const u8 as usize);
+            }
+            RuntimeValue::Instance(it) if seen.insert(it.address()) => {
+                values.extend(it.fields().into_iter().map(|(_, value)| value));
+            }
+            RuntimeValue::List(it) if seen.insert(it.address()) => values.extend(it.items()),
+            RuntimeValue::Map(it) if seen.insert(it.address()) => {
+                for (key, value) in it.entries() {
+                    values.push(key.to_value());
+                    values.push(value);
+                }
+            }
+            _ => {}
+        }
+    }
+    strings.len()
+}
@@ -1,19 +1,62 @@
-use std::{error::Error, fmt::Display};
+use std::{error::Error, fmt::Display, iter::Peekable};
 
 use crate::{
-    ast::{Expr, FunctionStmt, Stmt},
+    ast::{Expr, FunctionStmt, NodeId, StaticField, Stmt},
+    scanner::ScanError,
     token::{Token, TokenKind},
     value::RuntimeValue,
+    LoxError,
 };
 
-pub struct Parser {
-    tokens: Vec<Token>,
-    current: usize,
+// the superclass, methods and static fields of a class
+type ClassBody = (Option<Expr>, Vec<FunctionStmt>, Vec<StaticField>);
+
+/// Builds the syntax tree from tokens as it pulls them from a scanner, so it
+/// only ever holds the current token and the one before it, and the one
+/// after it where a loop's label needs it.
+pub struct Parser<I: Iterator> {
+    tokens: Peekable<I>,
+    current: Token,
+    previous: Token,
+    // the tokens end early at a scan error. it's reported instead of the
+    // parse errors that come from the missing tokens.
+    scan_error: Option<ScanError>,
 }
 
-impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+impl<I: Iterator<Item = Result<Token, ScanError>>> Parser<I> {
+    pub fn new(tokens: I) -> Self {
+        let eof = Token {
+            kind: TokenKind::Eof,
+            lexeme: "".into(),
+            literal: RuntimeValue::Nil,
+            line: 1,
+            column: 1,
+            scanner_index: 0,
+            length: 0,
+        };
+        let mut parser = Self {
+            tokens: tokens.peekable(),
+            current: eof.clone(),
+            previous: eof,
+            scan_error: None,
+        };
+        parser.current = parser.next_token();
+        parser
+    }
+
+    fn next_token(&mut self) -> Token {
+        match self.tokens.next() {
+            Some(Ok(token)) => return token,
+            Some(Err(e)) => self.scan_error = Some(e),
+            None => {}
+        }
+        Token {
+            kind: TokenKind::Eof,
+            lexeme: "".into(),
+            literal: RuntimeValue::Nil,
+            length: 0,
+            ..self.current.clone()
+        }
     }
 
     fn exact(&mut self, kinds: &[TokenKind]) -> bool {
@@ -26,6 +69,11 @@ impl Parser {
         false
     }
 
+    // whether the token after the current one is `kind`
+    fn check_next(&mut self, kind: TokenKind) -> bool {
+        matches!(self.tokens.peek(), Some(Ok(token)) if token.kind == kind)
+    }
+
     fn check(&self, kind: TokenKind) -> bool {
         if self.is_at_end() {
             false
@@ -34,16 +82,17 @@ impl Parser {
         }
     }
 
-    fn advance(&mut self) -> Token {
+    fn advance(&mut self) -> &Token {
         if !self.is_at_end() {
-            self.current += 1;
+            let next = self.next_token();
+            self.previous = std::mem::replace(&mut self.current, next);
         }
         self.previous()
     }
 
     fn consume(&mut self, kind: TokenKind, message: &str) -> Result<Token, ParserError> {
         if self.check(kind) {
-            Ok(self.advance())
+            Ok(self.advance().clone())
         } else {
             Err(parser_error(self.peek(), message))
         }
@@ -53,22 +102,50 @@ impl Parser {
         self.peek().kind == TokenKind::Eof
     }
 
-    fn peek(&self) -> Token {
-        self.tokens[self.current].clone()
+    fn peek(&self) -> &Token {
+        &self.current
+    }
+
+    fn previous(&self) -> &Token {
+        &self.previous
     }
 
-    fn previous(&self) -> Token {
-        self.tokens[self.current - 1].clone()
+    fn finish<T>(self, result: Result<T, Vec<ParserError>>) -> Result<T, LoxError> {
+        match self.scan_error {
+            Some(e) => Err(e.into()),
+            None => result.map_err(Into::into),
+        }
     }
 
-    pub fn parse(mut self) -> Result<Vec<Stmt>, ParserError> {
+    /// parses every statement, synchronizing after errors so all of them
+    /// are found in a single pass.
+    pub fn parse(mut self) -> Result<Vec<Stmt>, LoxError> {
         let mut statements = vec![];
+        let mut errors = vec![];
         while !self.is_at_end() {
-            if let Ok(stmt) = self.declaration() {
-                statements.push(stmt);
+            match self.declaration() {
+                Ok(stmt) => statements.push(stmt),
+                Err(e) => errors.push(e),
             }
         }
-        Ok(statements)
+        if errors.is_empty() {
+            self.finish(Ok(statements))
+        } else {
+            self.finish(Err(errors))
+        }
+    }
+
+    /// parses a single expression that must make up all of the tokens, like
+    /// the ones a debugger evaluates.
+    pub fn parse_expression(mut self) -> Result<Expr, LoxError> {
+        let result = match self.expression() {
+            Ok(_) if !self.is_at_end() => {
+                Err(vec![parser_error(self.peek(), "Expect end of expression.")])
+            }
+            Ok(expr) => Ok(expr),
+            Err(e) => Err(vec![e]),
+        };
+        self.finish(result)
     }
 
     fn declaration(&mut self) -> Result<Stmt, ParserError> {
@@ -93,10 +170,24 @@ impl Parser {
 
     fn class_declaration(&mut self) -> Result<Stmt, ParserError> {
         let name = self.consume(TokenKind::Identifier, "Expect class name.")?;
+        let (superclass, methods, statics) = self.class_body()?;
+        Ok(Stmt::Class {
+            name,
+            superclass,
+            methods,
+            statics,
+        })
+    }
 
+    // what follows the name of a class declaration, or the keyword of a
+    // class expression
+    fn class_body(&mut self) -> Result<ClassBody, ParserError> {
         let superclass = if self.exact(&[TokenKind::Less]) {
-            self.consume(TokenKind::Identifier, "Expect superclass name.")?;
-            Some(self.previous())
+            let name = self.consume(TokenKind::Identifier, "Expect superclass name.")?;
+            Some(Expr::Variable {
+                id: NodeId::next(),
+                name,
+            })
         } else {
             None
         };
@@ -104,16 +195,27 @@ impl Parser {
         self.consume(TokenKind::LeftBrace, "Expect '{' before class body.")?;
 
         let mut methods = vec![];
+        let mut statics = vec![];
         while !self.check(TokenKind::RightBrace) && !self.is_at_end() {
-            methods.push(self.function("method")?);
+            // `static` is only a keyword here, before the name of a field
+            if self.check(TokenKind::Identifier) && self.peek().lexeme == "static" {
+                let keyword = self.advance().clone();
+                if self.check(TokenKind::Identifier) {
+                    let name = self.advance().clone();
+                    self.consume(TokenKind::Equal, "Expect '=' after static field name.")?;
+                    let value = self.expression()?;
+                    self.consume(TokenKind::Semicolon, "Expect ';' after static field.")?;
+                    statics.push(StaticField::new(name, value));
+                } else {
+                    methods.push(self.function_named(keyword, "method")?);
+                }
+            } else {
+                methods.push(self.function("method")?);
+            }
         }
 
         self.consume(TokenKind::RightBrace, "Expect '}' after class body.")?;
-        Ok(Stmt::Class {
-            name,
-            superclass,
-            methods,
-        })
+        Ok((superclass, methods, statics))
     }
 
     fn var_declaration(&mut self) -> Result<Stmt, ParserError> {
@@ -133,8 +235,10 @@ impl Parser {
     }
 
     fn statement(&mut self) -> Result<Stmt, ParserError> {
-        if self.exact(&[TokenKind::For]) {
-            self.for_statement()
+        if self.check(TokenKind::Identifier) && self.check_next(TokenKind::Colon) {
+            self.labeled_statement()
+        } else if self.exact(&[TokenKind::For]) {
+            self.for_statement(None)
         } else if self.exact(&[TokenKind::If]) {
             self.if_statement()
         } else if self.exact(&[TokenKind::Print]) {
@@ -142,7 +246,21 @@ impl Parser {
         } else if self.exact(&[TokenKind::Return]) {
             self.return_statement()
         } else if self.exact(&[TokenKind::While]) {
-            self.while_statement()
+            self.while_statement(None)
+        } else if self.exact(&[TokenKind::Break]) {
+            let keyword = self.previous().clone();
+            let label = self.label()?;
+            self.consume(TokenKind::Semicolon, "Expect ';' after 'break'.")?;
+            Ok(Stmt::Break { keyword, label })
+        } else if self.exact(&[TokenKind::Continue]) {
+            let keyword = self.previous().clone();
+            let label = self.label()?;
+            self.consume(TokenKind::Semicolon, "Expect ';' after 'continue'.")?;
+            Ok(Stmt::Continue { keyword, label })
+        } else if self.exact(&[TokenKind::Breakpoint]) {
+            let keyword = self.previous().clone();
+            self.consume(TokenKind::Semicolon, "Expect ';' after 'breakpoint'.")?;
+            Ok(Stmt::Breakpoint { keyword })
         } else if self.exact(&[TokenKind::LeftBrace]) {
             Ok(Stmt::Block {
                 statements: self.block()?,
@@ -166,30 +284,49 @@ impl Parser {
         let condition = self.expression()?;
         self.consume(TokenKind::RightParen, "Expect ')' after if condition.")?;
 
-        let then_branch = self.statement()?.into();
+        let then_branch = self.statement()?;
         let else_branch = if self.exact(&[TokenKind::Else]) {
-            Some(self.statement()?.into())
+            Some(self.statement()?)
         } else {
             None
         };
 
-        Ok(Stmt::If {
-            condition,
-            then_branch,
-            else_branch,
-        })
+        Ok(Stmt::new_if(condition, then_branch, else_branch))
+    }
+
+    // `name: while (...)`, which `break name;` and `continue name;` in its
+    // body can refer to
+    fn labeled_statement(&mut self) -> Result<Stmt, ParserError> {
+        let label = self.advance().clone();
+        self.advance();
+        if self.exact(&[TokenKind::While]) {
+            self.while_statement(Some(label))
+        } else if self.exact(&[TokenKind::For]) {
+            self.for_statement(Some(label))
+        } else {
+            Err(parser_error(self.peek(), "Expect a loop after a label."))
+        }
     }
 
-    fn while_statement(&mut self) -> Result<Stmt, ParserError> {
+    // the label after `break` or `continue`, if there is one
+    fn label(&mut self) -> Result<Option<Token>, ParserError> {
+        if self.exact(&[TokenKind::Identifier]) {
+            Ok(Some(self.previous().clone()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn while_statement(&mut self, label: Option<Token>) -> Result<Stmt, ParserError> {
         self.consume(TokenKind::LeftParen, "Expect '(' after 'while'.")?;
         let condition = self.expression()?;
         self.consume(TokenKind::RightParen, "Expect ')' after condition.")?;
-        let body = self.statement()?.into();
+        let body = self.statement()?;
 
-        Ok(Stmt::While { condition, body })
+        Ok(Stmt::new_while(label, condition, body, None))
     }
 
-    fn for_statement(&mut self) -> Result<Stmt, ParserError> {
+    fn for_statement(&mut self, label: Option<Token>) -> Result<Stmt, ParserError> {
         self.consume(TokenKind::LeftParen, "Expect '(' after 'for'.")?;
 
         let initializer = if self.exact(&[TokenKind::Semicolon]) {
@@ -215,31 +352,18 @@ impl Parser {
         };
         self.consume(TokenKind::RightParen, "Expect ')' after for clauses.")?;
 
-        let mut body = self.statement()?;
-
-        if let Some(increment) = increment {
-            body = Stmt::Block {
-                statements: vec![
-                    body,
-                    Stmt::Expression {
-                        expression: increment,
-                    },
-                ],
-            }
-        }
+        let body = self.statement()?;
 
-        body = Stmt::While {
-            condition: condition.unwrap_or(Expr::Literal {
-                value: Token {
-                    kind: TokenKind::True,
-                    lexeme: "true".into(),
-                    literal: RuntimeValue::Bool(true),
-                    line: condition_semicolon.line,
-                    scanner_index: condition_semicolon.scanner_index,
-                },
-            }),
-            body: body.into(),
-        };
+        let condition = condition.unwrap_or(Expr::new_literal(Token {
+            kind: TokenKind::True,
+            lexeme: "true".into(),
+            literal: RuntimeValue::Bool(true),
+            line: condition_semicolon.line,
+            column: condition_semicolon.column,
+            scanner_index: condition_semicolon.scanner_index,
+            length: 0,
+        }));
+        let mut body = Stmt::new_while(label, condition, body, increment);
 
         if let Some(initializer) = initializer {
             body = Stmt::Block {
@@ -257,7 +381,7 @@ impl Parser {
     }
 
     fn return_statement(&mut self) -> Result<Stmt, ParserError> {
-        let keyword = self.previous();
+        let keyword = self.previous().clone();
         let value = if !self.check(TokenKind::Semicolon) {
             Some(self.expression()?)
         } else {
@@ -275,6 +399,11 @@ impl Parser {
 
     fn function(&mut self, kind: &str) -> Result<FunctionStmt, ParserError> {
         let name = self.consume(TokenKind::Identifier, &format!("Expect {} name.", kind))?;
+        self.function_named(name, kind)
+    }
+
+    // a function whose name was already consumed
+    fn function_named(&mut self, name: Token, kind: &str) -> Result<FunctionStmt, ParserError> {
         self.consume(
             TokenKind::LeftParen,
             &format!("Expect '(' after {} name", kind),
@@ -284,7 +413,7 @@ impl Parser {
             loop {
                 if parameters.len() >= 255 {
                     return Err(ParserError {
-                        token: self.peek(),
+                        token: self.peek().clone(),
                         message: "Can't have more than 255 arguments.".into(),
                     });
                 } // TODO! Report but don't print error
@@ -301,11 +430,8 @@ impl Parser {
             &format!("Expect '{{' before {} body.", kind),
         )?;
         let body = self.block()?;
-        Ok(FunctionStmt {
-            name,
-            params: parameters,
-            body,
-        })
+        let span = name.span().start..self.previous().span().end;
+        Ok(FunctionStmt::new(name, parameters, body, span))
     }
 
     fn expression(&mut self) -> Result<Expr, ParserError> {
@@ -316,25 +442,15 @@ impl Parser {
         let expr = self.or()?;
 
         if self.exact(&[TokenKind::Equal]) {
-            let equals = self.previous();
+            let equals = self.previous().clone();
             let value = self.assignment()?;
 
             if let Expr::Variable { name, .. } = expr {
-                Ok(Expr::Assign {
-                    name,
-                    value: Box::new(value),
-                })
+                Ok(Expr::new_assign(NodeId::next(), name, value))
             } else if let Expr::Get { name, object } = expr {
-                Ok(Expr::Set {
-                    name,
-                    object,
-                    value: value.into(),
-                })
+                Ok(Expr::new_set(*object, name, value))
             } else {
-                Err(ParserError {
-                    token: equals,
-                    message: "Invalid assignment target.".into(),
-                })
+                Err(parser_error(&equals, "Invalid assignment target."))
             }
         } else {
             Ok(expr)
@@ -344,13 +460,9 @@ impl Parser {
     fn or(&mut self) -> Result<Expr, ParserError> {
         let mut expr = self.and()?;
         while self.exact(&[TokenKind::Or]) {
-            let operator = self.previous();
-            let right = self.and()?.into();
-            expr = Expr::Logical {
-                left: expr.into(),
-                operator,
-                right,
-            };
+            let operator = self.previous().clone();
+            let right = self.and()?;
+            expr = Expr::new_logical(expr, operator, right);
         }
         Ok(expr)
     }
@@ -358,13 +470,9 @@ impl Parser {
     fn and(&mut self) -> Result<Expr, ParserError> {
         let mut expr = self.equality()?;
         while self.exact(&[TokenKind::And]) {
-            let operator = self.previous();
-            let right = self.equality()?.into();
-            expr = Expr::Logical {
-                left: expr.into(),
-                operator,
-                right,
-            };
+            let operator = self.previous().clone();
+            let right = self.equality()?;
+            expr = Expr::new_logical(expr, operator, right);
         }
         Ok(expr)
     }
@@ -372,14 +480,15 @@ impl Parser {
     fn equality(&mut self) -> Result<Expr, ParserError> {
         let mut expr = self.comparison()?;
 
-        while self.exact(&[TokenKind::BangEqual, TokenKind::EqualEqual]) {
-            let operator = self.previous();
+        while self.exact(&[
+            TokenKind::BangEqual,
+            TokenKind::EqualEqual,
+            TokenKind::BangEqualEqual,
+            TokenKind::EqualEqualEqual,
+        ]) {
+            let operator = self.previous().clone();
             let right = self.comparison()?;
-            expr = Expr::Binary {
-                left: expr.into(),
-                operator,
-                right: right.into(),
-            };
+            expr = Expr::new_binary(expr, operator, right);
         }
 
         Ok(expr)
@@ -394,13 +503,9 @@ impl Parser {
             TokenKind::Less,
             TokenKind::LessEqual,
         ]) {
-            let operator = self.previous();
+            let operator = self.previous().clone();
             let right = self.term()?;
-            expr = Expr::Binary {
-                left: expr.into(),
-                operator,
-                right: right.into(),
-            };
+            expr = Expr::new_binary(expr, operator, right);
         }
 
         Ok(expr)
@@ -410,13 +515,9 @@ impl Parser {
         let mut expr = self.factor()?;
 
         while self.exact(&[TokenKind::Minus, TokenKind::Plus]) {
-            let operator = self.previous();
+            let operator = self.previous().clone();
             let right = self.factor()?;
-            expr = Expr::Binary {
-                left: expr.into(),
-                operator,
-                right: right.into(),
-            };
+            expr = Expr::new_binary(expr, operator, right);
         }
 
         Ok(expr)
@@ -426,13 +527,9 @@ impl Parser {
         let mut expr = self.unary()?;
 
         while self.exact(&[TokenKind::Slash, TokenKind::Star]) {
-            let operator = self.previous();
+            let operator = self.previous().clone();
             let right = self.unary()?;
-            expr = Expr::Binary {
-                left: expr.into(),
-                operator,
-                right: right.into(),
-            };
+            expr = Expr::new_binary(expr, operator, right);
         }
 
         Ok(expr)
@@ -440,12 +537,9 @@ impl Parser {
 
     fn unary(&mut self) -> Result<Expr, ParserError> {
         if self.exact(&[TokenKind::Bang, TokenKind::Minus]) {
-            let operator = self.previous();
+            let operator = self.previous().clone();
             let right = self.unary()?;
-            Ok(Expr::Unary {
-                operator,
-                right: right.into(),
-            })
+            Ok(Expr::new_unary(operator, right))
         } else {
             self.call()
         }
@@ -459,10 +553,7 @@ impl Parser {
             } else if self.exact(&[TokenKind::Dot]) {
                 let name =
                     self.consume(TokenKind::Identifier, "Expect property name after '.'.")?;
-                expr = Expr::Get {
-                    object: expr.into(),
-                    name,
-                };
+                expr = Expr::new_get(expr, name);
             } else {
                 break;
             }
@@ -476,7 +567,7 @@ impl Parser {
             loop {
                 if arguments.len() >= 255 {
                     return Err(ParserError {
-                        token: self.peek(),
+                        token: self.peek().clone(),
                         message: "Can't have more than 255 arguments.".into(),
                     });
                 } // TODO! Report but don't print error
@@ -487,11 +578,7 @@ impl Parser {
             }
         }
         let paren = self.consume(TokenKind::RightParen, "Expect ')' after arguments.")?;
-        Ok(Expr::Call {
-            callee: callee.into(),
-            paren,
-            arguments,
-        })
+        Ok(Expr::new_call(callee, paren, arguments))
     }
 
     fn primary(&mut self) -> Result<Expr, ParserError> {
@@ -503,27 +590,35 @@ impl Parser {
             TokenKind::String,
         ]) {
             Ok(Expr::Literal {
-                value: self.previous(),
+                value: self.previous().clone(),
             })
         } else if self.exact(&[TokenKind::LeftParen]) {
             let expr = self.expression()?;
             self.consume(TokenKind::RightParen, "Expect ')' after expression.")?;
-            Ok(Expr::Grouping {
-                expression: expr.into(),
-            })
+            Ok(Expr::new_grouping(expr))
         } else if self.exact(&[TokenKind::Super]) {
-            let keyword = self.previous();
+            let keyword = self.previous().clone();
             self.consume(TokenKind::Dot, "Expect '.' after 'super'.")?;
             let method = self.consume(TokenKind::Identifier, "Expect superclass method name.")?;
-            Ok(Expr::Super { keyword, method })
+            Ok(Expr::Super {
+                id: NodeId::next(),
+                keyword,
+                method,
+            })
         } else if self.exact(&[TokenKind::This]) {
             Ok(Expr::This {
-                keyword: self.previous(),
+                id: NodeId::next(),
+                keyword: self.previous().clone(),
             })
         } else if self.exact(&[TokenKind::Identifier]) {
             Ok(Expr::Variable {
-                name: self.previous(),
+                id: NodeId::next(),
+                name: self.previous().clone(),
             })
+        } else if self.exact(&[TokenKind::Class]) {
+            let keyword = self.previous().clone();
+            let (superclass, methods, statics) = self.class_body()?;
+            Ok(Expr::new_class(keyword, superclass, methods, statics))
         } else {
             Err(parser_error(self.peek(), "Expect expression."))
         }
@@ -543,6 +638,8 @@ impl Parser {
                 | TokenKind::For
                 | TokenKind::If
                 | TokenKind::While
+                | TokenKind::Break
+                | TokenKind::Continue
                 | TokenKind::Print
                 | TokenKind::Return => return,
                 _ => self.advance(),
@@ -551,14 +648,9 @@ impl Parser {
     }
 }
 
-fn report(line: usize, wher: &str, message: &str) {
-    println!("[Line {}] Error {}: {}", line, wher, message);
-    // hadError = true;
-}
-fn parser_error(token: Token, message: &str) -> ParserError {
-    report(token.line, &format!("at '{}'", token.lexeme), message);
+fn parser_error(token: &Token, message: &str) -> ParserError {
     ParserError {
-        token,
+        token: token.clone(),
         message: message.to_string(),
     }
 }
@@ -568,9 +660,30 @@ pub struct ParserError {
     token: Token,
     message: String,
 }
+impl ParserError {
+    pub fn token(&self) -> &Token {
+        &self.token
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
 impl Display for ParserError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.message)
+        if self.token.kind == TokenKind::Eof {
+            write!(
+                f,
+                "[Line {}] Error at end: {}",
+                self.token.line, self.message
+            )
+        } else {
+            write!(
+                f,
+                "[Line {}] Error at '{}': {}",
+                self.token.line, self.token.lexeme, self.message
+            )
+        }
     }
 }
 impl Error for ParserError {}
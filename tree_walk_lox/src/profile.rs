@@ -0,0 +1,166 @@
+//! Per-function timing for `lox --profile`: how often each function, native
+//! and class was called, and how long the calls took, with and without the
+//! calls they made in turn.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    hooks::InterpreterHooks,
+    interpreter::{Interpreter, InterpreterError},
+    value::RuntimeValue,
+};
+
+/// Times the calls a script makes. Install a clone with
+/// `Interpreter::set_hooks` and read the times from this one afterwards.
+#[derive(Clone, Default)]
+pub struct Profiler(Arc<Mutex<Calls>>);
+
+#[derive(Default)]
+struct Calls {
+    // the calls that haven't returned yet, innermost last
+    stack: Vec<Frame>,
+    functions: BTreeMap<String, FunctionProfile>,
+    // exclusive time by the names of the calls that led to it, outermost
+    // first
+    stacks: BTreeMap<Vec<String>, Duration>,
+}
+
+struct Frame {
+    name: String,
+    start: Instant,
+    // how long the calls it made took
+    children: Duration,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn report(&self) -> ProfileReport {
+        let calls = self.0.lock().unwrap();
+        let mut functions = calls.functions.values().cloned().collect::<Vec<_>>();
+        functions.sort_by(|a, b| b.exclusive.cmp(&a.exclusive).then(a.name.cmp(&b.name)));
+        ProfileReport {
+            functions,
+            stacks: calls.stacks.clone(),
+        }
+    }
+}
+
+impl InterpreterHooks for Profiler {
+    fn on_function_enter(
+        &mut self,
+        _interpreter: &mut Interpreter,
+        name: &str,
+        _args: &[RuntimeValue],
+    ) {
+        self.0.lock().unwrap().stack.push(Frame {
+            name: name.to_string(),
+            start: Instant::now(),
+            children: Duration::ZERO,
+        });
+    }
+
+    fn on_function_exit(
+        &mut self,
+        _interpreter: &mut Interpreter,
+        _name: &str,
+        _result: &Result<RuntimeValue, InterpreterError>,
+    ) {
+        let mut calls = self.0.lock().unwrap();
+        let calls = &mut *calls;
+        let Some(frame) = calls.stack.pop() else {
+            return;
+        };
+        let inclusive = frame.start.elapsed();
+        let exclusive = inclusive.saturating_sub(frame.children);
+        if let Some(parent) = calls.stack.last_mut() {
+            parent.children += inclusive;
+        }
+
+        let mut path = calls
+            .stack
+            .iter()
+            .map(|it| it.name.clone())
+            .collect::<Vec<_>>();
+        // a recursive call's time is already part of the outermost one
+        let recursive = path.contains(&frame.name);
+        path.push(frame.name.clone());
+        *calls.stacks.entry(path).or_default() += exclusive;
+
+        let function = calls
+            .functions
+            .entry(frame.name.clone())
+            .or_insert_with(|| FunctionProfile {
+                name: frame.name,
+                ..FunctionProfile::default()
+            });
+        function.calls += 1;
+        function.exclusive += exclusive;
+        if !recursive {
+            function.inclusive += inclusive;
+        }
+    }
+}
+
+/// The calls to one function, or native or class, by its name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FunctionProfile {
+    pub name: String,
+    pub calls: u64,
+    /// with the calls it made
+    pub inclusive: Duration,
+    /// without the calls it made
+    pub exclusive: Duration,
+}
+
+/// What a `Profiler` measured.
+pub struct ProfileReport {
+    functions: Vec<FunctionProfile>,
+    stacks: BTreeMap<Vec<String>, Duration>,
+}
+
+impl ProfileReport {
+    /// The functions that were called, the ones that took longest on their
+    /// own first.
+    pub fn functions(&self) -> &[FunctionProfile] {
+        &self.functions
+    }
+
+    /// The time spent in each chain of calls as collapsed stacks, a line of
+    /// `outer;inner microseconds` for each, which flamegraph tools read.
+    pub fn to_collapsed(&self) -> String {
+        let mut collapsed = String::new();
+        for (path, time) in &self.stacks {
+            collapsed.push_str(&format!("{} {}\n", path.join(";"), time.as_micros()));
+        }
+        collapsed
+    }
+}
+
+impl Display for ProfileReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:>9} {:>12} {:>12}  function",
+            "calls", "inclusive", "exclusive"
+        )?;
+        for function in &self.functions {
+            writeln!(
+                f,
+                "{:>9} {:>12} {:>12}  {}",
+                function.calls,
+                format!("{:.3?}", function.inclusive),
+                format!("{:.3?}", function.exclusive),
+                function.name
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -1,16 +1,29 @@
-use std::collections::HashMap;
+use std::{error::Error, fmt::Display, ops::RangeInclusive};
+
+use rustc_hash::FxHashMap;
 
 use crate::{
-    ast::{Expr, FunctionStmt, Stmt},
+    ast::{Expr, FunctionStmt, NodeId, StaticField, Stmt},
     interpreter::Interpreter,
+    symbol::{self, Symbol},
     token::Token,
 };
 
 pub struct Resolver<'interp> {
     interpreter: &'interp mut Interpreter,
-    scopes: Vec<HashMap<String, bool>>,
+    scopes: Vec<FxHashMap<Symbol, Local>>,
     current_function: FunctionType,
     current_class: ClassType,
+    // the labels of the loops around the statement being resolved, in the
+    // current function
+    loops: Vec<Option<Token>>,
+    errors: Vec<ResolverError>,
+    warnings: Vec<Warning>,
+    references: Vec<Reference>,
+    // the first declaration of each global, and the names that weren't
+    // found in any local scope and so might refer to one
+    globals: FxHashMap<Symbol, Token>,
+    unresolved: Vec<Token>,
 }
 impl<'interp> Resolver<'interp> {
     pub fn new(interpreter: &'interp mut Interpreter) -> Self {
@@ -19,9 +32,41 @@ impl<'interp> Resolver<'interp> {
             scopes: vec![],
             current_function: FunctionType::None,
             current_class: ClassType::None,
+            loops: vec![],
+            errors: vec![],
+            warnings: vec![],
+            references: vec![],
+            globals: FxHashMap::default(),
+            unresolved: vec![],
+        }
+    }
+
+    /// What was found out about the statements resolved so far.
+    pub fn finish(self) -> Resolution {
+        let mut references = self.references;
+        for name in self.unresolved {
+            if let Some(declaration) = self.globals.get(&name.lexeme) {
+                references.push(Reference {
+                    name,
+                    declaration: declaration.clone(),
+                });
+            }
+        }
+        references.sort_by_key(|it| it.name.scanner_index);
+        Resolution {
+            errors: self.errors,
+            warnings: self.warnings,
+            references,
         }
     }
 
+    fn error(&mut self, token: &Token, message: &str) {
+        self.errors.push(ResolverError {
+            token: token.clone(),
+            message: message.to_string(),
+        });
+    }
+
     pub fn resolve(&mut self, statements: &[Stmt]) {
         for stmt in statements {
             self.resolve_stmt(stmt);
@@ -61,83 +106,138 @@ impl<'interp> Resolver<'interp> {
                     self.resolve_stmt(branch);
                 }
             }
+            Stmt::Breakpoint { .. } => {}
             Stmt::Print { expression } => {
                 self.resolve_expr(expression);
             }
-            Stmt::Return { value, .. } => {
+            Stmt::Return { keyword, value } => {
                 if self.current_function == FunctionType::None {
-                    todo!("Can't return from top-level code.");
+                    self.error(keyword, "Can't return from top-level code.");
                 }
-                if self.current_function == FunctionType::Initializer {
-                    todo!("Can't return a value from an initializer.");
+                if self.current_function == FunctionType::Initializer && value.is_some() {
+                    self.error(keyword, "Can't return a value from an initializer.");
                 }
                 if let Some(value) = value {
                     self.resolve_expr(value);
                 }
             }
-            Stmt::While { condition, body } => {
+            Stmt::While {
+                label,
+                condition,
+                body,
+                increment,
+            } => {
+                if let Some(label) = label {
+                    if self.find_loop(label) {
+                        self.error(label, "Already in a loop with this label.");
+                    }
+                }
                 self.resolve_expr(condition);
+                self.loops.push(label.clone());
                 self.resolve_stmt(body);
+                self.loops.pop();
+                if let Some(increment) = increment {
+                    self.resolve_expr(increment);
+                }
             }
+            Stmt::Break { keyword, label } | Stmt::Continue { keyword, label } => match label {
+                Some(label) if !self.find_loop(label) => {
+                    self.error(label, "No loop around this one has this label.");
+                }
+                None if self.loops.is_empty() => {
+                    let message = format!("Can't use '{}' outside of a loop.", keyword.lexeme);
+                    self.error(keyword, &message);
+                }
+                _ => {}
+            },
             Stmt::Class {
                 name,
                 superclass,
                 methods,
+                statics,
             } => {
-                let enclosing_class = self.current_class;
-                self.current_class = ClassType::Class;
-
                 self.declare(name);
                 self.define(name);
-
-                if let Some(superclass) = superclass {
-                    self.current_class = ClassType::Subclass;
-                    if name.lexeme == superclass.lexeme {
-                        todo!("A class can't inherit from itself.");
+                if let Some(Expr::Variable {
+                    name: superclass_name,
+                    ..
+                }) = superclass
+                {
+                    if name.lexeme == superclass_name.lexeme {
+                        self.error(superclass_name, "A class can't inherit from itself.");
                     }
-                    self.resolve_expr(&Expr::Variable {
-                        name: superclass.clone(),
-                    });
-
-                    self.begin_scope();
-                    self.scopes.last_mut().unwrap().insert("super".into(), true);
                 }
+                self.resolve_class(superclass.as_ref(), methods, statics);
+            }
+        }
+    }
 
-                self.begin_scope();
-                self.scopes.last_mut().unwrap().insert("this".into(), true);
-                for method in methods {
-                    let declaration = if method.name.lexeme == "init" {
-                        FunctionType::Initializer
-                    } else {
-                        FunctionType::Method
-                    };
-                    self.resolve_function(method, declaration);
-                }
-                self.end_scope();
+    // the superclass, methods and statics of a class declaration or
+    // expression
+    fn resolve_class(
+        &mut self,
+        superclass: Option<&Expr>,
+        methods: &[FunctionStmt],
+        statics: &[StaticField],
+    ) {
+        // statics are evaluated where the class is, outside of its methods
+        for field in statics {
+            self.resolve_expr(&field.value);
+        }
 
-                if superclass.is_some() {
-                    self.end_scope();
-                }
+        let enclosing_class = self.current_class;
+        self.current_class = ClassType::Class;
 
-                self.current_class = enclosing_class;
-            }
+        if let Some(superclass) = superclass {
+            self.current_class = ClassType::Subclass;
+            self.resolve_expr(superclass);
+
+            self.begin_scope();
+            self.scopes
+                .last_mut()
+                .unwrap()
+                .insert(*symbol::SUPER, Local::implicit());
         }
+
+        self.begin_scope();
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(*symbol::THIS, Local::implicit());
+        for method in methods {
+            let declaration = if method.name.lexeme == "init" {
+                FunctionType::Initializer
+            } else {
+                FunctionType::Method
+            };
+            self.resolve_function(method, declaration);
+        }
+        self.end_scope();
+
+        if superclass.is_some() {
+            self.end_scope();
+        }
+
+        self.current_class = enclosing_class;
     }
 
     fn resolve_expr(&mut self, expression: &Expr) {
         match expression {
-            Expr::Variable { name } => {
-                if let Some(false) = self.scopes.last().and_then(|it| it.get(&name.lexeme)) {
-                    todo!(
-                        "Can't read local variable in its own initializer. {}",
-                        name.line
-                    )
+            Expr::Variable { id, name } => {
+                if let Some(false) = self
+                    .scopes
+                    .last()
+                    .and_then(|it| it.get(&name.lexeme))
+                    .map(|it| it.defined)
+                {
+                    self.error(name, "Can't read local variable in its own initializer.");
                 }
-                self.resolve_local(expression, name);
+                self.mark_used(name);
+                self.resolve_local(*id, name);
             }
-            Expr::Assign { name, value } => {
+            Expr::Assign { id, name, value } => {
                 self.resolve_expr(value);
-                self.resolve_local(expression, name);
+                self.resolve_local(*id, name);
             }
             Expr::Call {
                 callee, arguments, ..
@@ -158,6 +258,12 @@ impl<'interp> Resolver<'interp> {
                 self.resolve_expr(expression);
             }
             Expr::Literal { .. } => {}
+            Expr::Class {
+                superclass,
+                methods,
+                statics,
+                ..
+            } => self.resolve_class(superclass.as_deref(), methods, statics),
             Expr::Logical { left, right, .. } => {
                 self.resolve_expr(left);
                 self.resolve_expr(right);
@@ -169,36 +275,55 @@ impl<'interp> Resolver<'interp> {
             Expr::Unary { right, .. } => {
                 self.resolve_expr(right);
             }
-            Expr::This { keyword } => {
+            Expr::This { id, keyword } => {
                 if self.current_class == ClassType::None {
-                    todo!("Can't use 'this' outside of a class. {}", keyword.line);
+                    self.error(keyword, "Can't use 'this' outside of a class.");
                 }
-                self.resolve_local(expression, keyword);
+                self.resolve_local(*id, keyword);
             }
-            Expr::Super { keyword, .. } => {
+            Expr::Super { id, keyword, .. } => {
                 if self.current_class == ClassType::None {
-                    todo!("Can't use 'super' outside of a class.");
+                    self.error(keyword, "Can't use 'super' outside of a class.");
                 } else if self.current_class != ClassType::Subclass {
-                    todo!("Can't use 'super' with no superclass.");
+                    self.error(keyword, "Can't use 'super' with no superclass.");
                 }
-                self.resolve_local(expression, keyword);
+                self.resolve_local(*id, keyword);
             }
         }
     }
 
-    fn resolve_local(&mut self, expression: &Expr, name: &Token) {
-        for (i, scope) in self.scopes.iter().enumerate() {
-            if scope.contains_key(&name.lexeme) {
-                self.interpreter
-                    .resolve(expression, self.scopes.len() - 1 - i);
+    fn resolve_local(&mut self, id: NodeId, name: &Token) {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if let Some(local) = scope.get(&name.lexeme) {
+                if let Some(declaration) = &local.declaration {
+                    self.references.push(Reference {
+                        name: name.clone(),
+                        declaration: declaration.clone(),
+                    });
+                }
+                self.interpreter.resolve(id, depth, local.slot);
                 return;
             }
         }
+        self.unresolved.push(name.clone());
+    }
+
+    fn mark_used(&mut self, name: &Token) {
+        if let Some(local) = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(&name.lexeme))
+        {
+            local.used = true;
+        }
     }
 
     fn resolve_function(&mut self, fun: &FunctionStmt, kind: FunctionType) {
         let enclosing_function = self.current_function;
         self.current_function = kind;
+        // a function's body can't leave the loops around its declaration
+        let enclosing_loops = std::mem::take(&mut self.loops);
 
         self.begin_scope();
         for param in &fun.params {
@@ -208,30 +333,177 @@ impl<'interp> Resolver<'interp> {
         self.resolve(&fun.body);
         self.end_scope();
 
+        self.loops = enclosing_loops;
         self.current_function = enclosing_function;
     }
 
+    // whether a loop around the current statement has the label
+    fn find_loop(&self, label: &Token) -> bool {
+        self.loops
+            .iter()
+            .flatten()
+            .any(|it| it.lexeme == label.lexeme)
+    }
+
     fn begin_scope(&mut self) {
-        self.scopes.push(HashMap::new());
+        self.scopes.push(FxHashMap::default());
     }
 
     fn end_scope(&mut self) {
-        self.scopes.pop();
+        let scope = self.scopes.pop().unwrap_or_default();
+        let mut unused = scope
+            .into_values()
+            .filter(|it| !it.used)
+            .filter_map(|it| it.declaration)
+            .filter(|it| !it.lexeme.starts_with('_'))
+            .collect::<Vec<_>>();
+        unused.sort_by_key(|it| it.scanner_index);
+        for token in unused {
+            let line = token.line;
+            self.warnings
+                .push(Warning::new(token, line, "Unused variable."));
+        }
     }
 
     fn declare(&mut self, name: &Token) {
-        if let Some(scope) = self.scopes.last_mut() {
-            if scope.contains_key(&name.lexeme) {
-                todo!("Already variable with this name in this scope.");
+        match self.scopes.last_mut() {
+            Some(scope) => {
+                if scope.contains_key(&name.lexeme) {
+                    self.error(name, "Already a variable with this name in this scope.");
+                    return;
+                }
+                let slot = scope.len();
+                scope.insert(name.lexeme, Local::declared(name, slot));
+            }
+            None => {
+                self.globals
+                    .entry(name.lexeme)
+                    .or_insert_with(|| name.clone());
             }
-            scope.insert(name.lexeme.clone(), false);
         }
     }
 
     fn define(&mut self, name: &Token) {
-        if let Some(scope) = self.scopes.last_mut() {
-            scope.insert(name.lexeme.clone(), true);
+        if let Some(local) = self
+            .scopes
+            .last_mut()
+            .and_then(|scope| scope.get_mut(&name.lexeme))
+        {
+            local.defined = true;
+        }
+    }
+}
+
+struct Local {
+    // `None` for `this` and `super`, which are never reported as unused
+    declaration: Option<Token>,
+    // where the interpreter puts it in the scope's environment
+    slot: usize,
+    defined: bool,
+    used: bool,
+}
+impl Local {
+    fn declared(name: &Token, slot: usize) -> Self {
+        Self {
+            declaration: Some(name.clone()),
+            slot,
+            defined: false,
+            used: false,
+        }
+    }
+
+    // `this` and `super` have a scope of their own, in the first slot
+    fn implicit() -> Self {
+        Self {
+            declaration: None,
+            slot: 0,
+            defined: true,
+            used: true,
+        }
+    }
+}
+
+/// Everything the resolver found out about a script.
+pub struct Resolution {
+    /// Mistakes that make the script invalid, like returning from top-level
+    /// code.
+    pub errors: Vec<ResolverError>,
+    pub warnings: Vec<Warning>,
+    /// Every variable that's read or assigned and the declaration it refers
+    /// to, in source order. Names that aren't declared anywhere are left out.
+    pub references: Vec<Reference>,
+}
+
+/// A use of a variable and where the variable was declared.
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub name: Token,
+    pub declaration: Token,
+}
+
+#[derive(Debug)]
+pub struct ResolverError {
+    token: Token,
+    message: String,
+}
+impl ResolverError {
+    pub fn token(&self) -> &Token {
+        &self.token
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+impl Display for ResolverError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[Line {}] Error at '{}': {}",
+            self.token.line, self.token.lexeme, self.message
+        )
+    }
+}
+impl Error for ResolverError {}
+
+/// Something suspicious that doesn't stop the script from running.
+#[derive(Debug)]
+pub struct Warning {
+    token: Token,
+    end_line: usize,
+    message: String,
+}
+impl Warning {
+    pub(crate) fn new(token: Token, end_line: usize, message: &str) -> Self {
+        Self {
+            token,
+            end_line,
+            message: message.to_string(),
+        }
+    }
+
+    /// The first token of the code the warning is about.
+    pub fn token(&self) -> &Token {
+        &self.token
+    }
+
+    /// The lines the code the warning is about spans.
+    pub fn lines(&self) -> RangeInclusive<usize> {
+        self.token.line..=self.end_line
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.end_line > self.token.line {
+            write!(f, "[Lines {}-{}]", self.token.line, self.end_line)?;
+        } else {
+            write!(f, "[Line {}]", self.token.line)?;
         }
+        write!(f, " Warning at '{}': {}", self.token.lexeme, self.message)
     }
 }
 
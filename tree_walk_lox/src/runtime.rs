@@ -0,0 +1,260 @@
+//! What the language does with values, for the interpreter and for the Rust
+//! code `lox transpile` writes. Operations that need the interpreter, for
+//! its globals or its output, take it last, so the generated code can pass
+//! it after evaluating the other arguments, which may need it too.
+
+use crate::{
+    interpreter::{Interpreter, InterpreterError},
+    shared::{Cell, Gc, MaybeSync},
+    stdlib,
+    symbol::Symbol,
+    token::{Token, TokenKind},
+    value::{BuiltInFunction, RuntimeValue},
+};
+
+/// A local variable of transpiled code. Closures share it with the scope
+/// they're defined in by holding a clone.
+#[derive(Clone)]
+pub struct Var(Gc<Cell<RuntimeValue>>);
+
+impl Var {
+    pub fn new(value: RuntimeValue) -> Self {
+        Self(Gc::new(value.into()))
+    }
+
+    pub fn get(&self) -> RuntimeValue {
+        self.0.borrow().clone()
+    }
+
+    /// Returns the value, since an assignment evaluates to it.
+    pub fn set(&self, value: RuntimeValue) -> RuntimeValue {
+        *self.0.borrow_mut() = value.clone();
+        value
+    }
+}
+
+pub fn number(value: f64) -> RuntimeValue {
+    RuntimeValue::Float(value)
+}
+
+pub fn string(value: &str) -> RuntimeValue {
+    RuntimeValue::Str(value.into())
+}
+
+// big integers that reach here, because the result isn't a whole number,
+// are rounded to floats
+fn float(value: &RuntimeValue) -> Option<f64> {
+    match value {
+        RuntimeValue::Float(x) => Some(*x),
+        RuntimeValue::BigInt(x) => Some(x.to_f64()),
+        _ => None,
+    }
+}
+
+fn numbers(
+    left: &RuntimeValue,
+    right: &RuntimeValue,
+    op: impl FnOnce(f64, f64) -> RuntimeValue,
+) -> Result<RuntimeValue, InterpreterError> {
+    match (float(left), float(right)) {
+        (Some(l), Some(r)) => Ok(op(l, r)),
+        _ => Err(InterpreterError::OperandsMustBeNumbers),
+    }
+}
+
+pub fn add(left: &RuntimeValue, right: &RuntimeValue) -> Result<RuntimeValue, InterpreterError> {
+    if let (Some(l), Some(r)) = (float(left), float(right)) {
+        return Ok(RuntimeValue::Float(l + r));
+    }
+    match (left, right) {
+        (RuntimeValue::Str(l), RuntimeValue::Str(r)) => {
+            let s = l.to_string() + r;
+            Ok(RuntimeValue::Str(s.as_str().into()))
+        }
+        _ => Err(InterpreterError::OperandsMustBeNumbersOrStr),
+    }
+}
+
+pub fn subtract(
+    left: &RuntimeValue,
+    right: &RuntimeValue,
+) -> Result<RuntimeValue, InterpreterError> {
+    numbers(left, right, |l, r| RuntimeValue::Float(l - r))
+}
+
+pub fn multiply(
+    left: &RuntimeValue,
+    right: &RuntimeValue,
+) -> Result<RuntimeValue, InterpreterError> {
+    numbers(left, right, |l, r| RuntimeValue::Float(l * r))
+}
+
+pub fn divide(left: &RuntimeValue, right: &RuntimeValue) -> Result<RuntimeValue, InterpreterError> {
+    numbers(left, right, |l, r| RuntimeValue::Float(l / r))
+}
+
+pub fn greater(
+    left: &RuntimeValue,
+    right: &RuntimeValue,
+) -> Result<RuntimeValue, InterpreterError> {
+    numbers(left, right, |l, r| RuntimeValue::Bool(l > r))
+}
+
+pub fn greater_equal(
+    left: &RuntimeValue,
+    right: &RuntimeValue,
+) -> Result<RuntimeValue, InterpreterError> {
+    numbers(left, right, |l, r| RuntimeValue::Bool(l >= r))
+}
+
+pub fn less(left: &RuntimeValue, right: &RuntimeValue) -> Result<RuntimeValue, InterpreterError> {
+    numbers(left, right, |l, r| RuntimeValue::Bool(l < r))
+}
+
+pub fn less_equal(
+    left: &RuntimeValue,
+    right: &RuntimeValue,
+) -> Result<RuntimeValue, InterpreterError> {
+    numbers(left, right, |l, r| RuntimeValue::Bool(l <= r))
+}
+
+pub fn equal(left: &RuntimeValue, right: &RuntimeValue) -> RuntimeValue {
+    RuntimeValue::Bool(left.equals(right))
+}
+
+pub fn not_equal(left: &RuntimeValue, right: &RuntimeValue) -> RuntimeValue {
+    RuntimeValue::Bool(!left.equals(right))
+}
+
+/// `==` with loose equality, where a string that reads as a number equals
+/// that number.
+pub fn loosely_equal(left: &RuntimeValue, right: &RuntimeValue) -> bool {
+    match (left, right) {
+        (RuntimeValue::Str(s), number @ (RuntimeValue::Float(_) | RuntimeValue::BigInt(_)))
+        | (number @ (RuntimeValue::Float(_) | RuntimeValue::BigInt(_)), RuntimeValue::Str(s)) => {
+            stdlib::parse_number(s).is_some_and(|it| number.equals(&RuntimeValue::Float(it)))
+        }
+        _ => left.equals(right),
+    }
+}
+
+pub fn negate(value: &RuntimeValue) -> Result<RuntimeValue, InterpreterError> {
+    match value {
+        RuntimeValue::Float(f) => Ok(RuntimeValue::Float(-f)),
+        RuntimeValue::BigInt(x) => Ok(RuntimeValue::BigInt(x.negate())),
+        v => Err(InterpreterError::UnaryMinusOperandMustBeNumber(v.clone())),
+    }
+}
+
+pub fn not(value: &RuntimeValue) -> RuntimeValue {
+    RuntimeValue::Bool(!value.is_truthy())
+}
+
+// errors point at tokens, transpiled code only has the names
+fn token(name: &str) -> Token {
+    Token {
+        kind: TokenKind::Identifier,
+        lexeme: Symbol::intern(name),
+        literal: RuntimeValue::Nil,
+        line: 0,
+        column: 0,
+        scanner_index: 0,
+        length: 0,
+    }
+}
+
+/// A function of transpiled code. It's a native function to the
+/// interpreter, since it has no syntax tree to run.
+pub fn function(
+    name: &str,
+    params: &[&str],
+    body: impl Fn(&mut Interpreter, Vec<RuntimeValue>) -> Result<RuntimeValue, InterpreterError>
+        + MaybeSync
+        + 'static,
+) -> RuntimeValue {
+    RuntimeValue::BuiltInFunction(BuiltInFunction::new(name, params.to_vec(), body))
+}
+
+pub fn call(
+    callee: &RuntimeValue,
+    arguments: Vec<RuntimeValue>,
+    lox: &mut Interpreter,
+) -> Result<RuntimeValue, InterpreterError> {
+    let callable = callee
+        .as_callable()
+        .ok_or_else(|| InterpreterError::NotCallable(callee.clone()))?;
+    if !callable.accepts(arguments.len()) {
+        return Err(InterpreterError::arity(
+            token(callable.name()),
+            callable,
+            arguments.len(),
+        ));
+    }
+    callable.call(lox, arguments)
+}
+
+pub fn get_property(object: &RuntimeValue, name: &str) -> Result<RuntimeValue, InterpreterError> {
+    let property = match object {
+        RuntimeValue::Instance(instance) => instance.get(name),
+        RuntimeValue::List(list) => stdlib::list_method(list, name),
+        RuntimeValue::Map(map) => stdlib::map_method(map, name),
+        RuntimeValue::Str(s) => stdlib::string_method(s, name),
+        RuntimeValue::Float(_) | RuntimeValue::BigInt(_) => stdlib::number_method(object, name),
+        _ => return Err(InterpreterError::MustAccessValueOnInstances),
+    };
+    property.ok_or_else(|| InterpreterError::UndefinedProperty(token(name)))
+}
+
+pub fn set_property(
+    object: &RuntimeValue,
+    name: &str,
+    value: RuntimeValue,
+) -> Result<RuntimeValue, InterpreterError> {
+    match object {
+        RuntimeValue::Instance(instance) if instance.is_frozen() => Err(
+            InterpreterError::FrozenInstance(token(name), object.type_name().to_string()),
+        ),
+        RuntimeValue::Instance(instance) => {
+            instance.set(name, value.clone());
+            Ok(value)
+        }
+        _ => Err(InterpreterError::MustAccessValueOnInstances),
+    }
+}
+
+pub fn global(name: &str, lox: &Interpreter) -> Result<RuntimeValue, InterpreterError> {
+    lox.get_global(name)
+        .ok_or_else(|| InterpreterError::UndefinedVariable(token(name)))
+}
+
+pub fn define(name: &str, value: RuntimeValue, lox: &mut Interpreter) {
+    lox.define_global(name, value);
+}
+
+pub fn assign(
+    name: &str,
+    value: RuntimeValue,
+    lox: &mut Interpreter,
+) -> Result<RuntimeValue, InterpreterError> {
+    match lox.globals().assign(Symbol::intern(name), value.clone()) {
+        Some(_) => Ok(value),
+        None => Err(InterpreterError::UndefinedVariable(token(name))),
+    }
+}
+
+pub fn print(value: &RuntimeValue, lox: &mut Interpreter) {
+    lox.print_line(&value.to_string());
+}
+
+/// Ends a transpiled program the way the interpreter ends a script.
+pub fn finish(result: Result<(), InterpreterError>, lox: &mut Interpreter) {
+    lox.end_line();
+    match result {
+        Ok(()) => {}
+        Err(InterpreterError::Exit(code)) => std::process::exit(code),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(70);
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use std::fmt::Display;
+
+/// Limits for running untrusted scripts, see `Interpreter::new_with_config`.
+/// The default configuration has no limits but keeps scripts away from the
+/// host, `unrestricted` allows everything.
+#[derive(Clone, Debug, Default)]
+pub struct SandboxConfig {
+    /// statements and expressions evaluated in a single call to `interpret`
+    pub max_steps: Option<u64>,
+    /// environments alive at once. every block and function call creates one
+    pub max_environments: Option<usize>,
+    /// names of the builtins to define, or all of them if `None`
+    pub allowed_builtins: Option<Vec<String>>,
+    /// whether scripts can read and write files
+    pub allow_files: bool,
+    /// whether scripts can read environment variables
+    pub allow_env: bool,
+    /// whether scripts can stop the interpreter with an exit code
+    pub allow_exit: bool,
+}
+
+impl SandboxConfig {
+    pub fn unrestricted() -> Self {
+        Self {
+            allow_files: true,
+            allow_env: true,
+            allow_exit: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn allows_builtin(&self, name: &str) -> bool {
+        match &self.allowed_builtins {
+            Some(allowed) => allowed.iter().any(|it| it == name),
+            None => true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    Steps(u64),
+    Environments(usize),
+}
+impl Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Limit::Steps(max) => write!(f, "{} steps", max),
+            Limit::Environments(max) => write!(f, "{} environments", max),
+        }
+    }
+}
@@ -1,258 +1,90 @@
-use lazy_static::lazy_static;
-use std::{collections::hash_map::HashMap, error::Error, fmt::Display};
-
+use crate::symbol::Symbol;
 use crate::token::{Token, TokenKind};
 use crate::value::RuntimeValue;
 
-lazy_static! {
-    static ref RESERVED_WORDS: HashMap<String, TokenKind> = {
-        let mut m = HashMap::new();
-        m.insert("and".into(), TokenKind::And);
-        m.insert("class".into(), TokenKind::Class);
-        m.insert("else".into(), TokenKind::Else);
-        m.insert("false".into(), TokenKind::False);
-        m.insert("for".into(), TokenKind::For);
-        m.insert("fun".into(), TokenKind::Fun);
-        m.insert("if".into(), TokenKind::If);
-        m.insert("nil".into(), TokenKind::Nil);
-        m.insert("or".into(), TokenKind::Or);
-        m.insert("print".into(), TokenKind::Print);
-        m.insert("return".into(), TokenKind::Return);
-        m.insert("super".into(), TokenKind::Super);
-        m.insert("this".into(), TokenKind::This);
-        m.insert("true".into(), TokenKind::True);
-        m.insert("var".into(), TokenKind::Var);
-        m.insert("while".into(), TokenKind::While);
-        m
-    };
-}
+pub use lox_frontend::ScanError;
 
+/// Splits the source into tokens as they're asked for, ending with an `Eof`
+/// token, or with the first error. The tokens are `lox_frontend`'s, with the
+/// values of their literals. Positions are byte offsets into the source, so
+/// a token's lexeme is the slice `scanner_index..` of its length, except for
+/// identifiers, whose lexemes are in Unicode's NFC so names that look the
+/// same are the same.
 pub struct Scanner {
-    source: Vec<char>,
-    tokens: Vec<Token>,
-
-    start: usize,
-    current: usize,
-    line: usize,
+    inner: lox_frontend::Scanner<String>,
+    finished: bool,
 }
 
 impl Scanner {
     pub fn new(source: String) -> Self {
         Self {
-            source: source.chars().collect(),
-            tokens: Vec::new(),
-            start: 0,
-            current: 0,
-            line: 1,
-        }
-    }
-
-    pub fn scan_tokens(mut self) -> Result<Vec<Token>, ScanError> {
-        while !self.is_at_end() {
-            self.start = self.current;
-            self.scan_token()?;
-        }
-        self.tokens.push(Token {
-            kind: TokenKind::Eof,
-            lexeme: "".into(),
-            literal: RuntimeValue::Nil,
-            line: self.line,
-            scanner_index: self.start,
-        });
-        Ok(self.tokens)
-    }
-
-    fn is_at_end(&self) -> bool {
-        self.current >= self.source.len()
-    }
-
-    fn scan_token(&mut self) -> Result<(), ScanError> {
-        let c = self.advance();
-        match c {
-            '(' => self.add_token(TokenKind::LeftParen),
-            ')' => self.add_token(TokenKind::RightParen),
-            '{' => self.add_token(TokenKind::LeftBrace),
-            '}' => self.add_token(TokenKind::RightBrace),
-            ',' => self.add_token(TokenKind::Comma),
-            '.' => self.add_token(TokenKind::Dot),
-            '-' => self.add_token(TokenKind::Minus),
-            '+' => self.add_token(TokenKind::Plus),
-            ';' => self.add_token(TokenKind::Semicolon),
-            '*' => self.add_token(TokenKind::Star),
-            '!' => {
-                let kind = if self.match_lookahead('=') {
-                    TokenKind::BangEqual
-                } else {
-                    TokenKind::Bang
-                };
-                self.add_token(kind)
-            }
-            '=' => {
-                let kind = if self.match_lookahead('=') {
-                    TokenKind::EqualEqual
-                } else {
-                    TokenKind::Equal
-                };
-                self.add_token(kind)
-            }
-            '<' => {
-                let kind = if self.match_lookahead('=') {
-                    TokenKind::LessEqual
-                } else {
-                    TokenKind::Less
-                };
-                self.add_token(kind)
-            }
-            '>' => {
-                let kind = if self.match_lookahead('=') {
-                    TokenKind::GreaterEqual
-                } else {
-                    TokenKind::Greater
-                };
-                self.add_token(kind)
-            }
-            '/' => {
-                if self.match_lookahead('/') {
-                    while self.peek() != '\n' && !self.is_at_end() {
-                        self.advance();
-                    }
-                } else {
-                    self.add_token(TokenKind::Slash);
-                }
-            }
-            ' ' | '\r' | '\t' => {}
-            '\n' => self.line += 1,
-            '"' => self.string()?,
-            c if c.is_digit(10) => self.number(),
-            c if c == '_' || c.is_alphabetic() => self.identifier(),
-            c => return Err(ScanError::UnexpectedCharacter(c, self.line)),
+            inner: lox_frontend::Scanner::new(source),
+            finished: false,
         }
-        Ok(())
     }
 
-    fn match_lookahead(&mut self, expected: char) -> bool {
-        if self.is_at_end() {
-            return false;
-        }
-        if self.source[self.current] != expected {
-            return false;
-        }
-
-        self.current += 1;
-        true
+    /// Scans `print` as the keyword of the book's print statement, instead
+    /// of the name of the native.
+    pub fn with_compat_jlox(mut self) -> Self {
+        self.inner = self.inner.with_print_keyword();
+        self
     }
 
-    fn advance(&mut self) -> char {
-        let result = self.source[self.current as usize];
-        self.current += 1;
-        result
-    }
-
-    fn peek(&self) -> char {
-        if self.is_at_end() {
-            '\0'
-        } else {
-            self.source[self.current]
-        }
+    /// Also produces a `Comment` token for each comment. The parser doesn't
+    /// expect them, these tokens are for tools that reproduce the source.
+    pub fn with_comments(mut self) -> Self {
+        self.inner = self.inner.with_comments();
+        self
     }
 
-    fn peek_next(&self) -> char {
-        if self.current + 1 >= self.source.len() {
-            '\0'
-        } else {
-            self.source[self.current + 1]
-        }
+    /// Scans all of the source at once, for tools that go over the tokens
+    /// themselves instead of parsing them.
+    pub fn scan_tokens(self) -> Result<Vec<Token>, ScanError> {
+        self.collect()
     }
 
-    fn add_token(&mut self, kind: TokenKind) {
-        let value = match kind {
+    fn make_token(&self, token: lox_frontend::Token) -> Token {
+        let lexeme = token.lexeme(self.inner.source());
+        let literal = match token.kind {
             TokenKind::True => RuntimeValue::Bool(true),
             TokenKind::False => RuntimeValue::Bool(false),
+            // the quotes are one byte each
+            TokenKind::String => RuntimeValue::Str(lexeme[1..lexeme.len() - 1].into()),
+            TokenKind::Number => {
+                RuntimeValue::Float(lexeme.parse().expect("Couldn't parse number"))
+            }
             _ => RuntimeValue::Nil,
         };
-        self.add_literal_token(kind, value);
-    }
-
-    fn add_literal_token(&mut self, kind: TokenKind, literal: RuntimeValue) {
-        let text: String = self.source[self.start..self.current].iter().collect();
-        self.tokens.push(Token {
-            kind,
-            lexeme: text,
+        let lexeme = if token.kind == TokenKind::Identifier {
+            Symbol::intern(&lox_frontend::identifier_name(lexeme))
+        } else {
+            Symbol::intern(lexeme)
+        };
+        Token {
+            kind: token.kind,
+            lexeme,
             literal,
-            line: self.line,
-            scanner_index: self.start,
-        });
-    }
-
-    fn string(&mut self) -> Result<(), ScanError> {
-        while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
-            }
-            self.advance();
+            line: token.line,
+            column: token.column,
+            scanner_index: token.span.start,
+            length: token.span.len(),
         }
-        if self.is_at_end() {
-            return Err(ScanError::UnterminatedString(self.line));
-        }
-        self.advance();
-        let value: String = self.source[self.start + 1..self.current - 1]
-            .iter()
-            .cloned()
-            .collect();
-        self.add_literal_token(TokenKind::String, RuntimeValue::Str(value.as_str().into()));
-        Ok(())
     }
+}
 
-    fn number(&mut self) {
-        while self.peek().is_digit(10) {
-            self.advance();
-        }
-        if self.peek() == '.' && self.peek_next().is_digit(10) {
-            self.advance();
-            while self.peek().is_digit(10) {
-                self.advance();
-            }
-        }
-        let value: f64 = self.source[self.start..self.current]
-            .iter()
-            .cloned()
-            .collect::<String>()
-            .parse()
-            .expect("Couldn't parse number");
-        self.add_literal_token(TokenKind::Number, RuntimeValue::Float(value));
-    }
+impl Iterator for Scanner {
+    type Item = Result<Token, ScanError>;
 
-    fn identifier(&mut self) {
-        while self.peek().is_alphanumeric() {
-            self.advance();
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
         }
-        let text = self.source[self.start..self.current]
-            .iter()
-            .cloned()
-            .collect::<String>();
-        let kind = RESERVED_WORDS
-            .get(&text)
-            .copied()
-            .unwrap_or(TokenKind::Identifier);
-        self.add_token(kind);
-    }
-}
-
-#[derive(Debug)]
-pub enum ScanError {
-    UnexpectedCharacter(char, usize),
-    UnterminatedString(usize),
-}
-impl Display for ScanError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            ScanError::UnexpectedCharacter(c, line) => {
-                write!(f, "[Line {}] Unexpected character '{}'.", line, c)
-            }
-            ScanError::UnterminatedString(line) => {
-                write!(f, "[Line {}] Unterminated string.", line)
+        match self.inner.next()? {
+            Ok(token) => Some(Ok(self.make_token(token))),
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
             }
         }
     }
 }
-impl Error for ScanError {}
@@ -0,0 +1,89 @@
+//! Running a script a statement at a time, for hosts that drive the
+//! interpreter themselves, like a game loop that gives scripts a slice of
+//! each frame, or several scripts taking turns on one thread.
+//!
+//! A step is a whole top-level statement, with the functions it calls and
+//! the loops in it, since the interpreter keeps where it is in those on the
+//! Rust stack. Hosts that need to stop inside them can install hooks, like
+//! the debugger does.
+
+use std::time::Instant;
+
+use crate::{
+    ast::Stmt,
+    interpreter::{Interpreter, InterpreterError},
+    LoxError,
+};
+
+/// What a call to `ExecutionSession::step` ran.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    /// of the statement, counting the script's top-level statements from 0
+    pub index: usize,
+    /// where the statement starts, `None` for an empty block
+    pub line: Option<usize>,
+}
+
+/// A script that runs a top-level statement each time it's stepped. The
+/// interpreter is passed to each step, and shouldn't run anything else
+/// between them that the script's variables could clash with.
+pub struct ExecutionSession {
+    statements: Vec<Stmt>,
+    next: usize,
+}
+
+impl ExecutionSession {
+    /// Scans, parses and resolves `source` for `interpreter`, without running
+    /// any of it.
+    pub fn new(interpreter: &mut Interpreter, source: String) -> Result<Self, LoxError> {
+        Ok(Self {
+            statements: crate::prepare_source(interpreter, source)?,
+            next: 0,
+        })
+    }
+
+    /// Whether every statement ran, or one of them failed.
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.statements.len()
+    }
+
+    /// Runs the next statement, or returns `None` if the session is
+    /// finished. An error finishes it, the statements after the one that
+    /// failed don't run.
+    pub fn step(
+        &mut self,
+        interpreter: &mut Interpreter,
+    ) -> Option<Result<Step, InterpreterError>> {
+        let index = self.next;
+        let stmt = self.statements.get(index)?;
+        let step = Step {
+            index,
+            line: stmt.line(),
+        };
+        self.next += 1;
+        let result = interpreter.interpret_one(stmt, index == 0);
+        if result.is_err() {
+            self.next = self.statements.len();
+        } else if self.is_finished() {
+            interpreter.end_line();
+        }
+        Some(result.map(|()| step))
+    }
+
+    /// Steps until the script finishes or `deadline` passes, returning
+    /// whether it finished. A statement that starts before the deadline
+    /// runs to its end, however long it takes.
+    pub fn run_until(
+        &mut self,
+        interpreter: &mut Interpreter,
+        deadline: Instant,
+    ) -> Result<bool, InterpreterError> {
+        while Instant::now() < deadline {
+            match self.step(interpreter) {
+                Some(result) => result?,
+                None => return Ok(true),
+            };
+        }
+        Ok(self.is_finished())
+    }
+}
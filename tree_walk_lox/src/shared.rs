@@ -0,0 +1,82 @@
+//! The pointer and the cell that values and environments share their state
+//! through. The interpreter only ever runs on one thread, so by default they
+//! are `Rc` and `RefCell`. The `sync` feature makes them `Arc` and `Mutex`,
+//! for hosts that send values to other threads.
+
+#[cfg(not(feature = "sync"))]
+mod imp {
+    use std::cell::RefCell;
+
+    pub type Gc<T> = std::rc::Rc<T>;
+    pub type Weak<T> = std::rc::Weak<T>;
+
+    /// What natives have to be to live in a value: anything.
+    pub trait MaybeSync {}
+    impl<T: ?Sized> MaybeSync for T {}
+    pub type CellRef<'a, T> = std::cell::Ref<'a, T>;
+    pub type CellRefMut<'a, T> = std::cell::RefMut<'a, T>;
+
+    #[derive(Debug, Default)]
+    pub struct Cell<T>(RefCell<T>);
+
+    impl<T> Cell<T> {
+        pub fn new(value: T) -> Self {
+            Self(RefCell::new(value))
+        }
+        pub fn borrow(&self) -> CellRef<'_, T> {
+            self.0.borrow()
+        }
+        pub fn borrow_mut(&self) -> CellRefMut<'_, T> {
+            self.0.borrow_mut()
+        }
+        pub fn try_borrow(&self) -> Option<CellRef<'_, T>> {
+            self.0.try_borrow().ok()
+        }
+        pub fn try_borrow_mut(&self) -> Option<CellRefMut<'_, T>> {
+            self.0.try_borrow_mut().ok()
+        }
+    }
+}
+
+#[cfg(feature = "sync")]
+mod imp {
+    use std::sync::{Mutex, MutexGuard};
+
+    pub type Gc<T> = std::sync::Arc<T>;
+    pub type Weak<T> = std::sync::Weak<T>;
+
+    /// What natives have to be to live in a value: `Send` and `Sync`.
+    pub trait MaybeSync: Send + Sync {}
+    impl<T: Send + Sync + ?Sized> MaybeSync for T {}
+    pub type CellRef<'a, T> = MutexGuard<'a, T>;
+    pub type CellRefMut<'a, T> = MutexGuard<'a, T>;
+
+    #[derive(Debug, Default)]
+    pub struct Cell<T>(Mutex<T>);
+
+    impl<T> Cell<T> {
+        pub fn new(value: T) -> Self {
+            Self(Mutex::new(value))
+        }
+        pub fn borrow(&self) -> CellRef<'_, T> {
+            self.0.lock().unwrap()
+        }
+        pub fn borrow_mut(&self) -> CellRefMut<'_, T> {
+            self.0.lock().unwrap()
+        }
+        pub fn try_borrow(&self) -> Option<CellRef<'_, T>> {
+            self.0.try_lock().ok()
+        }
+        pub fn try_borrow_mut(&self) -> Option<CellRefMut<'_, T>> {
+            self.0.try_lock().ok()
+        }
+    }
+}
+
+pub use imp::*;
+
+impl<T> From<T> for Cell<T> {
+    fn from(value: T) -> Self {
+        Cell::new(value)
+    }
+}
@@ -0,0 +1,19 @@
+//! Natives that make the cycle collector observable from scripts, for tests
+//! and benchmarks. The heap it manages is counted in environments.
+
+use lox_proc_macros::lox_native;
+
+use crate::interpreter::Interpreter;
+
+// collects now instead of waiting for environments to pile up, and returns
+// how many it freed
+#[lox_native(name = "gcCollect")]
+fn gc_collect(interpreter: &mut Interpreter) -> f64 {
+    interpreter.collect_garbage() as f64
+}
+
+// how many environments are alive, including the global one
+#[lox_native(name = "gcHeapSize")]
+fn gc_heap_size(interpreter: &mut Interpreter) -> f64 {
+    interpreter.live_environments() as f64
+}
@@ -0,0 +1,89 @@
+//! Natives for instances, for what property access can't do, and for
+//! looking at classes and instances from scripts.
+
+use lox_proc_macros::lox_native;
+
+use super::error;
+use crate::{
+    interpreter::{Interpreter, InterpreterError},
+    shared::Gc,
+    value::{CallableValue, ClassDefinition, ClassInstance, List, RuntimeValue},
+};
+
+// the names scripts can see. natives aren't methods of the class, so the
+// private ones, starting with `_`, are left out, except in jlox which has no
+// such convention
+fn names(interpreter: &Interpreter, names: impl IntoIterator<Item = String>) -> List {
+    List::new(
+        names
+            .into_iter()
+            .filter(|name| !is_private(interpreter, name))
+            .map(|name| RuntimeValue::Str(name.into()))
+            .collect(),
+    )
+}
+
+fn is_private(interpreter: &Interpreter, name: &str) -> bool {
+    !interpreter.compat_jlox() && name.starts_with('_')
+}
+
+// lets long-lived instances, like ones used as caches, forget fields. true
+// if the instance had the field
+#[lox_native(name = "deleteField")]
+fn delete_field(
+    interpreter: &mut Interpreter,
+    instance: ClassInstance,
+    name: Gc<str>,
+) -> Result<bool, InterpreterError> {
+    if is_private(interpreter, &name) {
+        return Err(error(
+            "deleteField",
+            format!(
+                "'{}' is private to class {}, use it through 'this' in its methods.",
+                name,
+                instance.class().name()
+            ),
+        ));
+    }
+    if instance.is_frozen() {
+        return Err(error(
+            "deleteField",
+            format!(
+                "Can't delete '{}' from a frozen instance of {}.",
+                name,
+                instance.class().name()
+            ),
+        ));
+    }
+    Ok(instance.remove(&name).is_some())
+}
+
+// makes the instance's fields read-only for the rest of the script, and
+// returns it, so `var config = freeze(Config());` works. fields that hold
+// instances can still change those
+#[lox_native]
+fn freeze(instance: ClassInstance) -> ClassInstance {
+    instance.freeze();
+    instance
+}
+
+#[lox_native(name = "classOf")]
+fn class_of(instance: ClassInstance) -> ClassDefinition {
+    instance.class().clone()
+}
+
+// including inherited ones, sorted, so a script can check an instance has
+// the methods it needs before using it
+#[lox_native]
+fn methods(interpreter: &mut Interpreter, class: ClassDefinition) -> List {
+    names(interpreter, class.method_names())
+}
+
+// the fields the instance has now, sorted. methods aren't fields
+#[lox_native]
+fn fields(interpreter: &mut Interpreter, instance: ClassInstance) -> List {
+    names(
+        interpreter,
+        instance.fields().into_iter().map(|(name, _)| name),
+    )
+}
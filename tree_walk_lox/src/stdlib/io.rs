@@ -0,0 +1,67 @@
+use std::fs;
+
+use lox_proc_macros::lox_native;
+
+use super::{check_allowed, error};
+use crate::{
+    interpreter::{Interpreter, InterpreterError},
+    shared::Gc,
+    value::{BuiltInFunction, RuntimeValue},
+};
+
+// the values separated by spaces
+fn join(
+    interpreter: &mut Interpreter,
+    values: &[RuntimeValue],
+) -> Result<String, InterpreterError> {
+    let values = values
+        .iter()
+        .map(|value| interpreter.stringify(value))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(values.join(" "))
+}
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    // `print` leaves the line open for what's printed next
+    let print = BuiltInFunction::variadic("print", vec![], |interpreter, args| {
+        let text = join(interpreter, &args)?;
+        interpreter.write(&text);
+        Ok(RuntimeValue::Nil)
+    });
+    interpreter.define_builtin(print);
+    let println = BuiltInFunction::variadic("println", vec![], |interpreter, args| {
+        let text = join(interpreter, &args)?;
+        interpreter.print_line(&text);
+        Ok(RuntimeValue::Nil)
+    });
+    interpreter.define_builtin(println);
+}
+
+#[lox_native(name = "readFile")]
+fn read_file(interpreter: &mut Interpreter, path: Gc<str>) -> Result<String, InterpreterError> {
+    check_allowed(interpreter.config().allow_files, "readFile", "files")?;
+    fs::read_to_string(&*path)
+        .map_err(|e| error("readFile", format!("Could not read '{}': {}.", path, e)))
+}
+
+// nil at the end of input. a prompt left on the line by `print` goes out
+// first
+#[lox_native(name = "readLine")]
+fn read_line(interpreter: &mut Interpreter) -> Result<Option<String>, InterpreterError> {
+    interpreter.end_line();
+    interpreter
+        .input()
+        .read_line()
+        .map_err(|e| error("readLine", format!("Could not read input: {}.", e)))
+}
+
+#[lox_native(name = "writeFile")]
+fn write_file(
+    interpreter: &mut Interpreter,
+    path: Gc<str>,
+    contents: Gc<str>,
+) -> Result<(), InterpreterError> {
+    check_allowed(interpreter.config().allow_files, "writeFile", "files")?;
+    fs::write(&*path, contents.as_bytes())
+        .map_err(|e| error("writeFile", format!("Could not write '{}': {}.", path, e)))
+}
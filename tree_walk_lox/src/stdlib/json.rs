@@ -0,0 +1,345 @@
+use std::{fmt::Write, iter::Peekable, str::CharIndices};
+
+use indexmap::IndexMap;
+
+use super::{error, native, string};
+use crate::{
+    interpreter::Interpreter,
+    shared::Gc,
+    value::{List, Map, MapKey, RuntimeValue},
+};
+
+// deeper than this is most likely a list or map that contains itself
+const MAX_DEPTH: usize = 512;
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    // objects become maps, arrays lists and null nil
+    native(interpreter, "jsonParse", vec!["text"], |_, args| {
+        let text = string("jsonParse", &args, 0)?;
+        Parser::new(&text)
+            .parse()
+            .map_err(|message| error("jsonParse", message))
+    });
+    // instances are written as objects with their fields
+    native(interpreter, "jsonStringify", vec!["value"], |_, args| {
+        let mut out = String::new();
+        stringify(&args[0], &mut out, 0).map_err(|message| error("jsonStringify", message))?;
+        Ok(RuntimeValue::Str(out.into()))
+    });
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            chars: text.char_indices().peekable(),
+            depth: 0,
+        }
+    }
+
+    fn parse(mut self) -> Result<RuntimeValue, String> {
+        let value = self.value()?;
+        self.skip_whitespace();
+        match self.chars.peek() {
+            None => Ok(value),
+            Some(_) => Err(self.unexpected()),
+        }
+    }
+
+    // "line 2, column 5" of the next character
+    fn position(&mut self) -> String {
+        let offset = self.chars.peek().map_or(self.text.len(), |(i, _)| *i);
+        let before = &self.text[..offset];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+        format!("line {}, column {}", line, column)
+    }
+
+    fn unexpected(&mut self) -> String {
+        match self.chars.peek() {
+            Some((_, c)) => {
+                let c = *c;
+                format!("Unexpected '{}' at {}.", c, self.position())
+            }
+            None => "Unexpected end of text.".to_string(),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some((_, ' ' | '\t' | '\n' | '\r')) = self.chars.peek() {
+            self.chars.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some((_, c)) if *c == expected => {
+                self.chars.next();
+                Ok(())
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn value(&mut self) -> Result<RuntimeValue, String> {
+        self.skip_whitespace();
+        match self.chars.peek().map(|(_, c)| *c) {
+            Some('{') => self.nested(Self::object),
+            Some('[') => self.nested(Self::array),
+            Some('"') => Ok(RuntimeValue::Str(self.string()?.into())),
+            Some('-' | '0'..='9') => self.number(),
+            Some('t') => self.word("true", RuntimeValue::Bool(true)),
+            Some('f') => self.word("false", RuntimeValue::Bool(false)),
+            Some('n') => self.word("null", RuntimeValue::Nil),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<RuntimeValue, String>,
+    ) -> Result<RuntimeValue, String> {
+        if self.depth == MAX_DEPTH {
+            return Err(format!("Text is nested too deeply at {}.", self.position()));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn object(&mut self) -> Result<RuntimeValue, String> {
+        self.expect('{')?;
+        let mut entries = IndexMap::new();
+        self.skip_whitespace();
+        if let Some((_, '}')) = self.chars.peek() {
+            self.chars.next();
+            return Ok(RuntimeValue::Map(Map::new(entries)));
+        }
+        loop {
+            self.skip_whitespace();
+            if !matches!(self.chars.peek(), Some((_, '"'))) {
+                return Err(self.unexpected());
+            }
+            let key = self.string()?;
+            self.expect(':')?;
+            let value = self.value()?;
+            entries.insert(MapKey::Str(key.into()), value);
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => continue,
+                Some((_, '}')) => return Ok(RuntimeValue::Map(Map::new(entries))),
+                _ => return Err(self.unexpected()),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<RuntimeValue, String> {
+        self.expect('[')?;
+        let mut items = vec![];
+        self.skip_whitespace();
+        if let Some((_, ']')) = self.chars.peek() {
+            self.chars.next();
+            return Ok(RuntimeValue::List(List::new(items)));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => continue,
+                Some((_, ']')) => return Ok(RuntimeValue::List(List::new(items))),
+                _ => return Err(self.unexpected()),
+            }
+        }
+    }
+
+    fn word(&mut self, word: &str, value: RuntimeValue) -> Result<RuntimeValue, String> {
+        for expected in word.chars() {
+            match self.chars.peek() {
+                Some((_, c)) if *c == expected => {
+                    self.chars.next();
+                }
+                _ => return Err(self.unexpected()),
+            }
+        }
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<RuntimeValue, String> {
+        let start = self.chars.peek().map_or(0, |(i, _)| *i);
+        let mut end = start;
+        while let Some((i, c)) = self.chars.peek() {
+            if c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E') {
+                end = i + c.len_utf8();
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+        let text = &self.text[start..end];
+        text.parse()
+            .map(RuntimeValue::Float)
+            .map_err(|_| format!("Invalid number '{}' before {}.", text, self.position()))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut out = String::new();
+        loop {
+            match self.chars.next() {
+                Some((_, '"')) => return Ok(out),
+                Some((_, '\\')) => {
+                    let escaped = match self.chars.next() {
+                        Some((_, '"')) => '"',
+                        Some((_, '\\')) => '\\',
+                        Some((_, '/')) => '/',
+                        Some((_, 'b')) => '\u{8}',
+                        Some((_, 'f')) => '\u{c}',
+                        Some((_, 'n')) => '\n',
+                        Some((_, 'r')) => '\r',
+                        Some((_, 't')) => '\t',
+                        Some((_, 'u')) => self.unicode_escape()?,
+                        _ => return Err(format!("Invalid escape before {}.", self.position())),
+                    };
+                    out.push(escaped);
+                }
+                Some((_, c)) if (c as u32) < 0x20 => {
+                    return Err(format!(
+                        "Control character in string before {}.",
+                        self.position()
+                    ))
+                }
+                Some((_, c)) => out.push(c),
+                None => return Err("Unterminated string.".to_string()),
+            }
+        }
+    }
+
+    // after the '\u'. characters outside the basic plane are written as two
+    // escapes, a surrogate pair
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let first = self.hex4()?;
+        let code = if (0xD800..0xDC00).contains(&first) {
+            if self.chars.next().map(|(_, c)| c) != Some('\\')
+                || self.chars.next().map(|(_, c)| c) != Some('u')
+            {
+                return Err(format!("Unpaired surrogate before {}.", self.position()));
+            }
+            let second = self.hex4()?;
+            if !(0xDC00..0xE000).contains(&second) {
+                return Err(format!("Unpaired surrogate before {}.", self.position()));
+            }
+            0x10000 + ((first - 0xD800) << 10) + (second - 0xDC00)
+        } else {
+            first
+        };
+        char::from_u32(code).ok_or_else(|| format!("Invalid escape before {}.", self.position()))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self
+                .chars
+                .next()
+                .and_then(|(_, c)| c.to_digit(16))
+                .ok_or_else(|| format!("Invalid escape before {}.", self.position()))?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+}
+
+fn stringify(value: &RuntimeValue, out: &mut String, depth: usize) -> Result<(), String> {
+    if depth > MAX_DEPTH {
+        return Err("Value is nested too deeply, it might contain itself.".to_string());
+    }
+    match value {
+        RuntimeValue::Nil => out.push_str("null"),
+        RuntimeValue::Bool(x) => write!(out, "{}", x).unwrap(),
+        RuntimeValue::Float(x) if x.is_finite() => write!(out, "{}", x).unwrap(),
+        RuntimeValue::Float(x) => return Err(format!("JSON has no number {}.", x)),
+        RuntimeValue::BigInt(x) => write!(out, "{}", x).unwrap(),
+        RuntimeValue::Str(s) => quote(s, out),
+        RuntimeValue::List(list) => {
+            out.push('[');
+            for (i, item) in list.items().iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                stringify(item, out, depth + 1)?;
+            }
+            out.push(']');
+        }
+        RuntimeValue::Map(map) => {
+            let mut entries = vec![];
+            for (key, value) in map.entries() {
+                match key {
+                    MapKey::Str(key) => entries.push((key, value)),
+                    other => {
+                        return Err(format!(
+                            "JSON object keys must be strings, but found {}.",
+                            other.to_value()
+                        ))
+                    }
+                }
+            }
+            object(entries, out, depth)?;
+        }
+        RuntimeValue::Instance(instance) => {
+            let fields = instance
+                .fields()
+                .into_iter()
+                .map(|(name, value)| (name.into(), value))
+                .collect();
+            object(fields, out, depth)?;
+        }
+        RuntimeValue::BuiltInFunction(_)
+        | RuntimeValue::UserFunction(_)
+        | RuntimeValue::Class(_) => {
+            return Err(format!("Can't convert {} to JSON.", value));
+        }
+    }
+    Ok(())
+}
+
+fn object(
+    entries: Vec<(Gc<str>, RuntimeValue)>,
+    out: &mut String,
+    depth: usize,
+) -> Result<(), String> {
+    out.push('{');
+    for (i, (key, value)) in entries.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        quote(key, out);
+        out.push(':');
+        stringify(value, out, depth + 1)?;
+    }
+    out.push('}');
+    Ok(())
+}
+
+fn quote(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
@@ -0,0 +1,237 @@
+//! Natives on lists. The ones that take a list first can also be called as
+//! methods on it: `items.push(1)` is `push(items, 1)`. The ones that make
+//! sense for maps too hand them over to `map.rs`.
+
+use std::cmp::Ordering;
+
+use super::{bind_method, describe, error, list, native, whole, Methods};
+use crate::{
+    interpreter::{Interpreter, InterpreterError},
+    value::{compare_numbers, CallableValue, List, RuntimeValue},
+};
+
+// natives that take the list as their first argument
+const METHODS: Methods = &[
+    ("len", &["collection"], len),
+    ("get", &["collection", "key"], get),
+    ("set", &["collection", "key", "value"], set),
+    ("push", &["list", "value"], push),
+    ("pop", &["list"], pop),
+    ("insert", &["list", "index", "value"], insert),
+    ("remove", &["collection", "key"], remove),
+    ("contains", &["collection", "value"], contains),
+    ("sort", &["list"], sort),
+    ("map", &["list", "function"], map),
+    ("filter", &["list", "function"], filter),
+    ("reduce", &["list", "function", "initial"], reduce),
+];
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    native(interpreter, "list", vec![], |_, _| {
+        Ok(RuntimeValue::List(List::default()))
+    });
+    for (name, args, function) in METHODS {
+        native(interpreter, name, args.to_vec(), *function);
+    }
+}
+
+/// The native `name` with `list` as its first argument, for `list.name`.
+pub(crate) fn method(list: &List, name: &str) -> Option<RuntimeValue> {
+    bind_method(METHODS, RuntimeValue::List(list.clone()), name)
+}
+
+fn out_of_range(function: &str, index: usize, len: usize) -> InterpreterError {
+    error(
+        function,
+        format!(
+            "Index {} is out of range for a list of {} items.",
+            index, len
+        ),
+    )
+}
+
+/// The argument at `index` as a function that takes `arity` arguments.
+fn function<'a>(
+    name: &str,
+    args: &'a [RuntimeValue],
+    index: usize,
+    arity: usize,
+) -> Result<&'a dyn CallableValue, InterpreterError> {
+    match args[index].as_callable() {
+        Some(callable) if callable.arity() == arity => Ok(callable),
+        _ => Err(error(
+            name,
+            format!(
+                "Argument {} must be a function taking {} argument{}, but was {}.",
+                index + 1,
+                arity,
+                if arity == 1 { "" } else { "s" },
+                describe(&args[index])
+            ),
+        )),
+    }
+}
+
+// also counts the characters of strings, so it isn't only a list method
+fn len(_: &mut Interpreter, args: Vec<RuntimeValue>) -> Result<RuntimeValue, InterpreterError> {
+    match &args[0] {
+        RuntimeValue::Str(s) => Ok(RuntimeValue::Float(s.chars().count() as f64)),
+        RuntimeValue::List(list) => Ok(RuntimeValue::Float(list.len() as f64)),
+        RuntimeValue::Map(map) => Ok(RuntimeValue::Float(map.len() as f64)),
+        other => Err(error(
+            "len",
+            format!(
+                "Argument 1 must be a string, list or map, but was {}.",
+                describe(other)
+            ),
+        )),
+    }
+}
+
+fn get(
+    interpreter: &mut Interpreter,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, InterpreterError> {
+    if let RuntimeValue::Map(_) = &args[0] {
+        return super::map::get(interpreter, args);
+    }
+    let list = list("get", &args, 0)?;
+    let index = whole("get", &args, 1)?;
+    list.get(index)
+        .ok_or_else(|| out_of_range("get", index, list.len()))
+}
+
+fn set(
+    interpreter: &mut Interpreter,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, InterpreterError> {
+    if let RuntimeValue::Map(_) = &args[0] {
+        return super::map::set(interpreter, args);
+    }
+    let list = list("set", &args, 0)?;
+    let index = whole("set", &args, 1)?;
+    let mut items = list.borrow_mut();
+    let len = items.len();
+    let item = items
+        .get_mut(index)
+        .ok_or_else(|| out_of_range("set", index, len))?;
+    *item = args[2].clone();
+    Ok(args[2].clone())
+}
+
+fn push(_: &mut Interpreter, args: Vec<RuntimeValue>) -> Result<RuntimeValue, InterpreterError> {
+    let list = list("push", &args, 0)?;
+    list.borrow_mut().push(args[1].clone());
+    Ok(RuntimeValue::Nil)
+}
+
+fn pop(_: &mut Interpreter, args: Vec<RuntimeValue>) -> Result<RuntimeValue, InterpreterError> {
+    let list = list("pop", &args, 0)?;
+    let item = list.borrow_mut().pop();
+    item.ok_or_else(|| error("pop", "Can't pop from an empty list.".into()))
+}
+
+fn insert(_: &mut Interpreter, args: Vec<RuntimeValue>) -> Result<RuntimeValue, InterpreterError> {
+    let list = list("insert", &args, 0)?;
+    let index = whole("insert", &args, 1)?;
+    let mut items = list.borrow_mut();
+    // inserting right after the last item is allowed
+    if index > items.len() {
+        return Err(out_of_range("insert", index, items.len()));
+    }
+    items.insert(index, args[2].clone());
+    Ok(RuntimeValue::Nil)
+}
+
+fn remove(
+    interpreter: &mut Interpreter,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, InterpreterError> {
+    if let RuntimeValue::Map(_) = &args[0] {
+        return super::map::remove(interpreter, args);
+    }
+    let list = list("remove", &args, 0)?;
+    let index = whole("remove", &args, 1)?;
+    let mut items = list.borrow_mut();
+    if index >= items.len() {
+        return Err(out_of_range("remove", index, items.len()));
+    }
+    Ok(items.remove(index))
+}
+
+fn contains(
+    interpreter: &mut Interpreter,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, InterpreterError> {
+    if let RuntimeValue::Map(_) = &args[0] {
+        return super::map::contains(interpreter, args);
+    }
+    let list = list("contains", &args, 0)?;
+    let found = list.borrow_mut().iter().any(|item| item.equals(&args[1]));
+    Ok(RuntimeValue::Bool(found))
+}
+
+// sorts in place. only lists of all numbers or all strings have an order
+fn sort(_: &mut Interpreter, args: Vec<RuntimeValue>) -> Result<RuntimeValue, InterpreterError> {
+    let list = list("sort", &args, 0)?;
+    let mut items = list.borrow_mut();
+    let comparable = items
+        .iter()
+        .all(|it| matches!(it, RuntimeValue::Float(_) | RuntimeValue::BigInt(_)))
+        || items.iter().all(|it| matches!(it, RuntimeValue::Str(_)));
+    if !comparable {
+        return Err(error(
+            "sort",
+            "Can only sort lists of numbers or lists of strings.".into(),
+        ));
+    }
+    items.sort_by(|a, b| match (a, b) {
+        (RuntimeValue::Str(a), RuntimeValue::Str(b)) => a.cmp(b),
+        (a, b) => compare_numbers(a, b).unwrap_or(Ordering::Equal),
+    });
+    Ok(RuntimeValue::Nil)
+}
+
+// the callbacks below get a copy of the items, so they can change the list
+// they are called on without affecting the iteration.
+
+fn map(
+    interpreter: &mut Interpreter,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, InterpreterError> {
+    let list = list("map", &args, 0)?;
+    let function = function("map", &args, 1, 1)?;
+    let mut mapped = vec![];
+    for item in list.items() {
+        mapped.push(function.call(interpreter, vec![item])?);
+    }
+    Ok(RuntimeValue::List(List::new(mapped)))
+}
+
+fn filter(
+    interpreter: &mut Interpreter,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, InterpreterError> {
+    let list = list("filter", &args, 0)?;
+    let function = function("filter", &args, 1, 1)?;
+    let mut kept = vec![];
+    for item in list.items() {
+        if function.call(interpreter, vec![item.clone()])?.is_truthy() {
+            kept.push(item);
+        }
+    }
+    Ok(RuntimeValue::List(List::new(kept)))
+}
+
+fn reduce(
+    interpreter: &mut Interpreter,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, InterpreterError> {
+    let list = list("reduce", &args, 0)?;
+    let function = function("reduce", &args, 1, 2)?;
+    let mut accumulator = args[2].clone();
+    for item in list.items() {
+        accumulator = function.call(interpreter, vec![accumulator, item])?;
+    }
+    Ok(accumulator)
+}
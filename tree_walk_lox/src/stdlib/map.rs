@@ -0,0 +1,152 @@
+//! Natives on maps. Like the list ones, they can be called as methods:
+//! `config.get("name")` is `get(config, "name")`.
+
+use super::{bind_method, describe, error, key, map, native, Methods};
+use crate::{
+    interpreter::{Interpreter, InterpreterError},
+    runtime,
+    value::{InstanceKey, List, Map, MapKey, RuntimeValue},
+};
+
+// `get`, `set`, `remove`, `contains` and `len` are defined once for lists
+// and maps together, in `list.rs`, which calls the functions here for maps.
+const METHODS: Methods = &[
+    ("len", &["map"], len),
+    ("get", &["map", "key"], get),
+    ("set", &["map", "key", "value"], set),
+    ("remove", &["map", "key"], remove),
+    ("contains", &["map", "key"], contains),
+    ("keys", &["map"], keys),
+    ("values", &["map"], values),
+];
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    native(interpreter, "newMap", vec![], |_, _| {
+        Ok(RuntimeValue::Map(Map::default()))
+    });
+    native(interpreter, "keys", vec!["map"], keys);
+    native(interpreter, "values", vec!["map"], values);
+}
+
+/// The native `name` with `map` as its first argument, for `map.name`.
+pub(crate) fn method(map: &Map, name: &str) -> Option<RuntimeValue> {
+    bind_method(METHODS, RuntimeValue::Map(map.clone()), name)
+}
+
+// the argument at `index` as a key of `map`. an instance can be one if its
+// class has `hash()` and `equals()` methods, and is the same key as an
+// equal instance that's already in the map. changing an instance in a way
+// that changes its hash while it's a key loses it
+fn map_key(
+    interpreter: &mut Interpreter,
+    map: &Map,
+    function: &str,
+    args: &[RuntimeValue],
+    index: usize,
+) -> Result<MapKey, InterpreterError> {
+    let instance = match &args[index] {
+        RuntimeValue::Instance(instance) => instance,
+        _ => return key(function, args, index),
+    };
+    let class = instance.class();
+    let (hash, equals) = match (class.find_method("hash"), class.find_method("equals")) {
+        (Some(hash), Some(equals)) => (hash, equals),
+        _ => {
+            let message = format!(
+                "Argument {} must have hash() and equals() methods to be a map key, but was {}.",
+                index + 1,
+                describe(&args[index])
+            );
+            return Err(error(function, message));
+        }
+    };
+    let hash = RuntimeValue::UserFunction(hash.bind(instance));
+    let hash = match runtime::call(&hash, vec![], interpreter)? {
+        RuntimeValue::Float(hash) => hash,
+        other => {
+            return Err(error(
+                function,
+                format!(
+                    "hash() must return a number, but returned {}.",
+                    describe(&other)
+                ),
+            ))
+        }
+    };
+    // the keys are copied out, since equals() could change the map
+    let candidates = map
+        .borrow_mut()
+        .keys()
+        .filter_map(|key| match key {
+            MapKey::Instance(key) if key.has_hash(hash) => Some(key.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let equals = RuntimeValue::UserFunction(equals.bind(instance));
+    for candidate in candidates {
+        let other = RuntimeValue::Instance(candidate.instance().clone());
+        if runtime::call(&equals, vec![other], interpreter)?.is_truthy() {
+            return Ok(MapKey::Instance(candidate));
+        }
+    }
+    Ok(MapKey::Instance(InstanceKey::new(hash, instance.clone())))
+}
+
+fn len(_: &mut Interpreter, args: Vec<RuntimeValue>) -> Result<RuntimeValue, InterpreterError> {
+    let map = map("len", &args, 0)?;
+    Ok(RuntimeValue::Float(map.len() as f64))
+}
+
+// nil for keys that aren't in the map
+pub(super) fn get(
+    interpreter: &mut Interpreter,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, InterpreterError> {
+    let map = map("get", &args, 0)?;
+    let key = map_key(interpreter, &map, "get", &args, 1)?;
+    Ok(map.get(&key).unwrap_or(RuntimeValue::Nil))
+}
+
+pub(super) fn set(
+    interpreter: &mut Interpreter,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, InterpreterError> {
+    let map = map("set", &args, 0)?;
+    let key = map_key(interpreter, &map, "set", &args, 1)?;
+    map.insert(key, args[2].clone());
+    Ok(args[2].clone())
+}
+
+// returns the removed value, or nil if the key wasn't there
+pub(super) fn remove(
+    interpreter: &mut Interpreter,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, InterpreterError> {
+    let map = map("remove", &args, 0)?;
+    let key = map_key(interpreter, &map, "remove", &args, 1)?;
+    let removed = map.borrow_mut().shift_remove(&key);
+    Ok(removed.unwrap_or(RuntimeValue::Nil))
+}
+
+// whether the map has the key, not the value
+pub(super) fn contains(
+    interpreter: &mut Interpreter,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, InterpreterError> {
+    let map = map("contains", &args, 0)?;
+    let key = map_key(interpreter, &map, "contains", &args, 1)?;
+    let found = map.borrow_mut().contains_key(&key);
+    Ok(RuntimeValue::Bool(found))
+}
+
+fn keys(_: &mut Interpreter, args: Vec<RuntimeValue>) -> Result<RuntimeValue, InterpreterError> {
+    let map = map("keys", &args, 0)?;
+    let keys = map.borrow_mut().keys().map(|key| key.to_value()).collect();
+    Ok(RuntimeValue::List(List::new(keys)))
+}
+
+fn values(_: &mut Interpreter, args: Vec<RuntimeValue>) -> Result<RuntimeValue, InterpreterError> {
+    let map = map("values", &args, 0)?;
+    let values = map.borrow_mut().values().cloned().collect();
+    Ok(RuntimeValue::List(List::new(values)))
+}
@@ -0,0 +1,64 @@
+//! The natives that take a number first can also be called as methods on
+//! it: `(3.7).floor()` is `floor(3.7)`.
+
+use std::f64::consts;
+
+use lox_proc_macros::lox_native;
+
+use super::bind_registered;
+use crate::{interpreter::Interpreter, value::RuntimeValue};
+
+// `#[lox_native]`s that take the number as their first argument
+const NATIVES: &[&str] = &["sqrt", "abs", "floor", "ceil", "round", "min", "max", "pow"];
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    interpreter.define_global("PI", RuntimeValue::Float(consts::PI));
+    interpreter.define_global("E", RuntimeValue::Float(consts::E));
+}
+
+/// The native `name` with `number` as its first argument, for
+/// `number.name`.
+pub(crate) fn method(number: &RuntimeValue, name: &str) -> Option<RuntimeValue> {
+    bind_registered(NATIVES, number.clone(), name)
+}
+
+#[lox_native]
+fn sqrt(x: f64) -> f64 {
+    x.sqrt()
+}
+
+#[lox_native]
+fn abs(x: f64) -> f64 {
+    x.abs()
+}
+
+#[lox_native]
+fn floor(x: f64) -> f64 {
+    x.floor()
+}
+
+#[lox_native]
+fn ceil(x: f64) -> f64 {
+    x.ceil()
+}
+
+// halfway cases round away from zero
+#[lox_native]
+fn round(x: f64) -> f64 {
+    x.round()
+}
+
+#[lox_native]
+fn min(a: f64, b: f64) -> f64 {
+    a.min(b)
+}
+
+#[lox_native]
+fn max(a: f64, b: f64) -> f64 {
+    a.max(b)
+}
+
+#[lox_native]
+fn pow(base: f64, exponent: f64) -> f64 {
+    base.powf(exponent)
+}
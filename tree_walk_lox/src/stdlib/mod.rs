@@ -0,0 +1,379 @@
+//! Native functions available to every script. Each module defines its
+//! natives through `Interpreter::define_builtin`, so the ones a sandbox
+//! doesn't allow are left out. Natives written with `#[lox_native]` are
+//! collected in a registry and defined along with the rest.
+
+use crate::{
+    interpreter::{Interpreter, InterpreterError},
+    shared::Gc,
+    value::{BuiltInFunction, ClassDefinition, ClassInstance, List, Map, MapKey, RuntimeValue},
+};
+
+mod gc;
+mod instance;
+mod io;
+mod json;
+mod list;
+mod map;
+mod math;
+mod process;
+mod random;
+mod string;
+mod test;
+mod time;
+mod types;
+
+pub(crate) use list::method as list_method;
+pub(crate) use map::method as map_method;
+pub(crate) use math::method as number_method;
+pub(crate) use random::Rng;
+pub(crate) use string::method as string_method;
+pub(crate) use types::parse_number;
+
+pub(crate) fn define_all(interpreter: &mut Interpreter) {
+    // sorted, since the registry's order depends on how the crate was linked
+    let mut registered = inventory::iter::<Registered>().collect::<Vec<_>>();
+    registered.sort_by_key(|it| it.name);
+    for it in registered {
+        native(interpreter, it.name, it.args.to_vec(), it.function);
+    }
+    io::define(interpreter);
+    json::define(interpreter);
+    list::define(interpreter);
+    map::define(interpreter);
+    math::define(interpreter);
+    process::define(interpreter);
+    random::define(interpreter);
+    test::define(interpreter);
+    time::define(interpreter);
+}
+
+type Native = fn(&mut Interpreter, Vec<RuntimeValue>) -> Result<RuntimeValue, InterpreterError>;
+
+fn native(interpreter: &mut Interpreter, name: &str, args: Vec<&str>, native: Native) {
+    interpreter.define_builtin(BuiltInFunction::new(name, args, native));
+}
+
+/// A native written with `#[lox_native]`, which submits one of these for
+/// its wrapper.
+pub(crate) struct Registered {
+    pub name: &'static str,
+    pub args: &'static [&'static str],
+    pub function: Native,
+}
+
+inventory::collect!(Registered);
+
+/// What a parameter of a `#[lox_native]` can be, converted from the
+/// argument at `index`.
+pub(crate) trait Arg: Sized {
+    fn from_arg(
+        function: &str,
+        args: &[RuntimeValue],
+        index: usize,
+    ) -> Result<Self, InterpreterError>;
+}
+
+impl Arg for RuntimeValue {
+    fn from_arg(_: &str, args: &[RuntimeValue], index: usize) -> Result<Self, InterpreterError> {
+        Ok(args[index].clone())
+    }
+}
+
+impl Arg for f64 {
+    fn from_arg(
+        function: &str,
+        args: &[RuntimeValue],
+        index: usize,
+    ) -> Result<Self, InterpreterError> {
+        number(function, args, index)
+    }
+}
+
+impl Arg for usize {
+    fn from_arg(
+        function: &str,
+        args: &[RuntimeValue],
+        index: usize,
+    ) -> Result<Self, InterpreterError> {
+        whole(function, args, index)
+    }
+}
+
+impl Arg for Gc<str> {
+    fn from_arg(
+        function: &str,
+        args: &[RuntimeValue],
+        index: usize,
+    ) -> Result<Self, InterpreterError> {
+        string(function, args, index)
+    }
+}
+
+impl Arg for List {
+    fn from_arg(
+        function: &str,
+        args: &[RuntimeValue],
+        index: usize,
+    ) -> Result<Self, InterpreterError> {
+        list(function, args, index)
+    }
+}
+
+impl Arg for Map {
+    fn from_arg(
+        function: &str,
+        args: &[RuntimeValue],
+        index: usize,
+    ) -> Result<Self, InterpreterError> {
+        map(function, args, index)
+    }
+}
+
+impl Arg for MapKey {
+    fn from_arg(
+        function: &str,
+        args: &[RuntimeValue],
+        index: usize,
+    ) -> Result<Self, InterpreterError> {
+        key(function, args, index)
+    }
+}
+
+impl Arg for ClassInstance {
+    fn from_arg(
+        function: &str,
+        args: &[RuntimeValue],
+        index: usize,
+    ) -> Result<Self, InterpreterError> {
+        match &args[index] {
+            RuntimeValue::Instance(instance) => Ok(instance.clone()),
+            other => Err(error(
+                function,
+                format!(
+                    "Argument {} must be an instance, but was {}.",
+                    index + 1,
+                    describe(other)
+                ),
+            )),
+        }
+    }
+}
+
+impl Arg for ClassDefinition {
+    fn from_arg(
+        function: &str,
+        args: &[RuntimeValue],
+        index: usize,
+    ) -> Result<Self, InterpreterError> {
+        match &args[index] {
+            RuntimeValue::Class(class) => Ok(class.clone()),
+            other => Err(error(
+                function,
+                format!(
+                    "Argument {} must be a class, but was {}.",
+                    index + 1,
+                    describe(other)
+                ),
+            )),
+        }
+    }
+}
+
+/// What a `#[lox_native]` can return: a value, `()` or `None` for nil, or a result
+/// of either for natives that can fail.
+pub(crate) trait Return {
+    fn into_result(self) -> Result<RuntimeValue, InterpreterError>;
+}
+
+macro_rules! returns {
+    ($($ty:ty => $variant:path),* $(,)?) => {
+        $(impl Return for $ty {
+            fn into_result(self) -> Result<RuntimeValue, InterpreterError> {
+                Ok($variant(self.into()))
+            }
+        })*
+    };
+}
+
+returns! {
+    bool => RuntimeValue::Bool,
+    f64 => RuntimeValue::Float,
+    Gc<str> => RuntimeValue::Str,
+    String => RuntimeValue::Str,
+    List => RuntimeValue::List,
+    Map => RuntimeValue::Map,
+    ClassDefinition => RuntimeValue::Class,
+    ClassInstance => RuntimeValue::Instance,
+}
+
+impl Return for () {
+    fn into_result(self) -> Result<RuntimeValue, InterpreterError> {
+        Ok(RuntimeValue::Nil)
+    }
+}
+
+impl Return for RuntimeValue {
+    fn into_result(self) -> Result<RuntimeValue, InterpreterError> {
+        Ok(self)
+    }
+}
+
+impl<T: Return> Return for Option<T> {
+    fn into_result(self) -> Result<RuntimeValue, InterpreterError> {
+        self.map_or(Ok(RuntimeValue::Nil), Return::into_result)
+    }
+}
+
+impl<T: Return> Return for Result<T, InterpreterError> {
+    fn into_result(self) -> Result<RuntimeValue, InterpreterError> {
+        self?.into_result()
+    }
+}
+
+// natives that can also be called as methods on the value of their first
+// argument, with the names of their arguments
+type Methods = &'static [(&'static str, &'static [&'static str], Native)];
+
+fn bind_method(methods: Methods, receiver: RuntimeValue, name: &str) -> Option<RuntimeValue> {
+    let (name, args, function) = methods.iter().find(|(it, _, _)| *it == name)?;
+    Some(bind(receiver, name, args, *function))
+}
+
+// the `#[lox_native]` called `name`, if it's one of `natives`, with
+// `receiver` as its first argument
+fn bind_registered(natives: &[&str], receiver: RuntimeValue, name: &str) -> Option<RuntimeValue> {
+    if !natives.contains(&name) {
+        return None;
+    }
+    let native = inventory::iter::<Registered>().find(|it| it.name == name)?;
+    Some(bind(receiver, native.name, native.args, native.function))
+}
+
+fn bind(receiver: RuntimeValue, name: &str, args: &[&str], function: Native) -> RuntimeValue {
+    let method = BuiltInFunction::new(name, args[1..].to_vec(), move |interpreter, mut args| {
+        args.insert(0, receiver.clone());
+        function(interpreter, args)
+    });
+    RuntimeValue::BuiltInFunction(method)
+}
+
+fn error(function: &str, message: String) -> InterpreterError {
+    InterpreterError::NativeFunction(function.to_string(), message)
+}
+
+// natives that reach out of the interpreter check with the sandbox first
+fn check_allowed(allowed: bool, function: &str, what: &str) -> Result<(), InterpreterError> {
+    if allowed {
+        Ok(())
+    } else {
+        Err(error(
+            function,
+            format!("Access to {} is not allowed.", what),
+        ))
+    }
+}
+
+// strings are quoted so they can't be mistaken for other values
+fn describe(value: &RuntimeValue) -> String {
+    match value {
+        RuntimeValue::Str(s) => format!("\"{}\"", s),
+        other => other.to_string(),
+    }
+}
+
+/// The argument at `index` as a number, or an error naming the function.
+fn number(function: &str, args: &[RuntimeValue], index: usize) -> Result<f64, InterpreterError> {
+    match &args[index] {
+        RuntimeValue::Float(x) => Ok(*x),
+        RuntimeValue::BigInt(x) => Ok(x.to_f64()),
+        other => Err(error(
+            function,
+            format!(
+                "Argument {} must be a number, but was {}.",
+                index + 1,
+                describe(other)
+            ),
+        )),
+    }
+}
+
+/// The argument at `index` as a string.
+fn string(
+    function: &str,
+    args: &[RuntimeValue],
+    index: usize,
+) -> Result<Gc<str>, InterpreterError> {
+    match &args[index] {
+        RuntimeValue::Str(s) => Ok(s.clone()),
+        other => Err(error(
+            function,
+            format!(
+                "Argument {} must be a string, but was {}.",
+                index + 1,
+                describe(other)
+            ),
+        )),
+    }
+}
+
+/// The argument at `index` as a list.
+fn list(function: &str, args: &[RuntimeValue], index: usize) -> Result<List, InterpreterError> {
+    match &args[index] {
+        RuntimeValue::List(list) => Ok(list.clone()),
+        other => Err(error(
+            function,
+            format!(
+                "Argument {} must be a list, but was {}.",
+                index + 1,
+                describe(other)
+            ),
+        )),
+    }
+}
+
+/// The argument at `index` as a map.
+fn map(function: &str, args: &[RuntimeValue], index: usize) -> Result<Map, InterpreterError> {
+    match &args[index] {
+        RuntimeValue::Map(map) => Ok(map.clone()),
+        other => Err(error(
+            function,
+            format!(
+                "Argument {} must be a map, but was {}.",
+                index + 1,
+                describe(other)
+            ),
+        )),
+    }
+}
+
+/// The argument at `index` as a key for a map.
+fn key(function: &str, args: &[RuntimeValue], index: usize) -> Result<MapKey, InterpreterError> {
+    MapKey::new(&args[index]).ok_or_else(|| {
+        error(
+            function,
+            format!(
+                "Argument {} must be a bool, number, string, nil or an instance with hash() and equals() to be a map key, but was {}.",
+                index + 1,
+                describe(&args[index])
+            ),
+        )
+    })
+}
+
+/// The argument at `index` as a position or count, which must be a whole
+/// number that isn't negative.
+fn whole(function: &str, args: &[RuntimeValue], index: usize) -> Result<usize, InterpreterError> {
+    let x = number(function, args, index)?;
+    if x < 0.0 || x.fract() != 0.0 {
+        return Err(error(
+            function,
+            format!(
+                "Argument {} must be a whole number that isn't negative, but was {}.",
+                index + 1,
+                x
+            ),
+        ));
+    }
+    Ok(x as usize)
+}
@@ -0,0 +1,33 @@
+use std::env;
+
+use super::{check_allowed, error, native, number, string};
+use crate::{
+    interpreter::{Interpreter, InterpreterError},
+    value::RuntimeValue,
+};
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    // nil if the variable isn't set
+    native(interpreter, "getenv", vec!["name"], |interpreter, args| {
+        check_allowed(interpreter.config().allow_env, "getenv", "the environment")?;
+        let name = string("getenv", &args, 0)?;
+        Ok(env::var(&*name)
+            .map(|value| RuntimeValue::Str(value.into()))
+            .unwrap_or(RuntimeValue::Nil))
+    });
+    // unwinds the whole script, it's up to the host to end the process
+    native(interpreter, "exit", vec!["code"], |interpreter, args| {
+        check_allowed(interpreter.config().allow_exit, "exit", "exit")?;
+        let code = number("exit", &args, 0)?;
+        if code.fract() != 0.0 || code < i32::MIN as f64 || code > i32::MAX as f64 {
+            return Err(error(
+                "exit",
+                format!("Exit code must be a whole number, but was {}.", code),
+            ));
+        }
+        Err(InterpreterError::Exit(code as i32))
+    });
+    native(interpreter, "platform", vec![], |_, _| {
+        Ok(RuntimeValue::Str(env::consts::OS.into()))
+    });
+}
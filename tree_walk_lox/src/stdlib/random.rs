@@ -0,0 +1,76 @@
+use super::{error, native, number};
+use crate::{interpreter::Interpreter, value::RuntimeValue};
+
+/// Small splitmix64 generator. Good enough for games and simulations, not
+/// for anything that needs to be unpredictable.
+#[derive(Default)]
+pub(crate) struct Rng {
+    // seeded from the clock on first use unless the script seeds it first
+    state: Option<u64>,
+}
+
+impl Rng {
+    pub(crate) fn seed(&mut self, seed: u64) {
+        self.state = Some(seed);
+    }
+
+    fn is_seeded(&self) -> bool {
+        self.state.is_some()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let state = self.state.get_or_insert(0);
+        *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+// a number in [0, 1)
+fn next_float(interpreter: &mut Interpreter) -> f64 {
+    if !interpreter.rng().is_seeded() {
+        let now = interpreter.clock().now().as_nanos() as u64;
+        interpreter.rng().seed(now);
+    }
+    let bits = interpreter.rng().next_u64();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    native(interpreter, "random", vec![], |interpreter, _| {
+        Ok(RuntimeValue::Float(next_float(interpreter)))
+    });
+    // both ends are included
+    native(
+        interpreter,
+        "randomInt",
+        vec!["lo", "hi"],
+        |interpreter, args| {
+            let lo = number("randomInt", &args, 0)?;
+            let hi = number("randomInt", &args, 1)?;
+            if lo.fract() != 0.0 || hi.fract() != 0.0 || lo > hi {
+                return Err(error(
+                    "randomInt",
+                    format!(
+                        "Expected two whole numbers lo <= hi, but got {} and {}.",
+                        lo, hi
+                    ),
+                ));
+            }
+            let offset = (next_float(interpreter) * (hi - lo + 1.0)).floor();
+            Ok(RuntimeValue::Float(lo + offset))
+        },
+    );
+    native(
+        interpreter,
+        "seedRandom",
+        vec!["seed"],
+        |interpreter, args| {
+            let seed = number("seedRandom", &args, 0)?;
+            interpreter.rng().seed(seed.to_bits());
+            Ok(RuntimeValue::Nil)
+        },
+    );
+}
@@ -0,0 +1,101 @@
+//! Positions and lengths count characters, not bytes. The natives that
+//! take a string first can also be called as methods on it:
+//! `name.toUpper()` is `toUpper(name)`.
+
+use lox_proc_macros::lox_native;
+
+use super::{bind_method, bind_registered, error, string, Methods};
+use crate::{
+    interpreter::{Interpreter, InterpreterError},
+    shared::Gc,
+    value::{List, RuntimeValue},
+};
+
+// `#[lox_native]`s that take the string as their first argument
+const NATIVES: &[&str] = &[
+    "substr", "indexOf", "toUpper", "toLower", "trim", "split", "charAt",
+];
+
+// methods that aren't natives, since `len` already is one for strings
+const METHODS: Methods = &[("length", &["string"], length)];
+
+/// The native `name` with `s` as its first argument, for `s.name`.
+pub(crate) fn method(s: &Gc<str>, name: &str) -> Option<RuntimeValue> {
+    let receiver = RuntimeValue::Str(s.clone());
+    bind_method(METHODS, receiver.clone(), name)
+        .or_else(|| bind_registered(NATIVES, receiver, name))
+}
+
+fn length(_: &mut Interpreter, args: Vec<RuntimeValue>) -> Result<RuntimeValue, InterpreterError> {
+    let s = string("length", &args, 0)?;
+    Ok(RuntimeValue::Float(s.chars().count() as f64))
+}
+
+#[lox_native]
+fn substr(s: Gc<str>, start: usize, len: usize) -> Result<String, InterpreterError> {
+    let count = s.chars().count();
+    if start + len > count {
+        return Err(error(
+            "substr",
+            format!(
+                "Can't take {} characters starting at {} from a string of {}.",
+                len, start, count
+            ),
+        ));
+    }
+    Ok(s.chars().skip(start).take(len).collect())
+}
+
+// -1 when the string doesn't contain it
+#[lox_native(name = "indexOf")]
+fn index_of(s: Gc<str>, search: Gc<str>) -> f64 {
+    match s.find(&*search) {
+        Some(byte) => s[..byte].chars().count() as f64,
+        None => -1.0,
+    }
+}
+
+#[lox_native(name = "toUpper")]
+fn to_upper(s: Gc<str>) -> String {
+    s.to_uppercase()
+}
+
+#[lox_native(name = "toLower")]
+fn to_lower(s: Gc<str>) -> String {
+    s.to_lowercase()
+}
+
+#[lox_native]
+fn trim(s: Gc<str>) -> Gc<str> {
+    s.trim().into()
+}
+
+// an empty separator splits the string into its characters
+#[lox_native]
+fn split(s: Gc<str>, separator: Gc<str>) -> List {
+    let parts = if separator.is_empty() {
+        s.chars()
+            .map(|c| RuntimeValue::Str(c.to_string().into()))
+            .collect()
+    } else {
+        s.split(&*separator)
+            .map(|part| RuntimeValue::Str(part.into()))
+            .collect()
+    };
+    List::new(parts)
+}
+
+#[lox_native(name = "charAt")]
+fn char_at(s: Gc<str>, index: usize) -> Result<String, InterpreterError> {
+    match s.chars().nth(index) {
+        Some(c) => Ok(c.to_string()),
+        None => Err(error(
+            "charAt",
+            format!(
+                "Index {} is out of range for a string of {} characters.",
+                index,
+                s.chars().count()
+            ),
+        )),
+    }
+}
@@ -0,0 +1,65 @@
+use super::{describe, error, native, string};
+use crate::{interpreter::Interpreter, testing::TestCase, value::RuntimeValue};
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    // runs the function as a test. a runtime error fails the test instead of
+    // stopping the script, so the tests after it still run.
+    native(
+        interpreter,
+        "test",
+        vec!["name", "function"],
+        |interpreter, args| {
+            let name = string("test", &args, 0)?;
+            let function = match args[1].as_callable() {
+                Some(function) if function.arity() == 0 => function,
+                _ => {
+                    return Err(error(
+                        "test",
+                        format!(
+                            "Argument 2 must be a function without parameters, but was {}.",
+                            describe(&args[1])
+                        ),
+                    ))
+                }
+            };
+            if interpreter.tests().current.is_some() {
+                return Err(error("test", "Tests can't be nested.".into()));
+            }
+
+            let report = interpreter.tests();
+            report.cases.push(TestCase {
+                name: name.to_string(),
+                failures: vec![],
+            });
+            report.current = Some(report.cases.len() - 1);
+            let result = match function.call(interpreter, vec![]) {
+                Ok(_) => Ok(RuntimeValue::Nil),
+                Err(e) if e.stops_script() => Err(e),
+                Err(e) => {
+                    interpreter.tests().fail(e.to_string());
+                    Ok(RuntimeValue::Nil)
+                }
+            };
+            interpreter.tests().current = None;
+            result
+        },
+    );
+    // records a failure if the values aren't equal and returns whether they
+    // were
+    native(
+        interpreter,
+        "expect",
+        vec!["actual", "expected"],
+        |interpreter, args| {
+            let equal = args[0].equals(&args[1]);
+            if !equal {
+                interpreter.tests().fail(format!(
+                    "Expected {} but got {}.",
+                    describe(&args[1]),
+                    describe(&args[0])
+                ));
+            }
+            Ok(RuntimeValue::Bool(equal))
+        },
+    );
+}
@@ -0,0 +1,155 @@
+//! Timestamps are seconds since the unix epoch. Dates are always in UTC,
+//! there is no time zone database to convert them to local time.
+
+use std::{fmt::Write, time::Duration};
+
+use super::{error, native, number, string};
+use crate::{
+    interpreter::{Interpreter, InterpreterError},
+    value::RuntimeValue,
+};
+
+struct DateTime {
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+}
+
+impl DateTime {
+    // days to civil date, from http://howardhinnant.github.io/date_algorithms.html
+    fn from_timestamp(timestamp: f64) -> Self {
+        let seconds = timestamp.floor() as i64;
+        let days = seconds.div_euclid(86_400);
+        let time = seconds.rem_euclid(86_400) as u32;
+
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let day_of_era = z.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        } as u32;
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        Self {
+            year,
+            month,
+            day,
+            hour: time / 3600,
+            minute: time / 60 % 60,
+            second: time % 60,
+        }
+    }
+}
+
+fn timestamp(function: &str, args: &[RuntimeValue]) -> Result<DateTime, InterpreterError> {
+    let timestamp = number(function, args, 0)?;
+    // beyond this the year doesn't fit in the calculations
+    if !timestamp.is_finite() || timestamp.abs() > 1e15 {
+        return Err(error(
+            function,
+            format!("{} is not a valid timestamp.", timestamp),
+        ));
+    }
+    Ok(DateTime::from_timestamp(timestamp))
+}
+
+fn format_time(date: &DateTime, format: &str) -> Result<String, String> {
+    let mut out = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => write!(out, "{:04}", date.year),
+            Some('m') => write!(out, "{:02}", date.month),
+            Some('d') => write!(out, "{:02}", date.day),
+            Some('H') => write!(out, "{:02}", date.hour),
+            Some('M') => write!(out, "{:02}", date.minute),
+            Some('S') => write!(out, "{:02}", date.second),
+            Some('%') => write!(out, "%"),
+            Some(other) => return Err(format!("Unknown format '%{}'.", other)),
+            None => return Err("Format ends with a lone '%'.".to_string()),
+        }
+        .unwrap();
+    }
+    Ok(out)
+}
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    // seconds since the unix epoch, like in the book
+    native(interpreter, "clock", vec![], |interpreter, _| {
+        Ok(RuntimeValue::Float(interpreter.clock().now().as_secs_f64()))
+    });
+    // nanoseconds since the unix epoch. a double can't hold all of them
+    // exactly, but differences between two calls are still precise enough
+    // for timing.
+    native(interpreter, "nanos", vec![], |interpreter, _| {
+        Ok(RuntimeValue::Float(
+            interpreter.clock().now().as_nanos() as f64
+        ))
+    });
+    native(
+        interpreter,
+        "sleep",
+        vec!["seconds"],
+        |interpreter, args| {
+            let seconds = number("sleep", &args, 0)?;
+            let duration = Duration::try_from_secs_f64(seconds)
+                .map_err(|_| error("sleep", format!("Can't sleep for {} seconds.", seconds)))?;
+            interpreter.clock().sleep(duration);
+            Ok(RuntimeValue::Nil)
+        },
+    );
+
+    native(interpreter, "now", vec![], |interpreter, _| {
+        Ok(RuntimeValue::Float(interpreter.clock().now().as_secs_f64()))
+    });
+    // supports %Y, %m, %d, %H, %M, %S and %% like strftime
+    native(
+        interpreter,
+        "formatTime",
+        vec!["timestamp", "format"],
+        |_, args| {
+            let date = timestamp("formatTime", &args)?;
+            let format = string("formatTime", &args, 1)?;
+            let formatted =
+                format_time(&date, &format).map_err(|message| error("formatTime", message))?;
+            Ok(RuntimeValue::Str(formatted.into()))
+        },
+    );
+    native(interpreter, "year", vec!["timestamp"], |_, args| {
+        Ok(RuntimeValue::Float(timestamp("year", &args)?.year as f64))
+    });
+    // 1 to 12
+    native(interpreter, "month", vec!["timestamp"], |_, args| {
+        Ok(RuntimeValue::Float(timestamp("month", &args)?.month as f64))
+    });
+    native(interpreter, "day", vec!["timestamp"], |_, args| {
+        Ok(RuntimeValue::Float(timestamp("day", &args)?.day as f64))
+    });
+    native(interpreter, "hour", vec!["timestamp"], |_, args| {
+        Ok(RuntimeValue::Float(timestamp("hour", &args)?.hour as f64))
+    });
+    native(interpreter, "minute", vec!["timestamp"], |_, args| {
+        Ok(RuntimeValue::Float(
+            timestamp("minute", &args)?.minute as f64,
+        ))
+    });
+    native(interpreter, "second", vec!["timestamp"], |_, args| {
+        Ok(RuntimeValue::Float(
+            timestamp("second", &args)?.second as f64,
+        ))
+    });
+}
@@ -0,0 +1,51 @@
+use lox_proc_macros::lox_native;
+
+use super::{describe, error};
+use crate::{
+    interpreter::{Interpreter, InterpreterError},
+    shared::Gc,
+    value::RuntimeValue,
+};
+
+// accepts numbers as Lox writes them, with an optional sign in front
+pub(crate) fn parse_number(s: &str) -> Option<f64> {
+    let s = s.trim();
+    let digits = s.strip_prefix(['-', '+']).unwrap_or(s);
+    let (whole, fraction) = match digits.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (digits, None),
+    };
+    let is_digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    if is_digits(whole) && fraction.is_none_or(is_digits) {
+        s.parse().ok()
+    } else {
+        None
+    }
+}
+
+// instances report the name of their class
+#[lox_native(name = "type")]
+fn type_of(value: RuntimeValue) -> Gc<str> {
+    value.type_name().into()
+}
+
+// nil if the string isn't a number
+#[lox_native]
+fn num(value: RuntimeValue) -> Result<Option<RuntimeValue>, InterpreterError> {
+    match &value {
+        RuntimeValue::Float(_) | RuntimeValue::BigInt(_) => Ok(Some(value)),
+        RuntimeValue::Str(s) => Ok(parse_number(s).map(RuntimeValue::Float)),
+        other => Err(error(
+            "num",
+            format!(
+                "Can only convert strings to numbers, but got {}.",
+                describe(other)
+            ),
+        )),
+    }
+}
+
+#[lox_native(name = "str")]
+fn to_str(interpreter: &mut Interpreter, value: RuntimeValue) -> Result<String, InterpreterError> {
+    interpreter.stringify(&value)
+}
@@ -0,0 +1,104 @@
+//! Interned names. Every token's lexeme is a `Symbol`, so copying one around
+//! is free and comparing or hashing two of them only looks at a pointer.
+
+use std::{
+    cmp::Ordering,
+    fmt::{Debug, Display},
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::Mutex,
+};
+
+use lazy_static::lazy_static;
+use rustc_hash::FxHashSet;
+
+lazy_static! {
+    // interned strings are never freed, a program only has so many names
+    static ref INTERNED: Mutex<FxHashSet<&'static str>> = Mutex::new(FxHashSet::default());
+
+    /// The names the interpreter defines itself.
+    pub static ref THIS: Symbol = Symbol::intern("this");
+    pub static ref SUPER: Symbol = Symbol::intern("super");
+}
+
+#[derive(Clone, Copy)]
+pub struct Symbol(&'static str);
+
+impl Symbol {
+    pub fn intern(name: &str) -> Self {
+        let mut interned = INTERNED.lock().unwrap();
+        match interned.get(name) {
+            Some(existing) => Symbol(existing),
+            None => {
+                let leaked: &'static str = Box::leak(name.to_string().into_boxed_str());
+                interned.insert(leaked);
+                Symbol(leaked)
+            }
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        self.0
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Symbol::intern(name)
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        // there is only one copy of each interned string
+        std::ptr::eq(self.0, other.0)
+    }
+}
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.0.as_ptr() as usize).hash(state);
+    }
+}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(other.0)
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.0
+    }
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+impl Debug for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
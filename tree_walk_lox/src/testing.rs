@@ -0,0 +1,60 @@
+//! Results of the `test` and `expect` natives, and discovery of test files
+//! for `lox test`.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// A `test(name, fn)` call and the expectations that failed in it.
+#[derive(Debug, Clone)]
+pub struct TestCase {
+    pub name: String,
+    pub failures: Vec<String>,
+}
+impl TestCase {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Everything the tests of a script recorded. `expect` calls outside of a
+/// test count as a test of their own, named after the script's top level.
+#[derive(Debug, Default)]
+pub struct TestReport {
+    pub cases: Vec<TestCase>,
+    // index into 'cases' of the test that is running
+    pub(crate) current: Option<usize>,
+}
+impl TestReport {
+    pub fn passed(&self) -> bool {
+        self.cases.iter().all(TestCase::passed)
+    }
+
+    pub(crate) fn fail(&mut self, message: String) {
+        let index = match self.current {
+            Some(index) => index,
+            None => self.top_level(),
+        };
+        self.cases[index].failures.push(message);
+    }
+
+    fn top_level(&mut self) -> usize {
+        const NAME: &str = "<top level>";
+        match self.cases.iter().position(|it| it.name == NAME) {
+            Some(index) => index,
+            None => {
+                self.cases.push(TestCase {
+                    name: NAME.to_string(),
+                    failures: vec![],
+                });
+                self.cases.len() - 1
+            }
+        }
+    }
+}
+
+/// The `*_test.lox` files in `dir` and its subdirectories, sorted by path.
+pub fn find_test_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    crate::find_files(dir, "_test.lox")
+}
@@ -1,64 +1,30 @@
-use std::hash::Hash;
+use std::{hash::Hash, ops::Range};
 
-use crate::value::RuntimeValue;
+use crate::{symbol::Symbol, value::RuntimeValue};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum TokenKind {
-    LeftParen,
-    RightParen,
-    LeftBrace,
-    RightBrace,
-    Comma,
-    Dot,
-    Minus,
-    Plus,
-    Semicolon,
-    Slash,
-    Star,
-
-    // One or two character tokens.
-    Bang,
-    BangEqual,
-    Equal,
-    EqualEqual,
-    Greater,
-    GreaterEqual,
-    Less,
-    LessEqual,
-
-    // Literals.
-    Identifier,
-    String,
-    Number,
-
-    // Keywords.
-    And,
-    Class,
-    Else,
-    False,
-    Fun,
-    For,
-    If,
-    Nil,
-    Or,
-    Print,
-    Return,
-    Super,
-    This,
-    True,
-    Var,
-    While,
-
-    Eof,
-}
+pub use lox_frontend::TokenKind;
 
 #[derive(Debug, Clone)]
 pub struct Token {
     pub kind: TokenKind,
-    pub lexeme: String,
+    pub lexeme: Symbol,
     pub literal: RuntimeValue,
     pub line: usize,
+    /// where the token starts on its line, counting grapheme clusters from
+    /// 1, as a reader would
+    pub column: usize,
+    /// byte offset of the lexeme in the source
     pub scanner_index: usize,
+    /// bytes of the source the token was scanned from. the lexeme of an
+    /// identifier is normalized, so it can be longer or shorter
+    pub length: usize,
+}
+
+impl Token {
+    /// The bytes of the source the token was scanned from.
+    pub fn span(&self) -> Range<usize> {
+        self.scanner_index..self.scanner_index + self.length
+    }
 }
 
 impl PartialEq for Token {
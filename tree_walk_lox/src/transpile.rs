@@ -0,0 +1,529 @@
+//! Translates scripts into Rust source that does what the interpreter would,
+//! with the values of the interpreter and the operations in `runtime`. The
+//! result is the `main.rs` of a crate that depends on this one.
+//!
+//! Globals stay in the interpreter's global environment, looked up by name
+//! like the interpreter does, so functions can refer to ones declared after
+//! them. Locals become Rust variables holding a `Var`, which closures
+//! capture by cloning. Functions become native functions. Classes aren't
+//! supported yet.
+
+use std::{error::Error, fmt::Display};
+
+use crate::{
+    ast::{Expr, FunctionStmt, Stmt},
+    interpreter::Interpreter,
+    parser::Parser,
+    resolver::Resolver,
+    scanner::Scanner,
+    symbol::Symbol,
+    token::{Token, TokenKind},
+    value::RuntimeValue,
+    LoxError,
+};
+
+const INDENT: &str = "    ";
+
+// names the generated code uses itself, and Rust's keywords. locals named
+// like them get an underscore after their name.
+const RESERVED: &[&str] = &[
+    "Interpreter",
+    "InterpreterError",
+    "RuntimeValue",
+    "Self",
+    "abstract",
+    "args",
+    "as",
+    "async",
+    "await",
+    "become",
+    "box",
+    "break",
+    "const",
+    "continue",
+    "crate",
+    "do",
+    "dyn",
+    "else",
+    "enum",
+    "extern",
+    "false",
+    "final",
+    "fn",
+    "for",
+    "if",
+    "impl",
+    "in",
+    "left",
+    "let",
+    "loop",
+    "lox",
+    "macro",
+    "match",
+    "mod",
+    "move",
+    "mut",
+    "override",
+    "priv",
+    "pub",
+    "ref",
+    "return",
+    "rt",
+    "self",
+    "static",
+    "struct",
+    "super",
+    "trait",
+    "true",
+    "try",
+    "type",
+    "typeof",
+    "unsafe",
+    "unsized",
+    "use",
+    "virtual",
+    "where",
+    "while",
+    "yield",
+];
+
+/// Returns the Rust source of a program that runs `source`.
+pub fn transpile(source: &str) -> Result<String, TranspileError> {
+    let statements = Parser::new(Scanner::new(source.to_string())).parse()?;
+    let mut interpreter = Interpreter::new();
+    let mut resolver = Resolver::new(&mut interpreter);
+    resolver.resolve(&statements);
+    let resolution = resolver.finish();
+    if !resolution.errors.is_empty() {
+        return Err(LoxError::from(resolution.errors).into());
+    }
+
+    let mut transpiler = Transpiler::default();
+    transpiler.line("//! Transpiled from a Lox script by `lox transpile`.");
+    transpiler.line("");
+    transpiler.line("#![allow(unreachable_code, unused_imports, unused_variables)]");
+    transpiler.line("");
+    transpiler.line("use lox::{");
+    transpiler.line("    interpreter::{Interpreter, InterpreterError},");
+    transpiler.line("    runtime as rt,");
+    transpiler.line("    value::RuntimeValue,");
+    transpiler.line("};");
+    transpiler.line("");
+    transpiler.line("fn main() {");
+    transpiler.line("    let mut lox = Interpreter::new();");
+    transpiler.line("    rt::finish(run(&mut lox), &mut lox);");
+    transpiler.line("}");
+    transpiler.line("");
+    transpiler.line("fn run(lox: &mut Interpreter) -> Result<(), InterpreterError> {");
+    transpiler.indent += 1;
+    for stmt in &statements {
+        transpiler.stmt(stmt)?;
+    }
+    transpiler.line("Ok(())");
+    transpiler.indent -= 1;
+    transpiler.line("}");
+    Ok(transpiler.out)
+}
+
+#[derive(Default)]
+struct Transpiler {
+    out: String,
+    indent: usize,
+    // the locals declared in each scope, innermost last. globals are in none.
+    scopes: Vec<Vec<Symbol>>,
+}
+
+impl Transpiler {
+    fn line(&mut self, text: &str) {
+        if !text.is_empty() {
+            self.out.push_str(&INDENT.repeat(self.indent));
+            self.out.push_str(text);
+        }
+        self.out.push('\n');
+    }
+
+    fn is_local(&self, name: Symbol) -> bool {
+        self.scopes.iter().any(|scope| scope.contains(&name))
+    }
+
+    fn declare(&mut self, name: Symbol) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(name);
+        }
+    }
+
+    // the statements of a block, or a single statement, in a scope of their
+    // own between lines the caller writes
+    fn body(&mut self, stmt: &Stmt) -> Result<(), TranspileError> {
+        self.indent += 1;
+        self.scopes.push(vec![]);
+        match stmt {
+            Stmt::Block { statements } => {
+                for stmt in statements {
+                    self.stmt(stmt)?;
+                }
+            }
+            stmt => self.stmt(stmt)?,
+        }
+        self.scopes.pop();
+        self.indent -= 1;
+        Ok(())
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Result<(), TranspileError> {
+        match stmt {
+            Stmt::Expression { expression } => {
+                let expression = self.expr(expression)?;
+                self.line(&format!("{};", expression));
+            }
+            Stmt::Print { expression } => {
+                let expression = self.expr(expression)?;
+                self.line(&format!("rt::print(&{}, lox);", expression));
+            }
+            Stmt::Breakpoint { .. } => self.line("// breakpoint"),
+            Stmt::Return { value, .. } => {
+                let value = match value {
+                    Some(value) => self.expr(value)?,
+                    None => "RuntimeValue::Nil".to_string(),
+                };
+                self.line(&format!("return Ok({});", value));
+            }
+            Stmt::Var { name, initializer } => {
+                let value = match initializer {
+                    Some(initializer) => self.expr(initializer)?,
+                    None => "RuntimeValue::Nil".to_string(),
+                };
+                if self.scopes.is_empty() {
+                    self.line(&format!("rt::define({:?}, {}, lox);", &*name.lexeme, value));
+                } else {
+                    self.declare(name.lexeme);
+                    let name = rust_name(&name.lexeme);
+                    self.line(&format!("let {} = rt::Var::new({});", name, value));
+                }
+            }
+            Stmt::While {
+                condition,
+                body,
+                increment,
+                ..
+            } => {
+                let condition = self.expr(condition)?;
+                self.line(&format!("while {}.is_truthy() {{", condition));
+                self.body(body)?;
+                if let Some(increment) = increment {
+                    let increment = self.expr(increment)?;
+                    self.indent += 1;
+                    self.line(&format!("{};", increment));
+                    self.indent -= 1;
+                }
+                self.line("}");
+            }
+            Stmt::Break { keyword, .. } | Stmt::Continue { keyword, .. } => {
+                return Err(TranspileError::Unsupported(
+                    keyword.clone(),
+                    "'break' and 'continue'",
+                ));
+            }
+            Stmt::Block { .. } => {
+                self.line("{");
+                self.body(stmt)?;
+                self.line("}");
+            }
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                let condition = self.expr(condition)?;
+                self.line(&format!("if {}.is_truthy() {{", condition));
+                self.body(then_branch)?;
+                if let Some(else_branch) = else_branch {
+                    self.line("} else {");
+                    self.body(else_branch)?;
+                }
+                self.line("}");
+            }
+            Stmt::Function(fun) => self.function(fun)?,
+            Stmt::Class { name, .. } => {
+                return Err(TranspileError::Unsupported(name.clone(), "classes"));
+            }
+        }
+        Ok(())
+    }
+
+    fn function(&mut self, fun: &FunctionStmt) -> Result<(), TranspileError> {
+        let name = &*fun.name.lexeme;
+        let params = fun
+            .params
+            .iter()
+            .map(|it| format!("{:?}", &*it.lexeme))
+            .collect::<Vec<_>>();
+        let function = format!("rt::function({:?}, &[{}], ", name, params.join(", "));
+        let global = self.scopes.is_empty();
+        if global {
+            self.line(&format!("rt::define({:?}, {}{{", name, function));
+        } else {
+            // declared first, so the function can call itself
+            self.declare(fun.name.lexeme);
+            let name = rust_name(name);
+            self.line(&format!("let {} = rt::Var::new(RuntimeValue::Nil);", name));
+            self.line(&format!("{}.set({}{{", name, function));
+        }
+
+        self.indent += 1;
+        let mut captures = vec![];
+        for stmt in &fun.body {
+            names_in_stmt(stmt, &mut captures);
+        }
+        captures.retain(|it| self.is_local(*it));
+        for capture in &captures {
+            let capture = rust_name(capture);
+            self.line(&format!("let {} = {}.clone();", capture, capture));
+        }
+        let args = if fun.params.is_empty() {
+            "_args"
+        } else {
+            "args"
+        };
+        self.line(&format!("move |lox, {}| {{", args));
+
+        self.indent += 1;
+        self.scopes.push(vec![]);
+        for (i, param) in fun.params.iter().enumerate() {
+            self.declare(param.lexeme);
+            let param = rust_name(&param.lexeme);
+            self.line(&format!(
+                "let {} = rt::Var::new(args[{}].clone());",
+                param, i
+            ));
+        }
+        for stmt in &fun.body {
+            self.stmt(stmt)?;
+        }
+        if !matches!(fun.body.last(), Some(Stmt::Return { .. })) {
+            self.line("Ok(RuntimeValue::Nil)");
+        }
+        self.scopes.pop();
+        self.indent -= 1;
+
+        self.line("}");
+        self.indent -= 1;
+        if global {
+            self.line("}), lox);");
+        } else {
+            self.line("}));");
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Result<String, TranspileError> {
+        let code = match expr {
+            Expr::Literal { value } => match &value.literal {
+                RuntimeValue::Float(n) => format!("rt::number({:?})", n),
+                RuntimeValue::Str(s) => format!("rt::string({:?})", &**s),
+                RuntimeValue::Bool(b) => format!("RuntimeValue::Bool({})", b),
+                _ => "RuntimeValue::Nil".to_string(),
+            },
+            Expr::Grouping { expression } => self.expr(expression)?,
+            Expr::Variable { name, .. } => {
+                if self.is_local(name.lexeme) {
+                    format!("{}.get()", rust_name(&name.lexeme))
+                } else {
+                    format!("rt::global({:?}, lox)?", &*name.lexeme)
+                }
+            }
+            Expr::Assign { name, value, .. } => {
+                let value = self.expr(value)?;
+                if self.is_local(name.lexeme) {
+                    format!("{}.set({})", rust_name(&name.lexeme), value)
+                } else {
+                    format!("rt::assign({:?}, {}, lox)?", &*name.lexeme, value)
+                }
+            }
+            Expr::Unary { operator, right } => {
+                let right = self.expr(right)?;
+                match operator.kind {
+                    TokenKind::Minus => format!("rt::negate(&{})?", right),
+                    _ => format!("rt::not(&{})", right),
+                }
+            }
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                let (left, right) = (self.expr(left)?, self.expr(right)?);
+                let (function, fallible) = match operator.kind {
+                    TokenKind::Plus => ("add", true),
+                    TokenKind::Minus => ("subtract", true),
+                    TokenKind::Star => ("multiply", true),
+                    TokenKind::Slash => ("divide", true),
+                    TokenKind::Greater => ("greater", true),
+                    TokenKind::GreaterEqual => ("greater_equal", true),
+                    TokenKind::Less => ("less", true),
+                    TokenKind::LessEqual => ("less_equal", true),
+                    TokenKind::EqualEqual | TokenKind::EqualEqualEqual => ("equal", false),
+                    _ => ("not_equal", false),
+                };
+                let question = if fallible { "?" } else { "" };
+                format!("rt::{}(&{}, &{}){}", function, left, right, question)
+            }
+            Expr::Logical {
+                left,
+                operator,
+                right,
+            } => {
+                let (left, right) = (self.expr(left)?, self.expr(right)?);
+                // `or` stops at a truthy value, `and` at a falsey one
+                let stop = if operator.kind == TokenKind::Or {
+                    "left.is_truthy()"
+                } else {
+                    "!left.is_truthy()"
+                };
+                format!(
+                    "{{ let left = {}; if {} {{ left }} else {{ {} }} }}",
+                    left, stop, right
+                )
+            }
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                let callee = self.expr(callee)?;
+                let arguments = arguments
+                    .iter()
+                    .map(|it| self.expr(it))
+                    .collect::<Result<Vec<_>, _>>()?;
+                format!(
+                    "rt::call(&{}, vec![{}], lox)?",
+                    callee,
+                    arguments.join(", ")
+                )
+            }
+            Expr::Get { object, name } => {
+                let object = self.expr(object)?;
+                format!("rt::get_property(&{}, {:?})?", object, &*name.lexeme)
+            }
+            Expr::Set {
+                object,
+                name,
+                value,
+            } => {
+                let (object, value) = (self.expr(object)?, self.expr(value)?);
+                format!(
+                    "rt::set_property(&{}, {:?}, {})?",
+                    object, &*name.lexeme, value
+                )
+            }
+            Expr::This { keyword, .. }
+            | Expr::Super { keyword, .. }
+            | Expr::Class { keyword, .. } => {
+                return Err(TranspileError::Unsupported(keyword.clone(), "classes"));
+            }
+        };
+        Ok(code)
+    }
+}
+
+fn rust_name(name: &str) -> String {
+    if RESERVED.contains(&name) {
+        format!("{}_", name)
+    } else {
+        name.to_string()
+    }
+}
+
+// the names of the variables `stmt` reads or assigns, including in the
+// functions inside it, in order of appearance
+fn names_in_stmt(stmt: &Stmt, names: &mut Vec<Symbol>) {
+    match stmt {
+        Stmt::Expression { expression } | Stmt::Print { expression } => {
+            names_in_expr(expression, names)
+        }
+        Stmt::Return { value, .. } => value.iter().for_each(|it| names_in_expr(it, names)),
+        Stmt::Var { initializer, .. } => initializer.iter().for_each(|it| names_in_expr(it, names)),
+        Stmt::While {
+            condition,
+            body,
+            increment,
+            ..
+        } => {
+            names_in_expr(condition, names);
+            names_in_stmt(body, names);
+            increment.iter().for_each(|it| names_in_expr(it, names));
+        }
+        Stmt::Block { statements } => statements.iter().for_each(|it| names_in_stmt(it, names)),
+        Stmt::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            names_in_expr(condition, names);
+            names_in_stmt(then_branch, names);
+            else_branch.iter().for_each(|it| names_in_stmt(it, names));
+        }
+        Stmt::Function(fun) => fun.body.iter().for_each(|it| names_in_stmt(it, names)),
+        Stmt::Class { .. }
+        | Stmt::Breakpoint { .. }
+        | Stmt::Break { .. }
+        | Stmt::Continue { .. } => {}
+    }
+}
+
+fn names_in_expr(expr: &Expr, names: &mut Vec<Symbol>) {
+    if let Expr::Variable { name, .. } | Expr::Assign { name, .. } = expr {
+        if !names.contains(&name.lexeme) {
+            names.push(name.lexeme);
+        }
+    }
+    match expr {
+        Expr::Assign { value, .. } => names_in_expr(value, names),
+        Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+            names_in_expr(left, names);
+            names_in_expr(right, names);
+        }
+        Expr::Call {
+            callee, arguments, ..
+        } => {
+            names_in_expr(callee, names);
+            arguments.iter().for_each(|it| names_in_expr(it, names));
+        }
+        Expr::Get { object, .. } => names_in_expr(object, names),
+        Expr::Set { object, value, .. } => {
+            names_in_expr(object, names);
+            names_in_expr(value, names);
+        }
+        Expr::Grouping { expression } => names_in_expr(expression, names),
+        Expr::Unary { right, .. } => names_in_expr(right, names),
+        Expr::Literal { .. }
+        | Expr::Variable { .. }
+        | Expr::This { .. }
+        | Expr::Super { .. }
+        | Expr::Class { .. } => {}
+    }
+}
+
+#[derive(Debug)]
+pub enum TranspileError {
+    Lox(LoxError),
+    /// the token starts a construct that can't be transpiled, like the
+    /// ones named in the message
+    Unsupported(Token, &'static str),
+}
+impl Display for TranspileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TranspileError::Lox(e) => write!(f, "{}", e),
+            TranspileError::Unsupported(token, what) => write!(
+                f,
+                "[Line {}] Can't transpile '{}': {} aren't supported yet.",
+                token.line, token.lexeme, what
+            ),
+        }
+    }
+}
+impl Error for TranspileError {}
+impl From<LoxError> for TranspileError {
+    fn from(e: LoxError) -> Self {
+        TranspileError::Lox(e)
+    }
+}
@@ -0,0 +1,338 @@
+//! Whole numbers of any size, for `--bignum`. Numbers are still floats
+//! whenever a float holds them exactly, which is up to 2^53; `normalize`
+//! turns anything in that range back into a float, so each whole number
+//! has a single representation.
+
+use std::{
+    cmp::Ordering,
+    fmt::{Display, Write},
+};
+
+use super::RuntimeValue;
+use crate::token::{Token, TokenKind};
+
+// each limb holds nine decimal digits, which keeps printing and parsing
+// simple and a product of two limbs inside a u64
+const BASE: u64 = 1_000_000_000;
+
+// the largest magnitude a float holds every whole number up to
+const MAX_EXACT: f64 = 9_007_199_254_740_992.0;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    // least significant first, without zeros at the end, so zero has none
+    limbs: Vec<u32>,
+}
+
+impl BigInt {
+    fn zero() -> Self {
+        Self {
+            negative: false,
+            limbs: vec![],
+        }
+    }
+
+    fn from_limbs(negative: bool, mut limbs: Vec<u32>) -> Self {
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+        let negative = negative && !limbs.is_empty();
+        Self { negative, limbs }
+    }
+
+    fn from_u64(mut value: u64) -> Self {
+        let mut limbs = vec![];
+        while value > 0 {
+            limbs.push((value % BASE) as u32);
+            value /= BASE;
+        }
+        Self::from_limbs(false, limbs)
+    }
+
+    /// The number of a float that's whole, which it holds exactly however
+    /// large it is.
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() || value.fract() != 0.0 {
+            return None;
+        }
+        if value.abs() < MAX_EXACT {
+            let magnitude = Self::from_u64(value.abs() as u64);
+            return Some(Self::from_limbs(value < 0.0, magnitude.limbs));
+        }
+        // a whole float this large is its 53 bit mantissa shifted left
+        let bits = value.to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) as i32 - 1075;
+        let mantissa = (bits & ((1 << 52) - 1)) | (1 << 52);
+        let mut magnitude = Self::from_u64(mantissa);
+        for _ in 0..exponent {
+            magnitude = magnitude.mul_small(2);
+        }
+        Some(Self::from_limbs(value < 0.0, magnitude.limbs))
+    }
+
+    /// Parses an integer literal, which is only digits.
+    pub fn parse(digits: &str) -> Option<Self> {
+        if digits.is_empty() || !digits.bytes().all(|it| it.is_ascii_digit()) {
+            return None;
+        }
+        let mut limbs = vec![];
+        let mut end = digits.len();
+        while end > 0 {
+            let start = end.saturating_sub(9);
+            limbs.push(digits[start..end].parse().ok()?);
+            end = start;
+        }
+        Some(Self::from_limbs(false, limbs))
+    }
+
+    /// The closest float, like parsing the digits would give.
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    fn compare_magnitude(&self, other: &Self) -> Ordering {
+        self.limbs
+            .len()
+            .cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+
+    fn add_magnitude(&self, other: &Self) -> Vec<u32> {
+        let mut limbs = vec![];
+        let mut carry = 0;
+        for i in 0..self.limbs.len().max(other.limbs.len()) {
+            let sum = carry
+                + self.limbs.get(i).copied().unwrap_or(0) as u64
+                + other.limbs.get(i).copied().unwrap_or(0) as u64;
+            limbs.push((sum % BASE) as u32);
+            carry = sum / BASE;
+        }
+        limbs.push(carry as u32);
+        limbs
+    }
+
+    // self's magnitude minus other's, which can't be larger
+    fn sub_magnitude(&self, other: &Self) -> Vec<u32> {
+        let mut limbs = vec![];
+        let mut borrow = 0;
+        for i in 0..self.limbs.len() {
+            let mut difference =
+                self.limbs[i] as i64 - other.limbs.get(i).copied().unwrap_or(0) as i64 - borrow;
+            borrow = 0;
+            if difference < 0 {
+                difference += BASE as i64;
+                borrow = 1;
+            }
+            limbs.push(difference as u32);
+        }
+        limbs
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        if self.negative == other.negative {
+            return Self::from_limbs(self.negative, self.add_magnitude(other));
+        }
+        match self.compare_magnitude(other) {
+            Ordering::Less => Self::from_limbs(other.negative, other.sub_magnitude(self)),
+            _ => Self::from_limbs(self.negative, self.sub_magnitude(other)),
+        }
+    }
+
+    pub fn negate(&self) -> Self {
+        Self::from_limbs(!self.negative, self.limbs.clone())
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        self.add(&other.negate())
+    }
+
+    fn mul_small(&self, factor: u32) -> Self {
+        let mut limbs = vec![];
+        let mut carry = 0;
+        for &limb in &self.limbs {
+            let product = limb as u64 * factor as u64 + carry;
+            limbs.push((product % BASE) as u32);
+            carry = product / BASE;
+        }
+        limbs.push(carry as u32);
+        Self::from_limbs(self.negative, limbs)
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        let mut limbs = vec![0u64; self.limbs.len() + other.limbs.len() + 1];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let product = limbs[i + j] + a as u64 * b as u64 + carry;
+                limbs[i + j] = product % BASE;
+                carry = product / BASE;
+            }
+            limbs[i + other.limbs.len()] += carry;
+        }
+        let limbs = limbs.into_iter().map(|it| it as u32).collect();
+        Self::from_limbs(self.negative != other.negative, limbs)
+    }
+
+    /// The quotient, rounded towards zero, and the remainder, or `None`
+    /// when dividing by zero.
+    pub fn div_rem(&self, divisor: &Self) -> Option<(Self, Self)> {
+        if divisor.is_zero() {
+            return None;
+        }
+        let divisor_magnitude = Self::from_limbs(false, divisor.limbs.clone());
+        let mut quotient = vec![0; self.limbs.len()];
+        let mut remainder = Self::zero();
+        for i in (0..self.limbs.len()).rev() {
+            // bring down the next limb, then find the largest digit whose
+            // multiple of the divisor still fits
+            let mut limbs = vec![self.limbs[i]];
+            limbs.extend(&remainder.limbs);
+            remainder = Self::from_limbs(false, limbs);
+            let (mut low, mut high) = (0, BASE as u32 - 1);
+            while low < high {
+                let middle = low + (high - low).div_ceil(2);
+                let multiple = divisor_magnitude.mul_small(middle);
+                if multiple.compare_magnitude(&remainder) == Ordering::Greater {
+                    high = middle - 1;
+                } else {
+                    low = middle;
+                }
+            }
+            quotient[i] = low;
+            remainder = remainder.sub(&divisor_magnitude.mul_small(low));
+        }
+        let quotient = Self::from_limbs(self.negative != divisor.negative, quotient);
+        let remainder = Self::from_limbs(self.negative, remainder.limbs);
+        Some((quotient, remainder))
+    }
+
+    /// The value as a float if a float holds it exactly, or as itself.
+    pub fn normalize(self) -> RuntimeValue {
+        let float = self.to_f64();
+        if float.abs() < MAX_EXACT {
+            RuntimeValue::Float(float)
+        } else {
+            RuntimeValue::BigInt(self)
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => self.compare_magnitude(other),
+            (true, true) => other.compare_magnitude(self),
+        }
+    }
+}
+
+impl Display for BigInt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut limbs = self.limbs.iter().rev();
+        let first = match limbs.next() {
+            Some(first) => first,
+            None => return f.write_char('0'),
+        };
+        if self.negative {
+            f.write_char('-')?;
+        }
+        write!(f, "{}", first)?;
+        for limb in limbs {
+            write!(f, "{:09}", limb)?;
+        }
+        Ok(())
+    }
+}
+
+// a whole number, whether it's a float or not
+fn whole(value: &RuntimeValue) -> Option<BigInt> {
+    match value {
+        RuntimeValue::Float(x) => BigInt::from_f64(*x),
+        RuntimeValue::BigInt(x) => Some(x.clone()),
+        _ => None,
+    }
+}
+
+/// `operator` on two whole numbers, exactly, the way `--bignum` does it.
+/// `None` if either isn't one, or if the result isn't either, so the
+/// operation is left to floats.
+pub(crate) fn exact(
+    operator: TokenKind,
+    left: &RuntimeValue,
+    right: &RuntimeValue,
+) -> Option<RuntimeValue> {
+    // floats are exact until they get this large, and are much faster
+    if let (RuntimeValue::Float(l), RuntimeValue::Float(r)) = (left, right) {
+        let result = match operator {
+            TokenKind::Plus => l + r,
+            TokenKind::Minus => l - r,
+            TokenKind::Star => l * r,
+            _ => f64::NAN,
+        };
+        let exact = |x: f64| x.fract() == 0.0 && x.abs() < MAX_EXACT;
+        if exact(*l) && exact(*r) && exact(result) {
+            return Some(RuntimeValue::Float(result));
+        }
+    }
+    let (l, r) = (whole(left)?, whole(right)?);
+    let result = match operator {
+        TokenKind::Plus => l.add(&r),
+        TokenKind::Minus => l.sub(&r),
+        TokenKind::Star => l.mul(&r),
+        TokenKind::Slash => match l.div_rem(&r)? {
+            (quotient, remainder) if remainder.is_zero() => quotient,
+            _ => return None,
+        },
+        TokenKind::Greater => return Some(RuntimeValue::Bool(l > r)),
+        TokenKind::GreaterEqual => return Some(RuntimeValue::Bool(l >= r)),
+        TokenKind::Less => return Some(RuntimeValue::Bool(l < r)),
+        TokenKind::LessEqual => return Some(RuntimeValue::Bool(l <= r)),
+        _ => return None,
+    };
+    Some(result.normalize())
+}
+
+/// The value of an integer literal too large for a float, which the
+/// scanner already rounded.
+pub(crate) fn integer_literal(token: &Token) -> Option<RuntimeValue> {
+    match token.literal {
+        RuntimeValue::Float(x) if x >= MAX_EXACT => {
+            Some(BigInt::parse(token.lexeme.as_str())?.normalize())
+        }
+        _ => None,
+    }
+}
+
+/// How two numbers compare, exactly if they're whole.
+pub(crate) fn compare_numbers(left: &RuntimeValue, right: &RuntimeValue) -> Option<Ordering> {
+    match (whole(left), whole(right)) {
+        (Some(l), Some(r)) => Some(l.cmp(&r)),
+        _ => match (left, right) {
+            (RuntimeValue::Float(l), RuntimeValue::Float(r)) => Some(l.total_cmp(r)),
+            (RuntimeValue::BigInt(l), RuntimeValue::Float(r)) => Some(l.to_f64().total_cmp(r)),
+            (RuntimeValue::Float(l), RuntimeValue::BigInt(r)) => Some(l.total_cmp(&r.to_f64())),
+            _ => None,
+        },
+    }
+}
+
+/// Whether two numbers are the same whole number, one of them big.
+pub(crate) fn equal(left: &RuntimeValue, right: &RuntimeValue) -> bool {
+    match (whole(left), whole(right)) {
+        (Some(l), Some(r)) => l == r,
+        _ => false,
+    }
+}
@@ -8,4 +8,13 @@ pub trait CallableValue {
         args: Vec<RuntimeValue>,
     ) -> Result<RuntimeValue, InterpreterError>;
     fn arity(&self) -> usize;
+    fn name(&self) -> &str;
+    /// Whether it takes any number of arguments past its arity, which is
+    /// then the least it takes.
+    fn is_variadic(&self) -> bool {
+        false
+    }
+    fn accepts(&self, count: usize) -> bool {
+        count == self.arity() || self.is_variadic() && count > self.arity()
+    }
 }
@@ -1,11 +1,14 @@
 use std::{
-    collections::HashMap,
     fmt::Display,
-    sync::{Arc, Mutex},
+    sync::atomic::{AtomicUsize, Ordering},
 };
 
+use rustc_hash::FxHashMap;
+
 use crate::{
+    gc::{self, Node, Trace},
     interpreter::{Interpreter, InterpreterError},
+    shared::{Cell, Gc},
     token::Token,
 };
 
@@ -13,21 +16,25 @@ use super::{CallableValue, RuntimeValue, UserFunction};
 
 #[derive(Debug)]
 pub struct ClassDefinitionStorage {
-    name: Token,
+    // `None` for a class made by a class expression
+    name: Option<Token>,
     superclass: Option<ClassDefinition>,
-    methods: HashMap<String, UserFunction>,
+    methods: FxHashMap<String, UserFunction>,
+    statics: Cell<FxHashMap<String, RuntimeValue>>,
 }
 #[derive(Debug, Clone)]
-pub struct ClassDefinition(Arc<ClassDefinitionStorage>);
+pub struct ClassDefinition(Gc<ClassDefinitionStorage>);
 
 impl Display for ClassDefinition {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "<class {}>", self.0.name.lexeme)
+        write!(f, "<class {}>", self.name())
     }
 }
+// like instances, classes are only the same as themselves. a factory
+// function makes a different class with the same name each time
 impl PartialEq for ClassDefinition {
     fn eq(&self, other: &Self) -> bool {
-        self.0.name == other.0.name
+        Gc::ptr_eq(&self.0, &other.0)
     }
 }
 impl CallableValue for ClassDefinition {
@@ -48,18 +55,27 @@ impl CallableValue for ClassDefinition {
         let initializer = self.find_method("init");
         initializer.as_ref().map(CallableValue::arity).unwrap_or(0)
     }
+
+    fn name(&self) -> &str {
+        match &self.0.name {
+            Some(name) => &name.lexeme,
+            None => "anonymous",
+        }
+    }
 }
 impl ClassDefinition {
     pub fn new(
-        name: &Token,
+        name: Option<&Token>,
         superclass: Option<ClassDefinition>,
-        methods: HashMap<String, UserFunction>,
+        methods: FxHashMap<String, UserFunction>,
+        statics: FxHashMap<String, RuntimeValue>,
     ) -> Self {
         Self(
             ClassDefinitionStorage {
-                name: name.clone(),
+                name: name.cloned(),
                 superclass,
                 methods,
+                statics: statics.into(),
             }
             .into(),
         )
@@ -72,25 +88,93 @@ impl ClassDefinition {
             (None, None) => None,
         }
     }
+    /// The names of the methods the class has, including inherited ones,
+    /// sorted.
+    pub fn method_names(&self) -> Vec<String> {
+        let mut names = match &self.0.superclass {
+            Some(sc) => sc.method_names(),
+            None => vec![],
+        };
+        names.extend(self.0.methods.keys().cloned());
+        names.sort();
+        names.dedup();
+        names
+    }
+    /// The static field, of this class or the closest superclass that has
+    /// it.
+    pub fn get_static(&self, name: &str) -> Option<RuntimeValue> {
+        let value = self.0.statics.borrow().get(name).cloned();
+        match (value, &self.0.superclass) {
+            (Some(value), _) => Some(value),
+            (None, Some(sc)) => sc.get_static(name),
+            (None, None) => None,
+        }
+    }
+    /// Sets the static field on this class, even if a superclass has one
+    /// with the same name.
+    pub fn set_static(&self, name: &str, value: RuntimeValue) {
+        self.0.statics.borrow_mut().insert(name.to_string(), value);
+    }
+}
+impl Trace for ClassDefinition {
+    fn address(&self) -> usize {
+        Gc::as_ptr(&self.0) as *const () as usize
+    }
+    fn strong_count(&self) -> usize {
+        Gc::strong_count(&self.0)
+    }
+    fn trace(&self, children: &mut Vec<Node>) -> bool {
+        let statics = match self.0.statics.try_borrow() {
+            Some(statics) => statics,
+            None => return false,
+        };
+        if let Some(superclass) = &self.0.superclass {
+            children.push(Node::Class(superclass.clone()));
+        }
+        for method in self.0.methods.values() {
+            children.push(Node::Function(method.clone()));
+        }
+        for value in statics.values() {
+            gc::trace_value(value, children);
+        }
+        true
+    }
+    fn clear(&self) {
+        let statics = self
+            .0
+            .statics
+            .try_borrow_mut()
+            .map(|mut it| std::mem::take(&mut *it));
+        drop(statics);
+    }
 }
+// how many instances are alive, of every interpreter in the process
+static LIVE_INSTANCES: AtomicUsize = AtomicUsize::new(0);
+
 #[derive(Debug)]
 struct ClassInstanceStorage {
-    class: Arc<ClassDefinition>,
-    fields: Mutex<HashMap<String, RuntimeValue>>,
+    class: ClassDefinition,
+    fields: Cell<FxHashMap<String, RuntimeValue>>,
+    // set by `freeze`, after which fields can't be set or removed
+    frozen: Cell<bool>,
+}
+impl Drop for ClassInstanceStorage {
+    fn drop(&mut self) {
+        LIVE_INSTANCES.fetch_sub(1, Ordering::Relaxed);
+    }
 }
 #[derive(Debug, Clone)]
-pub struct ClassInstance(Arc<ClassInstanceStorage>);
+pub struct ClassInstance(Gc<ClassInstanceStorage>);
 
 impl Display for ClassInstance {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
             "instance {}({})",
-            &self.0.class.0.name.lexeme,
+            self.0.class.name(),
             self.0
                 .fields
-                .lock()
-                .unwrap()
+                .borrow()
                 .keys()
                 .cloned()
                 .collect::<Vec<String>>()
@@ -98,40 +182,96 @@ impl Display for ClassInstance {
         )
     }
 }
+// instances are the same only if they're the same object, two instances
+// with equal fields are still different ones
 impl PartialEq for ClassInstance {
     fn eq(&self, other: &Self) -> bool {
-        let map: &HashMap<String, RuntimeValue> = &self.0.fields.lock().unwrap();
-        let other_map: &HashMap<String, RuntimeValue> = &other.0.fields.lock().unwrap();
-        self.0.class == other.0.class && map == other_map
+        Gc::ptr_eq(&self.0, &other.0)
     }
 }
 impl ClassInstance {
     pub fn new(class: &ClassDefinition) -> Self {
+        LIVE_INSTANCES.fetch_add(1, Ordering::Relaxed);
         Self(
             ClassInstanceStorage {
-                class: class.clone().into(),
-                fields: HashMap::new().into(),
+                class: class.clone(),
+                fields: FxHashMap::default().into(),
+                frozen: false.into(),
             }
             .into(),
         )
     }
-    pub fn get(&self, name: &Token) -> Option<RuntimeValue> {
-        let field = self.0.fields.lock().unwrap().get(&name.lexeme).cloned();
+    /// Number of instances alive, made by any interpreter in the process.
+    pub fn live_count() -> usize {
+        LIVE_INSTANCES.load(Ordering::Relaxed)
+    }
+    pub fn class(&self) -> &ClassDefinition {
+        &self.0.class
+    }
+    pub fn get(&self, name: &str) -> Option<RuntimeValue> {
+        let field = self.0.fields.borrow().get(name).cloned();
         match field {
             Some(_) => field,
             None => self
                 .0
                 .class
-                .find_method(&name.lexeme)
+                .find_method(name)
                 .map(|it| it.bind(self))
                 .map(RuntimeValue::UserFunction),
         }
     }
-    pub fn set(&self, name: &Token, value: RuntimeValue) {
-        self.0
+    pub fn set(&self, name: &str, value: RuntimeValue) {
+        self.0.fields.borrow_mut().insert(name.to_string(), value);
+    }
+    /// Removes the field, returning its value if there was one. Methods
+    /// aren't fields, so they can't be removed.
+    pub fn remove(&self, name: &str) -> Option<RuntimeValue> {
+        self.0.fields.borrow_mut().remove(name)
+    }
+    /// Stops the fields from changing, for good.
+    pub fn freeze(&self) {
+        *self.0.frozen.borrow_mut() = true;
+    }
+    pub fn is_frozen(&self) -> bool {
+        *self.0.frozen.borrow()
+    }
+    /// snapshot of the instance fields, sorted by name so the order is stable
+    pub fn fields(&self) -> Vec<(String, RuntimeValue)> {
+        let mut fields = self
+            .0
+            .fields
+            .borrow()
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect::<Vec<_>>();
+        fields.sort_by(|a, b| a.0.cmp(&b.0));
+        fields
+    }
+}
+impl Trace for ClassInstance {
+    fn address(&self) -> usize {
+        Gc::as_ptr(&self.0) as *const () as usize
+    }
+    fn strong_count(&self) -> usize {
+        Gc::strong_count(&self.0)
+    }
+    fn trace(&self, children: &mut Vec<Node>) -> bool {
+        let fields = match self.0.fields.try_borrow() {
+            Some(fields) => fields,
+            None => return false,
+        };
+        children.push(Node::Class(self.0.class.clone()));
+        for value in fields.values() {
+            gc::trace_value(value, children);
+        }
+        true
+    }
+    fn clear(&self) {
+        let fields = self
+            .0
             .fields
-            .lock()
-            .unwrap()
-            .insert(name.lexeme.clone(), value);
+            .try_borrow_mut()
+            .map(|mut it| std::mem::take(&mut *it));
+        drop(fields);
     }
 }
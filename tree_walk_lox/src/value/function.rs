@@ -1,12 +1,12 @@
-use std::{
-    fmt::{Debug, Display},
-    sync::Arc,
-};
+use std::fmt::{Debug, Display};
 
 use crate::{
     ast::FunctionStmt,
     environment::Environment,
+    gc::{Node, Trace},
     interpreter::{Interpreter, InterpreterError},
+    shared::{Gc, MaybeSync},
+    symbol,
 };
 
 use super::{CallableValue, ClassInstance, RuntimeValue};
@@ -17,7 +17,7 @@ struct UserFunctionStorage {
     is_initializer: bool,
 }
 #[derive(Clone)]
-pub struct UserFunction(Arc<UserFunctionStorage>);
+pub struct UserFunction(Gc<UserFunctionStorage>);
 
 impl Debug for UserFunction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -63,10 +63,25 @@ impl UserFunction {
     }
     pub fn bind(&self, instance: &ClassInstance) -> UserFunction {
         let environment = self.0.closure.child();
-        environment.define("this", RuntimeValue::Instance(instance.clone()));
+        environment.define(*symbol::THIS, RuntimeValue::Instance(instance.clone()));
         UserFunction::new(&self.0.declaration, &environment, self.0.is_initializer)
     }
 }
+impl Trace for UserFunction {
+    fn address(&self) -> usize {
+        Gc::as_ptr(&self.0) as *const () as usize
+    }
+    fn strong_count(&self) -> usize {
+        Gc::strong_count(&self.0)
+    }
+    fn trace(&self, children: &mut Vec<Node>) -> bool {
+        children.push(Node::Environment(self.0.closure.clone()));
+        true
+    }
+    // the closure goes away with the function, once its environment no
+    // longer holds it
+    fn clear(&self) {}
+}
 impl CallableValue for UserFunction {
     fn call(
         &self,
@@ -75,32 +90,44 @@ impl CallableValue for UserFunction {
     ) -> Result<RuntimeValue, InterpreterError> {
         let environment = self.0.closure.child();
         for (arg, arg_value) in self.0.declaration.params.iter().zip(&args) {
-            environment.define(&arg.lexeme, arg_value.clone());
+            environment.define(arg.lexeme, arg_value.clone());
         }
-        if let Err(e) = interpreter.execute_block(&self.0.declaration.body, &environment) {
-            match e {
-                InterpreterError::Return(_) if self.0.is_initializer => {
-                    Ok(self.0.closure.get_at(0, "this").unwrap())
-                }
-                InterpreterError::Return(v) => Ok(v),
-                e => Err(e),
+        match interpreter.execute_block(&self.0.declaration.body, &environment) {
+            // an initializer returns the instance, even when called again
+            // or when it returns early, and the closure of a bound method
+            // only has `this`
+            Ok(()) | Err(InterpreterError::Return(_)) if self.0.is_initializer => {
+                Ok(self.0.closure.get_at(0, 0).unwrap())
             }
-        } else {
-            Ok(RuntimeValue::Nil)
+            Ok(()) => Ok(RuntimeValue::Nil),
+            Err(InterpreterError::Return(v)) => Ok(v),
+            Err(e) => Err(e),
         }
     }
     fn arity(&self) -> usize {
         self.0.declaration.params.len()
     }
+    fn name(&self) -> &str {
+        &self.0.declaration.name.lexeme
+    }
 }
 
+#[cfg(not(feature = "sync"))]
+pub type NativeFn =
+    dyn Fn(&mut Interpreter, Vec<RuntimeValue>) -> Result<RuntimeValue, InterpreterError>;
+#[cfg(feature = "sync")]
+pub type NativeFn = dyn Fn(&mut Interpreter, Vec<RuntimeValue>) -> Result<RuntimeValue, InterpreterError>
+    + Send
+    + Sync;
+
 pub struct BuiltInFunctionStorage {
     name: String,
     args: Vec<String>,
-    callable: fn(&Interpreter, Vec<RuntimeValue>) -> Result<RuntimeValue, InterpreterError>,
+    variadic: bool,
+    callable: Box<NativeFn>,
 }
 #[derive(Clone)]
-pub struct BuiltInFunction(Arc<BuiltInFunctionStorage>);
+pub struct BuiltInFunction(Gc<BuiltInFunctionStorage>);
 
 impl Debug for BuiltInFunction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -113,7 +140,18 @@ impl Debug for BuiltInFunction {
 }
 impl Display for BuiltInFunction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "<fun {}({})>", self.0.name, self.0.args.join(", "))
+        let rest = match (self.0.variadic, self.0.args.is_empty()) {
+            (false, _) => "",
+            (true, true) => "...",
+            (true, false) => ", ...",
+        };
+        write!(
+            f,
+            "<fun {}({}{})>",
+            self.0.name,
+            self.0.args.join(", "),
+            rest
+        )
     }
 }
 impl PartialEq for BuiltInFunction {
@@ -125,13 +163,36 @@ impl BuiltInFunction {
     pub fn new(
         name: &str,
         args: Vec<&str>,
-        callable: fn(&Interpreter, Vec<RuntimeValue>) -> Result<RuntimeValue, InterpreterError>,
+        callable: impl Fn(&mut Interpreter, Vec<RuntimeValue>) -> Result<RuntimeValue, InterpreterError>
+            + MaybeSync
+            + 'static,
+    ) -> Self {
+        Self(
+            BuiltInFunctionStorage {
+                name: name.into(),
+                args: args.into_iter().map(str::to_string).collect(),
+                variadic: false,
+                callable: Box::new(callable),
+            }
+            .into(),
+        )
+    }
+
+    /// A native that takes `args` and then any number of arguments more,
+    /// all of which it's called with.
+    pub fn variadic(
+        name: &str,
+        args: Vec<&str>,
+        callable: impl Fn(&mut Interpreter, Vec<RuntimeValue>) -> Result<RuntimeValue, InterpreterError>
+            + MaybeSync
+            + 'static,
     ) -> Self {
         Self(
             BuiltInFunctionStorage {
                 name: name.into(),
                 args: args.into_iter().map(str::to_string).collect(),
-                callable,
+                variadic: true,
+                callable: Box::new(callable),
             }
             .into(),
         )
@@ -149,4 +210,10 @@ impl CallableValue for BuiltInFunction {
     fn arity(&self) -> usize {
         self.0.args.len()
     }
+    fn name(&self) -> &str {
+        &self.0.name
+    }
+    fn is_variadic(&self) -> bool {
+        self.0.variadic
+    }
 }
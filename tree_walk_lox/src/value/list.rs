@@ -0,0 +1,91 @@
+use std::fmt::Display;
+
+use crate::{
+    gc::{self, Node, Trace},
+    shared::{Cell, CellRefMut, Gc},
+};
+
+use super::{write_nested, RuntimeValue};
+
+/// A growable list of values. Copies of a list share the same items.
+#[derive(Debug, Clone, Default)]
+pub struct List(Gc<Cell<Vec<RuntimeValue>>>);
+
+impl Display for List {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write(f, &mut Vec::new())
+    }
+}
+// lists are compared by identity, like instances of a class
+impl PartialEq for List {
+    fn eq(&self, other: &Self) -> bool {
+        Gc::ptr_eq(&self.0, &other.0)
+    }
+}
+impl List {
+    pub fn new(items: Vec<RuntimeValue>) -> Self {
+        Self(Gc::new(Cell::new(items)))
+    }
+    pub fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn get(&self, index: usize) -> Option<RuntimeValue> {
+        self.0.borrow().get(index).cloned()
+    }
+    pub(crate) fn borrow_mut(&self) -> CellRefMut<'_, Vec<RuntimeValue>> {
+        self.0.borrow_mut()
+    }
+    /// A copy of the items as they are now.
+    pub fn items(&self) -> Vec<RuntimeValue> {
+        self.0.borrow().clone()
+    }
+    // checked before borrowing the items, which with `sync` would wait on
+    // the lock this list is already holding
+    pub(super) fn write(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        printing: &mut Vec<usize>,
+    ) -> std::fmt::Result {
+        if printing.contains(&self.address()) {
+            return write!(f, "[...]");
+        }
+        printing.push(self.address());
+        write!(f, "[")?;
+        for (i, item) in self.0.borrow().iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write_nested(f, item, printing)?;
+        }
+        printing.pop();
+        write!(f, "]")
+    }
+}
+impl Trace for List {
+    fn address(&self) -> usize {
+        Gc::as_ptr(&self.0) as *const () as usize
+    }
+    fn strong_count(&self) -> usize {
+        Gc::strong_count(&self.0)
+    }
+    fn trace(&self, children: &mut Vec<Node>) -> bool {
+        let items = match self.0.try_borrow() {
+            Some(items) => items,
+            None => return false,
+        };
+        for value in items.iter() {
+            gc::trace_value(value, children);
+        }
+        true
+    }
+    fn clear(&self) {
+        let items = self
+            .0
+            .try_borrow_mut()
+            .map(|mut it| std::mem::take(&mut *it));
+        drop(items);
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    fmt::Display,
+    hash::{Hash, Hasher},
+};
+
+use indexmap::IndexMap;
+
+use crate::{
+    gc::{self, Node, Trace},
+    shared::{Cell, CellRefMut, Gc},
+};
+
+use super::{write_nested, BigInt, ClassInstance, RuntimeValue};
+
+/// What a map is keyed by. Only values that are compared by what they hold
+/// can be keys, so a key can't change while it is in a map, apart from
+/// instances whose class says how to compare them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Bool(bool),
+    // the bits of the number, with -0 stored as 0 so they are the same key
+    Number(u64),
+    BigInt(BigInt),
+    Str(Gc<str>),
+    Instance(InstanceKey),
+    Nil,
+}
+
+// -0 and 0 are the same number, so they have to be the same key
+fn number_bits(x: f64) -> u64 {
+    if x == 0.0 {
+        0f64.to_bits()
+    } else {
+        x.to_bits()
+    }
+}
+
+/// An instance as a key, with what its `hash()` method returned. Hashing
+/// and `equals()` are methods, so they need the interpreter, which the
+/// map doesn't have: whoever makes the key looks for an equal one already
+/// in the map first, and uses that instead. So keys that are different
+/// instances are different keys.
+#[derive(Debug, Clone)]
+pub struct InstanceKey {
+    hash: u64,
+    instance: ClassInstance,
+}
+impl InstanceKey {
+    pub fn new(hash: f64, instance: ClassInstance) -> Self {
+        Self {
+            hash: number_bits(hash),
+            instance,
+        }
+    }
+    /// Whether `hash()` returned `hash` for this key.
+    pub fn has_hash(&self, hash: f64) -> bool {
+        self.hash == number_bits(hash)
+    }
+    pub fn instance(&self) -> &ClassInstance {
+        &self.instance
+    }
+}
+impl PartialEq for InstanceKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.instance == other.instance
+    }
+}
+impl Eq for InstanceKey {}
+impl Hash for InstanceKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hash.hash(state);
+    }
+}
+impl MapKey {
+    pub fn new(value: &RuntimeValue) -> Option<Self> {
+        match value {
+            RuntimeValue::Bool(x) => Some(MapKey::Bool(*x)),
+            RuntimeValue::Float(x) => Some(MapKey::Number(number_bits(*x))),
+            RuntimeValue::BigInt(x) => Some(MapKey::BigInt(x.clone())),
+            RuntimeValue::Str(x) => Some(MapKey::Str(x.clone())),
+            RuntimeValue::Nil => Some(MapKey::Nil),
+            _ => None,
+        }
+    }
+    pub fn to_value(&self) -> RuntimeValue {
+        match self {
+            MapKey::Bool(x) => RuntimeValue::Bool(*x),
+            MapKey::Number(x) => RuntimeValue::Float(f64::from_bits(*x)),
+            MapKey::BigInt(x) => RuntimeValue::BigInt(x.clone()),
+            MapKey::Str(x) => RuntimeValue::Str(x.clone()),
+            MapKey::Instance(x) => RuntimeValue::Instance(x.instance.clone()),
+            MapKey::Nil => RuntimeValue::Nil,
+        }
+    }
+}
+
+/// A map that remembers the order its keys were inserted in. Copies of a
+/// map share the same entries.
+#[derive(Debug, Clone, Default)]
+pub struct Map(Gc<Cell<IndexMap<MapKey, RuntimeValue>>>);
+
+impl Display for Map {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write(f, &mut Vec::new())
+    }
+}
+impl PartialEq for Map {
+    fn eq(&self, other: &Self) -> bool {
+        Gc::ptr_eq(&self.0, &other.0)
+    }
+}
+impl Map {
+    pub fn new(entries: IndexMap<MapKey, RuntimeValue>) -> Self {
+        Self(Gc::new(Cell::new(entries)))
+    }
+    pub fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn get(&self, key: &MapKey) -> Option<RuntimeValue> {
+        self.0.borrow().get(key).cloned()
+    }
+    pub fn insert(&self, key: MapKey, value: RuntimeValue) {
+        self.0.borrow_mut().insert(key, value);
+    }
+    /// A copy of the entries as they are now, in insertion order.
+    pub fn entries(&self) -> Vec<(MapKey, RuntimeValue)> {
+        self.0
+            .borrow()
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+    pub(crate) fn borrow_mut(&self) -> CellRefMut<'_, IndexMap<MapKey, RuntimeValue>> {
+        self.0.borrow_mut()
+    }
+    // like `List::write`, with {...} for a map that contains itself
+    pub(super) fn write(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        printing: &mut Vec<usize>,
+    ) -> std::fmt::Result {
+        if printing.contains(&self.address()) {
+            return write!(f, "{{...}}");
+        }
+        printing.push(self.address());
+        write!(f, "{{")?;
+        for (i, (key, value)) in self.0.borrow().iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write_nested(f, &key.to_value(), printing)?;
+            write!(f, ": ")?;
+            write_nested(f, value, printing)?;
+        }
+        printing.pop();
+        write!(f, "}}")
+    }
+}
+impl Trace for Map {
+    fn address(&self) -> usize {
+        Gc::as_ptr(&self.0) as *const () as usize
+    }
+    fn strong_count(&self) -> usize {
+        Gc::strong_count(&self.0)
+    }
+    fn trace(&self, children: &mut Vec<Node>) -> bool {
+        let items = match self.0.try_borrow() {
+            Some(items) => items,
+            None => return false,
+        };
+        for (key, value) in items.iter() {
+            if let MapKey::Instance(key) = key {
+                children.push(Node::Instance(key.instance.clone()));
+            }
+            gc::trace_value(value, children);
+        }
+        true
+    }
+    fn clear(&self) {
+        let items = self
+            .0
+            .try_borrow_mut()
+            .map(|mut it| std::mem::take(&mut *it));
+        drop(items);
+    }
+}
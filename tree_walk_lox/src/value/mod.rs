@@ -1,24 +1,38 @@
-use std::{
-    fmt::{Debug, Display},
-    sync::Arc,
-};
+use std::fmt::{Debug, Display};
 
+use crate::shared::Gc;
+
+mod bigint;
 mod callable;
 mod class;
 mod function;
+mod list;
+mod map;
+#[cfg(feature = "serde")]
+mod serialize;
+pub use bigint::BigInt;
+pub(crate) use bigint::{compare_numbers, exact, integer_literal};
 pub use callable::CallableValue;
 pub use class::{ClassDefinition, ClassInstance};
-pub use function::{BuiltInFunction, UserFunction};
+pub use function::{BuiltInFunction, NativeFn, UserFunction};
+pub use list::List;
+pub use map::{InstanceKey, Map, MapKey};
+#[cfg(feature = "serde")]
+pub use serialize::InstanceSeed;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum RuntimeValue {
     Bool(bool),
     Float(f64),
-    Str(Arc<str>),
+    /// A whole number too large for a float to hold exactly, with `--bignum`.
+    BigInt(BigInt),
+    Str(Gc<str>),
     BuiltInFunction(BuiltInFunction),
     UserFunction(UserFunction),
     Class(ClassDefinition),
     Instance(ClassInstance),
+    List(List),
+    Map(Map),
     Nil,
 }
 impl Display for RuntimeValue {
@@ -26,11 +40,14 @@ impl Display for RuntimeValue {
         match self {
             RuntimeValue::Bool(x) => write!(f, "{}", x),
             RuntimeValue::Float(x) => write!(f, "{}", x),
+            RuntimeValue::BigInt(x) => write!(f, "{}", x),
             RuntimeValue::Str(x) => write!(f, "{}", x),
             RuntimeValue::BuiltInFunction(x) => write!(f, "{}", x),
             RuntimeValue::UserFunction(x) => write!(f, "{}", x),
             RuntimeValue::Class(x) => write!(f, "{}", x),
             RuntimeValue::Instance(x) => write!(f, "{}", x),
+            RuntimeValue::List(x) => write!(f, "{}", x),
+            RuntimeValue::Map(x) => write!(f, "{}", x),
             RuntimeValue::Nil => write!(f, "nil"),
         }
     }
@@ -45,7 +62,11 @@ impl RuntimeValue {
         }
     }
     pub fn equals(&self, other: &RuntimeValue) -> bool {
-        self == other
+        match (self, other) {
+            (RuntimeValue::BigInt(_), RuntimeValue::Float(_))
+            | (RuntimeValue::Float(_), RuntimeValue::BigInt(_)) => bigint::equal(self, other),
+            _ => self == other,
+        }
     }
     pub fn as_callable(&self) -> Option<&dyn CallableValue> {
         match self {
@@ -55,4 +76,35 @@ impl RuntimeValue {
             _ => None,
         }
     }
+    /// Name of the value's type, or of its class for instances.
+    pub fn type_name(&self) -> &str {
+        match self {
+            RuntimeValue::Bool(_) => "bool",
+            RuntimeValue::Float(_) | RuntimeValue::BigInt(_) => "number",
+            RuntimeValue::Str(_) => "string",
+            RuntimeValue::BuiltInFunction(_) | RuntimeValue::UserFunction(_) => "function",
+            RuntimeValue::Class(_) => "class",
+            RuntimeValue::Instance(x) => x.class().name(),
+            RuntimeValue::List(_) => "list",
+            RuntimeValue::Map(_) => "map",
+            RuntimeValue::Nil => "nil",
+        }
+    }
+}
+
+// items of lists and maps are printed with strings quoted, so ["a, b"]
+// doesn't look like ["a", "b"]. `printing` has the addresses of the lists
+// and maps being printed, so one that contains itself is printed as [...]
+// the second time round instead of forever
+fn write_nested(
+    f: &mut std::fmt::Formatter<'_>,
+    value: &RuntimeValue,
+    printing: &mut Vec<usize>,
+) -> std::fmt::Result {
+    match value {
+        RuntimeValue::Str(s) => write!(f, "\"{}\"", s),
+        RuntimeValue::List(list) => list.write(f, printing),
+        RuntimeValue::Map(map) => map.write(f, printing),
+        other => write!(f, "{}", other),
+    }
 }
@@ -0,0 +1,200 @@
+use std::{cell::RefCell, fmt};
+
+use serde::{
+    de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor},
+    ser::{self, SerializeMap, SerializeSeq},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use indexmap::IndexMap;
+
+use crate::gc::Trace;
+
+use super::{ClassDefinition, ClassInstance, List, Map, MapKey, RuntimeValue};
+
+thread_local! {
+    // the addresses of the lists, maps and instances being serialized, so
+    // one that contains itself is an error instead of a stack overflow
+    static SERIALIZING: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+// serializes `value` with `serialize`, unless it's already being serialized
+// further out
+fn guarded<S: Serializer>(
+    value: &impl Trace,
+    kind: &str,
+    serialize: impl FnOnce() -> Result<S::Ok, S::Error>,
+) -> Result<S::Ok, S::Error> {
+    let address = value.address();
+    let repeated = SERIALIZING.with(|it| {
+        let mut serializing = it.borrow_mut();
+        let repeated = serializing.contains(&address);
+        if !repeated {
+            serializing.push(address);
+        }
+        repeated
+    });
+    if repeated {
+        return Err(ser::Error::custom(format!(
+            "Can't serialize {} that contains itself.",
+            kind
+        )));
+    }
+    let result = serialize();
+    SERIALIZING.with(|it| it.borrow_mut().pop());
+    result
+}
+
+// only plain data can leave the interpreter. functions and classes carry
+// their closures with them, so trying to serialize one is an error instead
+// of silently dropping it.
+impl Serialize for RuntimeValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            RuntimeValue::Bool(x) => serializer.serialize_bool(*x),
+            RuntimeValue::Float(x) => serializer.serialize_f64(*x),
+            RuntimeValue::BigInt(x) => serializer.serialize_f64(x.to_f64()),
+            RuntimeValue::Str(x) => serializer.serialize_str(x),
+            RuntimeValue::Nil => serializer.serialize_unit(),
+            RuntimeValue::Instance(x) => x.serialize(serializer),
+            RuntimeValue::List(x) => x.serialize(serializer),
+            RuntimeValue::Map(x) => x.serialize(serializer),
+            RuntimeValue::BuiltInFunction(_) | RuntimeValue::UserFunction(_) => Err(
+                ser::Error::custom(format!("Can't serialize function '{}'.", self)),
+            ),
+            RuntimeValue::Class(_) => Err(ser::Error::custom(format!(
+                "Can't serialize class '{}'.",
+                self
+            ))),
+        }
+    }
+}
+
+// an instance is serialized as a map of its fields. the class is not part
+// of the output: it is provided again when deserializing with 'InstanceSeed'.
+impl Serialize for ClassInstance {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        guarded::<S>(self, "an instance", || {
+            let fields = self.fields();
+            let mut map = serializer.serialize_map(Some(fields.len()))?;
+            for (name, value) in &fields {
+                map.serialize_entry(name, value)?;
+            }
+            map.end()
+        })
+    }
+}
+
+impl Serialize for List {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        guarded::<S>(self, "a list", || {
+            let items = self.items();
+            let mut seq = serializer.serialize_seq(Some(items.len()))?;
+            for item in &items {
+                seq.serialize_element(item)?;
+            }
+            seq.end()
+        })
+    }
+}
+
+impl Serialize for Map {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        guarded::<S>(self, "a map", || {
+            let entries = self.entries();
+            let mut map = serializer.serialize_map(Some(entries.len()))?;
+            for (key, value) in &entries {
+                map.serialize_entry(&key.to_value(), value)?;
+            }
+            map.end()
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for RuntimeValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(RuntimeValueVisitor)
+    }
+}
+
+struct RuntimeValueVisitor;
+
+impl<'de> Visitor<'de> for RuntimeValueVisitor {
+    type Value = RuntimeValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a bool, number, string, list, map or nil")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<RuntimeValue, E> {
+        Ok(RuntimeValue::Bool(v))
+    }
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<RuntimeValue, E> {
+        Ok(RuntimeValue::Float(v as f64))
+    }
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<RuntimeValue, E> {
+        Ok(RuntimeValue::Float(v as f64))
+    }
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<RuntimeValue, E> {
+        Ok(RuntimeValue::Float(v))
+    }
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<RuntimeValue, E> {
+        Ok(RuntimeValue::Str(v.into()))
+    }
+    fn visit_unit<E: de::Error>(self) -> Result<RuntimeValue, E> {
+        Ok(RuntimeValue::Nil)
+    }
+    fn visit_none<E: de::Error>(self) -> Result<RuntimeValue, E> {
+        Ok(RuntimeValue::Nil)
+    }
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<RuntimeValue, D::Error> {
+        RuntimeValue::deserialize(deserializer)
+    }
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<RuntimeValue, A::Error> {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(RuntimeValue::List(List::new(items)))
+    }
+    // instances are deserialized with 'InstanceSeed' instead, which knows
+    // the class to create
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<RuntimeValue, A::Error> {
+        let mut entries = IndexMap::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some((key, value)) = map.next_entry::<RuntimeValue, RuntimeValue>()? {
+            let key = MapKey::new(&key).ok_or_else(|| {
+                de::Error::custom(format!("'{}' can't be used as a map key.", key))
+            })?;
+            entries.insert(key, value);
+        }
+        Ok(RuntimeValue::Map(Map::new(entries)))
+    }
+}
+
+/// Deserializes a map of fields into a new instance of the given class.
+/// The initializer is not run, the fields are set as they were saved.
+pub struct InstanceSeed<'class>(pub &'class ClassDefinition);
+
+impl<'de, 'class> DeserializeSeed<'de> for InstanceSeed<'class> {
+    type Value = ClassInstance;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<ClassInstance, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, 'class> Visitor<'de> for InstanceSeed<'class> {
+    type Value = ClassInstance;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a map with the fields of an instance of {}", self.0)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<ClassInstance, A::Error> {
+        let instance = ClassInstance::new(self.0);
+        while let Some((name, value)) = map.next_entry::<String, RuntimeValue>()? {
+            instance.set(&name, value);
+        }
+        Ok(instance)
+    }
+}
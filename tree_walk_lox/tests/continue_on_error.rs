@@ -0,0 +1,67 @@
+//! With `set_continue_on_error`, a top-level statement that fails is kept
+//! as an error and the script goes on with the next one.
+
+use std::{cell::RefCell, rc::Rc};
+
+use lox::{
+    host::Output,
+    interpreter::{Interpreter, InterpreterError},
+};
+
+#[derive(Clone, Default)]
+struct Lines(Rc<RefCell<Vec<String>>>);
+
+impl Output for Lines {
+    fn print(&mut self, line: &str) {
+        self.0.borrow_mut().push(line.to_string());
+    }
+}
+
+fn interpreter() -> (Interpreter, Lines) {
+    let lines = Lines::default();
+    let mut interpreter = Interpreter::new();
+    interpreter.set_output(Box::new(lines.clone()));
+    interpreter.set_continue_on_error(true);
+    (interpreter, lines)
+}
+
+#[test]
+fn runs_the_statements_after_one_that_fails() {
+    let (mut interpreter, lines) = interpreter();
+    let source = "print(1);\nprint(missing);\nprint(2);\nvar x = -\"a\";\nprint(3);";
+    lox::run_source(&mut interpreter, source.to_string()).unwrap();
+    assert_eq!(*lines.0.borrow(), ["1", "2", "3"]);
+
+    let errors = interpreter.take_errors();
+    let errors = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        [
+            "[Line 2] Undefined variable 'missing'.",
+            "[Line 4] Unary minus must be applied to number, but value was a.",
+        ]
+    );
+    assert!(interpreter.take_errors().is_empty());
+}
+
+#[test]
+fn exiting_still_stops_the_script() {
+    let (mut interpreter, lines) = interpreter();
+    let source = "print(missing);\nexit(3);\nprint(1);";
+    let result = lox::run_source(&mut interpreter, source.to_string());
+    assert!(matches!(
+        result,
+        Err(lox::LoxError::Runtime(InterpreterError::Exit(3)))
+    ));
+    assert!(lines.0.borrow().is_empty());
+    assert_eq!(interpreter.take_errors().len(), 1);
+}
+
+#[test]
+fn stops_at_the_first_error_by_default() {
+    let mut interpreter = Interpreter::new();
+    let result = lox::run_source(&mut interpreter, "var a = nil + 1;\nvar b = 2;".to_string());
+    assert!(result.is_err());
+    assert_eq!(interpreter.get_global("b"), None);
+    assert!(interpreter.take_errors().is_empty());
+}
@@ -0,0 +1,70 @@
+//! Errors and warnings rendered by `Renderer` with the source they point at.
+
+use lox::{
+    diagnostic::{diagnostics, Diagnostic, Renderer},
+    interpreter::Interpreter,
+};
+
+fn render(source: &str, color: bool) -> String {
+    let mut interpreter = Interpreter::new();
+    let error = lox::run_source(&mut interpreter, source.to_string()).unwrap_err();
+    let renderer = Renderer::new(source, "script.lox").with_color(color);
+    diagnostics(&error)
+        .iter()
+        .map(|it| renderer.render(it))
+        .collect()
+}
+
+#[test]
+fn underlines_the_token_of_a_parse_error() {
+    let rendered = render("var a = 1;\nvar b = a +;\n", false);
+    assert_eq!(
+        rendered,
+        "\
+error: Expect expression.
+ --> script.lox:2:12
+  |
+2 | var b = a +;
+  |            ^
+"
+    );
+}
+
+#[test]
+fn points_runtime_errors_at_their_token() {
+    let rendered = render("var a = 1;\nprint(a + missing);\n", false);
+    assert_eq!(
+        rendered,
+        "\
+error: Undefined variable 'missing'.
+ --> script.lox:2:11
+  |
+2 | print(a + missing);
+  |           ^^^^^^^
+"
+    );
+}
+
+#[test]
+fn colors_the_severity_and_the_span() {
+    let rendered = render("print(missing);", true);
+    assert!(rendered.starts_with("\x1b[1;31merror\x1b[0m: Undefined variable 'missing'."));
+    assert!(rendered.contains("\x1b[36m^^^^^^^\x1b[0m"));
+
+    let warning = Diagnostic::warning("Unused.").at_line(1);
+    let rendered = Renderer::new("var a;", "script.lox")
+        .with_color(true)
+        .render(&warning);
+    assert!(rendered.starts_with("\x1b[1;33mwarning\x1b[0m: Unused."));
+}
+
+#[test]
+fn leaves_out_spans_of_other_sources() {
+    let mut interpreter = Interpreter::new();
+    let defined = "\n\nfun f() { return missing; }";
+    lox::run_source(&mut interpreter, defined.to_string()).unwrap();
+    let source = "f();";
+    let error = lox::run_source(&mut interpreter, source.to_string()).unwrap_err();
+    let rendered = Renderer::new(source, "<repl>").render(&diagnostics(&error)[0]);
+    assert_eq!(rendered, "error: Undefined variable 'missing'.\n");
+}
@@ -0,0 +1,24 @@
+//! `run_both` runs an expression on the tree-walk interpreter and on the
+//! bytecode vm, and tells apart scripts the bytecode compiler can't take.
+
+use lox::difftest::{run_both, Comparison};
+
+#[test]
+fn agrees_on_expressions_that_run() {
+    assert_eq!(run_both("1 + 2 * 3"), Comparison::Agreed);
+    assert_eq!(run_both("\"a\" + \"b\" // joined"), Comparison::Agreed);
+    assert_eq!(run_both("(1 + 2) < 4 == !nil"), Comparison::Agreed);
+}
+
+#[test]
+fn agrees_on_expressions_that_fail() {
+    assert_eq!(run_both("-\"a\""), Comparison::Agreed);
+}
+
+#[test]
+fn statements_are_unsupported() {
+    assert_eq!(
+        run_both("var x = 1;\nprintln(x);"),
+        Comparison::Unsupported("[line 1] Expect expression.".to_string())
+    );
+}
@@ -0,0 +1,23 @@
+//! `format_source` prints a script with canonical indentation and spacing,
+//! in either dialect.
+
+use lox::format::format_source;
+
+#[test]
+fn indents_blocks_and_spaces_operators() {
+    let source = "fun f(a,b){\nreturn a+b;\n}\n";
+    assert_eq!(
+        format_source(source, false).unwrap(),
+        "fun f(a, b) {\n  return a + b;\n}\n"
+    );
+}
+
+#[test]
+fn formats_print_statements_in_compat_jlox() {
+    let source = "if (true) {\nprint   \"hi\";\n}\n";
+    assert_eq!(
+        format_source(source, true).unwrap(),
+        "if (true) {\n  print \"hi\";\n}\n"
+    );
+    assert!(format_source(source, false).is_err());
+}
@@ -0,0 +1,63 @@
+//! Environments that only stay alive because of cycles through closures are
+//! freed by the cycle collector.
+
+use lox::{interpreter::Interpreter, sandbox::SandboxConfig};
+
+// every call leaves behind an environment holding a function that closes
+// over it, and a bound method stored in the instance it's bound to
+const CYCLES: &str = "
+fun make(n) {
+  fun f() { return f; }
+  return n;
+}
+class Node {
+  init() { this.me = this.get; }
+  get() { return this; }
+}
+var total = 0;
+for (var i = 0; i < 5000; i = i + 1) {
+  total = total + make(i);
+  var node = Node();
+}
+";
+
+#[test]
+fn collect_garbage_frees_closure_cycles() {
+    let mut interpreter = Interpreter::new();
+    lox::run_source(&mut interpreter, CYCLES.to_string()).unwrap();
+    interpreter.collect_garbage();
+    // only the globals are left
+    assert_eq!(interpreter.live_environments(), 1);
+}
+
+#[test]
+fn cycles_dont_count_against_the_environment_limit() {
+    let config = SandboxConfig {
+        max_environments: Some(100),
+        ..SandboxConfig::unrestricted()
+    };
+    let mut interpreter = Interpreter::new_with_config(config);
+    lox::run_source(&mut interpreter, CYCLES.to_string()).unwrap();
+}
+
+#[test]
+fn reachable_closures_survive_a_collection() {
+    let mut interpreter = Interpreter::new();
+    let source = "
+fun counter() {
+  var count = 0;
+  fun increment() {
+    count = count + 1;
+    return count;
+  }
+  return increment;
+}
+var next = counter();
+next();
+";
+    lox::run_source(&mut interpreter, source.to_string()).unwrap();
+    assert_eq!(interpreter.collect_garbage(), 0);
+    lox::run_source(&mut interpreter, "var last = next();".to_string()).unwrap();
+    let last = interpreter.get_global("last").map(|it| it.to_string());
+    assert_eq!(last.as_deref(), Some("2"));
+}
@@ -0,0 +1,38 @@
+//! The REPL's highlighting colors what the scanner scans, and leaves the
+//! rest of the line alone.
+
+use lox::highlight::Highlighter;
+
+#[test]
+fn colors_keywords_literals_and_comments() {
+    let line = r#"var x = "hi" + 12; // done"#;
+    assert_eq!(
+        Highlighter::new().highlight(line),
+        "\x1b[35mvar\x1b[0m x = \x1b[32m\"hi\"\x1b[0m + \x1b[33m12\x1b[0m; \x1b[90m// done\x1b[0m"
+    );
+}
+
+#[test]
+fn colors_an_unterminated_string_to_the_end() {
+    assert_eq!(
+        Highlighter::new().highlight(r#"if (a) "unfinished"#),
+        "\x1b[35mif\x1b[0m (a) \x1b[32m\"unfinished\x1b[0m"
+    );
+}
+
+#[test]
+fn print_is_a_keyword_only_for_jlox() {
+    assert_eq!(
+        Highlighter::new().highlight("print 1"),
+        "print \x1b[33m1\x1b[0m"
+    );
+    assert_eq!(
+        Highlighter::new().with_compat_jlox().highlight("print"),
+        "\x1b[35mprint\x1b[0m"
+    );
+}
+
+#[test]
+fn leaves_plain_lines_alone() {
+    assert_eq!(Highlighter::new().highlight("a.b(c) # d"), "a.b(c) # d");
+}
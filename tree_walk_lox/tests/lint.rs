@@ -0,0 +1,30 @@
+//! `lint_source` finds the warnings of a script without running it, in
+//! either dialect.
+
+fn warnings(source: &str, compat_jlox: bool) -> Vec<String> {
+    lox::lint_source(source.to_string(), compat_jlox)
+        .unwrap()
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+}
+
+#[test]
+fn finds_unused_variables() {
+    let source = "fun f() {\n  var unused = 1;\n  println(2);\n}";
+    assert_eq!(
+        warnings(source, false),
+        ["[Line 2] Warning at 'unused': Unused variable."]
+    );
+}
+
+#[test]
+fn reads_print_statements_in_compat_jlox() {
+    let source = "fun f() {\n  var unused = 1;\n  print \"hi\";\n}";
+    assert_eq!(
+        warnings(source, true),
+        ["[Line 2] Warning at 'unused': Unused variable."]
+    );
+
+    assert!(lox::lint_source(source.to_string(), false).is_err());
+}
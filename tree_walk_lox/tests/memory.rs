@@ -0,0 +1,55 @@
+//! `MemoryStats` counts what a script keeps alive, and `MemoryTracker` the
+//! most it kept alive at once.
+
+use lox::{
+    interpreter::Interpreter,
+    memory::{MemoryStats, MemoryTracker},
+    value::ClassInstance,
+};
+
+#[test]
+fn closures_keep_their_environments_alive() {
+    let mut interpreter = Interpreter::new();
+    let before = MemoryStats::of(&interpreter).environments;
+    let source = "
+fun counter() { var n = 0; fun inc() { n = n + 1; return n; } return inc; }
+var counters = list();
+for (var i = 0; i < 10; i = i + 1) counters.push(counter());
+";
+    lox::run_source(&mut interpreter, source.to_string()).unwrap();
+    assert_eq!(MemoryStats::of(&interpreter).environments, before + 10);
+}
+
+#[test]
+fn counts_the_strings_values_hold_once() {
+    let mut interpreter = Interpreter::new();
+    let source = "
+var a = \"one\";
+var b = a;
+var c = list();
+c.push(\"two\");
+var d = newMap();
+d.set(\"three\", c);
+";
+    lox::run_source(&mut interpreter, source.to_string()).unwrap();
+    assert_eq!(MemoryStats::of(&interpreter).strings, 3);
+}
+
+#[test]
+fn keeps_the_peak() {
+    let mut interpreter = Interpreter::new();
+    let tracker = MemoryTracker::new();
+    interpreter.set_hooks(Some(Box::new(tracker.clone())));
+    let instances = ClassInstance::live_count();
+    let source = "
+class Point { init(x) { this.x = x; } }
+fun deep(n) { var p = Point(n); if (n > 0) deep(n - 1); }
+deep(5);
+";
+    lox::run_source(&mut interpreter, source.to_string()).unwrap();
+    let last = MemoryStats::of(&interpreter);
+    let peak = tracker.peak(last);
+    assert_eq!(last.instances, instances);
+    assert_eq!(peak.instances, instances + 6);
+    assert!(peak.environments >= last.environments + 6);
+}
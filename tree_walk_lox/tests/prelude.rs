@@ -0,0 +1,53 @@
+//! `find_prelude` looks for `prelude.lox` through a search path like
+//! `LOX_PATH`, and what the prelude defines is there for the script.
+
+use std::{env, fs, path::PathBuf};
+
+use lox::interpreter::Interpreter;
+
+fn dirs(name: &str) -> (PathBuf, PathBuf) {
+    let root = env::temp_dir().join(format!("lox-prelude-{}-{}", name, std::process::id()));
+    let (first, second) = (root.join("first"), root.join("second"));
+    fs::create_dir_all(&first).unwrap();
+    fs::create_dir_all(&second).unwrap();
+    (first, second)
+}
+
+#[test]
+fn finds_the_first_prelude_on_the_path() {
+    let (first, second) = dirs("search");
+    fs::write(
+        second.join("prelude.lox"),
+        "fun square(x) { return x * x; }",
+    )
+    .unwrap();
+    let search_path = env::join_paths([&first, &second]).unwrap();
+    assert_eq!(
+        lox::find_prelude(&search_path),
+        Some(second.join("prelude.lox"))
+    );
+
+    fs::write(first.join("prelude.lox"), "").unwrap();
+    assert_eq!(
+        lox::find_prelude(&search_path),
+        Some(first.join("prelude.lox"))
+    );
+    fs::remove_dir_all(first.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn finds_nothing_without_a_prelude() {
+    let (first, second) = dirs("missing");
+    let search_path = env::join_paths([&first, &second]).unwrap();
+    assert_eq!(lox::find_prelude(&search_path), None);
+    fs::remove_dir_all(first.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn the_script_sees_what_the_prelude_defines() {
+    let mut interpreter = Interpreter::new();
+    let prelude = "fun square(x) { return x * x; }".to_string();
+    lox::run_source(&mut interpreter, prelude).unwrap();
+    lox::run_source(&mut interpreter, "var n = square(7);".to_string()).unwrap();
+    assert_eq!(interpreter.get_global("n").unwrap().to_string(), "49");
+}
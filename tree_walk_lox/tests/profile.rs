@@ -0,0 +1,65 @@
+//! The `Profiler` hooks count and time the calls a script makes.
+
+use lox::{interpreter::Interpreter, profile::Profiler};
+
+const SCRIPT: &str = "
+fun fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }
+fun twice(f) { f(); f(); }
+fun four() { return fib(3); }
+twice(four);
+";
+
+fn profile(source: &str) -> lox::profile::ProfileReport {
+    let mut interpreter = Interpreter::new();
+    let profiler = Profiler::new();
+    interpreter.set_hooks(Some(Box::new(profiler.clone())));
+    lox::run_source(&mut interpreter, source.to_string()).unwrap();
+    profiler.report()
+}
+
+#[test]
+fn counts_the_calls_to_each_function() {
+    let report = profile(SCRIPT);
+    let mut calls = report
+        .functions()
+        .iter()
+        .map(|it| (it.name.as_str(), it.calls))
+        .collect::<Vec<_>>();
+    calls.sort();
+    assert_eq!(calls, [("fib", 10), ("four", 2), ("twice", 1)]);
+}
+
+#[test]
+fn inclusive_time_covers_the_calls_made() {
+    let report = profile(SCRIPT);
+    let get = |name: &str| {
+        let functions = report.functions();
+        functions.iter().find(|it| it.name == name).unwrap().clone()
+    };
+    let (twice, four, fib) = (get("twice"), get("four"), get("fib"));
+    assert!(twice.inclusive >= four.inclusive);
+    assert!(four.inclusive >= fib.inclusive);
+    assert!(four.exclusive <= four.inclusive);
+    // the recursive calls are inside the outermost one's time
+    assert!(fib.inclusive <= four.inclusive);
+    assert!(fib.exclusive <= fib.inclusive);
+}
+
+#[test]
+fn collapses_the_chains_of_calls() {
+    let collapsed = profile(SCRIPT).to_collapsed();
+    let stacks = collapsed
+        .lines()
+        .map(|line| line.rsplit_once(' ').unwrap().0)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        stacks,
+        [
+            "twice",
+            "twice;four",
+            "twice;four;fib",
+            "twice;four;fib;fib",
+            "twice;four;fib;fib;fib",
+        ]
+    );
+}
@@ -0,0 +1,163 @@
+//! Values going through serde, here as JSON, with the `serde` feature.
+
+use lox::{
+    interpreter::Interpreter,
+    value::{InstanceSeed, RuntimeValue},
+};
+use serde::de::DeserializeSeed;
+use serde_json::json;
+
+// the value of the global `name` after running `source`
+fn value_of(source: &str, name: &str) -> RuntimeValue {
+    let mut interpreter = Interpreter::new();
+    lox::run_source(&mut interpreter, source.to_string()).unwrap();
+    interpreter.get_global(name).unwrap()
+}
+
+fn round_trip(value: &RuntimeValue) -> RuntimeValue {
+    let json = serde_json::to_string(value).unwrap();
+    serde_json::from_str(&json).unwrap()
+}
+
+#[test]
+fn round_trips_plain_values() {
+    for value in [
+        RuntimeValue::Bool(true),
+        RuntimeValue::Bool(false),
+        RuntimeValue::Float(1.5),
+        RuntimeValue::Float(-3.0),
+        RuntimeValue::Str("text".into()),
+        RuntimeValue::Nil,
+    ] {
+        assert_eq!(round_trip(&value), value);
+    }
+}
+
+#[test]
+fn round_trips_lists() {
+    let list = value_of(
+        r#"var l = list(); l.push(1); l.push("a"); l.push(nil);"#,
+        "l",
+    );
+    assert_eq!(
+        serde_json::to_value(&list).unwrap(),
+        json!([1.0, "a", null])
+    );
+
+    let RuntimeValue::List(back) = round_trip(&list) else {
+        panic!("expected a list");
+    };
+    assert_eq!(back.to_string(), list.to_string());
+}
+
+#[test]
+fn round_trips_maps() {
+    let map = value_of(
+        r#"var m = newMap(); m.set("b", 1); m.set("a", true); m.set("c", newMap());"#,
+        "m",
+    );
+    assert_eq!(
+        serde_json::to_string(&map).unwrap(),
+        r#"{"b":1.0,"a":true,"c":{}}"#
+    );
+
+    let RuntimeValue::Map(back) = round_trip(&map) else {
+        panic!("expected a map");
+    };
+    assert_eq!(back.to_string(), map.to_string());
+}
+
+#[test]
+fn serializes_big_integers_as_numbers() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_bignum(true);
+    let source = "var n = 12345678901234567890123;".to_string();
+    lox::run_source(&mut interpreter, source).unwrap();
+    let n = interpreter.get_global("n").unwrap();
+    assert!(matches!(n, RuntimeValue::BigInt(_)));
+
+    assert_eq!(round_trip(&n), RuntimeValue::Float(1.2345678901234568e22));
+}
+
+#[test]
+fn round_trips_instances_through_their_class() {
+    let source = r#"
+        class Point { init(x, y) { this.x = x; this.y = y; } }
+        var p = Point(1, "two");
+    "#;
+    let mut interpreter = Interpreter::new();
+    lox::run_source(&mut interpreter, source.to_string()).unwrap();
+    let point = interpreter.get_global("p").unwrap();
+    let Some(RuntimeValue::Class(class)) = interpreter.get_global("Point") else {
+        panic!("expected a class");
+    };
+
+    let json = serde_json::to_string(&point).unwrap();
+    assert_eq!(json, r#"{"x":1.0,"y":"two"}"#);
+
+    let mut deserializer = serde_json::Deserializer::from_str(&json);
+    let back = InstanceSeed(&class).deserialize(&mut deserializer).unwrap();
+    assert_eq!(back.class(), &class);
+    assert_eq!(back.get("x"), Some(RuntimeValue::Float(1.0)));
+    assert_eq!(back.get("y"), Some(RuntimeValue::Str("two".into())));
+}
+
+#[test]
+fn instances_need_a_map_of_fields() {
+    let class = match value_of("class Point {}", "Point") {
+        RuntimeValue::Class(class) => class,
+        _ => panic!("expected a class"),
+    };
+    let mut deserializer = serde_json::Deserializer::from_str("[1, 2]");
+    let error = InstanceSeed(&class)
+        .deserialize(&mut deserializer)
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "invalid type: sequence, expected a map with the fields of an instance of <class Point> at line 1 column 0"
+    );
+}
+
+#[test]
+fn functions_and_classes_cant_be_serialized() {
+    let source = "fun f() {} class C {} var n = clock;";
+    let cases = [
+        ("f", "Can't serialize function '<fun f()>'."),
+        ("n", "Can't serialize function '<fun clock()>'."),
+        ("C", "Can't serialize class '<class C>'."),
+    ];
+    for (name, message) in cases {
+        let error = serde_json::to_string(&value_of(source, name)).unwrap_err();
+        assert_eq!(error.to_string(), message);
+    }
+}
+
+#[test]
+fn functions_inside_lists_cant_be_serialized() {
+    let list = value_of("fun f() {} var l = list(); l.push(1); l.push(f);", "l");
+    let error = serde_json::to_string(&list).unwrap_err();
+    assert_eq!(error.to_string(), "Can't serialize function '<fun f()>'.");
+}
+
+#[test]
+fn values_that_contain_themselves_cant_be_serialized() {
+    let source = r#"
+        class Node {}
+        var l = list(); l.push(1); l.push(l);
+        var m = newMap(); var inner = list(); inner.push(m); m.set("inner", inner);
+        var n = Node(); n.next = n;
+    "#;
+    let cases = [
+        ("l", "Can't serialize a list that contains itself."),
+        ("m", "Can't serialize a map that contains itself."),
+        ("n", "Can't serialize an instance that contains itself."),
+    ];
+    for (name, message) in cases {
+        let error = serde_json::to_string(&value_of(source, name)).unwrap_err();
+        assert_eq!(error.to_string(), message);
+    }
+
+    // the same list twice side by side is fine, it only can't be inside itself
+    let twice = value_of("var a = list(); var b = list(); b.push(a); b.push(a);", "b");
+    assert_eq!(serde_json::to_string(&twice).unwrap(), "[[],[]]");
+}
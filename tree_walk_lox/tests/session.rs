@@ -0,0 +1,55 @@
+//! Scripts run a top-level statement at a time through an
+//! `ExecutionSession`, sharing the interpreter with the host in between.
+
+use std::time::{Duration, Instant};
+
+use lox::{interpreter::Interpreter, session::ExecutionSession};
+
+const SCRIPT: &str = "
+var count = 0;
+fun bump() { count = count + 1; }
+bump();
+
+bump();
+";
+
+#[test]
+fn steps_one_statement_at_a_time() {
+    let mut interpreter = Interpreter::new();
+    let mut session = ExecutionSession::new(&mut interpreter, SCRIPT.to_string()).unwrap();
+
+    let first = session.step(&mut interpreter).unwrap().unwrap();
+    assert_eq!((first.index, first.line), (0, Some(2)));
+    assert_eq!(interpreter.get_global("count").unwrap().to_string(), "0");
+
+    session.step(&mut interpreter).unwrap().unwrap();
+    let third = session.step(&mut interpreter).unwrap().unwrap();
+    assert_eq!((third.index, third.line), (2, Some(4)));
+    assert_eq!(interpreter.get_global("count").unwrap().to_string(), "1");
+
+    let last = session.step(&mut interpreter).unwrap().unwrap();
+    assert_eq!(last.line, Some(6));
+    assert!(session.is_finished());
+    assert!(session.step(&mut interpreter).is_none());
+    assert_eq!(interpreter.get_global("count").unwrap().to_string(), "2");
+}
+
+#[test]
+fn an_error_finishes_the_session() {
+    let mut interpreter = Interpreter::new();
+    let source = "var a = 1;\nvar b = a + nil;\nvar c = 3;".to_string();
+    let mut session = ExecutionSession::new(&mut interpreter, source).unwrap();
+    session.step(&mut interpreter).unwrap().unwrap();
+    assert!(session.step(&mut interpreter).unwrap().is_err());
+    assert!(session.is_finished());
+    assert_eq!(interpreter.get_global("c"), None);
+}
+
+#[test]
+fn runs_until_the_script_ends() {
+    let mut interpreter = Interpreter::new();
+    let mut session = ExecutionSession::new(&mut interpreter, SCRIPT.to_string()).unwrap();
+    let deadline = Instant::now() + Duration::from_secs(60);
+    assert!(session.run_until(&mut interpreter, deadline).unwrap());
+    assert_eq!(interpreter.get_global("count").unwrap().to_string(), "2");
+}
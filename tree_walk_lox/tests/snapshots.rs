@@ -0,0 +1,147 @@
+//! Runs every `.lox` file under `tests/lox` and compares what it prints with
+//! the annotations in its comments:
+//!
+//! ```lox
+//! println(1 + 2); // expect: 3
+//! println(nope); // error: Undefined variable 'nope'.
+//! ```
+//!
+//! Every line the script prints needs an `// expect:` in the same order. An
+//! `// error:` means the script stops with an error whose message contains
+//! the text. Add a language test by adding a file. Files under
+//! `compat_jlox` run with the book's print statement, and files under
+//! `bignum`, `strict_math` and `loose_equality` with the flag of that name.
+
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use lox::{host::Output, interpreter::Interpreter};
+
+#[derive(Clone, Default)]
+struct Capture(Rc<RefCell<Vec<String>>>);
+
+impl Output for Capture {
+    fn print(&mut self, line: &str) {
+        self.0.borrow_mut().push(line.to_string());
+    }
+}
+
+struct Expectations {
+    output: Vec<String>,
+    error: Option<String>,
+}
+
+fn parse(source: &str) -> Expectations {
+    let mut expectations = Expectations {
+        output: vec![],
+        error: None,
+    };
+    for line in source.lines() {
+        let comment = match line.find("//") {
+            Some(start) => line[start + 2..].trim(),
+            None => continue,
+        };
+        if let Some(output) = comment.strip_prefix("expect:") {
+            expectations.output.push(output.trim().to_string());
+        } else if let Some(error) = comment.strip_prefix("error:") {
+            expectations.error = Some(error.trim().to_string());
+        }
+    }
+    expectations
+}
+
+// the differences between what `path` should do and what it did
+fn check(path: &Path) -> Vec<String> {
+    let source = std::fs::read_to_string(path).unwrap();
+    let expectations = parse(&source);
+
+    let capture = Capture::default();
+    let mut interpreter = Interpreter::new();
+    interpreter.set_compat_jlox(path.parent().is_some_and(|it| it.ends_with("compat_jlox")));
+    interpreter.set_bignum(path.parent().is_some_and(|it| it.ends_with("bignum")));
+    interpreter.set_strict_math(path.parent().is_some_and(|it| it.ends_with("strict_math")));
+    interpreter.set_loose_equality(
+        path.parent()
+            .is_some_and(|it| it.ends_with("loose_equality")),
+    );
+    interpreter.set_output(Box::new(capture.clone()));
+    let result = lox::run_source(&mut interpreter, source);
+    let output = capture.0.borrow();
+
+    let mut failures = vec![];
+    if *output != expectations.output {
+        failures.push("output differs (- expected, + actual):".to_string());
+        for line in diff(&expectations.output, &output) {
+            failures.push(format!("    {}", line));
+        }
+    }
+    match (&expectations.error, result) {
+        (None, Ok(())) => {}
+        (None, Err(e)) => failures.push(format!("unexpected error: {}", e)),
+        (Some(expected), Ok(())) => failures.push(format!("expected an error with '{}'", expected)),
+        (Some(expected), Err(e)) if !e.to_string().contains(expected.as_str()) => {
+            failures.push(format!("expected an error with '{}', got: {}", expected, e))
+        }
+        (Some(_), Err(_)) => {}
+    }
+    failures
+}
+
+// a line diff: the longest common subsequence is kept, the rest is marked
+fn diff(expected: &[String], actual: &[String]) -> Vec<String> {
+    let (n, m) = (expected.len(), actual.len());
+    // lengths[i][j] is the longest common subsequence of expected[i..] and actual[j..]
+    let mut lengths = vec![vec![0; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i][j] = if expected[i] == actual[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let mut lines = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && expected[i] == actual[j] {
+            lines.push(format!("  {}", expected[i]));
+            i += 1;
+            j += 1;
+        } else if j == m || (i < n && lengths[i + 1][j] >= lengths[i][j + 1]) {
+            lines.push(format!("- {}", expected[i]));
+            i += 1;
+        } else {
+            lines.push(format!("+ {}", actual[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
+fn main() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/lox");
+    let mut failed = 0;
+    let files = lox::find_files(&dir, ".lox").unwrap();
+    for path in &files {
+        let name = path.strip_prefix(&dir).unwrap().display();
+        let failures = check(path);
+        if failures.is_empty() {
+            println!("PASS {}", name);
+        } else {
+            failed += 1;
+            println!("FAIL {}", name);
+            for failure in failures {
+                println!("    {}", failure);
+            }
+        }
+    }
+    println!();
+    println!("{} passed, {} failed", files.len() - failed, failed);
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}